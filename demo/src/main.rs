@@ -8,7 +8,7 @@ use log::info;
 
 use fidget::{
     context::Context,
-    eval::{BulkEvaluator, MathShape},
+    eval::{BulkEvaluator, EzShape, MathShape},
 };
 
 /// Simple test program
@@ -46,6 +46,20 @@ enum Command {
         #[clap(long)]
         color: bool,
 
+        /// Render with Lambert shading, using the surface normal
+        #[clap(long, conflicts_with = "color")]
+        shaded: bool,
+
+        /// Direction towards the light (for `--shaded`), as `x,y,z`
+        #[clap(
+            long,
+            value_delimiter = ',',
+            num_args = 3,
+            allow_negative_numbers = true,
+            default_values_t = [-1.0, 1.0, 2.0]
+        )]
+        light: Vec<f32>,
+
         /// Render using an isometric perspective
         #[clap(long)]
         isometric: bool,
@@ -114,12 +128,16 @@ struct MeshSettings {
     n: usize,
 }
 
+/// Brightness of surfaces which face away from the light
+const AMBIENT: f32 = 0.2;
+
 ////////////////////////////////////////////////////////////////////////////////
 fn run3d<S: fidget::eval::Shape>(
     shape: S,
     settings: &ImageSettings,
     isometric: bool,
     mode_color: bool,
+    light: Option<nalgebra::Vector3<f32>>,
 ) -> Vec<u8> {
     let mut mat = nalgebra::Transform3::identity();
     if !isometric {
@@ -139,7 +157,9 @@ fn run3d<S: fidget::eval::Shape>(
         (depth, color) = fidget::render::render3d(shape.clone(), &cfg);
     }
 
-    let out = if mode_color {
+    let out = if let Some(light) = light {
+        shade(&shape, settings.size as usize, &depth, light)
+    } else if mode_color {
         depth
             .into_iter()
             .zip(color)
@@ -169,6 +189,51 @@ fn run3d<S: fidget::eval::Shape>(
     out
 }
 
+/// Shades each pixel of a depth image with an ambient + Lambert model
+///
+/// The gradient is evaluated in bulk at each pixel's surface voxel (in the
+/// same `[-1, +1]` coordinates used by `render3d`), so the normals are in
+/// screen space, with +Z pointing towards the viewer.
+fn shade<S: fidget::eval::Shape>(
+    shape: &S,
+    size: usize,
+    depth: &[u32],
+    light: nalgebra::Vector3<f32>,
+) -> Vec<u8> {
+    let light = light.normalize();
+    let pos = |p: usize| 2.0 * p as f32 / size as f32 - 1.0;
+    let mut xs = vec![];
+    let mut ys = vec![];
+    let mut zs = vec![];
+    let mut pixels = vec![];
+    for (o, &d) in depth.iter().enumerate() {
+        if d > 0 {
+            xs.push(pos(o % size));
+            ys.push(pos(size - o / size - 1));
+            zs.push(pos(d as usize - 1));
+            pixels.push(o);
+        }
+    }
+
+    let mut out = vec![[0, 0, 0, 0]; depth.len()];
+    if !pixels.is_empty() {
+        let tape = shape.ez_grad_slice_tape();
+        let mut eval = S::new_grad_slice_eval();
+        let grads = eval.eval(&tape, &xs, &ys, &zs, &[]).unwrap();
+        for (g, o) in grads.iter().zip(pixels) {
+            let n = nalgebra::Vector3::new(g.dx, g.dy, g.dz).normalize();
+            out[o] = if n.iter().all(|v| v.is_finite()) {
+                let b = AMBIENT + (1.0 - AMBIENT) * n.dot(&light).max(0.0);
+                let b = (b * 255.0) as u8;
+                [b, b, b, 255]
+            } else {
+                [255, 0, 0, 255]
+            };
+        }
+    }
+    out.into_iter().flatten().collect()
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 fn run2d<S: fidget::eval::Shape>(
@@ -311,20 +376,24 @@ fn main() -> Result<()> {
         Command::Render3d {
             settings,
             color,
+            shaded,
+            light,
             isometric,
         } => {
+            let light = shaded
+                .then(|| nalgebra::Vector3::new(light[0], light[1], light[2]));
             let start = Instant::now();
             let buffer = match settings.eval {
                 #[cfg(feature = "jit")]
                 EvalMode::Jit => {
                     let shape = fidget::jit::JitShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run3d(shape, &settings, isometric, color)
+                    run3d(shape, &settings, isometric, color, light)
                 }
                 EvalMode::Vm => {
                     let shape = fidget::vm::VmShape::new(&ctx, root)?;
                     info!("Built shape in {:?}", start.elapsed());
-                    run3d(shape, &settings, isometric, color)
+                    run3d(shape, &settings, isometric, color, light)
                 }
             };
             info!(
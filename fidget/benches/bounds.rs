@@ -0,0 +1,34 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+};
+
+pub fn loose_bounds_thread_sweep(c: &mut Criterion) {
+    // `x - x + 0.1` is positive everywhere, but its interval bounds are only
+    // tight enough to prove it after five levels of subdivision, so every
+    // sub-box must be checked.
+    let mut ctx = fidget::Context::new();
+    let x = ctx.x();
+    let zero = ctx.sub(x, x).unwrap();
+    let root = ctx.add(zero, 0.1).unwrap();
+    let ctx = &ctx;
+    let r = [-1.0, 1.0];
+
+    let mut group =
+        c.benchmark_group("speed vs threads (loose bounds, is_empty_in)");
+    group.bench_function(BenchmarkId::new("is_empty_in", "serial"), |b| {
+        b.iter(|| black_box(ctx.is_empty_in(root, r, r, r, 5).unwrap()))
+    });
+    for threads in [1, 2, 4, 8] {
+        group.bench_function(BenchmarkId::new("is_empty_in", threads), |b| {
+            b.iter(|| {
+                black_box(
+                    ctx.is_empty_in_parallel(root, r, r, r, 5, threads)
+                        .unwrap(),
+                )
+            })
+        });
+    }
+}
+
+criterion_group!(benches, loose_bounds_thread_sweep);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+};
+use fidget::eval::MathShape;
+
+const COLONNADE: &str = include_str!("../../models/colonnade.vm");
+
+pub fn colonnade_grid_thread_sweep(c: &mut Criterion) {
+    let (ctx, root) = fidget::Context::from_text(COLONNADE.as_bytes()).unwrap();
+    let shape_vm = &fidget::vm::VmShape::new(&ctx, root).unwrap();
+    #[cfg(feature = "jit")]
+    let shape_jit = &fidget::jit::JitShape::new(&ctx, root).unwrap();
+
+    let mut group =
+        c.benchmark_group("speed vs threads (colonnade, grid) (64^3)");
+    let cfg = &fidget::render::RenderConfig::<3> {
+        image_size: 64,
+        ..Default::default()
+    };
+    #[cfg(feature = "jit")]
+    group.bench_function(BenchmarkId::new("jit", "serial"), move |b| {
+        b.iter(|| black_box(fidget::render::sample_grid(shape_jit, cfg)))
+    });
+    group.bench_function(BenchmarkId::new("vm", "serial"), move |b| {
+        b.iter(|| black_box(fidget::render::sample_grid(shape_vm, cfg)))
+    });
+
+    for threads in [1, 4, 8] {
+        let cfg = &fidget::render::RenderConfig::<3> {
+            image_size: 64,
+            threads,
+            ..Default::default()
+        };
+        #[cfg(feature = "jit")]
+        group.bench_function(BenchmarkId::new("jit", threads), move |b| {
+            b.iter(|| {
+                black_box(fidget::render::sample_grid_parallel(shape_jit, cfg))
+            })
+        });
+        group.bench_function(BenchmarkId::new("vm", threads), move |b| {
+            b.iter(|| {
+                black_box(fidget::render::sample_grid_parallel(shape_vm, cfg))
+            })
+        });
+    }
+}
+
+criterion_group!(benches, colonnade_grid_thread_sweep);
+criterion_main!(benches);
@@ -289,6 +289,9 @@ impl<const N: usize> RegisterAllocator<N> {
             | SsaOp::ExpReg(..)
             | SsaOp::LnReg(..)
             | SsaOp::NotReg(..) => self.op_reg(op),
+            SsaOp::CustomReg(out, arg, id) => {
+                self.op_reg_fn(out, arg, |o, a| RegOp::CustomReg(o, a, id))
+            }
 
             SsaOp::AddRegImm(..)
             | SsaOp::SubRegImm(..)
@@ -1,3 +1,5 @@
+use crate::context::CustomOpId;
+
 /// Macro to generate a set of opcodes, using the given type for registers
 macro_rules! opcodes {
     (
@@ -72,6 +74,9 @@ macro_rules! opcodes {
             #[doc = "Copies the given register"]
             CopyReg($t, $t),
 
+            #[doc = "Applies a user-defined operation to the given register"]
+            CustomReg($t, $t, CustomOpId),
+
             #[doc = "Add a register and an immediate"]
             AddRegImm($t, $t, f32),
             #[doc = "Multiply a register and an immediate"]
@@ -170,6 +175,7 @@ impl SsaOp {
             | SsaOp::ExpReg(out, ..)
             | SsaOp::LnReg(out, ..)
             | SsaOp::NotReg(out, ..)
+            | SsaOp::CustomReg(out, ..)
             | SsaOp::AddRegImm(out, ..)
             | SsaOp::MulRegImm(out, ..)
             | SsaOp::DivRegImm(out, ..)
@@ -217,6 +223,7 @@ impl SsaOp {
             | SsaOp::ExpReg(..)
             | SsaOp::LnReg(..)
             | SsaOp::NotReg(..)
+            | SsaOp::CustomReg(..)
             | SsaOp::AddRegImm(..)
             | SsaOp::MulRegImm(..)
             | SsaOp::SubRegImm(..)
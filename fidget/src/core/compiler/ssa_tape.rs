@@ -34,6 +34,14 @@ pub struct SsaTape {
     /// This is an `Arc` so it can be trivially shared by all of the tape's
     /// descendents, since the variable array order does not change.
     pub vars: Arc<HashMap<String, u32>>,
+
+    /// Constants which were out of range for an `f32`
+    ///
+    /// These are stored in the tape as infinities, so evaluators may disagree
+    /// with [`Context::eval`] (which uses `f64`).  Values are the original
+    /// (finite) `f64` constants from the [`Context`]; see
+    /// [`Context::clamp_to_f32`] to clamp them before building a tape.
+    pub f32_overflows: Vec<f64>,
 }
 
 impl SsaTape {
@@ -46,6 +54,7 @@ impl SsaTape {
         let mut parent_count: HashMap<Node, usize> = HashMap::new();
         let mut var_names = HashMap::new();
         let mut slot_count = 0;
+        let mut f32_overflows = vec![];
 
         // Get either a node or constant index
         #[derive(Copy, Clone)]
@@ -64,7 +73,11 @@ impl SsaTape {
             let op = ctx.get_op(node).ok_or(Error::BadNode)?;
             let prev = match op {
                 Op::Const(c) => {
-                    mapping.insert(node, Slot::Immediate(c.0 as f32))
+                    let v = c.0 as f32;
+                    if v.is_infinite() && c.0.is_finite() {
+                        f32_overflows.push(c.0);
+                    }
+                    mapping.insert(node, Slot::Immediate(v))
                 }
                 _ => {
                     let i = slot_count;
@@ -235,6 +248,15 @@ impl SsaTape {
                     };
                     op(i, lhs)
                 }
+                Op::Custom(id, lhs) => {
+                    let lhs = match mapping[lhs] {
+                        Slot::Reg(r) => r,
+                        Slot::Immediate(..) => {
+                            panic!("Cannot handle f(imm)")
+                        }
+                    };
+                    SsaOp::CustomReg(i, lhs, *id)
+                }
             };
             tape.push(op);
         }
@@ -250,6 +272,7 @@ impl SsaTape {
             tape,
             choice_count,
             vars: Arc::new(var_names),
+            f32_overflows,
         })
     }
 
@@ -274,6 +297,7 @@ impl SsaTape {
     pub fn reset(&mut self) {
         self.tape.clear();
         self.choice_count = 0;
+        self.f32_overflows.clear();
     }
     /// Pretty-prints the given tape to `stdout`
     pub fn pretty_print(&self) {
@@ -390,6 +414,9 @@ impl SsaTape {
                 SsaOp::CopyImm(out, imm) => {
                     println!("${out} = COPY {imm}");
                 }
+                SsaOp::CustomReg(out, arg, id) => {
+                    println!("${out} = CUSTOM[{}] ${arg}", id.get().name());
+                }
             }
         }
     }
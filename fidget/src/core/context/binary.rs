@@ -0,0 +1,310 @@
+//! Compact binary import and export of math graphs
+use super::{
+    op::{BINARY_OPS, UNARY_OPS},
+    Context, Node, Op,
+};
+use crate::Error;
+use std::io::{Read, Write};
+
+/// Magic bytes at the start of every binary graph
+const MAGIC: &[u8; 4] = b"FGRF";
+
+/// Current version of the binary format
+const VERSION: u8 = 1;
+
+/// Tags for each kind of node
+mod tag {
+    pub const CONST: u8 = 0;
+    pub const X: u8 = 1;
+    pub const Y: u8 = 2;
+    pub const Z: u8 = 3;
+    pub const VAR: u8 = 4;
+    pub const UNARY: u8 = 5;
+    pub const BINARY: u8 = 6;
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, Error> {
+    let mut b = [0; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<u32, Error> {
+    let mut b = [0; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_f64<R: Read>(r: &mut R) -> Result<f64, Error> {
+    let mut b = [0; 8];
+    r.read_exact(&mut b)?;
+    Ok(f64::from_le_bytes(b))
+}
+
+impl Context {
+    /// Writes the graph rooted at the given node in a compact binary format
+    ///
+    /// This is faster to read and write than [`to_json`](Self::to_json), and
+    /// stores constants exactly.  The format is:
+    /// - The magic bytes `FGRF`, then a one-byte format version (currently 1)
+    /// - The number of nodes, as a `u32`
+    /// - Each node in topological order (children before parents), as a tag
+    ///   byte followed by its data:
+    ///     - `0`: constant, followed by its value as an `f64`
+    ///     - `1`, `2`, `3`: the X, Y, or Z axis
+    ///     - `4`: named variable, followed by its name (as a `u32` length, then
+    ///       UTF-8 bytes)
+    ///     - `5`: unary operation, followed by an opcode byte and a child index
+    ///     - `6`: binary operation, followed by an opcode byte and two child
+    ///       indices
+    /// - The index of the root node
+    ///
+    /// All integers and floats are little-endian, and indices are `u32`.
+    /// Opcodes are positions in the list of opcode names used by
+    /// [`to_json`](Self::to_json).  Only nodes which are reachable from `root`
+    /// are written.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// # let mut ctx = Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.add(x, 0.1).unwrap();
+    ///
+    /// let mut out = vec![];
+    /// ctx.save(op, &mut out).unwrap();
+    /// let (ctx2, op2) = Context::load(out.as_slice()).unwrap();
+    /// assert_eq!(
+    ///     ctx.eval_xyz(op, 0.2, 0.0, 0.0).unwrap(),
+    ///     ctx2.eval_xyz(op2, 0.2, 0.0, 0.0).unwrap(),
+    /// );
+    /// ```
+    pub fn save<W: Write>(&self, root: Node, mut w: W) -> Result<(), Error> {
+        let (order, index) = self.topological_order(root)?;
+        let write_index =
+            |w: &mut W, n: &Node| w.write_all(&(index[n] as u32).to_le_bytes());
+
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        w.write_all(&(order.len() as u32).to_le_bytes())?;
+        for node in &order {
+            match self.get_op(*node).unwrap() {
+                Op::Input(v) => {
+                    let t = match self.vars.get_by_index(*v).unwrap().as_str() {
+                        "X" => tag::X,
+                        "Y" => tag::Y,
+                        "Z" => tag::Z,
+                        s => panic!("unexpected input {s}"),
+                    };
+                    w.write_all(&[t])?;
+                }
+                Op::Var(v) => {
+                    let name = self.vars.get_by_index(*v).unwrap();
+                    w.write_all(&[tag::VAR])?;
+                    w.write_all(&(name.len() as u32).to_le_bytes())?;
+                    w.write_all(name.as_bytes())?;
+                }
+                Op::Const(c) => {
+                    w.write_all(&[tag::CONST])?;
+                    w.write_all(&c.0.to_le_bytes())?;
+                }
+                Op::Unary(op, a) => {
+                    let i =
+                        UNARY_OPS.iter().position(|(o, _)| o == op).unwrap();
+                    w.write_all(&[tag::UNARY, i as u8])?;
+                    write_index(&mut w, a)?;
+                }
+                Op::Binary(op, a, b) => {
+                    let i =
+                        BINARY_OPS.iter().position(|(o, _)| o == op).unwrap();
+                    w.write_all(&[tag::BINARY, i as u8])?;
+                    write_index(&mut w, a)?;
+                    write_index(&mut w, b)?;
+                }
+                // Custom operation handles are only meaningful within the
+                // current process, so they can't be serialized
+                Op::Custom(..) => return Err(Error::UnsupportedOp("custom")),
+            }
+        }
+        write_index(&mut w, &root)?;
+        Ok(())
+    }
+
+    /// Reads a graph written by [`save`](Self::save)
+    ///
+    /// Returns a new context and the root node.  Each node may only refer to
+    /// nodes before it; otherwise, this returns [`Error::BadNode`].
+    pub fn load<R: Read>(mut r: R) -> Result<(Self, Node), Error> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        let version = read_u8(&mut r)?;
+        if version != VERSION {
+            return Err(Error::BadVersion(version));
+        }
+
+        let mut ctx = Self::new();
+        let count = read_u32(&mut r)? as usize;
+        let mut nodes: Vec<Node> = vec![];
+        let read_node = |r: &mut R, nodes: &[Node]| {
+            let i = read_u32(r)? as usize;
+            nodes.get(i).cloned().ok_or(Error::BadNode)
+        };
+        for _ in 0..count {
+            let node = match read_u8(&mut r)? {
+                tag::CONST => ctx.constant(read_f64(&mut r)?),
+                tag::X => ctx.x(),
+                tag::Y => ctx.y(),
+                tag::Z => ctx.z(),
+                tag::VAR => {
+                    let len = read_u32(&mut r)? as usize;
+                    let mut name = vec![];
+                    r.by_ref().take(len as u64).read_to_end(&mut name)?;
+                    if name.len() != len {
+                        return Err(std::io::Error::from(
+                            std::io::ErrorKind::UnexpectedEof,
+                        )
+                        .into());
+                    }
+                    let name =
+                        String::from_utf8(name).map_err(|_| Error::BadVar)?;
+                    ctx.var(&name)?
+                }
+                tag::UNARY => {
+                    let i = read_u8(&mut r)?;
+                    let (op, _) =
+                        UNARY_OPS.get(i as usize).ok_or_else(|| {
+                            Error::UnknownOpcode(format!("unary opcode {i}"))
+                        })?;
+                    let a = read_node(&mut r, &nodes)?;
+                    ctx.op_unary(a, *op)?
+                }
+                tag::BINARY => {
+                    let i = read_u8(&mut r)?;
+                    let (op, _) =
+                        BINARY_OPS.get(i as usize).ok_or_else(|| {
+                            Error::UnknownOpcode(format!("binary opcode {i}"))
+                        })?;
+                    let a = read_node(&mut r, &nodes)?;
+                    let b = read_node(&mut r, &nodes)?;
+                    ctx.op_binary(a, b, *op)?
+                }
+                t => return Err(Error::UnknownOpcode(format!("tag {t}"))),
+            };
+            nodes.push(node);
+        }
+        let root = read_node(&mut r, &nodes)?;
+        Ok((ctx, root))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COLONNADE: &str = include_str!("../../../../models/colonnade.vm");
+
+    #[test]
+    fn test_binary_round_trip() {
+        let (ctx, root) = Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let mut out = vec![];
+        ctx.save(root, &mut out).unwrap();
+        let (ctx2, root2) = Context::load(out.as_slice()).unwrap();
+        assert_eq!(ctx.len(), ctx2.len());
+
+        for i in 0..8 {
+            for j in 0..8 {
+                let x = i as f64 / 4.0 - 1.0 + 0.01;
+                let y = j as f64 / 4.0 - 1.0 + 0.03;
+                let z = x * y;
+                let a = ctx.eval_xyz(root, x, y, z).unwrap();
+                let b = ctx2.eval_xyz(root2, x, y, z).unwrap();
+                assert_eq!(
+                    a.to_bits(),
+                    b.to_bits(),
+                    "mismatch at {x}, {y}, {z}"
+                );
+            }
+        }
+
+        // Saving again produces the same bytes
+        let mut again = vec![];
+        ctx2.save(root2, &mut again).unwrap();
+        assert_eq!(out, again);
+    }
+
+    #[test]
+    fn test_binary_vars() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let a = ctx.var("radius").unwrap();
+        let c = ctx.constant(std::f64::consts::PI);
+        let out = ctx.mul(x, a).unwrap();
+        let out = ctx.sin(out).unwrap();
+        let out = ctx.sub(out, c).unwrap();
+
+        let mut data = vec![];
+        ctx.save(out, &mut data).unwrap();
+        let (mut ctx2, root2) = Context::load(data.as_slice()).unwrap();
+
+        let vars = [("X", 0.3), ("radius", 1.7)]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect();
+        assert_eq!(
+            ctx.eval(out, &vars).unwrap().to_bits(),
+            ctx2.eval(root2, &vars).unwrap().to_bits()
+        );
+        assert_eq!(ctx2.var("radius").unwrap(), ctx2.var("radius").unwrap());
+    }
+
+    #[test]
+    fn test_binary_errors() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let out = ctx.add(x, y).unwrap();
+        let mut good = vec![];
+        ctx.save(out, &mut good).unwrap();
+        assert!(Context::load(good.as_slice()).is_ok());
+
+        let mut bad = good.clone();
+        bad[0] = b'X';
+        assert!(matches!(
+            Context::load(bad.as_slice()),
+            Err(Error::BadMagic)
+        ));
+
+        let mut bad = good.clone();
+        bad[4] = 2;
+        assert!(matches!(
+            Context::load(bad.as_slice()),
+            Err(Error::BadVersion(2))
+        ));
+
+        // Truncated data
+        for n in 0..good.len() {
+            assert!(Context::load(&good[..n]).is_err());
+        }
+
+        // The root node index is out of range
+        let mut bad = good.clone();
+        let n = bad.len();
+        bad[n - 4..].copy_from_slice(&3u32.to_le_bytes());
+        assert!(matches!(Context::load(bad.as_slice()), Err(Error::BadNode)));
+
+        // The add node refers to itself
+        let mut bad = good.clone();
+        bad[n - 8..n - 4].copy_from_slice(&2u32.to_le_bytes());
+        assert!(matches!(Context::load(bad.as_slice()), Err(Error::BadNode)));
+
+        // Unknown opcode
+        let mut bad = good;
+        bad[n - 13] = 200;
+        assert!(matches!(
+            Context::load(bad.as_slice()),
+            Err(Error::UnknownOpcode(..))
+        ));
+    }
+}
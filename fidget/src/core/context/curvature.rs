@@ -0,0 +1,314 @@
+//! Second-order evaluation, used to compute surface curvature
+use super::{BinaryOpcode, Context, IndexVec, Node, Op, UnaryOpcode};
+use crate::Error;
+
+/// Value, gradient, and Hessian of an expression with respect to `X, Y, Z`
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Jet {
+    v: f64,
+    g: [f64; 3],
+    h: [[f64; 3]; 3],
+}
+
+impl Jet {
+    fn constant(v: f64) -> Self {
+        Self {
+            v,
+            g: [0.0; 3],
+            h: [[0.0; 3]; 3],
+        }
+    }
+
+    fn axis(v: f64, i: usize) -> Self {
+        let mut out = Self::constant(v);
+        out.g[i] = 1.0;
+        out
+    }
+
+    /// Applies a scalar function, given its first and second derivatives
+    fn chain(self, v: f64, d1: f64, d2: f64) -> Self {
+        let mut out = Self::constant(v);
+        for i in 0..3 {
+            out.g[i] = d1 * self.g[i];
+            for j in 0..3 {
+                out.h[i][j] = d1 * self.h[i][j] + d2 * self.g[i] * self.g[j];
+            }
+        }
+        out
+    }
+
+    /// Computes `a * self + b * other`
+    fn lin(self, a: f64, other: Self, b: f64) -> Self {
+        let mut out = Self::constant(a * self.v + b * other.v);
+        for i in 0..3 {
+            out.g[i] = a * self.g[i] + b * other.g[i];
+            for j in 0..3 {
+                out.h[i][j] = a * self.h[i][j] + b * other.h[i][j];
+            }
+        }
+        out
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let mut out = self.lin(other.v, other, self.v);
+        out.v = self.v * other.v;
+        for i in 0..3 {
+            for j in 0..3 {
+                out.h[i][j] += self.g[i] * other.g[j] + other.g[i] * self.g[j];
+            }
+        }
+        out
+    }
+
+    fn recip(self) -> Self {
+        let v = 1.0 / self.v;
+        self.chain(v, -v * v, 2.0 * v * v * v)
+    }
+}
+
+impl Context {
+    /// Evaluates the mean curvature of a node's isosurface at a point
+    ///
+    /// The node is evaluated with second-order automatic differentiation, and
+    /// the mean curvature is computed from its gradient `g` and Hessian `H` as
+    /// `(|g|² tr(H) - gᵀ H g) / (2 |g|³)`, i.e. half the divergence of the
+    /// normalized gradient.  Convex regions of a shape (where the value
+    /// increases outwards) have positive curvature, so a sphere of radius `r`
+    /// has mean curvature `1 / r`.  The point doesn't need to lie on the
+    /// surface; if the gradient is zero, the result is `NaN`.
+    ///
+    /// Non-differentiable operations (`min`, `max`, `abs`, etc.) use the
+    /// same subgradient conventions as [`Grad`](crate::types::Grad), and
+    /// second derivatives are zero except where they're smooth.
+    ///
+    /// Like [`eval`](Self::eval), this is inefficient; the graph may only
+    /// use the `X`, `Y`, and `Z` variables.  [Custom
+    /// operations](super::CustomOp) don't provide second derivatives, so
+    /// graphs which use them return [`Error::UnsupportedOp`].
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let y2 = ctx.square(y).unwrap();
+    /// let r2 = ctx.add(x2, y2).unwrap();
+    /// let r = ctx.sqrt(r2).unwrap();
+    /// let circle = ctx.sub(r, 2.0).unwrap();
+    ///
+    /// // An infinite cylinder of radius 2 is only curved in one direction
+    /// let k = ctx.eval_curvature(circle, 2.0, 0.0, 0.0).unwrap();
+    /// assert!((k - 0.25).abs() < 1e-9);
+    /// ```
+    pub fn eval_curvature(
+        &self,
+        root: Node,
+        x: f64,
+        y: f64,
+        z: f64,
+    ) -> Result<f64, Error> {
+        let mut cache = vec![None; self.ops.len()].into();
+        let j = self.jet_inner(root, [x, y, z], &mut cache)?;
+
+        let g = j.g;
+        let norm2: f64 = g.iter().map(|v| v * v).sum();
+        let trace: f64 = (0..3).map(|i| j.h[i][i]).sum();
+        if norm2 == 0.0 {
+            return Ok(f64::NAN);
+        }
+        let mut ghg = 0.0;
+        for (i, gi) in g.iter().enumerate() {
+            for (k, gk) in g.iter().enumerate() {
+                ghg += gi * j.h[i][k] * gk;
+            }
+        }
+        Ok((norm2 * trace - ghg) / (2.0 * norm2 * norm2.sqrt()))
+    }
+
+    fn jet_inner(
+        &self,
+        node: Node,
+        pos: [f64; 3],
+        cache: &mut IndexVec<Option<Jet>, Node>,
+    ) -> Result<Jet, Error> {
+        if node.0 >= cache.len() {
+            return Err(Error::BadNode);
+        }
+        if let Some(v) = cache[node] {
+            return Ok(v);
+        }
+        let mut get = |n: Node| self.jet_inner(n, pos, cache);
+        let v = match self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Var(v) | Op::Input(v) => {
+                let var_name = self.vars.get_by_index(*v).unwrap();
+                match var_name.as_str() {
+                    "X" => Jet::axis(pos[0], 0),
+                    "Y" => Jet::axis(pos[1], 1),
+                    "Z" => Jet::axis(pos[2], 2),
+                    _ => return Err(Error::UnknownVariable(var_name.clone())),
+                }
+            }
+            Op::Const(c) => Jet::constant(c.0),
+
+            Op::Binary(op, a, b) => {
+                let a = get(*a)?;
+                let b = get(*b)?;
+                match op {
+                    BinaryOpcode::Add => a.lin(1.0, b, 1.0),
+                    BinaryOpcode::Sub => a.lin(1.0, b, -1.0),
+                    BinaryOpcode::Mul => a.mul(b),
+                    BinaryOpcode::Div => a.mul(b.recip()),
+                    BinaryOpcode::Min => {
+                        if a.v <= b.v || b.v.is_nan() {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                    BinaryOpcode::Max => {
+                        if a.v >= b.v || b.v.is_nan() {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                    BinaryOpcode::Compare => Jet::constant(
+                        a.v.partial_cmp(&b.v)
+                            .map(|i| i as i8 as f64)
+                            .unwrap_or(f64::NAN),
+                    ),
+                    BinaryOpcode::Mod => {
+                        // a - b * floor(a / b), with a locally constant floor
+                        let mut out = a.lin(1.0, b, -(a.v / b.v).floor());
+                        out.v = a.v.rem_euclid(b.v);
+                        out
+                    }
+                    BinaryOpcode::And => {
+                        if a.v == 0.0 {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                    BinaryOpcode::Or => {
+                        if a.v != 0.0 {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                }
+            }
+
+            // Unary operations
+            Op::Unary(op, a) => {
+                let a = get(*a)?;
+                let v = a.v;
+                match op {
+                    UnaryOpcode::Neg => a.chain(-v, -1.0, 0.0),
+                    UnaryOpcode::Abs => {
+                        let s = if v == 0.0 { 0.0 } else { v.signum() };
+                        a.chain(v.abs(), s, 0.0)
+                    }
+                    UnaryOpcode::Recip => a.recip(),
+                    UnaryOpcode::Sqrt => {
+                        let s = v.sqrt();
+                        a.chain(s, 0.5 / s, -0.25 / (s * v))
+                    }
+                    UnaryOpcode::Square => a.chain(v * v, 2.0 * v, 2.0),
+                    UnaryOpcode::Sin => a.chain(v.sin(), v.cos(), -v.sin()),
+                    UnaryOpcode::Cos => a.chain(v.cos(), -v.sin(), -v.cos()),
+                    UnaryOpcode::Tan => {
+                        let t = v.tan();
+                        let d = 1.0 + t * t;
+                        a.chain(t, d, 2.0 * t * d)
+                    }
+                    UnaryOpcode::Asin => {
+                        let d = 1.0 - v * v;
+                        a.chain(v.asin(), d.powf(-0.5), v * d.powf(-1.5))
+                    }
+                    UnaryOpcode::Acos => {
+                        let d = 1.0 - v * v;
+                        a.chain(v.acos(), -d.powf(-0.5), -v * d.powf(-1.5))
+                    }
+                    UnaryOpcode::Atan => {
+                        let d = 1.0 / (1.0 + v * v);
+                        a.chain(v.atan(), d, -2.0 * v * d * d)
+                    }
+                    UnaryOpcode::Exp => {
+                        let e = v.exp();
+                        a.chain(e, e, e)
+                    }
+                    UnaryOpcode::Ln => a.chain(v.ln(), 1.0 / v, -1.0 / (v * v)),
+                    UnaryOpcode::Not => Jet::constant((v == 0.0).into()),
+                }
+            }
+            Op::Custom(..) => return Err(Error::UnsupportedOp("custom")),
+        };
+
+        cache[node] = Some(v);
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::test_sphere;
+
+    #[test]
+    fn test_sphere_curvature() {
+        for r in [0.5, 1.0, 3.0] {
+            let mut ctx = Context::new();
+            let s = test_sphere(&mut ctx, [0.0; 3], r);
+            for p in [
+                [r, 0.0, 0.0],
+                [0.0, -r, 0.0],
+                [0.0, 0.0, r],
+                [r / 3f64.sqrt(), r / 3f64.sqrt(), -r / 3f64.sqrt()],
+            ] {
+                let k = ctx.eval_curvature(s, p[0], p[1], p[2]).unwrap();
+                assert!(
+                    (k - 1.0 / r).abs() < 1e-9,
+                    "{k} != {} at {p:?}",
+                    1.0 / r
+                );
+            }
+
+            // The negated sphere (i.e. a spherical cavity) is concave
+            let n = ctx.neg(s).unwrap();
+            let k = ctx.eval_curvature(n, r, 0.0, 0.0).unwrap();
+            assert!((k + 1.0 / r).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_curvature_scaled() {
+        // Scaling the SDF doesn't change its isosurface's curvature, even
+        // though it changes the second derivatives
+        let mut ctx = Context::new();
+        let s = test_sphere(&mut ctx, [0.0; 3], 2.0);
+        let s2 = ctx.mul(s, 5.0).unwrap();
+        let e = ctx.exp(s2).unwrap();
+        let k = ctx.eval_curvature(e, 0.0, 2.0, 0.0).unwrap();
+        assert!((k - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_curvature_flat() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let plane = ctx.add(x, y).unwrap();
+        assert_eq!(ctx.eval_curvature(plane, 1.0, 2.0, 3.0).unwrap(), 0.0);
+
+        let c = ctx.constant(1.0);
+        assert!(ctx.eval_curvature(c, 0.0, 0.0, 0.0).unwrap().is_nan());
+
+        let a = ctx.var("a").unwrap();
+        assert!(matches!(
+            ctx.eval_curvature(a, 0.0, 0.0, 0.0),
+            Err(Error::UnknownVariable(..))
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+//! User-defined operations, dispatched through a global registry
+use crate::types::{Grad, Interval};
+use std::sync::{Arc, RwLock};
+
+/// A user-defined single-argument operation
+///
+/// Implementations are registered with [`CustomOpId::register`], then used in
+/// a graph with [`Context::custom`](crate::Context::custom).  The VM
+/// evaluators dispatch through the registry at runtime; JIT evaluators can't
+/// call back into arbitrary Rust code, so building a JIT function from a graph
+/// with custom operations returns [`Error::UnsupportedOp`](crate::Error).
+pub trait CustomOp: Send + Sync {
+    /// Returns a short name, used when printing graphs
+    fn name(&self) -> &str;
+
+    /// Evaluates the operation at a single value
+    fn eval(&self, x: f64) -> f64;
+
+    /// Evaluates the operation over an interval
+    ///
+    /// The result must contain every value of [`eval`](Self::eval) for inputs
+    /// within `x`, or interval-based pruning will produce incorrect results.
+    fn eval_interval(&self, x: Interval) -> Interval;
+
+    /// Evaluates the operation and its partial derivatives
+    ///
+    /// The input's partial derivatives should be propagated by the chain rule,
+    /// i.e. multiplied by the operation's derivative at `x.v`.
+    fn eval_grad(&self, x: Grad) -> Grad;
+}
+
+/// Handle to a [`CustomOp`] in the global registry
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct CustomOpId(u32);
+
+static REGISTRY: RwLock<Vec<Arc<dyn CustomOp>>> = RwLock::new(Vec::new());
+
+impl CustomOpId {
+    /// Adds an operation to the global registry, returning its handle
+    ///
+    /// Registered operations live for the rest of the program, and their
+    /// handles are valid in every [`Context`](crate::Context).
+    pub fn register(op: Box<dyn CustomOp>) -> Self {
+        let mut r = REGISTRY.write().unwrap();
+        let id = Self(r.len().try_into().unwrap());
+        r.push(Arc::from(op));
+        id
+    }
+
+    /// Looks up the operation associated with this handle
+    pub fn get(&self) -> Arc<dyn CustomOp> {
+        REGISTRY.read().unwrap()[self.0 as usize].clone()
+    }
+
+    /// Returns the raw index of this handle in the registry
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
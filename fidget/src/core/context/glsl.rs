@@ -0,0 +1,252 @@
+//! Export of math graphs as GLSL source
+use super::{BinaryOpcode, Context, Node, Op, UnaryOpcode};
+use crate::Error;
+use std::fmt::Write;
+
+/// Formats a constant as a GLSL `float` expression
+fn float_literal(c: f64) -> String {
+    let c = c as f32;
+    if c.is_finite() {
+        // Debug formatting always includes a decimal point or exponent
+        format!("{c:?}")
+    } else {
+        // GLSL has no literals for infinity or NaN
+        format!("uintBitsToFloat({:#x}u)", c.to_bits())
+    }
+}
+
+/// Checks whether a variable name can be used as a GLSL uniform
+///
+/// Names beginning with an underscore are reserved for local variables.
+fn is_uniform_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !s.starts_with("gl_")
+        && !s.contains("__")
+        && s != "p"
+        && s != "f"
+}
+
+impl Context {
+    /// Converts the given node into GLSL source
+    ///
+    /// The output declares a function `float f(vec3 p)`, which evaluates the
+    /// node at the point `p` and is compatible with GLSL ES 3.00 (i.e. WebGL
+    /// 2, with `#version 300 es`).  Each node is computed once, as a local
+    /// `float` variable, in topological order.  Variables other than X, Y, and
+    /// Z are declared as `uniform float` values before the function, and must
+    /// have names which are valid GLSL identifiers (not beginning with an
+    /// underscore, and not `p` or `f`); checking that they aren't GLSL
+    /// keywords is left to the caller.
+    ///
+    /// Division, `min`, and `max` map directly to their GLSL equivalents.
+    /// Results may differ from Fidget's evaluators when values are `NaN`,
+    /// because GLSL doesn't guarantee `NaN` propagation.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let r = ctx.var("r").unwrap();
+    /// let out = ctx.sub(x, r).unwrap();
+    /// assert_eq!(
+    ///     ctx.to_glsl(out).unwrap(),
+    ///     "uniform float r;
+    /// float f(vec3 p) {
+    ///     float _v0 = p.x;
+    ///     float _v1 = r;
+    ///     float _v2 = _v0 - _v1;
+    ///     return _v2;
+    /// }
+    /// "
+    /// );
+    /// ```
+    pub fn to_glsl(&self, root: Node) -> Result<String, Error> {
+        let (order, index) = self.topological_order(root)?;
+        let name = |n: &Node| format!("_v{}", index[n]);
+
+        let mut uniforms = String::new();
+        let mut body = String::new();
+        for (i, node) in order.iter().enumerate() {
+            let expr = match self.get_op(*node).unwrap() {
+                Op::Input(v) => {
+                    match self.vars.get_by_index(*v).unwrap().as_str() {
+                        "X" => "p.x".to_owned(),
+                        "Y" => "p.y".to_owned(),
+                        "Z" => "p.z".to_owned(),
+                        s => panic!("unexpected input {s}"),
+                    }
+                }
+                Op::Var(v) => {
+                    let s = self.vars.get_by_index(*v).unwrap();
+                    if !is_uniform_name(s) {
+                        return Err(Error::BadGlslName(s.clone()));
+                    }
+                    writeln!(uniforms, "uniform float {s};").unwrap();
+                    s.clone()
+                }
+                Op::Const(c) => float_literal(c.0),
+                Op::Unary(op, a) => {
+                    let a = name(a);
+                    match op {
+                        UnaryOpcode::Neg => format!("-{a}"),
+                        UnaryOpcode::Abs => format!("abs({a})"),
+                        UnaryOpcode::Recip => format!("1.0 / {a}"),
+                        UnaryOpcode::Sqrt => format!("sqrt({a})"),
+                        UnaryOpcode::Square => format!("{a} * {a}"),
+                        UnaryOpcode::Sin => format!("sin({a})"),
+                        UnaryOpcode::Cos => format!("cos({a})"),
+                        UnaryOpcode::Tan => format!("tan({a})"),
+                        UnaryOpcode::Asin => format!("asin({a})"),
+                        UnaryOpcode::Acos => format!("acos({a})"),
+                        UnaryOpcode::Atan => format!("atan({a})"),
+                        UnaryOpcode::Exp => format!("exp({a})"),
+                        UnaryOpcode::Ln => format!("log({a})"),
+                        UnaryOpcode::Not => format!("{a} == 0.0 ? 1.0 : 0.0"),
+                    }
+                }
+                Op::Binary(op, a, b) => {
+                    let (a, b) = (name(a), name(b));
+                    match op {
+                        BinaryOpcode::Add => format!("{a} + {b}"),
+                        BinaryOpcode::Sub => format!("{a} - {b}"),
+                        BinaryOpcode::Mul => format!("{a} * {b}"),
+                        BinaryOpcode::Div => format!("{a} / {b}"),
+                        BinaryOpcode::Min => format!("min({a}, {b})"),
+                        BinaryOpcode::Max => format!("max({a}, {b})"),
+                        BinaryOpcode::Compare => format!(
+                            "{a} < {b} ? -1.0 : ({a} > {b} ? 1.0 : 0.0)"
+                        ),
+                        // GLSL's `mod` takes the sign of the divisor, so use
+                        // its absolute value for the least non-negative
+                        // remainder
+                        BinaryOpcode::Mod => format!("mod({a}, abs({b}))"),
+                        BinaryOpcode::And => {
+                            format!("{a} == 0.0 ? {a} : {b}")
+                        }
+                        BinaryOpcode::Or => format!("{a} != 0.0 ? {a} : {b}"),
+                    }
+                }
+                Op::Custom(..) => return Err(Error::UnsupportedOp("custom")),
+            };
+            writeln!(body, "    float _v{i} = {expr};").unwrap();
+        }
+
+        Ok(format!(
+            "{uniforms}float f(vec3 p) {{\n{body}    return {};\n}}\n",
+            name(&root)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Checks that every local is declared before it's used, and that every
+    /// other identifier is a known GLSL builtin or a declared uniform
+    fn check_identifiers(src: &str) {
+        const BUILTINS: &str = "uniform float vec3 f p x y z return abs sqrt \
+            sin cos tan asin acos atan exp log min max mod uintBitsToFloat";
+        let mut declared: Vec<String> = vec![];
+        for line in src.lines() {
+            let words: Vec<&str> = line
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .filter(|w| {
+                    w.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                })
+                .collect();
+            let new = match words.as_slice() {
+                ["uniform", "float", name] => Some(name),
+                ["float", name, ..] if name.starts_with("_v") => Some(name),
+                _ => None,
+            };
+            for w in &words {
+                assert!(
+                    BUILTINS.split_whitespace().any(|b| b == *w)
+                        || declared.iter().any(|d| d == w)
+                        || Some(w) == new,
+                    "undeclared identifier {w} in {line:?}"
+                );
+            }
+            if let Some(name) = new {
+                assert!(!declared.iter().any(|d| d == name));
+                // A local may not refer to itself
+                assert_eq!(words.iter().filter(|w| *w == name).count(), 1);
+                declared.push(name.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_glsl() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let r = ctx.var("radius").unwrap();
+        let mut nodes = vec![x, y, z, r];
+        for op in [
+            UnaryOpcode::Neg,
+            UnaryOpcode::Abs,
+            UnaryOpcode::Recip,
+            UnaryOpcode::Sqrt,
+            UnaryOpcode::Square,
+            UnaryOpcode::Sin,
+            UnaryOpcode::Cos,
+            UnaryOpcode::Tan,
+            UnaryOpcode::Asin,
+            UnaryOpcode::Acos,
+            UnaryOpcode::Atan,
+            UnaryOpcode::Exp,
+            UnaryOpcode::Ln,
+            UnaryOpcode::Not,
+        ] {
+            nodes.push(ctx.op_unary(x, op).unwrap());
+        }
+        for op in [
+            BinaryOpcode::Add,
+            BinaryOpcode::Sub,
+            BinaryOpcode::Mul,
+            BinaryOpcode::Div,
+            BinaryOpcode::Min,
+            BinaryOpcode::Max,
+            BinaryOpcode::Compare,
+            BinaryOpcode::Mod,
+            BinaryOpcode::And,
+            BinaryOpcode::Or,
+        ] {
+            nodes.push(ctx.op_binary(y, r, op).unwrap());
+        }
+        for c in [0.5, -2.0, 1e-30, f64::INFINITY, f64::NAN] {
+            nodes.push(ctx.constant(c));
+        }
+        let mut sum = z;
+        for n in nodes {
+            sum = ctx.add(sum, n).unwrap();
+        }
+
+        let src = ctx.to_glsl(sum).unwrap();
+        check_identifiers(&src);
+        assert!(src.starts_with("uniform float radius;\nfloat f(vec3 p) {\n"));
+        assert_eq!(src.matches("uniform").count(), 1);
+        for s in [
+            " / _v",
+            "= min(_v",
+            "= max(_v",
+            "= mod(_v",
+            "= 0.5;",
+            "= -2.0;",
+            "= 1e-30;",
+            "= uintBitsToFloat(0x7f800000u);",
+            "= uintBitsToFloat(0x7fc00000u);",
+        ] {
+            assert!(src.contains(s), "missing {s:?} in\n{src}");
+        }
+
+        let bad = ctx.var("gl_Position").unwrap();
+        assert!(matches!(ctx.to_glsl(bad), Err(Error::BadGlslName(..))));
+        let bad = ctx.var("_v0").unwrap();
+        assert!(matches!(ctx.to_glsl(bad), Err(Error::BadGlslName(..))));
+    }
+}
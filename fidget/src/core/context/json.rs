@@ -0,0 +1,317 @@
+//! JSON import and export of math graphs
+use super::{
+    op::{BINARY_OPS, UNARY_OPS},
+    Context, Node, Op,
+};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// Serialized graph, as written by [`Context::to_json`]
+#[derive(Deserialize)]
+struct JsonGraph {
+    /// Nodes in topological order (children before parents)
+    nodes: Vec<JsonNode>,
+    /// Index of the root node in `nodes`
+    root: usize,
+    /// Map from variable name to node index
+    vars: BTreeMap<String, usize>,
+}
+
+/// A single node in a [`JsonGraph`]
+#[derive(Serialize, Deserialize)]
+struct JsonNode {
+    op: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    children: Vec<usize>,
+    #[serde(
+        rename = "const",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    value: Option<JsonConst>,
+}
+
+/// A constant value
+///
+/// JSON numbers can't represent infinities or `NaN`, so those are stored as
+/// the strings `"inf"`, `"-inf"`, and `"nan"`.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum JsonConst {
+    Number(f64),
+    Special(String),
+}
+
+impl From<f64> for JsonConst {
+    fn from(v: f64) -> Self {
+        if v.is_nan() {
+            JsonConst::Special("nan".to_owned())
+        } else if v == f64::INFINITY {
+            JsonConst::Special("inf".to_owned())
+        } else if v == f64::NEG_INFINITY {
+            JsonConst::Special("-inf".to_owned())
+        } else {
+            JsonConst::Number(v)
+        }
+    }
+}
+
+impl TryFrom<&JsonConst> for f64 {
+    type Error = Error;
+    fn try_from(v: &JsonConst) -> Result<Self, Error> {
+        match v {
+            JsonConst::Number(v) => Ok(*v),
+            JsonConst::Special(s) => match s.as_str() {
+                "nan" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                s => Err(Error::BadConstant(s.to_owned())),
+            },
+        }
+    }
+}
+
+impl Context {
+    /// Writes the graph rooted at the given node as JSON
+    ///
+    /// The output is an object with three fields:
+    /// - `nodes` is an array of nodes in topological order (children before
+    ///   parents).  Each node has an `op` field, using the same opcode names
+    ///   as [`from_text`](Self::from_text) (plus `recip` and `var` for named
+    ///   variables), a `children` array of node indices (for unary and binary
+    ///   operations), and a `const` value (for constants).  Constants which
+    ///   aren't finite are written as the strings `"inf"`, `"-inf"`, or
+    ///   `"nan"`.
+    /// - `root` is the index of the root node
+    /// - `vars` maps from variable name to node index
+    ///
+    /// Only nodes which are reachable from `root` are written.  Nodes are
+    /// streamed to `w` one at a time, so consider wrapping it in a
+    /// [`BufWriter`](std::io::BufWriter).
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.add(x, 1.0).unwrap();
+    ///
+    /// let mut out = vec![];
+    /// ctx.to_json(op, &mut out).unwrap();
+    /// assert_eq!(
+    ///     std::str::from_utf8(&out).unwrap(),
+    ///     r#"{"nodes":[{"op":"var-x"},{"op":"const","const":1.0},"#.to_owned()
+    ///         + r#"{"op":"add","children":[0,1]}],"root":2,"vars":{}}"#
+    /// );
+    /// ```
+    pub fn to_json<W: Write>(&self, root: Node, mut w: W) -> Result<(), Error> {
+        let (order, index) = self.topological_order(root)?;
+
+        // Custom operation handles are only meaningful within the current
+        // process, so they can't be serialized.  Check for them before writing
+        // anything, so that we don't leave a partial graph in `w`.
+        if order
+            .iter()
+            .any(|n| matches!(self.get_op(*n), Some(Op::Custom(..))))
+        {
+            return Err(Error::UnsupportedOp("custom"));
+        }
+
+        // Nodes are written one at a time, rather than building the whole
+        // graph in memory first
+        let mut vars = BTreeMap::new();
+        w.write_all(br#"{"nodes":["#)?;
+        for (i, node) in order.iter().enumerate() {
+            let op = self.get_op(*node).unwrap();
+            let children = op.iter_children().map(|c| index[&c]).collect();
+            let (op, value) = match op {
+                Op::Input(v) => {
+                    let name = self.vars.get_by_index(*v).unwrap();
+                    (format!("var-{}", name.to_lowercase()), None)
+                }
+                Op::Var(v) => {
+                    let name = self.vars.get_by_index(*v).unwrap();
+                    vars.insert(name.clone(), index[node]);
+                    ("var".to_owned(), None)
+                }
+                Op::Const(c) => ("const".to_owned(), Some(c.0.into())),
+                Op::Unary(op, ..) => {
+                    let (_, name) =
+                        UNARY_OPS.iter().find(|(o, _)| o == op).unwrap();
+                    (name.to_string(), None)
+                }
+                Op::Binary(op, ..) => {
+                    let (_, name) =
+                        BINARY_OPS.iter().find(|(o, _)| o == op).unwrap();
+                    (name.to_string(), None)
+                }
+                Op::Custom(..) => unreachable!(),
+            };
+            if i > 0 {
+                w.write_all(b",")?;
+            }
+            let node = JsonNode {
+                op,
+                children,
+                value,
+            };
+            serde_json::to_writer(&mut w, &node)?;
+        }
+        write!(w, r#"],"root":{},"vars":"#, index[&root])?;
+        serde_json::to_writer(&mut w, &vars)?;
+        w.write_all(b"}")?;
+        Ok(())
+    }
+
+    /// Reads a graph written by [`to_json`](Self::to_json)
+    ///
+    /// Returns a new context and the root node.  Children must precede their
+    /// parents in the `nodes` array; otherwise, this returns
+    /// [`Error::BadNode`].
+    pub fn from_json<R: Read>(r: R) -> Result<(Self, Node), Error> {
+        let graph: JsonGraph = serde_json::from_reader(r)?;
+        let var_names: BTreeMap<usize, &str> =
+            graph.vars.iter().map(|(k, v)| (*v, k.as_str())).collect();
+
+        let mut ctx = Self::new();
+        let mut nodes: Vec<Node> = Vec::with_capacity(graph.nodes.len());
+        for (i, n) in graph.nodes.iter().enumerate() {
+            let child = |j: usize| -> Result<Node, Error> {
+                let c = *n.children.get(j).ok_or(Error::BadNode)?;
+                nodes.get(c).cloned().ok_or(Error::BadNode)
+            };
+            let arity = |k: usize| {
+                if n.children.len() == k {
+                    Ok(())
+                } else {
+                    Err(Error::BadNode)
+                }
+            };
+            let node = match n.op.as_str() {
+                "const" => {
+                    arity(0)?;
+                    let v = n.value.as_ref().ok_or(Error::BadNode)?;
+                    ctx.constant(v.try_into()?)
+                }
+                "var-x" => {
+                    arity(0)?;
+                    ctx.x()
+                }
+                "var-y" => {
+                    arity(0)?;
+                    ctx.y()
+                }
+                "var-z" => {
+                    arity(0)?;
+                    ctx.z()
+                }
+                "var" => {
+                    arity(0)?;
+                    let name = var_names.get(&i).ok_or(Error::BadVar)?;
+                    ctx.var(name)?
+                }
+                op => {
+                    if let Some((u, _)) =
+                        UNARY_OPS.iter().find(|(_, name)| *name == op)
+                    {
+                        arity(1)?;
+                        ctx.op_unary(child(0)?, *u)?
+                    } else if let Some((b, _)) =
+                        BINARY_OPS.iter().find(|(_, name)| *name == op)
+                    {
+                        arity(2)?;
+                        ctx.op_binary(child(0)?, child(1)?, *b)?
+                    } else {
+                        return Err(Error::UnknownOpcode(op.to_owned()));
+                    }
+                }
+            };
+            nodes.push(node);
+        }
+        let root = *nodes.get(graph.root).ok_or(Error::BadNode)?;
+        Ok((ctx, root))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COLONNADE: &str = include_str!("../../../../models/colonnade.vm");
+
+    #[test]
+    fn test_json_round_trip() {
+        let (ctx, root) = Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let mut out = vec![];
+        ctx.to_json(root, &mut out).unwrap();
+        let (ctx2, root2) = Context::from_json(out.as_slice()).unwrap();
+        assert_eq!(ctx.len(), ctx2.len());
+
+        for i in 0..8 {
+            for j in 0..8 {
+                let x = i as f64 / 4.0 - 1.0;
+                let y = j as f64 / 4.0 - 1.0;
+                let z = x * y;
+                let a = ctx.eval_xyz(root, x, y, z).unwrap();
+                let b = ctx2.eval_xyz(root2, x, y, z).unwrap();
+                assert_eq!(a, b, "mismatch at {x}, {y}, {z}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_vars() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let a = ctx.var("a").unwrap();
+        let inf = ctx.constant(f64::INFINITY);
+        let out = ctx.mul(x, a).unwrap();
+        let out = ctx.recip(out).unwrap();
+        let out = ctx.min(out, inf).unwrap();
+
+        let mut json = vec![];
+        ctx.to_json(out, &mut json).unwrap();
+        let (mut ctx2, root2) = Context::from_json(json.as_slice()).unwrap();
+
+        let vars = [("X", 2.0), ("a", 3.0)]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect();
+        assert_eq!(ctx.eval(out, &vars).unwrap(), 1.0 / 6.0);
+        assert_eq!(ctx2.eval(root2, &vars).unwrap(), 1.0 / 6.0);
+        assert_eq!(ctx2.var("a").unwrap(), ctx2.var("a").unwrap());
+    }
+
+    #[test]
+    fn test_json_errors() {
+        let bad = [
+            r#"{"nodes":[{"op":"sin","children":[1]},{"op":"var-x"}],"#
+                .to_owned()
+                + r#""root":0,"vars":{}}"#,
+            r#"{"nodes":[{"op":"var-x"}],"root":1,"vars":{}}"#.to_owned(),
+            r#"{"nodes":[{"op":"var"}],"root":0,"vars":{}}"#.to_owned(),
+            r#"{"nodes":[{"op":"add","children":[]}],"root":0,"vars":{}}"#
+                .to_owned(),
+        ];
+        for b in bad {
+            assert!(Context::from_json(b.as_bytes()).is_err(), "{b}");
+        }
+        assert!(matches!(
+            Context::from_json(
+                r#"{"nodes":[{"op":"cube"}],"root":0,"vars":{}}"#.as_bytes()
+            ),
+            Err(Error::UnknownOpcode(..))
+        ));
+        assert!(matches!(
+            Context::from_json(
+                r#"{"nodes":[{"op":"const","const":"big"}],"root":0,"vars":{}}"#
+                    .as_bytes()
+            ),
+            Err(Error::BadConstant(s)) if s == "big"
+        ));
+        assert!(matches!(
+            Context::from_json("{".as_bytes()),
+            Err(Error::JsonError(..))
+        ));
+    }
+}
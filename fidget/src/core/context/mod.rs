@@ -1,17 +1,32 @@
 //! Infrastructure for representing math expressions as graphs
+mod binary;
+mod curvature;
+mod custom;
+mod glsl;
 mod indexed;
+#[cfg(feature = "json")]
+mod json;
+mod monotonic;
 mod op;
+mod quality;
+mod random;
+mod scad;
 
 #[cfg(test)]
 pub(crate) mod bound;
 
+pub use custom::{CustomOp, CustomOpId};
 use indexed::{define_index, Index, IndexMap, IndexVec};
+pub use monotonic::Monotonicity;
 pub use op::{BinaryOpcode, Op, UnaryOpcode};
+pub use quality::IntervalQuality;
+pub(crate) use random::Rng;
 
 use crate::Error;
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet};
 use std::fmt::Write;
+use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader, Read};
 
 use ordered_float::OrderedFloat;
@@ -30,6 +45,199 @@ pub struct Context {
     vars: IndexMap<String, VarNode>,
 }
 
+/// Position of a point relative to a shape, returned by [`Context::classify`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Classification {
+    /// The point is inside the shape, farther than the tolerance from its
+    /// surface
+    Inside,
+    /// The point is within the tolerance of the surface
+    Surface,
+    /// The point is outside the shape, farther than the tolerance from its
+    /// surface
+    Outside,
+}
+
+/// LaTeX precedence levels, used by [`Context::to_latex`] to decide where
+/// parentheses are needed
+const LATEX_ADD: u8 = 0;
+const LATEX_MUL: u8 = 1;
+const LATEX_ATOM: u8 = 2;
+
+/// A macro declared in the [`Context::from_text`] format
+#[derive(Debug)]
+struct TextMacro {
+    /// Number of arguments
+    arg_count: usize,
+    /// Body of the macro
+    body: TextExpr,
+    /// Line on which the macro is defined, used for error reporting
+    line: TextLine,
+}
+
+/// An expression in the body of a [`TextMacro`]
+#[derive(Debug)]
+enum TextExpr {
+    /// Constant value
+    Const(f64),
+    /// Argument to the macro, by index
+    Arg(usize),
+    /// Call to an opcode or another macro
+    Call {
+        /// Name of the opcode or macro
+        name: String,
+        /// Byte position of the name within the definition line
+        pos: usize,
+        /// Arguments to the call
+        args: Vec<TextExpr>,
+    },
+}
+
+impl TextExpr {
+    /// Parses an expression from a list of tokens, advancing `i`
+    ///
+    /// Tokens are slices of `line`'s text; names in `args` are parsed as
+    /// arguments, numbers as constants, and any other name as a call (with
+    /// parentheses if it has arguments).
+    fn parse(
+        line: &TextLine,
+        tokens: &[&str],
+        i: &mut usize,
+        args: &[&str],
+    ) -> Result<Self, Error> {
+        let Some(&t) = tokens.get(*i) else {
+            return Err(line.eol());
+        };
+        *i += 1;
+        if matches!(t, "(" | ")" | ",") {
+            let e = Error::ExpectedToken("expression", t.to_owned());
+            return Err(line.error(line.pos(t), e));
+        }
+        if tokens.get(*i) != Some(&"(") {
+            if let Some(a) = args.iter().position(|a| *a == t) {
+                return Ok(Self::Arg(a));
+            } else if let Ok(v) = t.parse() {
+                return Ok(Self::Const(v));
+            }
+        }
+        let mut out = vec![];
+        if tokens.get(*i) == Some(&"(") {
+            *i += 1;
+            if tokens.get(*i) == Some(&")") {
+                *i += 1;
+            } else {
+                loop {
+                    out.push(Self::parse(line, tokens, i, args)?);
+                    match tokens.get(*i) {
+                        Some(&",") => *i += 1,
+                        Some(&")") => {
+                            *i += 1;
+                            break;
+                        }
+                        Some(t) => {
+                            let e = Error::ExpectedToken(
+                                "`,` or `)`",
+                                t.to_string(),
+                            );
+                            return Err(line.error(line.pos(t), e));
+                        }
+                        None => return Err(line.eol()),
+                    }
+                }
+            }
+        }
+        Ok(Self::Call {
+            name: t.to_owned(),
+            pos: line.pos(t),
+            args: out,
+        })
+    }
+
+    /// Checks whether this expression calls the given macro, either directly
+    /// or through other macros
+    fn calls(&self, name: &str, macros: &BTreeMap<String, TextMacro>) -> bool {
+        match self {
+            Self::Const(..) | Self::Arg(..) => false,
+            Self::Call { name: n, args, .. } => {
+                n == name
+                    || macros.get(n).is_some_and(|m| m.body.calls(name, macros))
+                    || args.iter().any(|a| a.calls(name, macros))
+            }
+        }
+    }
+}
+
+/// Splits text into name tokens and the punctuation `(`, `)`, and `,`
+///
+/// Tokens are slices of the input; a `#` begins a comment, which continues to
+/// the end of the text.
+fn text_tokens(text: &str) -> Vec<&str> {
+    let text = text.split('#').next().unwrap();
+    let mut out = vec![];
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        let punct = matches!(c, '(' | ')' | ',');
+        if punct || c.is_whitespace() {
+            if let Some(s) = start.take() {
+                out.push(&text[s..i]);
+            }
+            if punct {
+                out.push(&text[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        out.push(&text[s..]);
+    }
+    out
+}
+
+/// A line in the [`Context::from_text`] format, with its location in the input
+#[derive(Debug)]
+struct TextLine {
+    /// Text of the line, without its line ending
+    text: String,
+    /// Line number (1-indexed)
+    line: usize,
+    /// Byte offset of the start of the line within the input
+    offset: usize,
+}
+
+impl TextLine {
+    /// Returns the byte position of a token (which must be a slice of `text`)
+    fn pos(&self, token: &str) -> usize {
+        token.as_ptr() as usize - self.text.as_ptr() as usize
+    }
+
+    /// Attaches a location (as a byte position in this line) to an error
+    ///
+    /// Errors which already have a location are returned unchanged.
+    fn error(&self, pos: usize, e: Error) -> Error {
+        if matches!(e, Error::ParseError { .. }) {
+            return e;
+        }
+        let prefix = &self.text[..pos];
+        let caret: String = prefix
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        Error::ParseError {
+            offset: self.offset + pos,
+            line: self.line,
+            column: prefix.chars().count() + 1,
+            snippet: format!("{}\n{caret}^", self.text),
+            source: Box::new(e),
+        }
+    }
+
+    /// Builds an error for a missing token at the end of the line
+    fn eol(&self) -> Error {
+        self.error(self.text.len(), Error::UnexpectedEndOfLine)
+    }
+}
+
 impl Context {
     /// Build a new empty context
     pub fn new() -> Self {
@@ -78,6 +286,37 @@ impl Context {
         self.get_op(node).ok_or(Error::BadNode).map(|_| ())
     }
 
+    /// Lists the nodes reachable from `root`, with children before parents
+    ///
+    /// Returns the list, and a map from each node to its position in the list.
+    fn topological_order(
+        &self,
+        root: Node,
+    ) -> Result<(Vec<Node>, BTreeMap<Node, usize>), Error> {
+        self.check_node(root)?;
+
+        // Depth-first recursion on the heap, to protect against stack overflows
+        let mut order = vec![];
+        let mut index = BTreeMap::new();
+        let mut todo = vec![(false, root)];
+        while let Some((up, node)) = todo.pop() {
+            if up {
+                if let std::collections::btree_map::Entry::Vacant(e) =
+                    index.entry(node)
+                {
+                    e.insert(order.len());
+                    order.push(node);
+                }
+            } else if !index.contains_key(&node) {
+                todo.push((true, node));
+                let op = self.get_op(node).unwrap();
+                let children: Vec<_> = op.iter_children().collect();
+                todo.extend(children.into_iter().rev().map(|c| (false, c)));
+            }
+        }
+        Ok((order, index))
+    }
+
     /// Erases the most recently added node from the tree.
     ///
     /// A few caveats apply, so this must be used with caution:
@@ -184,8 +423,17 @@ impl Context {
     /// Find or create a [Node] for the given unary operation, with constant
     /// folding.
     fn op_unary(&mut self, a: Node, op: UnaryOpcode) -> Result<Node, Error> {
+        self.op_unary_f(a, |arg| Op::Unary(op, arg))
+    }
+
+    /// Find or create a [Node] for a generic unary operation (represented by a
+    /// thunk), with constant folding.
+    fn op_unary_f<F>(&mut self, a: Node, f: F) -> Result<Node, Error>
+    where
+        F: Fn(Node) -> Op,
+    {
         let op_a = *self.get_op(a).ok_or(Error::BadNode)?;
-        let n = self.ops.insert(Op::Unary(op, a));
+        let n = self.ops.insert(f(a));
         let out = if matches!(op_a, Op::Const(_)) {
             let v = self.eval(n, &BTreeMap::new())?;
             self.pop().unwrap(); // removes `n`
@@ -537,6 +785,19 @@ impl Context {
         self.op_unary(a, UnaryOpcode::Ln)
     }
 
+    /// Builds a node which applies a user-defined operation to its input
+    ///
+    /// The operation must first be added to the registry with
+    /// [`CustomOpId::register`].
+    pub fn custom<A: IntoNode>(
+        &mut self,
+        id: CustomOpId,
+        a: A,
+    ) -> Result<Node, Error> {
+        let a = a.into_node(self)?;
+        self.op_unary_f(a, |arg| Op::Custom(id, arg))
+    }
+
     ////////////////////////////////////////////////////////////////////////////
     // Derived functions
     /// Builds a node which squares its input
@@ -637,171 +898,1045 @@ impl Context {
         self.op_binary(a, b, BinaryOpcode::Mod)
     }
 
-    ////////////////////////////////////////////////////////////////////////////
+    /// Builds a node which clamps `value` to the range `[lo, hi]`
+    ///
+    /// This is lowered to `max(min(value, hi), lo)`, so clamping the same
+    /// value to the same bounds twice returns the same node.  If `lo > hi`,
+    /// the result is `lo`.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.clamp(x, -1.0, 1.0).unwrap();
+    /// assert_eq!(ctx.eval_xyz(op, 0.5, 0.0, 0.0).unwrap(), 0.5);
+    /// assert_eq!(ctx.eval_xyz(op, 3.0, 0.0, 0.0).unwrap(), 1.0);
+    /// ```
+    pub fn clamp<A: IntoNode, B: IntoNode, C: IntoNode>(
+        &mut self,
+        value: A,
+        lo: B,
+        hi: C,
+    ) -> Result<Node, Error> {
+        let value = value.into_node(self)?;
+        let lo = lo.into_node(self)?;
+        let hi = hi.into_node(self)?;
+        let v = self.min(value, hi)?;
+        self.max(v, lo)
+    }
 
-    /// Remaps the X, Y, Z nodes to the given values
-    pub fn remap_xyz(
+    /// Builds a node which linearly interpolates from `a` to `b`
+    ///
+    /// This is lowered to `a + t * (b - a)`, which is `a` when `t = 0` and
+    /// `b` when `t = 1`; `t` isn't clamped, so other values extrapolate.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.mix(2.0, 4.0, x).unwrap();
+    /// assert_eq!(ctx.eval_xyz(op, 0.25, 0.0, 0.0).unwrap(), 2.5);
+    /// ```
+    pub fn mix<A: IntoNode, B: IntoNode, C: IntoNode>(
         &mut self,
-        root: Node,
-        xyz: [Node; 3],
+        a: A,
+        b: B,
+        t: C,
     ) -> Result<Node, Error> {
-        self.check_node(root)?;
-        xyz.iter().try_for_each(|x| self.check_node(*x))?;
+        let a = a.into_node(self)?;
+        let b = b.into_node(self)?;
+        let t = t.into_node(self)?;
+        let d = self.sub(b, a)?;
+        let d = self.mul(t, d)?;
+        self.add(a, d)
+    }
 
-        let mut done = [self.x(), self.y(), self.z()]
-            .into_iter()
-            .zip(xyz)
-            .collect::<BTreeMap<_, _>>();
+    /// Builds a metaball-style blend of many fields
+    ///
+    /// Each field `f_i` contributes `weight_i * falloff(f_i)`, where
+    /// `falloff(d) = 1 / (1 + d²)` is 1 on the field's surface and decays
+    /// smoothly with distance.  The result is `threshold - sum(...)`, which is
+    /// negative wherever the summed contributions exceed the threshold, so
+    /// nearby fields merge into a single blobby surface.
+    ///
+    /// The result is **not** a distance field: its magnitude doesn't measure
+    /// the distance to the surface, and its gradient isn't normalized.
+    ///
+    /// Returns `threshold` (as a constant) if `fields` is empty.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let a = ctx.sub(x, 1.0).unwrap();
+    /// let b = ctx.add(x, 1.0).unwrap();
+    /// let op = ctx.metaball(&[(a, 1.0), (b, 1.0)], 0.5).unwrap();
+    ///
+    /// // Between the two fields, their contributions add up
+    /// let v = ctx.eval_xyz(op, 0.0, 0.0, 0.0).unwrap();
+    /// assert_eq!(v, -0.5);
+    /// ```
+    pub fn metaball(
+        &mut self,
+        fields: &[(Node, f64)],
+        threshold: f64,
+    ) -> Result<Node, Error> {
+        let mut sum = None;
+        for &(f, weight) in fields {
+            let d2 = self.square(f)?;
+            let denom = self.add(d2, 1.0)?;
+            let falloff = self.div(weight, denom)?;
+            sum = Some(match sum {
+                Some(s) => self.add(s, falloff)?,
+                None => falloff,
+            });
+        }
+        match sum {
+            Some(s) => self.sub(threshold, s),
+            None => Ok(self.constant(threshold)),
+        }
+    }
 
-        // Depth-first recursion on the heap, to protect against stack overflows
-        enum Action {
-            Down,
-            Up,
+    /// Subtracts every shape in `tools` from `body`
+    ///
+    /// The result is `max(body, -min(tools...))`, where the `min` is built as
+    /// a balanced tree rather than a chain of `max(a, -b)` operations.  This
+    /// keeps the graph shallow for large numbers of tools, and gives tighter
+    /// results during interval evaluation.
+    ///
+    /// Returns `body` unchanged if `tools` is empty.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let body = ctx.sub(x, 10.0).unwrap(); // x < 10
+    /// let a = ctx.sub(x, 1.0).unwrap(); // x < 1
+    /// let b = ctx.sub(x, 2.0).unwrap(); // x < 2
+    /// let op = ctx.difference_all(body, &[a, b]).unwrap();
+    /// assert_eq!(ctx.eval_xyz(op, 0.0, 0.0, 0.0).unwrap(), 2.0);
+    /// assert_eq!(ctx.eval_xyz(op, 5.0, 0.0, 0.0).unwrap(), -3.0);
+    /// ```
+    pub fn difference_all(
+        &mut self,
+        body: Node,
+        tools: &[Node],
+    ) -> Result<Node, Error> {
+        self.check_node(body)?;
+        match self.min_balanced(tools)? {
+            Some(t) => {
+                let t = self.neg(t)?;
+                self.max(body, t)
+            }
+            None => Ok(body),
         }
+    }
 
-        let mut todo = vec![(Action::Down, root)];
-        let mut seen = BTreeSet::new();
-        while let Some((action, node)) = todo.pop() {
-            match action {
-                Action::Down => {
-                    if !seen.insert(node) {
-                        continue;
-                    }
-                    todo.push((Action::Up, node));
-                    todo.extend(
-                        self.get_op(node)
-                            .unwrap()
-                            .iter_children()
-                            .map(|c| (Action::Down, c)),
-                    );
-                }
-                Action::Up => {
-                    let r = match self.get_op(node).unwrap() {
-                        Op::Binary(op, lhs, rhs) => {
-                            let a = done.get(lhs).unwrap();
-                            let b = done.get(rhs).unwrap();
-                            self.op_binary(*a, *b, *op).unwrap()
-                        }
-                        Op::Unary(op, arg) => {
-                            let a = done.get(arg).unwrap();
-                            self.op_unary(*a, *op).unwrap()
-                        }
-                        Op::Var(..) | Op::Const(..) => node,
-                        Op::Input(..) => *done.get(&node).unwrap_or(&node),
-                    };
-                    done.insert(node, r);
-                }
+    /// Builds a balanced tree of `min` operations, or `None` if `nodes` is
+    /// empty
+    fn min_balanced(&mut self, nodes: &[Node]) -> Result<Option<Node>, Error> {
+        match nodes {
+            [] => Ok(None),
+            [n] => {
+                self.check_node(*n)?;
+                Ok(Some(*n))
+            }
+            _ => {
+                let (a, b) = nodes.split_at(nodes.len() / 2);
+                let a = self.min_balanced(a)?.unwrap();
+                let b = self.min_balanced(b)?.unwrap();
+                self.min(a, b).map(Some)
             }
         }
-        Ok(*done.get(&root).unwrap())
     }
 
-    ////////////////////////////////////////////////////////////////////////////
-    /// Evaluates the given node with the provided values for X, Y, and Z.
+    /// Builds a piecewise function from a list of regions
     ///
-    /// This is extremely inefficient; consider converting the node into a
-    /// [`Shape`](crate::eval::Shape) and using its evaluators instead.
+    /// Each region is a `(condition, expr)` pair, and is active wherever its
+    /// condition is negative.  At each point, the result is the expression of
+    /// the first active region, or `otherwise` if no region is active.
     ///
+    /// The regions are lowered to nested selects (built from `compare`, `and`,
+    /// and `or`), which can be simplified by a tracing evaluator.  Nothing
+    /// checks that the pieces agree at their boundaries; use
+    /// [`Context::piecewise_discontinuity`] to test that numerically.
     /// ```
     /// # let mut ctx = fidget::context::Context::new();
     /// let x = ctx.x();
-    /// let y = ctx.y();
-    /// let z = ctx.z();
-    /// let op = ctx.mul(x, y).unwrap();
-    /// let op = ctx.div(op, z).unwrap();
-    /// let v = ctx.eval_xyz(op, 3.0, 5.0, 2.0).unwrap();
-    /// assert_eq!(v, 7.5); // (3.0 * 5.0) / 2.0
+    /// let neg_x = ctx.neg(x).unwrap();
+    /// let op = ctx.piecewise(&[(x, neg_x)], x).unwrap(); // |x|
+    /// assert_eq!(ctx.eval_xyz(op, -2.0, 0.0, 0.0).unwrap(), 2.0);
+    /// assert_eq!(ctx.eval_xyz(op, 3.0, 0.0, 0.0).unwrap(), 3.0);
     /// ```
-    pub fn eval_xyz(
-        &self,
-        root: Node,
-        x: f64,
-        y: f64,
-        z: f64,
-    ) -> Result<f64, Error> {
-        let vars = [("X", x), ("Y", y), ("Z", z)]
-            .into_iter()
-            .map(|(a, b)| (a.to_string(), b))
-            .collect();
-        self.eval(root, &vars)
+    pub fn piecewise(
+        &mut self,
+        regions: &[(Node, Node)],
+        otherwise: Node,
+    ) -> Result<Node, Error> {
+        self.check_node(otherwise)?;
+        let mut out = otherwise;
+        for &(cond, expr) in regions.iter().rev() {
+            // `inside` is -1 where the condition is negative and 0 elsewhere
+            let cmp = self.compare(cond, 0.0)?;
+            let inside = self.min(cmp, 0.0)?;
+            let outside = self.not(inside)?;
+            let a = self.and(inside, expr)?;
+            let b = self.and(outside, out)?;
+            out = self.or(a, b)?;
+        }
+        Ok(out)
     }
 
-    /// Evaluates the given node with a generic set of variables
+    /// Measures the largest jump at the boundaries of a piecewise function
     ///
-    /// This is extremely inefficient; consider converting the node into a
-    /// [`Shape`](crate::eval::Shape) and using its evaluators instead.
-    pub fn eval(
-        &self,
-        root: Node,
-        vars: &BTreeMap<String, f64>,
-    ) -> Result<f64, Error> {
-        let mut cache = vec![None; self.ops.len()].into();
-        self.eval_inner(root, vars, &mut cache)
-    }
-
-    fn eval_inner(
+    /// `regions` and `otherwise` have the same meaning as in
+    /// [`Context::piecewise`].  The function is sampled on a grid with `n`
+    /// cells along each axis of `bounds` (given as `[min, max]` for X, Y, and
+    /// Z); wherever a region's condition changes sign along a grid edge, the
+    /// boundary point is found by bisection and the two pieces that meet
+    /// there are compared.
+    ///
+    /// Returns the largest absolute difference found, which is (close to)
+    /// zero if the function is C0-continuous within the sampled region.
+    pub fn piecewise_discontinuity(
         &self,
-        node: Node,
-        vars: &BTreeMap<String, f64>,
-        cache: &mut IndexVec<Option<f64>, Node>,
+        regions: &[(Node, Node)],
+        otherwise: Node,
+        bounds: [[f64; 2]; 3],
+        n: usize,
     ) -> Result<f64, Error> {
-        if node.0 >= cache.len() {
-            return Err(Error::BadNode);
-        }
-        if let Some(v) = cache[node] {
-            return Ok(v);
-        }
-        let mut get = |n: Node| self.eval_inner(n, vars, cache);
-        let v = match self.get_op(node).ok_or(Error::BadNode)? {
-            Op::Var(v) | Op::Input(v) => {
-                let var_name = self.vars.get_by_index(*v).unwrap();
-                *vars.get(var_name).unwrap()
+        let eval = |node, p: [f64; 3]| self.eval_xyz(node, p[0], p[1], p[2]);
+        let inside = |node, p| eval(node, p).map(|v| v < 0.0);
+
+        // Evaluates the piecewise function built from `regions[start..]`
+        let eval_from = |start: usize, p| {
+            for &(cond, expr) in &regions[start..] {
+                if inside(cond, p)? {
+                    return eval(expr, p);
+                }
             }
-            Op::Const(c) => c.0,
+            eval(otherwise, p)
+        };
 
-            Op::Binary(op, a, b) => {
-                let a = get(*a)?;
-                let b = get(*b)?;
-                match op {
-                    BinaryOpcode::Add => a + b,
-                    BinaryOpcode::Sub => a - b,
-                    BinaryOpcode::Mul => a * b,
-                    BinaryOpcode::Div => a / b,
-                    BinaryOpcode::Min => a.min(b),
-                    BinaryOpcode::Max => a.max(b),
-                    BinaryOpcode::Compare => a
-                        .partial_cmp(&b)
-                        .map(|i| i as i8 as f64)
-                        .unwrap_or(f64::NAN),
-                    BinaryOpcode::Mod => a.rem_euclid(b),
-                    BinaryOpcode::And => {
-                        if a == 0.0 {
-                            a
-                        } else {
-                            b
-                        }
+        let n = n.max(1);
+        let point = |i: [usize; 3]| {
+            [0, 1, 2].map(|k| {
+                let [lo, hi] = bounds[k];
+                lo + (hi - lo) * i[k] as f64 / n as f64
+            })
+        };
+        let mut worst = 0.0f64;
+        for (r, &(cond, expr)) in regions.iter().enumerate() {
+            for index in 0..(n + 1).pow(3) {
+                let i = [
+                    index % (n + 1),
+                    index / (n + 1) % (n + 1),
+                    index / (n + 1).pow(2),
+                ];
+                for axis in 0..3 {
+                    if i[axis] == n {
+                        continue;
                     }
-                    BinaryOpcode::Or => {
-                        if a != 0.0 {
-                            a
+                    let mut j = i;
+                    j[axis] += 1;
+                    let (mut lo, mut hi) = (point(i), point(j));
+                    let lo_inside = inside(cond, lo)?;
+                    if lo_inside == inside(cond, hi)? {
+                        continue;
+                    }
+
+                    // Bisect to find the boundary point
+                    for _ in 0..48 {
+                        let mid = [0, 1, 2].map(|k| (lo[k] + hi[k]) / 2.0);
+                        if inside(cond, mid)? == lo_inside {
+                            lo = mid;
                         } else {
-                            b
+                            hi = mid;
                         }
                     }
+
+                    // Skip boundaries which are hidden by an earlier region
+                    let mut hidden = false;
+                    for &(prev, _) in &regions[..r] {
+                        hidden |= inside(prev, lo)?;
+                    }
+                    if !hidden {
+                        let d = eval(expr, lo)? - eval_from(r + 1, lo)?;
+                        worst = worst.max(d.abs());
+                    }
                 }
             }
+        }
+        Ok(worst)
+    }
 
-            // Unary operations
-            Op::Unary(op, a) => {
-                let a = get(*a)?;
-                match op {
-                    UnaryOpcode::Neg => -a,
-                    UnaryOpcode::Abs => a.abs(),
-                    UnaryOpcode::Recip => 1.0 / a,
-                    UnaryOpcode::Sqrt => a.sqrt(),
-                    UnaryOpcode::Square => a * a,
-                    UnaryOpcode::Sin => a.sin(),
-                    UnaryOpcode::Cos => a.cos(),
+    ////////////////////////////////////////////////////////////////////////////
+
+    /// Remaps the X, Y, Z nodes to the given values
+    pub fn remap_xyz(
+        &mut self,
+        root: Node,
+        xyz: [Node; 3],
+    ) -> Result<Node, Error> {
+        self.check_node(root)?;
+        xyz.iter().try_for_each(|x| self.check_node(*x))?;
+
+        let done = [self.x(), self.y(), self.z()]
+            .into_iter()
+            .zip(xyz)
+            .collect::<BTreeMap<_, _>>();
+        Ok(self.remap(root, done))
+    }
+
+    /// Twists a shape around the Z axis
+    ///
+    /// Each horizontal slice of the shape is rotated counterclockwise (seen
+    /// from above) by an angle of `k * z` radians, so the twist accumulates
+    /// with height.  This is built with [`remap_xyz`](Self::remap_xyz); the
+    /// deformation stretches space away from the Z axis, so the result is no
+    /// longer a true distance field (distances are overestimated by up to a
+    /// factor of `sqrt(1 + (k * r)²)` at radius `r`).
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.twist(x, std::f64::consts::FRAC_PI_2).unwrap();
+    ///
+    /// // At z = 1, the half-space x < 0 has rotated by 90° to become y < 0
+    /// let v = ctx.eval_xyz(op, 0.0, -1.0, 1.0).unwrap();
+    /// assert!((v + 1.0).abs() < 1e-12);
+    /// ```
+    pub fn twist(&mut self, root: Node, k: f64) -> Result<Node, Error> {
+        let x = self.x();
+        let y = self.y();
+        let z = self.z();
+        let angle = self.mul(z, k)?;
+        let (nx, ny) = self.rotate_xy(x, y, angle)?;
+        self.remap_xyz(root, [nx, ny, z])
+    }
+
+    /// Bends a shape around the Z axis
+    ///
+    /// Each point is rotated about the Z axis by an angle of `k * x` radians,
+    /// which bends the shape's X axis onto the curve `y = -x tan(k x)`.  Like
+    /// [`twist`](Self::twist), this distorts the distance field, increasingly
+    /// so far from the origin.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let y = ctx.y();
+    /// let op = ctx.bend(y, 0.5).unwrap();
+    ///
+    /// // The shape is unchanged on the Y axis
+    /// assert_eq!(ctx.eval_xyz(op, 0.0, 2.0, 0.0).unwrap(), 2.0);
+    /// ```
+    pub fn bend(&mut self, root: Node, k: f64) -> Result<Node, Error> {
+        let x = self.x();
+        let y = self.y();
+        let z = self.z();
+        let angle = self.mul(x, -k)?;
+        let (nx, ny) = self.rotate_xy(x, y, angle)?;
+        self.remap_xyz(root, [nx, ny, z])
+    }
+
+    /// Mirrors a shape across the YZ plane
+    ///
+    /// X is remapped to `abs(x)`, so the half of the shape with `x > 0` is
+    /// kept and reflected onto the `x < 0` side; this is a convenient way to
+    /// build symmetric models from a half-definition.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.sub(x, 1.0).unwrap();
+    /// let op = ctx.mirror_x(op).unwrap();
+    /// assert_eq!(ctx.eval_xyz(op, -3.0, 0.0, 0.0).unwrap(), 2.0);
+    /// ```
+    pub fn mirror_x(&mut self, root: Node) -> Result<Node, Error> {
+        let x = self.x();
+        let y = self.y();
+        let z = self.z();
+        let ax = self.abs(x)?;
+        self.remap_xyz(root, [ax, y, z])
+    }
+
+    /// Mirrors a shape across the XZ plane
+    ///
+    /// This is the equivalent of [`mirror_x`](Self::mirror_x), remapping Y to
+    /// `abs(y)`.
+    pub fn mirror_y(&mut self, root: Node) -> Result<Node, Error> {
+        let x = self.x();
+        let y = self.y();
+        let z = self.z();
+        let ay = self.abs(y)?;
+        self.remap_xyz(root, [x, ay, z])
+    }
+
+    /// Mirrors a shape across the XY plane
+    ///
+    /// This is the equivalent of [`mirror_x`](Self::mirror_x), remapping Z to
+    /// `abs(z)`.
+    pub fn mirror_z(&mut self, root: Node) -> Result<Node, Error> {
+        let x = self.x();
+        let y = self.y();
+        let z = self.z();
+        let az = self.abs(z)?;
+        self.remap_xyz(root, [x, y, az])
+    }
+
+    /// Builds a node which computes the four-quadrant arctangent of `y / x`
+    ///
+    /// The result is in the range `[-π, π]`, and is `NaN` if both arguments
+    /// are zero.  This is built from [`atan`](Self::atan), with the quadrant
+    /// correction lowered to a [`piecewise`](Self::piecewise) select.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let op = ctx.atan2(y, x).unwrap();
+    /// let v = ctx.eval_xyz(op, -1.0, -1.0, 0.0).unwrap();
+    /// assert!((v + 3.0 * std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+    /// ```
+    pub fn atan2<A: IntoNode, B: IntoNode>(
+        &mut self,
+        y: A,
+        x: B,
+    ) -> Result<Node, Error> {
+        use std::f64::consts::PI;
+        let y = y.into_node(self)?;
+        let x = x.into_node(self)?;
+        let a = self.div(y, x)?;
+        let a = self.atan(a)?;
+        let below = self.sub(a, PI)?;
+        let above = self.add(a, PI)?;
+        let left = self.piecewise(&[(y, below)], above)?;
+        self.piecewise(&[(x, left)], a)
+    }
+
+    /// Repeats a wedge of a shape `n` times around the Z axis
+    ///
+    /// The XY plane is folded into the wedge `-π / n <= θ < π / n` (centered
+    /// on the +X axis) by remapping each point's polar angle with
+    /// [`atan2`](Self::atan2) and [`modulo`](Self::modulo), so whatever part
+    /// of the shape lies in that wedge is repeated with `n`-fold rotational
+    /// symmetry.  Like the mirror functions, this preserves distances within
+    /// the wedge, but can underestimate them across its boundaries.
+    ///
+    /// If `n` is 0 or 1, the shape is returned unchanged.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.sub(x, 1.0).unwrap();
+    /// let op = ctx.radial_symmetry(op, 4).unwrap();
+    /// let v = ctx.eval_xyz(op, 0.0, -3.0, 0.0).unwrap();
+    /// assert!((v - 2.0).abs() < 1e-12);
+    /// ```
+    pub fn radial_symmetry(
+        &mut self,
+        root: Node,
+        n: usize,
+    ) -> Result<Node, Error> {
+        self.check_node(root)?;
+        if n <= 1 {
+            return Ok(root);
+        }
+        let wedge = std::f64::consts::TAU / n as f64;
+
+        let x = self.x();
+        let y = self.y();
+        let z = self.z();
+        let angle = self.atan2(y, x)?;
+        let angle = self.add(angle, wedge / 2.0)?;
+        let angle = self.modulo(angle, wedge)?;
+        let angle = self.sub(angle, wedge / 2.0)?;
+
+        let x2 = self.square(x)?;
+        let y2 = self.square(y)?;
+        let r = self.add(x2, y2)?;
+        let r = self.sqrt(r)?;
+        let c = self.cos(angle)?;
+        let s = self.sin(angle)?;
+        let nx = self.mul(r, c)?;
+        let ny = self.mul(r, s)?;
+
+        // The angle is `NaN` at the origin, so return `r = 0` there instead
+        let nx = self.and(r, nx)?;
+        let ny = self.and(r, ny)?;
+        self.remap_xyz(root, [nx, ny, z])
+    }
+
+    /// Builds the exact 2D distance to the line segment from `a` to `b`
+    ///
+    /// The result is `length(pa - ba * clamp(dot(pa, ba) / dot(ba, ba), 0, 1))`
+    /// (with `pa = (x, y) - a` and `ba = b - a`), which is zero on the
+    /// segment and positive everywhere else; subtract a radius to get a
+    /// stroke with rounded ends.  It does not depend on Z.
+    ///
+    /// If `a` and `b` are the same point, this is the distance to that point.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let op = ctx.segment2d([0.0, 0.0], [2.0, 0.0]).unwrap();
+    /// assert_eq!(ctx.eval_xyz(op, 1.0, 3.0, 0.0).unwrap(), 3.0);
+    /// assert_eq!(ctx.eval_xyz(op, 5.0, 4.0, 0.0).unwrap(), 5.0);
+    /// ```
+    pub fn segment2d(
+        &mut self,
+        a: [f64; 2],
+        b: [f64; 2],
+    ) -> Result<Node, Error> {
+        let x = self.x();
+        let y = self.y();
+        let pax = self.sub(x, a[0])?;
+        let pay = self.sub(y, a[1])?;
+        let (bax, bay) = (b[0] - a[0], b[1] - a[1]);
+
+        // dot(ba, ba) is a constant, so it's folded into the projection
+        let len2 = bax * bax + bay * bay;
+        let (dx, dy) = if len2 > 0.0 {
+            let tx = self.mul(pax, bax / len2)?;
+            let ty = self.mul(pay, bay / len2)?;
+            let t = self.add(tx, ty)?;
+            let t = self.max(t, 0.0)?;
+            let t = self.min(t, 1.0)?;
+            let ox = self.mul(t, bax)?;
+            let oy = self.mul(t, bay)?;
+            (self.sub(pax, ox)?, self.sub(pay, oy)?)
+        } else {
+            (pax, pay)
+        };
+        let dx2 = self.square(dx)?;
+        let dy2 = self.square(dy)?;
+        let r = self.add(dx2, dy2)?;
+        self.sqrt(r)
+    }
+
+    /// Builds expressions for `(x, y)` rotated clockwise by `angle`
+    ///
+    /// Sampling a shape at the clockwise-rotated point rotates the shape
+    /// itself counterclockwise.
+    fn rotate_xy(
+        &mut self,
+        x: Node,
+        y: Node,
+        angle: Node,
+    ) -> Result<(Node, Node), Error> {
+        let c = self.cos(angle)?;
+        let s = self.sin(angle)?;
+        let xc = self.mul(x, c)?;
+        let ys = self.mul(y, s)?;
+        let xs = self.mul(x, s)?;
+        let yc = self.mul(y, c)?;
+        Ok((self.add(xc, ys)?, self.sub(yc, xs)?))
+    }
+
+    /// Replaces a variable with a constant value, returning a new root
+    ///
+    /// `var` may be a named variable (from [`Context::var`]) or one of the
+    /// X, Y, Z inputs.  Operations which only depend on constants after the
+    /// substitution are folded, so the resulting graph is specialized for the
+    /// given value (e.g. to render a fixed Z slice of a 3D model).  The
+    /// original graph is left unchanged.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let z = ctx.z();
+    /// let z2 = ctx.square(z).unwrap();
+    /// let op = ctx.add(x, z2).unwrap();
+    ///
+    /// let slice = ctx.bind_var(op, z, 3.0).unwrap();
+    /// assert_eq!(ctx.eval_xyz(slice, 1.0, 0.0, 100.0).unwrap(), 10.0);
+    /// ```
+    pub fn bind_var(
+        &mut self,
+        root: Node,
+        var: Node,
+        value: f64,
+    ) -> Result<Node, Error> {
+        self.check_node(root)?;
+        match self.get_op(var).ok_or(Error::BadNode)? {
+            Op::Var(..) | Op::Input(..) => (),
+            _ => return Err(Error::BadVar),
+        }
+        let c = self.constant(value);
+        Ok(self.remap(root, [(var, c)].into_iter().collect()))
+    }
+
+    /// Applies a function to every constant in a graph, returning a new root
+    ///
+    /// Every constant reachable from `root` is replaced by `f` applied to its
+    /// value, and the graph is rebuilt (and deduplicated) around the new
+    /// constants.  The original graph is left unchanged.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.mul(x, 3.0).unwrap();
+    /// let op = ctx.add(op, 1.0).unwrap();
+    ///
+    /// let scaled = ctx.map_constants(op, |c| c * 2.0).unwrap();
+    /// assert_eq!(ctx.eval_xyz(scaled, 1.0, 0.0, 0.0).unwrap(), 8.0);
+    /// ```
+    pub fn map_constants<F: Fn(f64) -> f64>(
+        &mut self,
+        root: Node,
+        f: F,
+    ) -> Result<Node, Error> {
+        self.check_node(root)?;
+        let mut todo = vec![root];
+        let mut seen = BTreeSet::new();
+        let mut consts = vec![];
+        while let Some(node) = todo.pop() {
+            if !seen.insert(node) {
+                continue;
+            }
+            let op = self.get_op(node).unwrap();
+            if let Op::Const(c) = op {
+                consts.push((node, c.0));
+            }
+            todo.extend(op.iter_children());
+        }
+        let done = consts
+            .into_iter()
+            .map(|(node, c)| (node, self.constant(f(c))))
+            .collect();
+        Ok(self.remap(root, done))
+    }
+
+    /// Simplifies the graph at `root` with constant folding and identities
+    ///
+    /// The builder methods (e.g. [`add`](Self::add)) already fold constants
+    /// and apply identities, but graphs which are rebuilt or loaded in other
+    /// ways (e.g. by [`map_constants`](Self::map_constants) or
+    /// [`from_json`](Self::from_json)) may still contain operations such as
+    /// `x * 1`.  This rebuilds the graph, folding every operation whose
+    /// arguments are all constants, and applying the identities `x + 0 = x`,
+    /// `x - 0 = x`, `x * 1 = x`, `x / 1 = x`, `-(-x) = x`, and
+    /// `min(x, x) = max(x, x) = x`.
+    ///
+    /// Only rewrites which are exact for every `f64` value (treating `-0.0`
+    /// and `0.0` as equal, like the builders) are applied, so the result
+    /// evaluates to the same value as the original at every point; notably,
+    /// `x * 0` isn't folded, because it's `NaN` if `x` is infinite or `NaN`.
+    /// Use [`node_count`](Self::node_count) to measure the reduction.  The
+    /// original graph is left unchanged.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.mul(x, 3.0).unwrap();
+    ///
+    /// // Changing the constant leaves an `x * 1` node behind
+    /// let op = ctx.map_constants(op, |_| 1.0).unwrap();
+    /// assert_eq!(ctx.node_count(op).unwrap(), 3);
+    ///
+    /// let folded = ctx.fold_constants(op).unwrap();
+    /// assert_eq!(folded, x);
+    /// assert_eq!(ctx.node_count(folded).unwrap(), 1);
+    /// ```
+    pub fn fold_constants(&mut self, root: Node) -> Result<Node, Error> {
+        let (order, _) = self.topological_order(root)?;
+        let mut done: BTreeMap<Node, Node> = BTreeMap::new();
+        for node in order {
+            let r = match *self.get_op(node).unwrap() {
+                Op::Binary(op, lhs, rhs) => {
+                    let (a, b) = (done[&lhs], done[&rhs]);
+                    let ca = self.const_value(a)?;
+                    let cb = self.const_value(b)?;
+                    match op {
+                        BinaryOpcode::Add if ca == Some(0.0) => b,
+                        BinaryOpcode::Add | BinaryOpcode::Sub
+                            if cb == Some(0.0) =>
+                        {
+                            a
+                        }
+                        BinaryOpcode::Mul if ca == Some(1.0) => b,
+                        BinaryOpcode::Mul | BinaryOpcode::Div
+                            if cb == Some(1.0) =>
+                        {
+                            a
+                        }
+                        BinaryOpcode::Min | BinaryOpcode::Max if a == b => a,
+                        _ => self.op_binary(a, b, op)?,
+                    }
+                }
+                Op::Unary(op, arg) => {
+                    let a = done[&arg];
+                    match (op, *self.get_op(a).unwrap()) {
+                        (
+                            UnaryOpcode::Neg,
+                            Op::Unary(UnaryOpcode::Neg, inner),
+                        ) => inner,
+                        _ => self.op_unary(a, op)?,
+                    }
+                }
+                Op::Custom(id, arg) => self.custom(id, done[&arg])?,
+                Op::Const(..) | Op::Var(..) | Op::Input(..) => node,
+            };
+            done.insert(node, r);
+        }
+        Ok(done[&root])
+    }
+
+    /// Rebuilds the graph at `root`, replacing leaf (input, variable, and
+    /// constant) nodes
+    ///
+    /// Nodes which aren't in `done` are left unchanged.
+    fn remap(&mut self, root: Node, mut done: BTreeMap<Node, Node>) -> Node {
+        // Depth-first recursion on the heap, to protect against stack overflows
+        enum Action {
+            Down,
+            Up,
+        }
+
+        let mut todo = vec![(Action::Down, root)];
+        let mut seen = BTreeSet::new();
+        while let Some((action, node)) = todo.pop() {
+            match action {
+                Action::Down => {
+                    if !seen.insert(node) {
+                        continue;
+                    }
+                    todo.push((Action::Up, node));
+                    todo.extend(
+                        self.get_op(node)
+                            .unwrap()
+                            .iter_children()
+                            .map(|c| (Action::Down, c)),
+                    );
+                }
+                Action::Up => {
+                    let r = match self.get_op(node).unwrap() {
+                        Op::Binary(op, lhs, rhs) => {
+                            let a = done.get(lhs).unwrap();
+                            let b = done.get(rhs).unwrap();
+                            self.op_binary(*a, *b, *op).unwrap()
+                        }
+                        Op::Unary(op, arg) => {
+                            let a = done.get(arg).unwrap();
+                            self.op_unary(*a, *op).unwrap()
+                        }
+                        Op::Custom(id, arg) => {
+                            let a = done.get(arg).unwrap();
+                            self.custom(*id, *a).unwrap()
+                        }
+                        Op::Const(..) | Op::Var(..) | Op::Input(..) => {
+                            *done.get(&node).unwrap_or(&node)
+                        }
+                    };
+                    done.insert(node, r);
+                }
+            }
+        }
+        *done.get(&root).unwrap()
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    /// Evaluates the given node with the provided values for X, Y, and Z.
+    ///
+    /// This is extremely inefficient; consider converting the node into a
+    /// [`Shape`](crate::eval::Shape) and using its evaluators instead.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let z = ctx.z();
+    /// let op = ctx.mul(x, y).unwrap();
+    /// let op = ctx.div(op, z).unwrap();
+    /// let v = ctx.eval_xyz(op, 3.0, 5.0, 2.0).unwrap();
+    /// assert_eq!(v, 7.5); // (3.0 * 5.0) / 2.0
+    /// ```
+    pub fn eval_xyz(
+        &self,
+        root: Node,
+        x: f64,
+        y: f64,
+        z: f64,
+    ) -> Result<f64, Error> {
+        self.eval(root, &Self::xyz_vars(x, y, z))
+    }
+
+    /// Classifies a point as inside, outside, or on the surface of a shape
+    ///
+    /// The point is on the [`Surface`](Classification::Surface) if the node's
+    /// absolute value is less than `eps`; otherwise, the sign of the value
+    /// decides whether it's [`Inside`](Classification::Inside) (negative) or
+    /// [`Outside`](Classification::Outside).  If the node is a distance field,
+    /// `eps` is the half-width of the band around the surface.  Points where
+    /// the node is `NaN` are considered to be outside.
+    ///
+    /// Like [`eval_xyz`](Self::eval_xyz), this is inefficient, and is meant
+    /// for occasional queries (e.g. picking or snapping a single point).
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// use fidget::context::Classification;
+    /// let x = ctx.x();
+    /// let op = ctx.sub(x, 1.0).unwrap();
+    /// let c = ctx.classify(op, 0.999, 0.0, 0.0, 0.01).unwrap();
+    /// assert_eq!(c, Classification::Surface);
+    /// let c = ctx.classify(op, 0.5, 0.0, 0.0, 0.01).unwrap();
+    /// assert_eq!(c, Classification::Inside);
+    /// ```
+    pub fn classify(
+        &self,
+        root: Node,
+        x: f64,
+        y: f64,
+        z: f64,
+        eps: f64,
+    ) -> Result<Classification, Error> {
+        let v = self.eval_xyz(root, x, y, z)?;
+        Ok(if v.abs() < eps {
+            Classification::Surface
+        } else if v < 0.0 {
+            Classification::Inside
+        } else {
+            Classification::Outside
+        })
+    }
+
+    /// Evaluates the given node at a position transformed by a 4×4 matrix
+    ///
+    /// `inv_transform` is a row-major homogeneous matrix, which is applied to
+    /// `p` before evaluation; as such, it should be the _inverse_ of the
+    /// transform that you'd like to apply to the shape.  This is a quick
+    /// alternative to [`remap_xyz`](Self::remap_xyz), which doesn't modify the
+    /// graph.
+    ///
+    /// If the transform includes non-uniform scaling (or shearing), the result
+    /// is no longer a true distance, since distances are distorted differently
+    /// along each axis; the sign (and therefore the zero set) is still correct.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// // Translate the shape by +2 along the X axis
+    /// let inv = [
+    ///     [1.0, 0.0, 0.0, -2.0],
+    ///     [0.0, 1.0, 0.0, 0.0],
+    ///     [0.0, 0.0, 1.0, 0.0],
+    ///     [0.0, 0.0, 0.0, 1.0],
+    /// ];
+    /// let v = ctx.eval_xyz_transformed(x, [3.0, 0.0, 0.0], &inv).unwrap();
+    /// assert_eq!(v, 1.0);
+    /// ```
+    pub fn eval_xyz_transformed(
+        &self,
+        root: Node,
+        p: [f64; 3],
+        inv_transform: &[[f64; 4]; 4],
+    ) -> Result<f64, Error> {
+        let p = [p[0], p[1], p[2], 1.0];
+        let [x, y, z, w] = inv_transform
+            .map(|row| row.iter().zip(&p).map(|(a, b)| a * b).sum::<f64>());
+        self.eval_xyz(root, x / w, y / w, z / w)
+    }
+
+    /// Evaluates the given node at a point, also returning the active feature
+    ///
+    /// The feature is found by starting at `root` and following the branch
+    /// selected by each `min` or `max` operation, stopping at the first node
+    /// which is neither.  For a CSG model, this is the primitive which
+    /// determines the value at the given point.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let a = ctx.sub(x, 1.0).unwrap();
+    /// let b = ctx.neg(x).unwrap();
+    /// let u = ctx.min(a, b).unwrap();
+    /// let (v, f) = ctx.eval_with_feature(u, 2.0, 0.0, 0.0).unwrap();
+    /// assert_eq!(v, -2.0);
+    /// assert_eq!(f, b);
+    /// ```
+    pub fn eval_with_feature(
+        &self,
+        root: Node,
+        x: f64,
+        y: f64,
+        z: f64,
+    ) -> Result<(f64, Node), Error> {
+        let vars = Self::xyz_vars(x, y, z);
+        let mut cache = vec![None; self.ops.len()].into();
+        let v = self.eval_inner(root, &vars, &mut cache)?;
+
+        // Evaluating a min or max populates both of its branches in the cache
+        let mut node = root;
+        while let Some(Op::Binary(op, a, b)) = self.get_op(node) {
+            let (va, vb) = (cache[*a].unwrap(), cache[*b].unwrap());
+            node = match op {
+                BinaryOpcode::Min if vb < va => *b,
+                BinaryOpcode::Max if vb > va => *b,
+                BinaryOpcode::Min | BinaryOpcode::Max => *a,
+                _ => break,
+            };
+        }
+        Ok((v, node))
+    }
+
+    /// Finds nodes whose values at a point are out of range for an `f32`
+    ///
+    /// The graph is stored and evaluated here in `f64`, but is converted to
+    /// `f32` when building a [`Shape`](crate::eval::Shape).  A large constant
+    /// (e.g. `1e39`), or an intermediate value such as the square of `1e20`,
+    /// can overflow to infinity in the `f32` evaluators even when the final
+    /// result is finite, so they'll disagree with [`eval_xyz`](Self::eval_xyz).
+    ///
+    /// Returns every node (in order of creation) whose finite `f64` value at
+    /// the given point exceeds [`f32::MAX`] in magnitude.  An empty result
+    /// means the model is safe to evaluate in `f32` at this point.
+    ///
+    /// Constants are also checked when building a tape, without needing an
+    /// evaluation point (see
+    /// [`VmData::f32_overflows`](crate::vm::VmData::f32_overflows)), and can
+    /// be clamped with [`clamp_to_f32`](Self::clamp_to_f32).
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let m = ctx.mul(x, 1e39).unwrap();
+    /// let op = ctx.div(m, 1e38).unwrap();
+    /// assert_eq!(ctx.eval_xyz(op, 1.0, 0.0, 0.0).unwrap(), 10.0);
+    ///
+    /// let big = ctx.constant(1e39);
+    /// assert_eq!(ctx.f32_overflows(op, 1.0, 0.0, 0.0).unwrap(), [big, m]);
+    /// ```
+    pub fn f32_overflows(
+        &self,
+        root: Node,
+        x: f64,
+        y: f64,
+        z: f64,
+    ) -> Result<Vec<Node>, Error> {
+        let vars = Self::xyz_vars(x, y, z);
+        let mut cache = vec![None; self.ops.len()].into();
+        self.eval_inner(root, &vars, &mut cache)?;
+        let overflows = |v: f64| v.is_finite() && v.abs() > f32::MAX as f64;
+        Ok(cache
+            .into_iter()
+            .enumerate()
+            .filter(|(_, v)| v.map(overflows).unwrap_or(false))
+            .map(|(i, _)| Node(i))
+            .collect())
+    }
+
+    /// Clamps every out-of-range constant to the range of an `f32`
+    ///
+    /// Finite constants which exceed [`f32::MAX`] in magnitude would become
+    /// infinities when the graph is converted into a tape; this replaces them
+    /// with `±f32::MAX` instead (using
+    /// [`map_constants`](Self::map_constants)), so that the result stays
+    /// finite wherever it doesn't depend on the exact value.  Infinite
+    /// constants are left unchanged.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let op = ctx.min(x, 1e39).unwrap();
+    /// let op = ctx.clamp_to_f32(op).unwrap();
+    /// assert_eq!(ctx.eval_xyz(op, 1e39, 0.0, 0.0).unwrap(), f32::MAX as f64);
+    /// ```
+    pub fn clamp_to_f32(&mut self, root: Node) -> Result<Node, Error> {
+        let max = f32::MAX as f64;
+        self.map_constants(root, |c| {
+            if c.is_finite() {
+                c.clamp(-max, max)
+            } else {
+                c
+            }
+        })
+    }
+
+    /// Builds a variable map for the three coordinate axes
+    fn xyz_vars(x: f64, y: f64, z: f64) -> BTreeMap<String, f64> {
+        [("X", x), ("Y", y), ("Z", z)]
+            .into_iter()
+            .map(|(a, b)| (a.to_string(), b))
+            .collect()
+    }
+
+    /// Evaluates the given node with a generic set of variables
+    ///
+    /// `vars` maps from variable names to values; the axes are named `X`,
+    /// `Y`, and `Z`.  Returns [`Error::UnknownVariable`] if the node uses a
+    /// variable which isn't in the map.
+    ///
+    /// This is extremely inefficient; consider converting the node into a
+    /// [`Shape`](crate::eval::Shape) and using its evaluators instead.
+    ///
+    /// ```
+    /// # use std::collections::BTreeMap;
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let r = ctx.var("radius").unwrap();
+    /// let op = ctx.sub(x, r).unwrap();
+    /// let mut vars = BTreeMap::new();
+    /// vars.insert("X".to_owned(), 3.0);
+    /// vars.insert("radius".to_owned(), 1.0);
+    /// assert_eq!(ctx.eval(op, &vars).unwrap(), 2.0);
+    /// ```
+    pub fn eval(
+        &self,
+        root: Node,
+        vars: &BTreeMap<String, f64>,
+    ) -> Result<f64, Error> {
+        let mut cache = vec![None; self.ops.len()].into();
+        self.eval_inner(root, vars, &mut cache)
+    }
+
+    fn eval_inner(
+        &self,
+        node: Node,
+        vars: &BTreeMap<String, f64>,
+        cache: &mut IndexVec<Option<f64>, Node>,
+    ) -> Result<f64, Error> {
+        if node.0 >= cache.len() {
+            return Err(Error::BadNode);
+        }
+        if let Some(v) = cache[node] {
+            return Ok(v);
+        }
+        let mut get = |n: Node| self.eval_inner(n, vars, cache);
+        let v = match self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Var(v) | Op::Input(v) => {
+                let var_name = self.vars.get_by_index(*v).unwrap();
+                *vars
+                    .get(var_name)
+                    .ok_or_else(|| Error::UnknownVariable(var_name.clone()))?
+            }
+            Op::Const(c) => c.0,
+
+            Op::Binary(op, a, b) => {
+                let a = get(*a)?;
+                let b = get(*b)?;
+                match op {
+                    BinaryOpcode::Add => a + b,
+                    BinaryOpcode::Sub => a - b,
+                    BinaryOpcode::Mul => a * b,
+                    BinaryOpcode::Div => a / b,
+                    BinaryOpcode::Min => a.min(b),
+                    BinaryOpcode::Max => a.max(b),
+                    BinaryOpcode::Compare => a
+                        .partial_cmp(&b)
+                        .map(|i| i as i8 as f64)
+                        .unwrap_or(f64::NAN),
+                    BinaryOpcode::Mod => a.rem_euclid(b),
+                    BinaryOpcode::And => {
+                        if a == 0.0 {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                    BinaryOpcode::Or => {
+                        if a != 0.0 {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                }
+            }
+
+            // Unary operations
+            Op::Unary(op, a) => {
+                let a = get(*a)?;
+                match op {
+                    UnaryOpcode::Neg => -a,
+                    UnaryOpcode::Abs => a.abs(),
+                    UnaryOpcode::Recip => 1.0 / a,
+                    UnaryOpcode::Sqrt => a.sqrt(),
+                    UnaryOpcode::Square => a * a,
+                    UnaryOpcode::Sin => a.sin(),
+                    UnaryOpcode::Cos => a.cos(),
                     UnaryOpcode::Tan => a.tan(),
                     UnaryOpcode::Asin => a.asin(),
                     UnaryOpcode::Acos => a.acos(),
@@ -811,12 +1946,170 @@ impl Context {
                     UnaryOpcode::Not => (a == 0.0).into(),
                 }
             }
+            Op::Custom(id, a) => id.get().eval(get(*a)?),
         };
 
         cache[node] = Some(v);
         Ok(v)
     }
 
+    /// Computes a structural hash of the graph rooted at the given node
+    ///
+    /// The hash depends only on the structure of the expression (operations,
+    /// constants, and variable names), not on node indices, so equivalent
+    /// graphs built in different contexts (or in a different order) hash
+    /// identically.  Operands of commutative operations (`add`, `mul`, `min`,
+    /// and `max`) are hashed in a canonical order, so `a + b` and `b + a`
+    /// produce the same hash.
+    ///
+    /// Hashes are only stable within a single build of the library.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let mut a = Context::new();
+    /// let x = a.x();
+    /// let y = a.y();
+    /// let s1 = a.min(x, y).unwrap();
+    ///
+    /// let mut b = Context::new();
+    /// let y = b.y();
+    /// let x = b.x();
+    /// let s2 = b.min(y, x).unwrap();
+    /// assert_eq!(a.graph_hash(s1).unwrap(), b.graph_hash(s2).unwrap());
+    /// ```
+    pub fn graph_hash(&self, root: Node) -> Result<u64, Error> {
+        let mut cache = vec![None; self.ops.len()].into();
+        self.graph_hash_inner(root, &mut cache)
+    }
+
+    fn graph_hash_inner(
+        &self,
+        node: Node,
+        cache: &mut IndexVec<Option<u64>, Node>,
+    ) -> Result<u64, Error> {
+        if node.0 >= cache.len() {
+            return Err(Error::BadNode);
+        }
+        if let Some(h) = cache[node] {
+            return Ok(h);
+        }
+        let mut hasher = DefaultHasher::new();
+        match self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Input(v) => {
+                0u8.hash(&mut hasher);
+                self.get_var_by_index(*v)?.hash(&mut hasher);
+            }
+            Op::Var(v) => {
+                1u8.hash(&mut hasher);
+                self.get_var_by_index(*v)?.hash(&mut hasher);
+            }
+            Op::Const(c) => {
+                2u8.hash(&mut hasher);
+                c.hash(&mut hasher);
+            }
+            Op::Unary(op, a) => {
+                3u8.hash(&mut hasher);
+                op.hash(&mut hasher);
+                self.graph_hash_inner(*a, cache)?.hash(&mut hasher);
+            }
+            Op::Custom(id, a) => {
+                5u8.hash(&mut hasher);
+                id.hash(&mut hasher);
+                self.graph_hash_inner(*a, cache)?.hash(&mut hasher);
+            }
+            Op::Binary(op, a, b) => {
+                let mut a = self.graph_hash_inner(*a, cache)?;
+                let mut b = self.graph_hash_inner(*b, cache)?;
+                if matches!(
+                    op,
+                    BinaryOpcode::Add
+                        | BinaryOpcode::Mul
+                        | BinaryOpcode::Min
+                        | BinaryOpcode::Max
+                ) && a > b
+                {
+                    std::mem::swap(&mut a, &mut b);
+                }
+                4u8.hash(&mut hasher);
+                op.hash(&mut hasher);
+                a.hash(&mut hasher);
+                b.hash(&mut hasher);
+            }
+        }
+        let h = hasher.finish();
+        cache[node] = Some(h);
+        Ok(h)
+    }
+
+    /// Returns the depth of the graph rooted at the given node
+    ///
+    /// The depth is the number of edges in the longest path from `root` to a
+    /// leaf (an input, variable, or constant), so a lone leaf has depth 0.
+    /// Shared subexpressions are only visited once, and the traversal uses a
+    /// heap-allocated stack, so this is safe to call on very deep graphs.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let sum = ctx.add(x2, y).unwrap();
+    /// assert_eq!(ctx.depth(x).unwrap(), 0);
+    /// assert_eq!(ctx.depth(sum).unwrap(), 2);
+    /// ```
+    pub fn depth(&self, root: Node) -> Result<usize, Error> {
+        self.check_node(root)?;
+
+        // Depth-first recursion on the heap, to protect against stack overflows
+        enum Action {
+            Down,
+            Up,
+        }
+
+        let mut depth: IndexVec<Option<usize>, Node> =
+            vec![None; self.ops.len()].into();
+        let mut todo = vec![(Action::Down, root)];
+        while let Some((action, node)) = todo.pop() {
+            let op = self.get_op(node).ok_or(Error::BadNode)?;
+            match action {
+                Action::Down => {
+                    if depth[node].is_some() {
+                        continue;
+                    }
+                    todo.push((Action::Up, node));
+                    todo.extend(
+                        op.iter_children()
+                            .filter(|c| depth[*c].is_none())
+                            .map(|c| (Action::Down, c)),
+                    );
+                }
+                Action::Up => {
+                    let d = op
+                        .iter_children()
+                        .map(|c| depth[c].unwrap() + 1)
+                        .max()
+                        .unwrap_or(0);
+                    depth[node] = Some(d);
+                }
+            }
+        }
+        Ok(depth[root].unwrap())
+    }
+
+    /// Returns the number of distinct nodes in the graph rooted at `root`
+    ///
+    /// Shared subexpressions are only counted once, and the root is included.
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let sum = ctx.add(x2, x).unwrap();
+    /// assert_eq!(ctx.node_count(sum).unwrap(), 3);
+    /// ```
+    pub fn node_count(&self, root: Node) -> Result<usize, Error> {
+        Ok(self.topological_order(root)?.0.len())
+    }
+
     /// Parses a flat text representation of a math tree. For example, the
     /// circle `(- (+ (square x) (square y)) 1)` can be parsed from
     /// ```
@@ -835,64 +2128,726 @@ impl Context {
     /// assert_eq!(ctx.len(), 7);
     /// ```
     ///
+    /// Reusable sub-expressions may be declared as macros, with a line of the
+    /// form `def <name>(<args...>) = <expr>`.  The expression is written with
+    /// function calls, e.g. `sub(sqrt(add(square(var-x), square(var-y))), r)`,
+    /// using the opcodes above, numeric constants, the macro's arguments, and
+    /// other macros.  A macro is invoked like an opcode (`out sphere r`), and
+    /// is expanded at parse time; identical expansions are deduplicated by the
+    /// `Context`.  Macros may not be recursive, directly or through other
+    /// macros.
+    /// ```
+    /// # use fidget::context::Context;
+    /// let txt = "
+    /// def double(a) = add(a, a)
+    /// def quad(a) = double(double(a))
+    /// x var-x
+    /// x2 double x
+    /// x4 quad x
+    /// ";
+    /// let (ctx, node) = Context::from_text(&mut txt.as_bytes()).unwrap();
+    /// assert_eq!(ctx.eval_xyz(node, 1.5, 0.0, 0.0).unwrap(), 6.0);
+    /// ```
+    ///
+    /// Lines of the form `output <name> = <node>` declare named outputs; see
+    /// [`from_text_outputs`](Self::from_text_outputs) to retrieve them.  The
+    /// returned node is the last one defined or declared as an output.
+    ///
     /// This representation is loosely defined and only intended for use in
     /// quick experiments.
+    ///
+    /// If parsing fails, the error is an [`Error::ParseError`], which records
+    /// the location of the offending token and a snippet of its line:
+    /// ```
+    /// # use fidget::{context::Context, Error};
+    /// let txt = "x var-x\ny frob x\n";
+    /// let Err(e) = Context::from_text(txt.as_bytes()) else { panic!() };
+    /// assert_eq!(
+    ///     e.to_string(),
+    ///     "line 2, column 3: unknown opcode frob\ny frob x\n  ^"
+    /// );
+    /// ```
     pub fn from_text<R: Read>(r: R) -> Result<(Self, Node), Error> {
-        let reader = BufReader::new(r);
+        let (ctx, node, _outputs) = Self::from_text_outputs(r)?;
+        Ok((ctx, node))
+    }
+
+    /// Parses a text representation with named outputs
+    ///
+    /// This uses the same format as [`from_text`](Self::from_text), and also
+    /// returns a map of every output declared with `output <name> = <node>`.
+    /// The returned node is the same as for `from_text`, so a file with no
+    /// named outputs returns its last node (and an empty map).
+    /// ```
+    /// # use fidget::context::Context;
+    /// let txt = "
+    /// x var-x
+    /// y var-y
+    /// sum add x y
+    /// output left = x
+    /// output total = sum
+    /// ";
+    /// let (ctx, _node, outputs) =
+    ///     Context::from_text_outputs(txt.as_bytes()).unwrap();
+    /// assert_eq!(outputs.len(), 2);
+    /// let v = ctx.eval_xyz(outputs["total"], 1.0, 2.0, 0.0).unwrap();
+    /// assert_eq!(v, 3.0);
+    /// ```
+    pub fn from_text_outputs<R: Read>(
+        r: R,
+    ) -> Result<(Self, Node, BTreeMap<String, Node>), Error> {
+        Self::from_text_inner(r, None)
+    }
+
+    /// Parses a text representation, tolerating unknown opcodes
+    ///
+    /// This uses the same format as [`from_text`](Self::from_text), but a line
+    /// with an unknown opcode (e.g. from a file written by a newer tool) is
+    /// replaced by a placeholder constant of 0 instead of failing, so the rest
+    /// of the model still loads.  If the unknown opcode is within a macro,
+    /// then the line which invokes the macro is replaced.  Other errors still
+    /// cause parsing to fail.
+    ///
+    /// Returns the context, the last node (as in `from_text`), and a warning
+    /// for each replaced line, as an [`Error::ParseError`] with its location.
+    /// ```
+    /// # use fidget::context::Context;
+    /// let txt = "x var-x\ny frob x\nz add x y\n";
+    /// let (ctx, node, warnings) =
+    ///     Context::from_text_tolerant(txt.as_bytes()).unwrap();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(ctx.eval_xyz(node, 2.0, 0.0, 0.0).unwrap(), 2.0);
+    /// ```
+    pub fn from_text_tolerant<R: Read>(
+        r: R,
+    ) -> Result<(Self, Node, Vec<Error>), Error> {
+        let mut warnings = vec![];
+        let (ctx, node, _outputs) =
+            Self::from_text_inner(r, Some(&mut warnings))?;
+        Ok((ctx, node, warnings))
+    }
+
+    /// Parses a text representation
+    ///
+    /// If `warnings` is present, then lines with unknown opcodes are replaced
+    /// by a constant (see [`from_text_tolerant`](Self::from_text_tolerant)),
+    /// and their errors are recorded there.
+    fn from_text_inner<R: Read>(
+        r: R,
+        mut warnings: Option<&mut Vec<Error>>,
+    ) -> Result<(Self, Node, BTreeMap<String, Node>), Error> {
+        let mut reader = BufReader::new(r);
         let mut ctx = Self::new();
         let mut seen = BTreeMap::new();
+        let mut macros = BTreeMap::new();
+        let mut outputs = BTreeMap::new();
         let mut last = None;
 
-        for line in reader.lines().map(|line| line.unwrap()) {
-            if line.is_empty() || line.starts_with('#') {
-                continue;
+        let mut buf = String::new();
+        let mut offset = 0;
+        for line in 1.. {
+            buf.clear();
+            let n = reader.read_line(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            let text = buf.trim_end_matches(['\n', '\r']).to_owned();
+            let line = TextLine { text, line, offset };
+            offset += n;
+
+            if line.text.is_empty() || line.text.starts_with('#') {
+                continue;
+            }
+            if line.text.starts_with("def ") {
+                let (name, m) = Self::parse_text_macro(line, &macros)?;
+                macros.insert(name, m);
+                continue;
+            }
+            if let Some(rest) = line.text.strip_prefix("output ") {
+                let mut iter = rest.split_whitespace();
+                let name = iter.next().ok_or_else(|| line.eol())?;
+                let eq = iter.next().ok_or_else(|| line.eol())?;
+                if eq != "=" {
+                    let e = Error::ExpectedEquals(eq.to_owned());
+                    return Err(line.error(line.pos(eq), e));
+                }
+                let txt = iter.next().ok_or_else(|| line.eol())?;
+                let node = seen.get(txt).cloned().ok_or_else(|| {
+                    let e = Error::UnknownVariable(txt.to_owned());
+                    line.error(line.pos(txt), e)
+                })?;
+                if outputs.insert(name.to_owned(), node).is_some() {
+                    let e = Error::DuplicateOutput(name.to_owned());
+                    return Err(line.error(line.pos(name), e));
+                }
+                last = Some(node);
+                continue;
+            }
+            let (i, node) = match ctx.parse_text_line(&line, &seen, &macros) {
+                Ok(v) => v,
+                Err(e) => {
+                    let unknown = matches!(&e, Error::ParseError { source, .. }
+                        if matches!(**source, Error::UnknownOpcode(..)));
+                    let Some(w) = warnings.as_mut().filter(|_| unknown) else {
+                        return Err(e);
+                    };
+                    w.push(e);
+                    // There must be a name, since the opcode was found
+                    let i = line.text.split_whitespace().next().unwrap();
+                    (i.to_owned(), ctx.constant(0.0))
+                }
+            };
+            seen.insert(i, node);
+            last = Some(node);
+        }
+        match last {
+            Some(node) => Ok((ctx, node, outputs)),
+            None => Err(Error::EmptyFile),
+        }
+    }
+
+    /// Parses a single line of the [`from_text`](Self::from_text) format
+    ///
+    /// Returns the line's name and the resulting node
+    fn parse_text_line(
+        &mut self,
+        line: &TextLine,
+        seen: &BTreeMap<String, Node>,
+        macros: &BTreeMap<String, TextMacro>,
+    ) -> Result<(String, Node), Error> {
+        let mut iter = line.text.split_whitespace();
+        let i: String = iter.next().ok_or_else(|| line.eol())?.to_owned();
+        let opcode = iter.next().ok_or_else(|| line.eol())?;
+        let node = self
+            .parse_text_op(line, opcode, iter, seen, macros)
+            .map_err(|e| line.error(line.pos(opcode), e))?;
+        Ok((i, node))
+    }
+
+    /// Parses a macro definition of the form `def <name>(<args...>) = <expr>`
+    fn parse_text_macro(
+        line: TextLine,
+        macros: &BTreeMap<String, TextMacro>,
+    ) -> Result<(String, TextMacro), Error> {
+        let (name, arg_count, body) = {
+            let tokens = text_tokens(&line.text["def ".len()..]);
+            let mut iter = tokens.iter().copied();
+            let expect = |t: Option<&str>, want: &'static str| match t {
+                Some(t) if t == want => Ok(()),
+                Some(t) => {
+                    let e = Error::ExpectedToken(want, t.to_owned());
+                    Err(line.error(line.pos(t), e))
+                }
+                None => Err(line.eol()),
+            };
+            let name = iter.next().ok_or_else(|| line.eol())?;
+            expect(iter.next(), "(")?;
+            let mut args = vec![];
+            let mut t = iter.next();
+            if t != Some(")") {
+                loop {
+                    match t {
+                        Some("(" | ")" | ",") | None => {
+                            expect(t, "argument name")?
+                        }
+                        Some(a) => args.push(a),
+                    }
+                    t = iter.next();
+                    if t == Some(")") {
+                        break;
+                    }
+                    expect(t, ",")?;
+                    t = iter.next();
+                }
+            }
+            match iter.next() {
+                Some("=") => (),
+                Some(eq) => {
+                    let e = Error::ExpectedEquals(eq.to_owned());
+                    return Err(line.error(line.pos(eq), e));
+                }
+                None => return Err(line.eol()),
+            }
+
+            let tokens = &tokens[tokens.len() - iter.len()..];
+            if tokens.is_empty() {
+                let e = Error::EmptyMacro(name.to_owned());
+                return Err(line.error(line.pos(name), e));
+            }
+            let mut i = 0;
+            let body = TextExpr::parse(&line, tokens, &mut i, &args)?;
+            if let Some(t) = tokens.get(i) {
+                let e = Error::ExpectedToken("end of line", t.to_string());
+                return Err(line.error(line.pos(t), e));
+            }
+            // Macros which are already defined can't be recursive, so any
+            // cycle must pass through this one
+            if body.calls(name, macros) {
+                let e = Error::RecursiveMacro(name.to_owned());
+                return Err(line.error(line.pos(name), e));
+            }
+            (name.to_owned(), args.len(), body)
+        };
+        Ok((
+            name,
+            TextMacro {
+                arg_count,
+                body,
+                line,
+            },
+        ))
+    }
+
+    /// Parses the opcode and arguments of a [`from_text`](Self::from_text)
+    /// line
+    ///
+    /// Errors without a location are attributed to the opcode by the caller.
+    fn parse_text_op<'a>(
+        &mut self,
+        line: &'a TextLine,
+        opcode: &str,
+        mut iter: impl Iterator<Item = &'a str>,
+        seen: &BTreeMap<String, Node>,
+        macros: &BTreeMap<String, TextMacro>,
+    ) -> Result<Node, Error> {
+        let lookup = |txt: &str| {
+            seen.get(txt).cloned().ok_or_else(|| {
+                line.error(line.pos(txt), Error::UnknownVariable(txt.into()))
+            })
+        };
+        if opcode == "const" {
+            let txt = iter.next().ok_or_else(|| line.eol())?;
+            let v = txt.parse().map_err(|_| {
+                let e = Error::BadConstant(txt.to_owned());
+                line.error(line.pos(txt), e)
+            })?;
+            return Ok(self.constant(v));
+        }
+        let pop = || lookup(iter.next().ok_or_else(|| line.eol())?);
+        if let Some(node) = self.text_builtin(opcode, pop)? {
+            return Ok(node);
+        }
+        let Some(m) = macros.get(opcode) else {
+            return Err(Error::UnknownOpcode(opcode.to_owned()));
+        };
+        let args = iter.map(lookup).collect::<Result<Vec<_>, _>>()?;
+        if args.len() != m.arg_count {
+            return Err(Error::BadMacroArgs(
+                opcode.to_owned(),
+                m.arg_count,
+                args.len(),
+            ));
+        }
+        self.expand_text_macro(&m.line, &m.body, &args, macros)
+    }
+
+    /// Expands the body of a macro, given nodes for its arguments
+    ///
+    /// Errors are located within the macro's definition `line`.
+    fn expand_text_macro(
+        &mut self,
+        line: &TextLine,
+        expr: &TextExpr,
+        args: &[Node],
+        macros: &BTreeMap<String, TextMacro>,
+    ) -> Result<Node, Error> {
+        let (name, pos, children) = match expr {
+            TextExpr::Const(v) => return Ok(self.constant(*v)),
+            TextExpr::Arg(i) => return Ok(args[*i]),
+            TextExpr::Call { name, pos, args } => (name, *pos, args),
+        };
+        let values = children
+            .iter()
+            .map(|c| self.expand_text_macro(line, c, args, macros))
+            .collect::<Result<Vec<_>, _>>()?;
+        let bad_args = || {
+            let e = Error::BadOpArgs(name.clone(), values.len());
+            line.error(pos, e)
+        };
+
+        let mut iter = values.iter().cloned();
+        let pop = || iter.next().ok_or_else(bad_args);
+        let node = if let Some(node) = self
+            .text_builtin(name, pop)
+            .map_err(|e| line.error(pos, e))?
+        {
+            if iter.next().is_some() {
+                return Err(bad_args());
+            }
+            node
+        } else if let Some(m) = macros.get(name) {
+            if values.len() != m.arg_count {
+                let e = Error::BadMacroArgs(
+                    name.clone(),
+                    m.arg_count,
+                    values.len(),
+                );
+                return Err(line.error(pos, e));
+            }
+            self.expand_text_macro(&m.line, &m.body, &values, macros)?
+        } else {
+            let e = Error::UnknownOpcode(name.clone());
+            return Err(line.error(pos, e));
+        };
+        Ok(node)
+    }
+
+    /// Applies a built-in opcode of the [`from_text`](Self::from_text) format
+    ///
+    /// Arguments are taken from `pop`.  Returns `None` if `opcode` isn't built
+    /// in (`const` is handled separately, since it takes a value rather than a
+    /// node).
+    fn text_builtin(
+        &mut self,
+        opcode: &str,
+        mut pop: impl FnMut() -> Result<Node, Error>,
+    ) -> Result<Option<Node>, Error> {
+        let node = match opcode {
+            "var-x" => self.x(),
+            "var-y" => self.y(),
+            "var-z" => self.z(),
+            "abs" => self.abs(pop()?)?,
+            "neg" => self.neg(pop()?)?,
+            "sqrt" => self.sqrt(pop()?)?,
+            "square" => self.square(pop()?)?,
+            "sin" => self.sin(pop()?)?,
+            "cos" => self.cos(pop()?)?,
+            "tan" => self.tan(pop()?)?,
+            "asin" => self.asin(pop()?)?,
+            "acos" => self.acos(pop()?)?,
+            "atan" => self.atan(pop()?)?,
+            "ln" => self.ln(pop()?)?,
+            "not" => self.not(pop()?)?,
+            "exp" => self.exp(pop()?)?,
+            "add" => self.add(pop()?, pop()?)?,
+            "mul" => self.mul(pop()?, pop()?)?,
+            "min" => self.min(pop()?, pop()?)?,
+            "max" => self.max(pop()?, pop()?)?,
+            "div" => self.div(pop()?, pop()?)?,
+            "sub" => self.sub(pop()?, pop()?)?,
+            "compare" => self.compare(pop()?, pop()?)?,
+            "mod" => self.modulo(pop()?, pop()?)?,
+            "and" => self.and(pop()?, pop()?)?,
+            "or" => self.or(pop()?, pop()?)?,
+            _ => return Ok(None),
+        };
+        Ok(Some(node))
+    }
+
+    /// Converts the given node into a LaTeX math expression
+    ///
+    /// Subexpressions which are used more than once are factored out into
+    /// `\text{let}` lines (one per line, separated by `\\`), which precede the
+    /// final expression.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let y2 = ctx.square(y).unwrap();
+    /// let r = ctx.add(x2, y2).unwrap();
+    /// let r = ctx.sqrt(r).unwrap();
+    /// let c = ctx.sub(r, 1.0).unwrap();
+    /// assert_eq!(ctx.to_latex(c).unwrap(), r"\sqrt{x^{2} + y^{2}} - 1");
+    /// ```
+    pub fn to_latex(&self, root: Node) -> Result<String, Error> {
+        self.check_node(root)?;
+
+        // Count the number of parents of each node in this subtree
+        let mut uses: BTreeMap<Node, usize> = BTreeMap::new();
+        let mut todo = vec![root];
+        let mut seen = BTreeSet::new();
+        while let Some(node) = todo.pop() {
+            if !seen.insert(node) {
+                continue;
+            }
+            for c in self.get_op(node).unwrap().iter_children() {
+                *uses.entry(c).or_default() += 1;
+                todo.push(c);
+            }
+        }
+        let shared: BTreeSet<Node> = uses
+            .into_iter()
+            .filter(|(n, count)| {
+                *count > 1
+                    && matches!(
+                        self.get_op(*n).unwrap(),
+                        Op::Unary(..) | Op::Binary(..) | Op::Custom(..)
+                    )
+            })
+            .map(|(n, _)| n)
+            .collect();
+
+        // Name shared nodes in post-order, so that each `\text{let}` line
+        // only refers to names defined above it.  The traversal uses a
+        // heap-allocated stack, to protect against stack overflows.
+        enum Action {
+            Down,
+            Up,
+        }
+        let mut names = BTreeMap::new();
+        let mut lines = vec![];
+        let mut todo = vec![(Action::Down, root)];
+        let mut seen = BTreeSet::new();
+        while let Some((action, node)) = todo.pop() {
+            match action {
+                Action::Down => {
+                    if !seen.insert(node) {
+                        continue;
+                    }
+                    todo.push((Action::Up, node));
+                    let op = self.get_op(node).unwrap();
+                    let children: Vec<_> = op.iter_children().collect();
+                    todo.extend(
+                        children.into_iter().rev().map(|c| (Action::Down, c)),
+                    );
+                }
+                Action::Up => {
+                    if shared.contains(&node) {
+                        let name = format!("s_{{{}}}", names.len());
+                        let mut line = format!(r"\text{{let }} {name} = ");
+                        self.latex_inner(node, &names, &mut line);
+                        lines.push(line);
+                        names.insert(node, name);
+                    }
+                }
+            }
+        }
+        let mut out = String::new();
+        self.latex_inner(root, &names, &mut out);
+        lines.push(out);
+        Ok(lines.join(" \\\\\n"))
+    }
+
+    /// Precedence of a node when written in LaTeX (one of the `LATEX_*`
+    /// constants), which is used to decide where parentheses are needed
+    fn latex_precedence(
+        &self,
+        node: Node,
+        names: &BTreeMap<Node, String>,
+    ) -> u8 {
+        if names.contains_key(&node) {
+            return LATEX_ATOM;
+        }
+        match self.get_op(node).unwrap() {
+            Op::Const(c) if c.0 < 0.0 => LATEX_ADD,
+            Op::Const(..) | Op::Input(..) | Op::Var(..) | Op::Custom(..) => {
+                LATEX_ATOM
             }
-            let mut iter = line.split_whitespace();
-            let i: String = iter.next().unwrap().to_owned();
-            let opcode = iter.next().unwrap();
-
-            let mut pop = || {
-                let txt = iter.next().unwrap();
-                seen.get(txt)
-                    .cloned()
-                    .ok_or_else(|| Error::UnknownVariable(txt.to_string()))
-            };
-            let node = match opcode {
-                "const" => ctx.constant(iter.next().unwrap().parse().unwrap()),
-                "var-x" => ctx.x(),
-                "var-y" => ctx.y(),
-                "var-z" => ctx.z(),
-                "abs" => ctx.abs(pop()?)?,
-                "neg" => ctx.neg(pop()?)?,
-                "sqrt" => ctx.sqrt(pop()?)?,
-                "square" => ctx.square(pop()?)?,
-                "sin" => ctx.sin(pop()?)?,
-                "cos" => ctx.cos(pop()?)?,
-                "tan" => ctx.tan(pop()?)?,
-                "asin" => ctx.asin(pop()?)?,
-                "acos" => ctx.acos(pop()?)?,
-                "atan" => ctx.atan(pop()?)?,
-                "ln" => ctx.ln(pop()?)?,
-                "not" => ctx.not(pop()?)?,
-                "exp" => ctx.exp(pop()?)?,
-                "add" => ctx.add(pop()?, pop()?)?,
-                "mul" => ctx.mul(pop()?, pop()?)?,
-                "min" => ctx.min(pop()?, pop()?)?,
-                "max" => ctx.max(pop()?, pop()?)?,
-                "div" => ctx.div(pop()?, pop()?)?,
-                "sub" => ctx.sub(pop()?, pop()?)?,
-                "compare" => ctx.compare(pop()?, pop()?)?,
-                "mod" => ctx.modulo(pop()?, pop()?)?,
-                "and" => ctx.and(pop()?, pop()?)?,
-                "or" => ctx.or(pop()?, pop()?)?,
-                op => return Err(Error::UnknownOpcode(op.to_owned())),
-            };
-            seen.insert(i, node);
-            last = Some(node);
+            Op::Unary(op, ..) => match op {
+                UnaryOpcode::Neg => LATEX_ADD,
+                UnaryOpcode::Not => LATEX_MUL,
+                _ => LATEX_ATOM,
+            },
+            Op::Binary(op, ..) => match op {
+                BinaryOpcode::Add
+                | BinaryOpcode::Sub
+                | BinaryOpcode::And
+                | BinaryOpcode::Or => LATEX_ADD,
+                BinaryOpcode::Mul | BinaryOpcode::Mod => LATEX_MUL,
+                BinaryOpcode::Div
+                | BinaryOpcode::Min
+                | BinaryOpcode::Max
+                | BinaryOpcode::Compare => LATEX_ATOM,
+            },
         }
-        match last {
-            Some(node) => Ok((ctx, node)),
-            None => Err(Error::EmptyFile),
+    }
+
+    /// Checks whether a node's LaTeX string begins with a minus sign
+    fn latex_leading_minus(
+        &self,
+        mut node: Node,
+        names: &BTreeMap<Node, String>,
+    ) -> bool {
+        while !names.contains_key(&node) {
+            match self.get_op(node).unwrap() {
+                Op::Const(c) => {
+                    return c.0.is_sign_negative() && !c.0.is_nan();
+                }
+                Op::Unary(UnaryOpcode::Neg, ..) => return true,
+                Op::Binary(BinaryOpcode::Add | BinaryOpcode::Sub, a, _) => {
+                    node = *a
+                }
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Helper function for [`Context::to_latex`]
+    ///
+    /// Writes the LaTeX string for `node` to `out`, using names from `names`
+    /// for shared children (but not for `node` itself).  Pieces of the output
+    /// are written in order from a heap-allocated stack, so this is safe to
+    /// call on very deep graphs.
+    fn latex_inner(
+        &self,
+        node: Node,
+        names: &BTreeMap<Node, String>,
+        out: &mut String,
+    ) {
+        enum Piece {
+            Text(&'static str),
+            Node(Node),
+        }
+        // Pushes a child in reverse order, wrapping it in parentheses if its
+        // precedence is below `min`
+        let arg = |todo: &mut Vec<Piece>, c: Node, min: u8| {
+            if self.latex_precedence(c, names) < min {
+                todo.push(Piece::Text(r"\right)"));
+                todo.push(Piece::Node(c));
+                todo.push(Piece::Text(r"\left("));
+            } else {
+                todo.push(Piece::Node(c));
+            }
+        };
+
+        let mut todo = vec![];
+        let mut next = Some(node);
+        loop {
+            let node = match next.take() {
+                Some(n) => n,
+                None => match todo.pop() {
+                    Some(Piece::Text(s)) => {
+                        out.push_str(s);
+                        continue;
+                    }
+                    Some(Piece::Node(n)) => {
+                        if let Some(name) = names.get(&n) {
+                            out.push_str(name);
+                            continue;
+                        }
+                        n
+                    }
+                    None => break,
+                },
+            };
+            // Each arm writes its prefix to `out`, then pushes the remaining
+            // pieces onto `todo` in reverse order.
+            match self.get_op(node).unwrap() {
+                Op::Const(c) => write!(out, "{}", c.0).unwrap(),
+                Op::Input(v) => {
+                    let name = self.vars.get_by_index(*v).unwrap();
+                    out.push_str(&name.to_lowercase());
+                }
+                Op::Var(v) => {
+                    let name = self.vars.get_by_index(*v).unwrap();
+                    let name = name.replace('_', r"\_");
+                    write!(out, r"\mathit{{{name}}}").unwrap();
+                }
+                Op::Unary(op, a) => match op {
+                    UnaryOpcode::Neg => {
+                        out.push('-');
+                        arg(&mut todo, *a, LATEX_MUL);
+                    }
+                    UnaryOpcode::Abs => {
+                        out.push_str(r"\left|");
+                        todo.push(Piece::Text(r"\right|"));
+                        todo.push(Piece::Node(*a));
+                    }
+                    UnaryOpcode::Recip => {
+                        out.push_str(r"\frac{1}{");
+                        todo.push(Piece::Text("}"));
+                        todo.push(Piece::Node(*a));
+                    }
+                    UnaryOpcode::Sqrt => {
+                        out.push_str(r"\sqrt{");
+                        todo.push(Piece::Text("}"));
+                        todo.push(Piece::Node(*a));
+                    }
+                    UnaryOpcode::Square => {
+                        todo.push(Piece::Text("^{2}"));
+                        arg(&mut todo, *a, LATEX_ATOM);
+                    }
+                    UnaryOpcode::Exp => {
+                        out.push_str("e^{");
+                        todo.push(Piece::Text("}"));
+                        todo.push(Piece::Node(*a));
+                    }
+                    UnaryOpcode::Not => {
+                        out.push_str(r"\lnot ");
+                        arg(&mut todo, *a, LATEX_ATOM);
+                    }
+                    UnaryOpcode::Sin
+                    | UnaryOpcode::Cos
+                    | UnaryOpcode::Tan
+                    | UnaryOpcode::Asin
+                    | UnaryOpcode::Acos
+                    | UnaryOpcode::Atan
+                    | UnaryOpcode::Ln => {
+                        let f = match op {
+                            UnaryOpcode::Sin => r"\sin",
+                            UnaryOpcode::Cos => r"\cos",
+                            UnaryOpcode::Tan => r"\tan",
+                            UnaryOpcode::Asin => r"\arcsin",
+                            UnaryOpcode::Acos => r"\arccos",
+                            UnaryOpcode::Atan => r"\arctan",
+                            UnaryOpcode::Ln => r"\ln",
+                            _ => unreachable!(),
+                        };
+                        write!(out, r"{f}\left(").unwrap();
+                        todo.push(Piece::Text(r"\right)"));
+                        todo.push(Piece::Node(*a));
+                    }
+                },
+                Op::Custom(id, a) => {
+                    let name = id.get().name().replace('_', r"\_");
+                    write!(out, r"\operatorname{{{name}}}\left(").unwrap();
+                    todo.push(Piece::Text(r"\right)"));
+                    todo.push(Piece::Node(*a));
+                }
+                Op::Binary(op, a, b) => {
+                    let (a, b) = (*a, *b);
+                    let (sep, min) = match op {
+                        BinaryOpcode::Add => {
+                            if self.latex_leading_minus(b, names) {
+                                arg(&mut todo, b, LATEX_MUL);
+                            } else {
+                                todo.push(Piece::Node(b));
+                            }
+                            todo.push(Piece::Text(" + "));
+                            todo.push(Piece::Node(a));
+                            continue;
+                        }
+                        BinaryOpcode::Sub => {
+                            arg(&mut todo, b, LATEX_MUL);
+                            todo.push(Piece::Text(" - "));
+                            todo.push(Piece::Node(a));
+                            continue;
+                        }
+                        BinaryOpcode::Div => {
+                            out.push_str(r"\frac{");
+                            todo.push(Piece::Text("}"));
+                            todo.push(Piece::Node(b));
+                            todo.push(Piece::Text("}{"));
+                            todo.push(Piece::Node(a));
+                            continue;
+                        }
+                        BinaryOpcode::Min
+                        | BinaryOpcode::Max
+                        | BinaryOpcode::Compare => {
+                            out.push_str(match op {
+                                BinaryOpcode::Min => r"\min\left(",
+                                BinaryOpcode::Max => r"\max\left(",
+                                _ => r"\operatorname{cmp}\left(",
+                            });
+                            todo.push(Piece::Text(r"\right)"));
+                            todo.push(Piece::Node(b));
+                            todo.push(Piece::Text(", "));
+                            todo.push(Piece::Node(a));
+                            continue;
+                        }
+                        BinaryOpcode::Mul => (r" \cdot ", LATEX_MUL),
+                        BinaryOpcode::Mod => (r" \bmod ", LATEX_ATOM),
+                        BinaryOpcode::And => (r" \land ", LATEX_ATOM),
+                        BinaryOpcode::Or => (r" \lor ", LATEX_ATOM),
+                    };
+                    arg(&mut todo, b, min);
+                    todo.push(Piece::Text(sep));
+                    arg(&mut todo, a, min);
+                }
+            }
         }
     }
 
@@ -949,6 +2904,7 @@ impl Context {
                 UnaryOpcode::Ln => out += "ln",
                 UnaryOpcode::Not => out += "not",
             },
+            Op::Custom(id, ..) => out += id.get().name(),
         };
         write!(
             out,
@@ -1009,6 +2965,36 @@ impl IntoNode for f64 {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Builds a sphere (or a circle, given a 2D center) for unit tests
+///
+/// The result is `sqrt((x - cx)² + (y - cy)² + ...) - radius`.  Offsets and
+/// radii of zero are skipped, so a radius of zero returns the distance from
+/// `center`.
+#[cfg(test)]
+pub(crate) fn test_sphere<const N: usize>(
+    ctx: &mut Context,
+    center: [f64; N],
+    radius: f64,
+) -> Node {
+    let axes = [Context::x, Context::y, Context::z];
+    let mut sum = None;
+    for (axis, c) in axes.iter().zip(center) {
+        let a = axis(ctx);
+        let d = if c == 0.0 { a } else { ctx.sub(a, c).unwrap() };
+        let d = ctx.square(d).unwrap();
+        sum = Some(match sum {
+            Some(s) => ctx.add(s, d).unwrap(),
+            None => d,
+        });
+    }
+    let r = ctx.sqrt(sum.unwrap()).unwrap();
+    if radius == 0.0 {
+        r
+    } else {
+        ctx.sub(r, radius).unwrap()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1051,6 +3037,40 @@ mod test {
         assert_eq!(tape.len(), 2);
     }
 
+    #[test]
+    fn test_commutative_dedup() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let a = ctx.square(x).unwrap();
+        let b = ctx.sin(y).unwrap();
+        for f in [Context::add, Context::mul, Context::min, Context::max] {
+            let ab = f(&mut ctx, a, b).unwrap();
+            let ba = f(&mut ctx, b, a).unwrap();
+            assert_eq!(ab, ba);
+            assert_eq!(
+                ctx.graph_hash(ab).unwrap(),
+                ctx.graph_hash(ba).unwrap()
+            );
+        }
+
+        // Non-commutative operations are distinct
+        let ab = ctx.sub(a, b).unwrap();
+        let ba = ctx.sub(b, a).unwrap();
+        assert_ne!(ab, ba);
+        assert_ne!(ctx.graph_hash(ab).unwrap(), ctx.graph_hash(ba).unwrap());
+
+        // Hashes are independent of node creation order
+        let mut other = Context::new();
+        let oy = other.y();
+        let ox = other.x();
+        let ob = other.sin(oy).unwrap();
+        let oa = other.square(ox).unwrap();
+        let m1 = other.min(ob, oa).unwrap();
+        let m2 = ctx.min(a, b).unwrap();
+        assert_eq!(other.graph_hash(m1).unwrap(), ctx.graph_hash(m2).unwrap());
+    }
+
     #[test]
     fn test_var_errors() {
         let mut ctx = Context::new();
@@ -1061,6 +3081,88 @@ mod test {
         assert_eq!(a1, a2);
     }
 
+    #[test]
+    fn test_f32_overflows() {
+        use crate::{
+            eval::{EzShape, MathShape, Shape, TracingEvaluator},
+            vm::VmShape,
+        };
+        let mut ctx = Context::new();
+        let x = ctx.x();
+
+        // (x * 1e20)² / 1e40 is x² in f64, but the square overflows in f32
+        let a = ctx.mul(x, 1e20).unwrap();
+        let sq = ctx.square(a).unwrap();
+        let root = ctx.div(sq, 1e20).unwrap();
+        let root = ctx.div(root, 1e20).unwrap();
+        assert_eq!(ctx.eval_xyz(root, 0.5, 0.0, 0.0).unwrap(), 0.25);
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_point_tape();
+        let mut eval = VmShape::new_point_eval();
+        let (v, _) = eval.eval(&tape, 0.5, 0.0, 0.0, &[]).unwrap();
+        assert!(v.is_infinite());
+        assert_eq!(ctx.f32_overflows(root, 0.5, 0.0, 0.0).unwrap(), [sq]);
+
+        // Overflow depends on the evaluation point
+        assert!(ctx.f32_overflows(root, 0.0, 0.0, 0.0).unwrap().is_empty());
+
+        // Constants are always checked; values which are already infinite in
+        // f64 aren't reported, since both paths agree
+        let big = ctx.constant(1e300);
+        let p = ctx.mul(x, big).unwrap();
+        let inf = ctx.mul(p, big).unwrap();
+        let root = ctx.min(inf, 1.0).unwrap();
+        assert_eq!(ctx.f32_overflows(root, 1.0, 0.0, 0.0).unwrap(), [big, p]);
+        assert_eq!(ctx.f32_overflows(root, 0.0, 0.0, 0.0).unwrap(), [big]);
+
+        // The tape builder warns about the overflowing constant, and the
+        // f32 path disagrees with the f64 path at x = 0
+        let shape = VmShape::new(&ctx, root).unwrap();
+        assert_eq!(shape.data().f32_overflows(), [1e300]);
+        let tape = shape.ez_point_tape();
+        assert_eq!(ctx.eval_xyz(root, 0.0, 0.0, 0.0).unwrap(), 0.0);
+        let (v, _) = eval.eval(&tape, 0.0, 0.0, 0.0, &[]).unwrap();
+        assert!(v.is_nan()); // 0 * inf
+
+        // Clamping the constants fixes the disagreement
+        let clamped = ctx.clamp_to_f32(root).unwrap();
+        let shape = VmShape::new(&ctx, clamped).unwrap();
+        assert!(shape.data().f32_overflows().is_empty());
+        let tape = shape.ez_point_tape();
+        let (v, _) = eval.eval(&tape, 0.0, 0.0, 0.0, &[]).unwrap();
+        assert_eq!(v, 0.0);
+
+        let big = ctx.constant(-1e39);
+        let c = ctx.clamp_to_f32(big).unwrap();
+        assert_eq!(ctx.const_value(c).unwrap(), Some(-f32::MAX as f64));
+        let inf = ctx.constant(f64::INFINITY);
+        assert_eq!(ctx.clamp_to_f32(inf).unwrap(), inf);
+    }
+
+    #[test]
+    fn test_eval_vars() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let r = ctx.var("r").unwrap();
+        let op = ctx.sub(x, r).unwrap();
+
+        let mut vars = BTreeMap::new();
+        vars.insert("X".to_owned(), 2.0);
+        for (r, expected) in [(0.5, 1.5), (1.0, 1.0), (3.0, -1.0)] {
+            vars.insert("r".to_owned(), r);
+            assert_eq!(ctx.eval(op, &vars).unwrap(), expected);
+        }
+
+        // Missing variables are an error, rather than a panic
+        vars.remove("r");
+        let e = ctx.eval(op, &vars).unwrap_err();
+        assert!(matches!(e, Error::UnknownVariable(ref v) if v == "r"));
+        assert!(matches!(
+            ctx.eval_xyz(r, 0.0, 0.0, 0.0),
+            Err(Error::UnknownVariable(..))
+        ));
+    }
+
     #[test]
     fn test_remap_xyz() {
         let mut ctx = Context::new();
@@ -1077,4 +3179,868 @@ mod test {
         let v = ctx.remap_xyz(s, [one, y, z]).unwrap();
         assert_eq!(ctx.eval_xyz(v, 0.0, 1.0, 0.0).unwrap(), 4.0);
     }
+
+    #[test]
+    fn test_clamp_mix() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let op = ctx.clamp(x, -1.0, 2.0).unwrap();
+        for (v, expected) in [
+            (-3.0, -1.0),
+            (-1.0, -1.0),
+            (0.5, 0.5),
+            (2.0, 2.0),
+            (5.0, 2.0),
+        ] {
+            assert_eq!(ctx.eval_xyz(op, v, 0.0, 0.0).unwrap(), expected);
+        }
+
+        // Repeated clamps share nodes
+        let len = ctx.len();
+        assert_eq!(ctx.clamp(x, -1.0, 2.0).unwrap(), op);
+        assert_eq!(ctx.len(), len);
+
+        // Clamping a constant folds to a constant
+        let c = ctx.clamp(5.0, 0.0, 1.0).unwrap();
+        assert_eq!(ctx.const_value(c).unwrap(), Some(1.0));
+
+        let op = ctx.mix(x, y, 0.0).unwrap();
+        assert_eq!(op, x);
+        let z = ctx.z();
+        let op = ctx.mix(x, y, z).unwrap();
+        for (t, expected) in [(0.0, 2.0), (0.5, 5.0), (1.0, 8.0)] {
+            assert_eq!(ctx.eval_xyz(op, 2.0, 8.0, t).unwrap(), expected);
+        }
+        let len = ctx.len();
+        assert_eq!(ctx.mix(x, y, z).unwrap(), op);
+        assert_eq!(ctx.len(), len);
+    }
+
+    #[test]
+    fn test_from_text_tolerant() {
+        let txt = "
+x var-x
+y var-y
+def frobnicate(a) = frob(a)
+r2 hypot x y
+s sum x y
+sum add x y
+m frobnicate x
+out max sum r2
+";
+        assert!(Context::from_text(txt.as_bytes()).is_err());
+        let (ctx, node, warnings) =
+            Context::from_text_tolerant(txt.as_bytes()).unwrap();
+        let lines: Vec<usize> = warnings
+            .iter()
+            .map(|w| match w {
+                Error::ParseError { line, source, .. } => {
+                    assert!(matches!(**source, Error::UnknownOpcode(..)));
+                    *line
+                }
+                e => panic!("unexpected warning {e:?}"),
+            })
+            .collect();
+        // The error in the macro is reported at its definition
+        assert_eq!(lines, [5, 6, 4]);
+
+        // The unknown `hypot` line is replaced by zero
+        assert_eq!(ctx.eval_xyz(node, 1.0, 2.0, 0.0).unwrap(), 3.0);
+        assert_eq!(ctx.eval_xyz(node, -1.0, -2.0, 0.0).unwrap(), 0.0);
+
+        // Other errors aren't tolerated
+        let txt = "x var-x\ny frob x\nz add x q\n";
+        assert!(Context::from_text_tolerant(txt.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let zero = ctx.constant(0.0);
+        let one = ctx.constant(1.0);
+
+        // Build nodes directly, bypassing the builders' simplifications
+        let a = ctx.op_binary(x, one, BinaryOpcode::Mul).unwrap();
+        let b = ctx.op_binary(zero, a, BinaryOpcode::Add).unwrap();
+        let c = ctx.op_binary(b, zero, BinaryOpcode::Sub).unwrap();
+        let d = ctx.op_binary(c, one, BinaryOpcode::Div).unwrap();
+        let e = ctx.op_binary(d, x, BinaryOpcode::Min).unwrap();
+        let f = ctx.op_binary(e, y, BinaryOpcode::Max).unwrap();
+        let g = ctx.op_unary(f, UnaryOpcode::Neg).unwrap();
+        let h = ctx.op_unary(g, UnaryOpcode::Neg).unwrap();
+        let m = ctx.op_binary(one, h, BinaryOpcode::Mul).unwrap();
+        let root = ctx.op_binary(m, m, BinaryOpcode::Max).unwrap();
+        assert_eq!(ctx.node_count(root).unwrap(), 14);
+
+        let folded = ctx.fold_constants(root).unwrap();
+        assert_eq!(ctx.node_count(folded).unwrap(), 3);
+        let expected = ctx.max(x, y).unwrap();
+        assert_eq!(folded, expected);
+        for (px, py) in [(0.0, 1.0), (-2.0, 3.0), (f64::INFINITY, 0.0)] {
+            assert_eq!(
+                ctx.eval_xyz(root, px, py, 0.0).unwrap(),
+                ctx.eval_xyz(folded, px, py, 0.0).unwrap()
+            );
+        }
+
+        // `x * 0` isn't folded, since it's not exact for infinite `x`
+        let xz = ctx.op_binary(x, zero, BinaryOpcode::Mul).unwrap();
+        assert_eq!(ctx.fold_constants(xz).unwrap(), xz);
+        let v = ctx.eval_xyz(xz, f64::INFINITY, 0.0, 0.0).unwrap();
+        assert!(v.is_nan());
+    }
+
+    #[test]
+    fn test_twist_bend() {
+        // A flat bar: |x| < 1, |y| < 0.2, |z| < 2
+        fn bar(ctx: &mut Context) -> Node {
+            let x = ctx.x();
+            let y = ctx.y();
+            let z = ctx.z();
+            let ax = ctx.abs(x).unwrap();
+            let ay = ctx.abs(y).unwrap();
+            let az = ctx.abs(z).unwrap();
+            let bx = ctx.sub(ax, 1.0).unwrap();
+            let by = ctx.sub(ay, 0.2).unwrap();
+            let bz = ctx.sub(az, 2.0).unwrap();
+            let b = ctx.max(bx, by).unwrap();
+            ctx.max(b, bz).unwrap()
+        }
+
+        let mut ctx = Context::new();
+        let b = bar(&mut ctx);
+        let k = std::f64::consts::FRAC_PI_4;
+        let twisted = ctx.twist(b, k).unwrap();
+
+        // The bar rotates progressively with height
+        for z in [-1.5, 0.0, 0.5, 1.0, 1.5] {
+            let a = k * z;
+            let along = ctx
+                .eval_xyz(twisted, 0.8 * a.cos(), 0.8 * a.sin(), z)
+                .unwrap();
+            assert!(along < 0.0, "expected material along the bar at {z}");
+            let (c, s) = ((a + 0.5).cos(), (a + 0.5).sin());
+            let off = ctx.eval_xyz(twisted, 0.8 * c, 0.8 * s, z).unwrap();
+            assert!(off > 0.0, "expected no material off the bar at {z}");
+        }
+        assert!(ctx.eval_xyz(twisted, 0.0, 0.0, 2.5).unwrap() > 0.0);
+
+        // The twist angle is zero at z = 0 (the middle of the bar), so that
+        // slice is unchanged
+        for (x, y) in [(0.5, 0.1), (0.9, -0.3), (-0.2, 0.15)] {
+            let a = ctx.eval_xyz(b, x, y, 0.0).unwrap();
+            let t = ctx.eval_xyz(twisted, x, y, 0.0).unwrap();
+            assert!((a - t).abs() < 1e-12);
+        }
+
+        // Bending moves the bar's centerline onto y = -x tan(k x)
+        let k = 0.3;
+        let bent = ctx.bend(b, k).unwrap();
+        for x in [-0.8f64, 0.5, 0.9] {
+            let y = -x * (k * x).tan();
+            assert!(ctx.eval_xyz(bent, x, y, 0.0).unwrap() < 0.0);
+            assert!(ctx.eval_xyz(bent, x, y + 0.4, 0.0).unwrap() > 0.0);
+        }
+        assert!(ctx.eval_xyz(bent, 0.9, 0.0, 0.0).unwrap() > 0.0);
+        assert!(ctx.eval_xyz(b, 0.9, 0.0, 0.0).unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_mirror_radial_symmetry() {
+        let mut ctx = Context::new();
+
+        // A sphere of radius 0.5 at (1, 0.5, 0.25), in the +X +Y +Z octant
+        let sphere = test_sphere(&mut ctx, [1.0, 0.5, 0.25], 0.5);
+
+        let mx = ctx.mirror_x(sphere).unwrap();
+        let my = ctx.mirror_y(mx).unwrap();
+        let all = ctx.mirror_z(my).unwrap();
+        for p in [[1.0, 0.5, 0.25], [1.3, 0.2, 0.0], [0.1, 2.0, 0.7]] {
+            let v = ctx.eval_xyz(sphere, p[0], p[1], p[2]).unwrap();
+            for sx in [-1.0, 1.0] {
+                for sy in [-1.0, 1.0] {
+                    for sz in [-1.0, 1.0] {
+                        let q = [p[0] * sx, p[1] * sy, p[2] * sz];
+                        let m = ctx.eval_xyz(all, q[0], q[1], q[2]).unwrap();
+                        assert_eq!(v, m);
+                    }
+                }
+            }
+        }
+        // Mirroring only affects the chosen axis
+        let v = ctx.eval_xyz(mx, -1.0, -0.5, 0.25).unwrap();
+        assert!(v > 0.0);
+        let v = ctx.eval_xyz(mx, -1.0, 0.5, 0.25).unwrap();
+        assert!(v < 0.0);
+
+        // Radial symmetry of a circle at (1, 0)
+        let circle = test_sphere(&mut ctx, [1.0, 0.0], 0.2);
+        let n = 6;
+        let sym = ctx.radial_symmetry(circle, n).unwrap();
+        let wedge = std::f64::consts::TAU / n as f64;
+        for k in 0..n {
+            let a = wedge * k as f64;
+            let v = ctx.eval_xyz(sym, a.cos(), a.sin(), 0.0).unwrap();
+            assert!((v + 0.2).abs() < 1e-9, "missing copy {k}: {v}");
+            let a = a + wedge / 2.0;
+            let v = ctx.eval_xyz(sym, a.cos(), a.sin(), 0.0).unwrap();
+            assert!(v > 0.0, "unexpected material between copies");
+        }
+        for (px, py) in [(0.3, 0.1), (-0.7, 0.4), (0.2, -1.5), (-1.0, 0.0)] {
+            let v = ctx.eval_xyz(sym, px, py, 0.0).unwrap();
+            for k in 1..n {
+                let (s, c) = (wedge * k as f64).sin_cos();
+                let (qx, qy) = (px * c - py * s, px * s + py * c);
+                let w = ctx.eval_xyz(sym, qx, qy, 0.0).unwrap();
+                assert!((v - w).abs() < 1e-9, "{v} != {w} at {k}");
+            }
+        }
+        assert_eq!(ctx.eval_xyz(sym, 0.0, 0.0, 0.0).unwrap(), 0.8);
+        assert_eq!(ctx.radial_symmetry(circle, 1).unwrap(), circle);
+    }
+
+    #[test]
+    fn test_classify() {
+        let mut ctx = Context::new();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 1.0);
+
+        let eps = 0.01;
+        for (p, expected) in [
+            ([0.0, 0.0, 0.0], Classification::Inside),
+            ([0.98, 0.0, 0.0], Classification::Inside),
+            ([0.0, 0.995, 0.0], Classification::Surface),
+            ([0.0, 0.0, 1.0], Classification::Surface),
+            ([-1.005, 0.0, 0.0], Classification::Surface),
+            ([0.0, 1.02, 0.0], Classification::Outside),
+            ([2.0, 2.0, 2.0], Classification::Outside),
+        ] {
+            let c = ctx.classify(sphere, p[0], p[1], p[2], eps).unwrap();
+            assert_eq!(c, expected, "bad classification at {p:?}");
+        }
+
+        // With no tolerance, only the sign matters
+        let c = ctx.classify(sphere, 0.0, 0.0, 0.999, 0.0).unwrap();
+        assert_eq!(c, Classification::Inside);
+
+        let nan = ctx.sqrt(-1.0).unwrap();
+        let c = ctx.classify(nan, 0.0, 0.0, 0.0, eps).unwrap();
+        assert_eq!(c, Classification::Outside);
+    }
+
+    #[test]
+    fn test_segment2d() {
+        fn distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+            let d = |p: [f64; 2], q: [f64; 2]| (p[0] - q[0]).hypot(p[1] - q[1]);
+            let (bx, by) = (b[0] - a[0], b[1] - a[1]);
+            let len2 = bx * bx + by * by;
+            if len2 == 0.0 {
+                return d(p, a);
+            }
+            let t = ((p[0] - a[0]) * bx + (p[1] - a[1]) * by) / len2;
+            let t = t.clamp(0.0, 1.0);
+            d(p, [a[0] + t * bx, a[1] + t * by])
+        }
+
+        let mut ctx = Context::new();
+        for (a, b) in [
+            ([0.0, 0.0], [1.0, 0.0]),
+            ([-0.5, 0.25], [0.75, -1.0]),
+            ([0.3, 0.3], [0.3, 0.3]),
+        ] {
+            let op = ctx.segment2d(a, b).unwrap();
+            for i in 0..20 {
+                for j in 0..20 {
+                    let x = i as f64 / 5.0 - 2.0;
+                    let y = j as f64 / 5.0 - 2.0;
+                    let v = ctx.eval_xyz(op, x, y, 1.5).unwrap();
+                    let expected = distance([x, y], a, b);
+                    assert!(
+                        (v - expected).abs() < 1e-12,
+                        "mismatch at ({x}, {y}): {v} != {expected}"
+                    );
+                }
+            }
+            assert_eq!(ctx.eval_xyz(op, a[0], a[1], 0.0).unwrap(), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_metaball() {
+        // Checks whether every point on the X axis between the two centers is
+        // inside the shape, i.e. whether the blobs are connected
+        fn connected(ctx: &Context, op: Node, c: f64) -> bool {
+            (0..=100).all(|i| {
+                let x = c * (i as f64 / 50.0 - 1.0);
+                ctx.eval_xyz(op, x, 0.0, 0.0).unwrap() < 0.0
+            })
+        }
+
+        let mut ctx = Context::new();
+        for (c, merged) in [(0.9, true), (5.0, false)] {
+            // Distance from the points (±c, 0)
+            let a = test_sphere(&mut ctx, [-c, 0.0], 0.0);
+            let b = test_sphere(&mut ctx, [c, 0.0], 0.0);
+            let op = ctx.metaball(&[(a, 1.0), (b, 1.0)], 0.5).unwrap();
+
+            // Each blob's center is inside, and far away is outside
+            assert!(ctx.eval_xyz(op, -c, 0.0, 0.0).unwrap() < 0.0);
+            assert!(ctx.eval_xyz(op, c, 0.0, 0.0).unwrap() < 0.0);
+            assert!(ctx.eval_xyz(op, 0.0, 10.0, 0.0).unwrap() > 0.0);
+            assert_eq!(connected(&ctx, op, c), merged, "c = {c}");
+        }
+
+        let empty = ctx.metaball(&[], 0.5).unwrap();
+        assert_eq!(ctx.const_value(empty).unwrap(), Some(0.5));
+    }
+
+    #[test]
+    fn test_bind_var() {
+        let mut ctx = Context::new();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 1.0);
+
+        // These are deduplicated against the sphere's nodes
+        let x = ctx.x();
+        let z = ctx.z();
+        let x2 = ctx.square(x).unwrap();
+        let z2 = ctx.square(z).unwrap();
+
+        let slice = ctx.bind_var(sphere, z, 0.5).unwrap();
+        for i in 0..10 {
+            for j in 0..10 {
+                let x = i as f64 / 5.0 - 1.0;
+                let y = j as f64 / 5.0 - 1.0;
+                // The Z value is ignored by the specialized graph
+                assert_eq!(
+                    ctx.eval_xyz(slice, x, y, 100.0).unwrap(),
+                    ctx.eval_xyz(sphere, x, y, 0.5).unwrap()
+                );
+            }
+        }
+
+        // `z * z` has been folded into a constant
+        let mut seen = BTreeSet::new();
+        let mut todo = vec![slice];
+        while let Some(n) = todo.pop() {
+            if seen.insert(n) {
+                todo.extend(ctx.get_op(n).unwrap().iter_children());
+            }
+        }
+        assert!(!seen.contains(&z));
+        assert!(!seen.contains(&z2));
+        assert!(seen.contains(&ctx.constant(0.25)));
+
+        // Named variables can also be bound
+        let a = ctx.var("a").unwrap();
+        let s = ctx.mul(x, a).unwrap();
+        let s = ctx.bind_var(s, a, 2.0).unwrap();
+        assert_eq!(ctx.eval_xyz(s, 3.0, 0.0, 0.0).unwrap(), 6.0);
+
+        assert!(matches!(ctx.bind_var(s, x2, 1.0), Err(Error::BadVar)));
+    }
+
+    #[test]
+    fn test_map_constants() {
+        let mut ctx = Context::new();
+        let r = test_sphere(&mut ctx, [0.0; 3], 0.0);
+        let sphere = ctx.sub(r, 1.0).unwrap();
+        let len = ctx.len();
+
+        let scaled = ctx.map_constants(sphere, |c| c * 2.0).unwrap();
+        assert_ne!(scaled, sphere);
+        assert_eq!(ctx.eval_xyz(scaled, 2.0, 0.0, 0.0).unwrap(), 0.0);
+        assert_eq!(ctx.eval_xyz(scaled, 0.0, 0.0, -2.0).unwrap(), 0.0);
+        assert_eq!(ctx.eval_xyz(scaled, 0.0, 3.0, 0.0).unwrap(), 1.0);
+
+        // The original graph is unchanged
+        assert_eq!(ctx.eval_xyz(sphere, 1.0, 0.0, 0.0).unwrap(), 0.0);
+
+        // Rebuilding is deduplicated, so only the new constant and the final
+        // subtraction are added (and doing it again adds nothing)
+        assert_eq!(ctx.len(), len + 2);
+        let again = ctx.map_constants(sphere, |c| c * 2.0).unwrap();
+        assert_eq!(again, scaled);
+        assert_eq!(ctx.len(), len + 2);
+
+        // A graph without constants is returned as-is
+        assert_eq!(ctx.map_constants(r, |c| c * 2.0).unwrap(), r);
+    }
+
+    #[test]
+    fn test_from_text_macro() {
+        let txt = "
+def sphere(r) = sub(sqrt(add(add(square(var-x), square(var-y)), square(var-z))), r)
+r1 const 1
+r2 const 2
+s1 sphere r1
+s2 sphere r2
+out min s1 s2
+";
+        let (ctx, root) = Context::from_text(txt.as_bytes()).unwrap();
+        assert_eq!(ctx.eval_xyz(root, 3.0, 0.0, 0.0).unwrap(), 1.0);
+
+        // x, y, z, their squares, both sums, sqrt, and one const + sub per
+        // sphere, then the final min
+        assert_eq!(ctx.len(), 9 + 2 * 2 + 1);
+
+        // Both instances share the radius-independent subgraph
+        let Op::Binary(BinaryOpcode::Min, a, b) = ctx.get_op(root).unwrap()
+        else {
+            panic!("invalid root op");
+        };
+        let (Op::Binary(_, da, _), Op::Binary(_, db, _)) =
+            (ctx.get_op(*a).unwrap(), ctx.get_op(*b).unwrap())
+        else {
+            panic!("invalid sphere op");
+        };
+        assert_eq!(da, db);
+
+        // Expanding the same macro with the same argument dedups entirely
+        let txt = "
+def double(a) = add(a, a)
+x var-x
+a double x
+b double x
+out sub a b
+";
+        let (ctx, root) = Context::from_text(txt.as_bytes()).unwrap();
+        let Op::Binary(BinaryOpcode::Sub, a, b) = ctx.get_op(root).unwrap()
+        else {
+            panic!("invalid root op");
+        };
+        assert_eq!(a, b);
+
+        let inner = |txt: &str| match Context::from_text(txt.as_bytes()) {
+            Err(Error::ParseError { source, .. }) => *source,
+            _ => panic!("expected a parse error"),
+        };
+        let bad = "def f(a) = neg(a)\nx var-x\ny f x x\n";
+        assert!(matches!(inner(bad), Error::BadMacroArgs(_, 1, 2)));
+        let bad = "def f(a) = neg(a, a)\nx var-x\ny f x\n";
+        assert!(matches!(inner(bad), Error::BadOpArgs(_, 2)));
+        let bad = "def f(a) = add(a)\nx var-x\ny f x\n";
+        assert!(matches!(inner(bad), Error::BadOpArgs(_, 1)));
+        let bad = "x var-x\ny f x\n";
+        assert!(matches!(inner(bad), Error::UnknownOpcode(_)));
+
+        // Recursive macros are rejected when they're defined, even if they're
+        // never used
+        let bad = "def f(a) = neg(f(a))\nx var-x\n";
+        assert!(matches!(inner(bad), Error::RecursiveMacro(n) if n == "f"));
+        let bad = "def f(a) = g(a)\ndef g(a) = abs(h(a))\ndef h(a) = f(a)\n";
+        assert!(matches!(inner(bad), Error::RecursiveMacro(n) if n == "h"));
+
+        // Macros may use macros which are defined later, as long as they're
+        // defined by the time of expansion
+        let txt = "def f(a) = g(a)\ndef g(a) = neg(a)\nx var-x\ny f x\n";
+        let (ctx, root) = Context::from_text(txt.as_bytes()).unwrap();
+        assert_eq!(ctx.eval_xyz(root, 2.0, 0.0, 0.0).unwrap(), -2.0);
+        let bad = "def f(a) = g(a)\nx var-x\ny f x\ndef g(a) = neg(a)\n";
+        assert!(matches!(inner(bad), Error::UnknownOpcode(n) if n == "g"));
+
+        // Empty bodies (including ones that are only a comment) are errors
+        for bad in ["def f(a) =\n", "def f(a) = # nothing\n"] {
+            assert!(matches!(inner(bad), Error::EmptyMacro(n) if n == "f"));
+        }
+
+        // Syntax errors
+        let bad = "def f a = a\n";
+        assert!(matches!(inner(bad), Error::ExpectedToken("(", _)));
+        let bad = "def f(a) a\n";
+        assert!(matches!(inner(bad), Error::ExpectedEquals(_)));
+        let bad = "def f(a) = neg(a\n";
+        assert!(matches!(inner(bad), Error::UnexpectedEndOfLine));
+        let bad = "def f(a) = neg(a) a\n";
+        assert!(matches!(inner(bad), Error::ExpectedToken("end of line", _)));
+    }
+
+    #[test]
+    fn test_from_text_outputs() {
+        let txt = "
+x var-x
+y var-y
+r const 1
+x2 square x
+y2 square y
+s add x2 y2
+d sqrt s
+circle sub d r
+output circle = circle
+shifted sub x r
+output plane = shifted
+output both = s
+both min circle shifted
+";
+        let (ctx, root, outputs) =
+            Context::from_text_outputs(txt.as_bytes()).unwrap();
+        assert_eq!(outputs.len(), 3);
+        let eval =
+            |name: &str, x, y| ctx.eval_xyz(outputs[name], x, y, 0.0).unwrap();
+        assert_eq!(eval("circle", 3.0, 4.0), 4.0);
+        assert_eq!(eval("plane", 3.0, 4.0), 2.0);
+        assert_eq!(eval("both", 3.0, 4.0), 25.0);
+
+        // The root is still the last node in the file
+        assert_eq!(ctx.eval_xyz(root, 3.0, 4.0, 0.0).unwrap(), 2.0);
+        let (_, node) = Context::from_text(txt.as_bytes()).unwrap();
+        assert_eq!(node, root);
+
+        // Files without outputs behave as before
+        let (ctx, root, outputs) = Context::from_text_outputs(
+            "x var-x
+y neg x
+"
+            .as_bytes(),
+        )
+        .unwrap();
+        assert!(outputs.is_empty());
+        assert_eq!(ctx.eval_xyz(root, 2.0, 0.0, 0.0).unwrap(), -2.0);
+
+        let inner = |txt: &str| match Context::from_text_outputs(txt.as_bytes())
+        {
+            Err(Error::ParseError { source, .. }) => *source,
+            _ => panic!("expected a parse error"),
+        };
+        let bad = "x var-x
+output a x
+";
+        assert!(matches!(inner(bad), Error::ExpectedEquals(..)));
+        let bad = "x var-x
+output a = y
+";
+        assert!(matches!(inner(bad), Error::UnknownVariable(..)));
+        let bad = "x var-x
+output a =
+";
+        assert!(matches!(inner(bad), Error::UnexpectedEndOfLine));
+        let bad = "x var-x
+output a = x
+output a = x
+";
+        assert!(matches!(inner(bad), Error::DuplicateOutput(..)));
+    }
+
+    #[test]
+    fn test_from_text_error_location() {
+        let check = |txt: &str, line: usize, column: usize| {
+            let Err(Error::ParseError {
+                line: l,
+                column: c,
+                offset,
+                snippet,
+                source,
+            }) = Context::from_text(txt.as_bytes())
+            else {
+                panic!("expected a parse error for {txt:?}");
+            };
+            assert_eq!((l, c), (line, column), "bad location for {txt:?}");
+
+            // The offset and snippet agree with the line and column
+            let text = txt.split('\n').nth(line - 1).unwrap();
+            let text = text.trim_end_matches('\r');
+            let line_start: usize =
+                txt.split('\n').take(line - 1).map(|t| t.len() + 1).sum();
+            assert_eq!(offset - line_start, column - 1);
+            assert_eq!(snippet, format!("{text}\n{}^", " ".repeat(column - 1)));
+            *source
+        };
+
+        let e = check("x var-x\ny frob x\n", 2, 3);
+        assert!(matches!(e, Error::UnknownOpcode(..)));
+
+        // Comments and blank lines are still counted
+        let e = check("# comment\n\nx var-x\ny add x  q\n", 4, 10);
+        assert!(matches!(e, Error::UnknownVariable(..)));
+
+        // Missing arguments point at the end of the line
+        let e = check("x var-x\ny add x\n", 2, 8);
+        assert!(matches!(e, Error::UnexpectedEndOfLine));
+        let e = check("x\n", 1, 2);
+        assert!(matches!(e, Error::UnexpectedEndOfLine));
+
+        let e = check("x var-x\n  c const 1.2.3\n", 2, 11);
+        assert!(matches!(e, Error::BadConstant(..)));
+
+        // Windows line endings don't affect columns
+        let e = check("x var-x\r\ny frob x\r\n", 2, 3);
+        assert!(matches!(e, Error::UnknownOpcode(..)));
+
+        // Errors in macro bodies point into the body
+        let e = check("def f(a) = neg(frob(a))\nx var-x\ny f x\n", 1, 16);
+        assert!(matches!(e, Error::UnknownOpcode(..)));
+        let e = check("def f(a) = neg(a b)\n", 1, 18);
+        assert!(matches!(e, Error::ExpectedToken(..)));
+        let e = check("x var-x\ndef f(a) = \n", 2, 5);
+        assert!(matches!(e, Error::EmptyMacro(..)));
+    }
+
+    #[test]
+    fn test_eval_with_feature() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let a = test_sphere(&mut ctx, [-1.0, 0.0, 0.0], 0.5);
+        let b = test_sphere(&mut ctx, [1.0, 0.0, 0.0], 0.25);
+        let u = ctx.min(a, b).unwrap();
+
+        let (v, f) = ctx.eval_with_feature(u, -1.0, 0.0, 0.0).unwrap();
+        assert_eq!(v, -0.5);
+        assert_eq!(f, a);
+
+        let (v, f) = ctx.eval_with_feature(u, 2.0, 0.0, 0.0).unwrap();
+        assert_eq!(v, 0.75);
+        assert_eq!(f, b);
+
+        // Features are found through nested min / max operations
+        let c = test_sphere(&mut ctx, [-1.0, 0.0, 0.0], 0.75);
+        let neg_c = ctx.neg(c).unwrap();
+        let cut = ctx.max(u, neg_c).unwrap();
+        let (v, f) = ctx.eval_with_feature(cut, -1.0, 0.0, 0.0).unwrap();
+        assert_eq!(v, 0.75);
+        assert_eq!(f, neg_c);
+        let (_, f) = ctx.eval_with_feature(cut, 0.9, 0.0, 0.0).unwrap();
+        assert_eq!(f, b);
+
+        // Non-branching nodes are their own features
+        assert_eq!(ctx.eval_with_feature(x, 1.0, 2.0, 3.0).unwrap(), (1.0, x));
+    }
+
+    #[test]
+    fn test_to_latex() {
+        let mut ctx = Context::new();
+        let r = test_sphere(&mut ctx, [0.0; 3], 0.0);
+        let sphere = ctx.sub(r, 1.0).unwrap();
+        assert_eq!(
+            ctx.to_latex(sphere).unwrap(),
+            r"\sqrt{x^{2} + y^{2} + z^{2}} - 1"
+        );
+
+        // A hollow shell, where the radius is used twice
+        let inner = ctx.sub(0.5, r).unwrap();
+        let shell = ctx.max(sphere, inner).unwrap();
+        assert_eq!(
+            ctx.to_latex(shell).unwrap(),
+            r"\text{let } s_{0} = \sqrt{x^{2} + y^{2} + z^{2}} \\".to_owned()
+                + "\n"
+                + r"\max\left(s_{0} - 1, 0.5 - s_{0}\right)"
+        );
+
+        // Check parenthesization
+        let [x, y, z] = [ctx.x(), ctx.y(), ctx.z()];
+        let sum = ctx.add(x, y).unwrap();
+        let diff = ctx.sub(x, sum).unwrap();
+        let prod = ctx.mul(diff, z).unwrap();
+        let neg = ctx.neg(prod).unwrap();
+        let div = ctx.div(neg, 2.0).unwrap();
+        assert_eq!(
+            ctx.to_latex(div).unwrap(),
+            r"\frac{-z \cdot \left(x - \left(x + y\right)\right)}{2}"
+        );
+        let a = ctx.var("a_1").unwrap();
+        let sq = ctx.square(sum).unwrap();
+        let s = ctx.sin(a).unwrap();
+        let out = ctx.mul(sq, s).unwrap();
+        assert_eq!(
+            ctx.to_latex(out).unwrap(),
+            r"\left(x + y\right)^{2} \cdot \sin\left(\mathit{a\_1}\right)"
+        );
+    }
+
+    #[test]
+    fn test_to_latex_deep() {
+        // A chain of 100k nodes, which would overflow the stack if converted
+        // recursively
+        let mut ctx = Context::new();
+        let mut out = ctx.x();
+        for _ in 0..50_000 {
+            let sum = ctx.add(out, 1.0).unwrap();
+            out = ctx.mul(sum, 2.0).unwrap();
+        }
+        let s = ctx.to_latex(out).unwrap();
+
+        // Commutative ops are sorted by node index, so only the innermost ops
+        // (built before the constants) keep their arguments in order
+        let expected = r"2 \cdot \left(1 + ".repeat(49_999)
+            + r"\left(x + 1\right) \cdot 2"
+            + &*r"\right)".repeat(49_999);
+        assert!(s == expected, "bad LaTeX for deep graph");
+    }
+
+    #[test]
+    fn test_eval_xyz_transformed() {
+        let mut ctx = Context::new();
+
+        // Sphere with radius 0.5, centered at [1, 0, 0]
+        let sphere = test_sphere(&mut ctx, [1.0, 0.0, 0.0], 0.5);
+
+        // Rotate by 90° about the Z axis, then translate by [0, 0, 2], which
+        // moves the center to [0, 1, 2].  The inverse transform maps
+        // [x, y, z] => [y, -x, z - 2].
+        let inv = [
+            [0.0, 1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, -2.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        let eval = |p| ctx.eval_xyz_transformed(sphere, p, &inv).unwrap();
+        assert_eq!(eval([0.0, 1.0, 2.0]), -0.5);
+        for p in [
+            [0.0, 1.5, 2.0],
+            [0.0, 0.5, 2.0],
+            [0.5, 1.0, 2.0],
+            [-0.5, 1.0, 2.0],
+            [0.0, 1.0, 2.5],
+            [0.0, 1.0, 1.5],
+        ] {
+            let v = eval(p);
+            assert!(v.abs() < 1e-12, "{p:?} is not on the surface ({v})");
+        }
+        // The original center is now outside the shape
+        assert_eq!(eval([1.0, 0.0, 0.0]), 6.0f64.sqrt() - 0.5);
+
+        // Homogeneous coordinates are normalized
+        let inv = inv.map(|row| row.map(|v| v * 2.0));
+        let v = ctx.eval_xyz_transformed(sphere, [0.0, 1.5, 2.0], &inv);
+        assert!(v.unwrap().abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_difference_all() {
+        // Square from -4 to 4 on X and Y
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let ax = ctx.abs(x).unwrap();
+        let ay = ctx.abs(y).unwrap();
+        let body = ctx.max(ax, ay).unwrap();
+        let body = ctx.sub(body, 4.0).unwrap();
+
+        let centers = [(-2.0, -2.0), (2.0, -2.0), (-2.0, 2.0), (2.0, 2.0)];
+        let centers = centers.into_iter().chain([(0.0, 0.0)]);
+        let tools: Vec<Node> = centers
+            .clone()
+            .map(|(cx, cy)| test_sphere(&mut ctx, [cx, cy], 0.5))
+            .collect();
+        let op = ctx.difference_all(body, &tools).unwrap();
+
+        // Each hole is empty, with solid material between the holes
+        for (cx, cy) in centers {
+            assert!(ctx.eval_xyz(op, cx, cy, 0.0).unwrap() > 0.0);
+            let v = ctx.eval_xyz(op, cx + 0.75, cy + 0.1, 0.0).unwrap();
+            assert!(v < 0.0, "expected material next to ({cx}, {cy})");
+        }
+        assert!(ctx.eval_xyz(op, -1.0, 1.0, 0.0).unwrap() < 0.0);
+        assert!(ctx.eval_xyz(op, 5.0, 0.0, 0.0).unwrap() > 0.0);
+
+        // The tools are combined as a balanced tree, which is shallower than
+        // a chain of pairwise differences
+        let mut chain = body;
+        for &t in &tools {
+            let t = ctx.neg(t).unwrap();
+            chain = ctx.max(chain, t).unwrap();
+        }
+        assert!(ctx.depth(op).unwrap() < ctx.depth(chain).unwrap());
+        for (x, y) in [(0.0, 0.0), (1.0, 1.5), (-2.2, 2.1), (3.0, -3.9)] {
+            assert_eq!(
+                ctx.eval_xyz(op, x, y, 0.0).unwrap(),
+                ctx.eval_xyz(chain, x, y, 0.0).unwrap()
+            );
+        }
+
+        // No tools leaves the body untouched
+        assert_eq!(ctx.difference_all(body, &[]).unwrap(), body);
+        assert!(matches!(
+            ctx.difference_all(body, &[Node(usize::MAX)]),
+            Err(Error::BadNode)
+        ));
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        assert_eq!(ctx.depth(x).unwrap(), 0);
+
+        // Linear chain: ((x + 1) + 1) + ...
+        let mut linear = x;
+        for _ in 0..8 {
+            linear = ctx.add(linear, 1.0).unwrap();
+        }
+        assert_eq!(ctx.depth(linear).unwrap(), 8);
+
+        // Balanced tree over 8 leaves
+        let mut layer: Vec<Node> =
+            (0..8).map(|i| ctx.var(&format!("v{i}")).unwrap()).collect();
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|c| ctx.max(c[0], c[1]).unwrap())
+                .collect();
+        }
+        assert_eq!(ctx.depth(layer[0]).unwrap(), 3);
+
+        // Shared subexpressions are counted along the longest path
+        let y = ctx.y();
+        let sum = ctx.add(linear, y).unwrap();
+        let out = ctx.mul(sum, linear).unwrap();
+        assert_eq!(ctx.depth(out).unwrap(), 10);
+
+        // Very deep graphs don't overflow the stack
+        let mut deep = x;
+        for _ in 0..100_000 {
+            deep = ctx.neg(deep).unwrap();
+        }
+        assert_eq!(ctx.depth(deep).unwrap(), 100_000);
+
+        assert!(matches!(ctx.depth(Node(usize::MAX)), Err(Error::BadNode)));
+    }
+
+    #[test]
+    fn test_piecewise() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+
+        // Two half-spaces, tilted in opposite directions, which meet along
+        // the seam at x = 0 (where both evaluate to y)
+        let left = ctx.add(y, x).unwrap();
+        let right = ctx.sub(y, x).unwrap();
+        let op = ctx.piecewise(&[(x, left)], right).unwrap();
+        assert_eq!(ctx.eval_xyz(op, -1.0, 0.5, 0.0).unwrap(), -0.5);
+        assert_eq!(ctx.eval_xyz(op, 1.0, 0.5, 0.0).unwrap(), -0.5);
+        assert_eq!(ctx.eval_xyz(op, 0.0, 0.5, 0.0).unwrap(), 0.5);
+
+        let bounds = [[-1.0, 1.0]; 3];
+        let jump = ctx
+            .piecewise_discontinuity(&[(x, left)], right, bounds, 7)
+            .unwrap();
+        assert!(jump < 1e-9, "unexpected discontinuity: {jump}");
+
+        // Offsetting one side produces a jump at the seam
+        let shifted = ctx.add(right, 0.25).unwrap();
+        let jump = ctx
+            .piecewise_discontinuity(&[(x, left)], shifted, bounds, 7)
+            .unwrap();
+        assert!((jump - 0.25).abs() < 1e-9, "bad discontinuity: {jump}");
+
+        // A seam outside the sampled bounds isn't checked
+        let cond = ctx.sub(x, 2.0).unwrap();
+        let jump = ctx
+            .piecewise_discontinuity(&[(cond, left)], shifted, bounds, 7)
+            .unwrap();
+        assert_eq!(jump, 0.0);
+
+        // Boundaries covered by an earlier region are ignored
+        let everywhere = ctx.constant(-1.0);
+        let jump = ctx
+            .piecewise_discontinuity(
+                &[(everywhere, y), (x, left)],
+                shifted,
+                bounds,
+                7,
+            )
+            .unwrap();
+        assert_eq!(jump, 0.0);
+    }
 }
@@ -0,0 +1,342 @@
+//! Monotonicity analysis over a region
+use super::{BinaryOpcode, Context, IndexVec, Node, Op, UnaryOpcode};
+use crate::{types::Interval, Error};
+use std::collections::BTreeMap;
+
+/// How a function changes along one axis within a region
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Monotonicity {
+    /// The function doesn't depend on this axis
+    Constant,
+    /// The function is non-decreasing along this axis
+    Increasing,
+    /// The function is non-increasing along this axis
+    Decreasing,
+    /// The function may both increase and decrease along this axis
+    Unknown,
+}
+
+impl Monotonicity {
+    /// Returns the sign of values within an interval
+    ///
+    /// This treats an interval which is entirely non-negative as
+    /// `Increasing`, entirely non-positive as `Decreasing`, and exactly zero
+    /// as `Constant`, so it can be multiplied by a derivative's sign.
+    fn sign_of(i: Interval) -> Self {
+        if i.has_nan() {
+            Self::Unknown
+        } else if i.lower() == 0.0 && i.upper() == 0.0 {
+            Self::Constant
+        } else if i.lower() >= 0.0 {
+            Self::Increasing
+        } else if i.upper() <= 0.0 {
+            Self::Decreasing
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Flips the direction, e.g. for negation
+    fn flip(self) -> Self {
+        match self {
+            Self::Increasing => Self::Decreasing,
+            Self::Decreasing => Self::Increasing,
+            m => m,
+        }
+    }
+
+    /// Sign of the sum of two derivatives
+    fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Constant, m) | (m, Self::Constant) => m,
+            (a, b) if a == b => a,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Sign of the product of two signs
+    fn mul(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Constant, _) | (_, Self::Constant) => Self::Constant,
+            (Self::Unknown, _) | (_, Self::Unknown) => Self::Unknown,
+            (a, b) if a == b => Self::Increasing,
+            _ => Self::Decreasing,
+        }
+    }
+
+    /// Returns `Constant` if the input is constant, and `Unknown` otherwise
+    fn unknown(self) -> Self {
+        if self == Self::Constant {
+            Self::Constant
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+impl Context {
+    /// Checks whether a node is monotonic along each axis within a region
+    ///
+    /// The node is evaluated with interval arithmetic over the box
+    /// `x × y × z`, and the sign of its partial derivative along each axis is
+    /// propagated through the graph (e.g. using the product rule for
+    /// multiplication, with the sign of each factor taken from its interval).
+    /// If the result is [`Monotonicity::Increasing`] or
+    /// [`Monotonicity::Decreasing`] on every axis, then the node's extrema
+    /// within the box are at its corners.
+    ///
+    /// The analysis is conservative:  a result other than
+    /// [`Monotonicity::Unknown`] is guaranteed, but `Unknown` may be returned
+    /// for functions which are in fact monotonic.  Like
+    /// [`interval_quality`](Self::interval_quality), the graph may only use
+    /// the `X`, `Y`, and `Z` variables.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// use fidget::{context::Monotonicity, types::Interval};
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let op = ctx.sub(x, y).unwrap();
+    ///
+    /// let r = Interval::new(-1.0, 1.0);
+    /// assert_eq!(
+    ///     ctx.monotonicity(op, r, r, r).unwrap(),
+    ///     [
+    ///         Monotonicity::Increasing,
+    ///         Monotonicity::Decreasing,
+    ///         Monotonicity::Constant
+    ///     ]
+    /// );
+    /// ```
+    pub fn monotonicity(
+        &self,
+        root: Node,
+        x: Interval,
+        y: Interval,
+        z: Interval,
+    ) -> Result<[Monotonicity; 3], Error> {
+        let vars: BTreeMap<String, Interval> = [("X", x), ("Y", y), ("Z", z)]
+            .into_iter()
+            .map(|(a, b)| (a.to_string(), b))
+            .collect();
+        let mut intervals: IndexVec<Option<Interval>, Node> =
+            vec![None; self.ops.len()].into();
+        self.interval_inner(root, &vars, &mut intervals, false)?;
+
+        let (order, _) = self.topological_order(root)?;
+        let mut out: BTreeMap<Node, [Monotonicity; 3]> = BTreeMap::new();
+        for node in order {
+            let interval = |n: &Node| intervals[*n].unwrap();
+            let m = match self.get_op(node).unwrap() {
+                Op::Input(v) | Op::Var(v) => {
+                    let name = self.vars.get_by_index(*v).unwrap();
+                    ["X", "Y", "Z"].map(|a| {
+                        if a == name {
+                            Monotonicity::Increasing
+                        } else {
+                            Monotonicity::Constant
+                        }
+                    })
+                }
+                Op::Const(..) => [Monotonicity::Constant; 3],
+                Op::Unary(op, a) => {
+                    let i = interval(a);
+                    let ma = out[a];
+                    ma.map(|m| Self::monotonic_unary(*op, m, i))
+                }
+                Op::Binary(op, a, b) => {
+                    let (ia, ib) = (interval(a), interval(b));
+                    let (ma, mb) = (out[a], out[b]);
+                    [0, 1, 2].map(|k| {
+                        Self::monotonic_binary(*op, ma[k], mb[k], ia, ib)
+                    })
+                }
+                Op::Custom(_, a) => out[a].map(Monotonicity::unknown),
+            };
+            // Undefined values may appear anywhere within the region, so
+            // they aren't monotonic
+            let m = if interval(&node).has_nan() {
+                m.map(Monotonicity::unknown)
+            } else {
+                m
+            };
+            out.insert(node, m);
+        }
+        Ok(out[&root])
+    }
+
+    /// Monotonicity of a unary operation, given its argument's monotonicity
+    /// `m` and interval `a`
+    fn monotonic_unary(
+        op: UnaryOpcode,
+        m: Monotonicity,
+        a: Interval,
+    ) -> Monotonicity {
+        use Monotonicity as M;
+        match op {
+            UnaryOpcode::Neg | UnaryOpcode::Acos => m.flip(),
+            UnaryOpcode::Sqrt
+            | UnaryOpcode::Asin
+            | UnaryOpcode::Atan
+            | UnaryOpcode::Exp
+            | UnaryOpcode::Ln => m,
+            UnaryOpcode::Abs | UnaryOpcode::Square => m.mul(M::sign_of(a)),
+            UnaryOpcode::Recip => {
+                if a.contains(0.0) {
+                    m.unknown()
+                } else {
+                    m.flip()
+                }
+            }
+            // d/dx sin(f) = cos(f) f', and d/dx cos(f) = -sin(f) f'
+            UnaryOpcode::Sin => m.mul(M::sign_of(a.cos())),
+            UnaryOpcode::Cos => m.mul(M::sign_of(a.sin()).flip()),
+            // The interval of `tan` is NaN if it crosses a discontinuity
+            UnaryOpcode::Tan => {
+                if a.tan().has_nan() {
+                    m.unknown()
+                } else {
+                    m
+                }
+            }
+            UnaryOpcode::Not => {
+                if a.contains(0.0) {
+                    m.unknown()
+                } else {
+                    M::Constant
+                }
+            }
+        }
+    }
+
+    /// Monotonicity of a binary operation, given its arguments' monotonicity
+    /// (`ma` and `mb`) and intervals (`a` and `b`)
+    fn monotonic_binary(
+        op: BinaryOpcode,
+        ma: Monotonicity,
+        mb: Monotonicity,
+        a: Interval,
+        b: Interval,
+    ) -> Monotonicity {
+        use Monotonicity as M;
+        match op {
+            // Min and max of functions which move in the same direction also
+            // move in that direction, so they combine like addition
+            BinaryOpcode::Add | BinaryOpcode::Min | BinaryOpcode::Max => {
+                if op == BinaryOpcode::Min && a.upper() < b.lower()
+                    || op == BinaryOpcode::Max && a.lower() > b.upper()
+                {
+                    ma
+                } else if op == BinaryOpcode::Min && b.upper() < a.lower()
+                    || op == BinaryOpcode::Max && b.lower() > a.upper()
+                {
+                    mb
+                } else {
+                    ma.add(mb)
+                }
+            }
+            // `compare` is a non-decreasing step function of `a - b`
+            BinaryOpcode::Sub | BinaryOpcode::Compare => ma.add(mb.flip()),
+            // Product rule: (ab)' = a'b + ab'
+            BinaryOpcode::Mul => {
+                ma.mul(M::sign_of(b)).add(mb.mul(M::sign_of(a)))
+            }
+            // Quotient rule: (a/b)' = (a'b - ab') / b², where 1/b has the
+            // same sign as b
+            BinaryOpcode::Div => {
+                if b.contains(0.0) {
+                    ma.add(mb).unknown()
+                } else {
+                    let sb = M::sign_of(b);
+                    ma.mul(sb).add(mb.flip().mul(M::sign_of(a)))
+                }
+            }
+            BinaryOpcode::And => {
+                if a.lower() == 0.0 && a.upper() == 0.0 {
+                    ma
+                } else if !a.contains(0.0) {
+                    mb
+                } else {
+                    ma.add(mb).unknown()
+                }
+            }
+            BinaryOpcode::Or => {
+                if a.lower() == 0.0 && a.upper() == 0.0 {
+                    mb
+                } else if !a.contains(0.0) {
+                    ma
+                } else {
+                    ma.add(mb).unknown()
+                }
+            }
+            BinaryOpcode::Mod => ma.add(mb).unknown(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use Monotonicity as M;
+
+    #[test]
+    fn test_monotonicity() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let r = Interval::new(-1.0, 1.0);
+        let pos = Interval::new(0.5, 2.0);
+
+        let m = ctx.monotonicity(x, r, r, r).unwrap();
+        assert_eq!(m, [M::Increasing, M::Constant, M::Constant]);
+
+        // x² is unknown over a box straddling 0, but not on either side
+        let x2 = ctx.square(x).unwrap();
+        let m = ctx.monotonicity(x2, r, r, r).unwrap();
+        assert_eq!(m, [M::Unknown, M::Constant, M::Constant]);
+        let m = ctx.monotonicity(x2, pos, r, r).unwrap();
+        assert_eq!(m[0], M::Increasing);
+        let m = ctx.monotonicity(x2, -pos, r, r).unwrap();
+        assert_eq!(m[0], M::Decreasing);
+
+        // Same for x * x, which uses the product rule
+        let xx = ctx.op_binary(x, x, BinaryOpcode::Mul).unwrap();
+        let m = ctx.monotonicity(xx, r, r, r).unwrap();
+        assert_eq!(m[0], M::Unknown);
+        let m = ctx.monotonicity(xx, pos, r, r).unwrap();
+        assert_eq!(m[0], M::Increasing);
+
+        // Distance from the Z axis, on one side of it
+        let y2 = ctx.square(y).unwrap();
+        let sum = ctx.add(x2, y2).unwrap();
+        let d = ctx.sqrt(sum).unwrap();
+        let m = ctx.monotonicity(d, pos, r, r).unwrap();
+        assert_eq!(m, [M::Increasing, M::Unknown, M::Constant]);
+
+        // Division and reciprocals
+        let q = ctx.div(y, z).unwrap();
+        let m = ctx.monotonicity(q, r, pos, pos).unwrap();
+        assert_eq!(m, [M::Constant, M::Increasing, M::Decreasing]);
+        let m = ctx.monotonicity(q, r, pos, r).unwrap();
+        assert_eq!(m, [M::Constant, M::Unknown, M::Unknown]);
+
+        // Sine is increasing near 0, but not over a full period
+        let s = ctx.sin(x).unwrap();
+        let m = ctx.monotonicity(s, r, r, r).unwrap();
+        assert_eq!(m[0], M::Increasing);
+        let wide = Interval::new(-3.0, 3.0);
+        let m = ctx.monotonicity(s, wide, r, r).unwrap();
+        assert_eq!(m[0], M::Unknown);
+
+        // min picks the side which is always lower, if there is one
+        let neg_y = ctx.neg(y).unwrap();
+        let far = ctx.add(neg_y, 10.0).unwrap();
+        let op = ctx.min(x, far).unwrap();
+        let m = ctx.monotonicity(op, r, r, r).unwrap();
+        assert_eq!(m, [M::Increasing, M::Constant, M::Constant]);
+        let op = ctx.min(x, neg_y).unwrap();
+        let m = ctx.monotonicity(op, r, r, r).unwrap();
+        assert_eq!(m, [M::Increasing, M::Decreasing, M::Constant]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::context::{indexed::Index, Node, VarNode};
+use crate::context::{indexed::Index, CustomOpId, Node, VarNode};
 use ordered_float::OrderedFloat;
 
 /// A one-argument math operation
@@ -37,6 +37,44 @@ pub enum BinaryOpcode {
     Or,
 }
 
+/// Names of unary opcodes, used by the JSON and binary formats
+///
+/// The binary format stores indices into this array, so it must only be
+/// extended at the end.
+pub(super) const UNARY_OPS: [(UnaryOpcode, &str); 14] = [
+    (UnaryOpcode::Neg, "neg"),
+    (UnaryOpcode::Abs, "abs"),
+    (UnaryOpcode::Recip, "recip"),
+    (UnaryOpcode::Sqrt, "sqrt"),
+    (UnaryOpcode::Square, "square"),
+    (UnaryOpcode::Sin, "sin"),
+    (UnaryOpcode::Cos, "cos"),
+    (UnaryOpcode::Tan, "tan"),
+    (UnaryOpcode::Asin, "asin"),
+    (UnaryOpcode::Acos, "acos"),
+    (UnaryOpcode::Atan, "atan"),
+    (UnaryOpcode::Exp, "exp"),
+    (UnaryOpcode::Ln, "ln"),
+    (UnaryOpcode::Not, "not"),
+];
+
+/// Names of binary opcodes, used by the JSON and binary formats
+///
+/// The binary format stores indices into this array, so it must only be
+/// extended at the end.
+pub(super) const BINARY_OPS: [(BinaryOpcode, &str); 10] = [
+    (BinaryOpcode::Add, "add"),
+    (BinaryOpcode::Sub, "sub"),
+    (BinaryOpcode::Mul, "mul"),
+    (BinaryOpcode::Div, "div"),
+    (BinaryOpcode::Min, "min"),
+    (BinaryOpcode::Max, "max"),
+    (BinaryOpcode::Compare, "compare"),
+    (BinaryOpcode::Mod, "mod"),
+    (BinaryOpcode::And, "and"),
+    (BinaryOpcode::Or, "or"),
+];
+
 /// An operation in a math expression.
 ///
 /// `Op`s should be constructed by calling functions on
@@ -54,6 +92,9 @@ pub enum Op {
     Const(OrderedFloat<f64>),
     Binary(BinaryOpcode, Node, Node),
     Unary(UnaryOpcode, Node),
+    /// A user-defined operation, dispatched through the
+    /// [`CustomOp`](crate::context::CustomOp) registry
+    Custom(CustomOpId, Node),
 }
 
 fn dot_color_to_rgb(s: &str) -> &'static str {
@@ -75,7 +116,7 @@ impl Op {
             Op::Binary(BinaryOpcode::Min | BinaryOpcode::Max, ..) => {
                 "dodgerblue"
             }
-            Op::Binary(..) | Op::Unary(..) => "goldenrod",
+            Op::Binary(..) | Op::Unary(..) | Op::Custom(..) => "goldenrod",
         }
     }
 
@@ -84,7 +125,7 @@ impl Op {
         match self {
             Op::Const(..) => "oval",
             Op::Var(..) | Op::Input(..) => "circle",
-            Op::Binary(..) | Op::Unary(..) => "box",
+            Op::Binary(..) | Op::Unary(..) | Op::Custom(..) => "box",
         }
     }
 
@@ -92,7 +133,7 @@ impl Op {
     pub fn iter_children(&self) -> impl Iterator<Item = Node> {
         let out = match self {
             Op::Binary(_, a, b) => [Some(*a), Some(*b)],
-            Op::Unary(_, a) => [Some(*a), None],
+            Op::Unary(_, a) | Op::Custom(_, a) => [Some(*a), None],
             Op::Var(..) | Op::Input(..) | Op::Const(..) => [None, None],
         };
         out.into_iter().flatten()
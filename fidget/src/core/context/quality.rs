@@ -0,0 +1,678 @@
+//! Diagnostics for the quality of interval bounds
+use super::{BinaryOpcode, Context, IndexVec, Node, Op, UnaryOpcode};
+use crate::{types::Interval, Error};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// Interval bounds for a node, along with the range seen by sampling
+///
+/// Interval arithmetic is conservative: the interval is guaranteed to contain
+/// every value of the node within the region, but may be much wider than the
+/// true range (e.g. `x - x` over `[-1, 1]` produces `[-2, 2]` instead of
+/// `[0, 0]`).  The sampled range is a lower bound on the true range, so
+/// comparing the two gives an estimate of how loose the interval is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IntervalQuality {
+    /// Result of interval arithmetic
+    pub interval: Interval,
+    /// Range of values seen when sampling points within the region
+    pub sampled: Interval,
+}
+
+impl IntervalQuality {
+    /// Estimates how loose the interval is, as a value in `[0, 1]`
+    ///
+    /// This is the fraction of the interval's width which isn't covered by the
+    /// sampled range, so 0 means that the interval is as tight as sampling can
+    /// tell, and values near 1 mean that the interval is much wider than the
+    /// true range.  Intervals which are infinite or contain `NaN` are
+    /// considered entirely loose (unless sampling also found such values).
+    pub fn looseness(&self) -> f32 {
+        let i = self.interval.width();
+        let s = self.sampled.width();
+        if i == 0.0 || s.is_nan() || s.is_infinite() {
+            0.0
+        } else if i.is_nan() || i.is_infinite() {
+            1.0
+        } else {
+            (1.0 - s / i).clamp(0.0, 1.0)
+        }
+    }
+}
+
+impl Context {
+    /// Evaluates interval bounds over a region, estimating their quality
+    ///
+    /// The node (and each of its subexpressions) is evaluated with interval
+    /// arithmetic over the region `x × y × z`, then sampled on a grid of
+    /// `samples³` points (including the region's corners; `samples` is
+    /// clamped to at least 2).  The result maps every node in the graph to its
+    /// [`IntervalQuality`], so subexpressions that blow up the bounds can be
+    /// found by looking for high [`looseness`](IntervalQuality::looseness).
+    ///
+    /// Like [`eval`](Self::eval), this is inefficient and meant for
+    /// diagnostics; the graph may only use the `X`, `Y`, and `Z` variables.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// use fidget::types::Interval;
+    /// let x = ctx.x();
+    /// let x2 = ctx.square(x).unwrap();
+    /// let out = ctx.sub(x2, x).unwrap();
+    ///
+    /// let r = Interval::new(-1.0, 1.0);
+    /// let q = ctx.interval_quality(out, r, r, r, 5).unwrap();
+    /// assert_eq!(q[&out].interval, Interval::new(-1.0, 2.0));
+    /// assert_eq!(q[&out].sampled, Interval::new(-0.25, 2.0));
+    /// assert_eq!(q[&x2].looseness(), 0.0);
+    /// ```
+    pub fn interval_quality(
+        &self,
+        root: Node,
+        x: Interval,
+        y: Interval,
+        z: Interval,
+        samples: usize,
+    ) -> Result<BTreeMap<Node, IntervalQuality>, Error> {
+        let vars: BTreeMap<String, Interval> = [("X", x), ("Y", y), ("Z", z)]
+            .into_iter()
+            .map(|(a, b)| (a.to_string(), b))
+            .collect();
+        let mut intervals = vec![None; self.ops.len()].into();
+        self.interval_inner(root, &vars, &mut intervals, false)?;
+
+        let samples = samples.max(2);
+        let pos = |r: Interval, i: usize| {
+            r.lerp(i as f32 / (samples - 1) as f32) as f64
+        };
+        let mut sampled: BTreeMap<Node, Interval> = BTreeMap::new();
+        for i in 0..samples {
+            for j in 0..samples {
+                for k in 0..samples {
+                    let vars = Self::xyz_vars(pos(x, i), pos(y, j), pos(z, k));
+                    let mut cache = vec![None; self.ops.len()].into();
+                    self.eval_inner(root, &vars, &mut cache)?;
+                    for (n, v) in cache.into_iter().enumerate() {
+                        let Some(v) = v else {
+                            continue;
+                        };
+                        let v = v as f32;
+                        sampled
+                            .entry(Node(n))
+                            .and_modify(|s| {
+                                *s = Interval::new(
+                                    s.lower().min(v),
+                                    s.upper().max(v),
+                                )
+                            })
+                            .or_insert(Interval::from(v));
+                    }
+                }
+            }
+        }
+
+        Ok(sampled
+            .into_iter()
+            .map(|(n, sampled)| {
+                let interval = intervals[n].unwrap();
+                (n, IntervalQuality { interval, sampled })
+            })
+            .collect())
+    }
+
+    /// Evaluates interval bounds for both sides of every `min` and `max` node
+    ///
+    /// The node is evaluated with interval arithmetic over the region
+    /// `x × y × z`, and each `min` or `max` node which it uses is returned
+    /// along with the intervals of its two arguments, with children before
+    /// their parents.  This shows which branches are active within the
+    /// region, e.g. to decide how to subdivide it in an adaptive renderer;
+    /// a side can only be chosen if its lower bound (for `min`) or upper bound
+    /// (for `max`) is reached by the other side's interval.
+    ///
+    /// Results are rounded outwards (see [`Interval::widen_by`]), so each
+    /// interval contains the true range of its argument.  To record the same
+    /// information while evaluating a tape, use
+    /// [`VmIntervalEval::eval_with_choices`](crate::vm::VmIntervalEval::eval_with_choices).
+    ///
+    /// Like [`interval_quality`](Self::interval_quality), the graph may only
+    /// use the `X`, `Y`, and `Z` variables.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// use fidget::types::Interval;
+    /// let x = ctx.x();
+    /// let y = ctx.y();
+    /// let op = ctx.min(x, y).unwrap();
+    ///
+    /// let r = Interval::new(0.0, 1.0);
+    /// let s = Interval::new(2.0, 3.0);
+    /// let out = ctx.interval_choices(op, r, s, r).unwrap();
+    /// assert_eq!(out, vec![(op, r, s)]);
+    /// ```
+    pub fn interval_choices(
+        &self,
+        root: Node,
+        x: Interval,
+        y: Interval,
+        z: Interval,
+    ) -> Result<Vec<(Node, Interval, Interval)>, Error> {
+        let vars: BTreeMap<String, Interval> = [("X", x), ("Y", y), ("Z", z)]
+            .into_iter()
+            .map(|(a, b)| (a.to_string(), b))
+            .collect();
+        let mut intervals = vec![None; self.ops.len()].into();
+        self.interval_inner(root, &vars, &mut intervals, true)?;
+
+        let (order, _) = self.topological_order(root)?;
+        Ok(order
+            .into_iter()
+            .filter_map(|n| match self.get_op(n).unwrap() {
+                Op::Binary(BinaryOpcode::Min | BinaryOpcode::Max, a, b) => {
+                    Some((n, intervals[*a].unwrap(), intervals[*b].unwrap()))
+                }
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Checks whether the surface provably doesn't pass through a region
+    ///
+    /// The node is evaluated with interval arithmetic over the box
+    /// `x × y × z` (each given as `[min, max]`).  If the result doesn't
+    /// contain zero, the box is entirely inside or entirely outside the shape;
+    /// otherwise, the box is split into octants and each one is checked, up
+    /// to `depth` levels of subdivision.
+    ///
+    /// Every interval result is rounded outwards (see [`Interval::widen_by`]),
+    /// so returning `true` means that there's no surface in the region;
+    /// `false` means that it couldn't be ruled out, either because the surface
+    /// is present or because the bounds are too loose at the given depth.
+    ///
+    /// Evaluation uses `f32` intervals, so finite bounds beyond the range of
+    /// `f32` are clamped to `±f32::MAX`.  Regions with reversed bounds
+    /// (`min > max`) or non-finite bounds are never proven empty.
+    ///
+    /// Like [`interval_quality`](Self::interval_quality), the graph may only
+    /// use the `X`, `Y`, and `Z` variables.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let plane = ctx.sub(x, 1.0).unwrap();
+    /// let r = [-1.0, 1.0];
+    /// assert!(ctx.is_empty_in(plane, [2.0, 3.0], r, r, 0).unwrap());
+    /// assert!(!ctx.is_empty_in(plane, [0.0, 2.0], r, r, 4).unwrap());
+    /// ```
+    pub fn is_empty_in(
+        &self,
+        root: Node,
+        x: [f64; 2],
+        y: [f64; 2],
+        z: [f64; 2],
+        depth: usize,
+    ) -> Result<bool, Error> {
+        let [Some(x), Some(y), Some(z)] = [x, y, z].map(region_interval) else {
+            return Ok(false);
+        };
+        self.is_empty_inner(root, [x, y, z], depth)
+    }
+
+    /// Parallel version of [`is_empty_in`](Self::is_empty_in)
+    ///
+    /// The region is subdivided until there are a few sub-boxes per thread,
+    /// which are then checked by `threads` worker threads (at least one).  The
+    /// result is identical to [`is_empty_in`](Self::is_empty_in); workers stop
+    /// early once any sub-box is found that may contain the surface.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::context::Context::new();
+    /// let x = ctx.x();
+    /// let plane = ctx.sub(x, 1.0).unwrap();
+    /// let r = [-1.0, 1.0];
+    /// let empty = ctx.is_empty_in_parallel(plane, [2.0, 3.0], r, r, 4, 8);
+    /// assert!(empty.unwrap());
+    /// ```
+    pub fn is_empty_in_parallel(
+        &self,
+        root: Node,
+        x: [f64; 2],
+        y: [f64; 2],
+        z: [f64; 2],
+        depth: usize,
+        threads: usize,
+    ) -> Result<bool, Error> {
+        let [Some(x), Some(y), Some(z)] = [x, y, z].map(region_interval) else {
+            return Ok(false);
+        };
+        let region = [x, y, z];
+        let threads = threads.max(1);
+
+        // Expand the region breadth-first until there's enough work to share
+        // between threads, dropping sub-boxes which are already proven empty
+        let mut boxes = vec![region];
+        let mut depth = depth;
+        while depth > 0 && boxes.len() < threads * 4 {
+            let mut next = vec![];
+            for b in boxes {
+                if !self.is_proven_empty(root, b)? {
+                    next.extend(split_box(b));
+                }
+            }
+            boxes = next;
+            depth -= 1;
+        }
+
+        let found = AtomicBool::new(false);
+        let boxes = Mutex::new(boxes.into_iter());
+        std::thread::scope(|s| {
+            let mut handles = vec![];
+            for _ in 0..threads {
+                handles.push(s.spawn(|| -> Result<(), Error> {
+                    while !found.load(Ordering::Relaxed) {
+                        let Some(b) = boxes.lock().unwrap().next() else {
+                            break;
+                        };
+                        if !self.is_empty_inner(root, b, depth)? {
+                            found.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    Ok(())
+                }));
+            }
+            handles.into_iter().try_for_each(|h| h.join().unwrap())
+        })?;
+        Ok(!found.into_inner())
+    }
+
+    fn is_empty_inner(
+        &self,
+        root: Node,
+        region: [Interval; 3],
+        depth: usize,
+    ) -> Result<bool, Error> {
+        if self.is_proven_empty(root, region)? {
+            return Ok(true);
+        } else if depth == 0 {
+            return Ok(false);
+        }
+        for b in split_box(region) {
+            if !self.is_empty_inner(root, b, depth - 1)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Checks whether interval arithmetic excludes zero over the region
+    fn is_proven_empty(
+        &self,
+        root: Node,
+        [x, y, z]: [Interval; 3],
+    ) -> Result<bool, Error> {
+        let vars: BTreeMap<String, Interval> = [("X", x), ("Y", y), ("Z", z)]
+            .into_iter()
+            .map(|(a, b)| (a.to_string(), b))
+            .collect();
+        let mut cache = vec![None; self.ops.len()].into();
+        let i = self.interval_inner(root, &vars, &mut cache, true)?;
+        Ok(i.lower() > 0.0 || i.upper() < 0.0)
+    }
+
+    /// Evaluates `node` over the given variable intervals, memoizing results
+    /// in `cache`
+    ///
+    /// If `round` is set, every result which may have been rounded is widened
+    /// with [`Interval::widen_by`], so that it contains the true range.
+    pub(super) fn interval_inner(
+        &self,
+        node: Node,
+        vars: &BTreeMap<String, Interval>,
+        cache: &mut IndexVec<Option<Interval>, Node>,
+        round: bool,
+    ) -> Result<Interval, Error> {
+        if node.0 >= cache.len() {
+            return Err(Error::BadNode);
+        }
+        if let Some(v) = cache[node] {
+            return Ok(v);
+        }
+        let mut get = |n: Node| self.interval_inner(n, vars, cache, round);
+        let v = match self.get_op(node).ok_or(Error::BadNode)? {
+            Op::Var(v) | Op::Input(v) => {
+                let var_name = self.vars.get_by_index(*v).unwrap();
+                *vars
+                    .get(var_name)
+                    .ok_or_else(|| Error::UnknownVariable(var_name.clone()))?
+            }
+            Op::Const(c) => Interval::from(c.0 as f32),
+
+            Op::Binary(op, a, b) => {
+                let a = get(*a)?;
+                let b = get(*b)?;
+                match op {
+                    BinaryOpcode::Add => a + b,
+                    BinaryOpcode::Sub => a - b,
+                    BinaryOpcode::Mul => a * b,
+                    BinaryOpcode::Div => a / b,
+                    BinaryOpcode::Min => a.min_choice(b).0,
+                    BinaryOpcode::Max => a.max_choice(b).0,
+                    BinaryOpcode::Compare => {
+                        if a.has_nan() || b.has_nan() {
+                            f32::NAN.into()
+                        } else if a.upper() < b.lower() {
+                            Interval::from(-1.0)
+                        } else if a.lower() > b.upper() {
+                            Interval::from(1.0)
+                        } else {
+                            Interval::new(-1.0, 1.0)
+                        }
+                    }
+                    BinaryOpcode::Mod => a.rem_euclid(b),
+                    BinaryOpcode::And => a.and_choice(b).0,
+                    BinaryOpcode::Or => a.or_choice(b).0,
+                }
+            }
+
+            // Unary operations
+            Op::Unary(op, a) => {
+                let a = get(*a)?;
+                match op {
+                    UnaryOpcode::Neg => -a,
+                    UnaryOpcode::Abs => a.abs(),
+                    UnaryOpcode::Recip => a.recip(),
+                    UnaryOpcode::Sqrt => a.sqrt(),
+                    UnaryOpcode::Square => a.square(),
+                    UnaryOpcode::Sin => a.sin(),
+                    UnaryOpcode::Cos => a.cos(),
+                    UnaryOpcode::Tan => a.tan(),
+                    UnaryOpcode::Asin => a.asin(),
+                    UnaryOpcode::Acos => a.acos(),
+                    UnaryOpcode::Atan => a.atan(),
+                    UnaryOpcode::Exp => a.exp(),
+                    UnaryOpcode::Ln => a.ln(),
+                    UnaryOpcode::Not => {
+                        if !a.contains(0.0) {
+                            Interval::new(0.0, 0.0)
+                        } else if a.lower() == 0.0 && a.upper() == 0.0 {
+                            Interval::new(1.0, 1.0)
+                        } else {
+                            Interval::new(0.0, 1.0)
+                        }
+                    }
+                }
+            }
+            Op::Custom(id, a) => id.get().eval_interval(get(*a)?),
+        };
+        let ulps = match self.get_op(node).unwrap() {
+            Op::Var(..) | Op::Input(..) => 0,
+            Op::Const(c) => (c.0 as f32 as f64 != c.0) as u32,
+            Op::Binary(op, ..) => match op {
+                BinaryOpcode::Add
+                | BinaryOpcode::Sub
+                | BinaryOpcode::Mul
+                | BinaryOpcode::Div
+                | BinaryOpcode::Mod => 1,
+                _ => 0,
+            },
+            Op::Unary(op, ..) => match op {
+                UnaryOpcode::Neg | UnaryOpcode::Abs | UnaryOpcode::Not => 0,
+                UnaryOpcode::Recip
+                | UnaryOpcode::Sqrt
+                | UnaryOpcode::Square => 1,
+                _ => Interval::TRANSCENDENTAL_ULPS,
+            },
+            Op::Custom(..) => 1,
+        };
+        let v = if round { v.widen_by(ulps) } else { v };
+
+        cache[node] = Some(v);
+        Ok(v)
+    }
+}
+
+/// Converts one axis of a region into an interval, rounding outwards
+///
+/// Returns `None` if the bounds are reversed or not finite; bounds beyond the
+/// range of `f32` are clamped to `±f32::MAX`.
+fn region_interval([lo, hi]: [f64; 2]) -> Option<Interval> {
+    if !(lo.is_finite() && hi.is_finite() && lo <= hi) {
+        return None;
+    }
+    let clamp = |v: f64| v.clamp(-f32::MAX as f64, f32::MAX as f64) as f32;
+    let r = Interval::new(clamp(lo), clamp(hi)).widen();
+    Some(Interval::new(
+        r.lower().max(-f32::MAX),
+        r.upper().min(f32::MAX),
+    ))
+}
+
+/// Splits a box into its eight octants
+fn split_box([x, y, z]: [Interval; 3]) -> impl Iterator<Item = [Interval; 3]> {
+    let split = |r: Interval| {
+        // Halve before adding, so that large bounds don't overflow
+        let mid =
+            (r.lower() / 2.0 + r.upper() / 2.0).clamp(r.lower(), r.upper());
+        [Interval::new(r.lower(), mid), Interval::new(mid, r.upper())]
+    };
+    split(x).into_iter().flat_map(move |x| {
+        split(y)
+            .into_iter()
+            .flat_map(move |y| split(z).into_iter().map(move |z| [x, y, z]))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::test_sphere;
+
+    #[test]
+    fn test_interval_quality() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let zero = ctx.sub(x, x).unwrap();
+        let out = ctx.add(zero, y).unwrap();
+
+        let r = Interval::new(-10.0, 10.0);
+        let q = ctx.interval_quality(out, r, r, r, 4).unwrap();
+        assert_eq!(q.len(), 4);
+
+        // `x - x` is always zero, but intervals can't tell
+        assert_eq!(q[&zero].interval, Interval::new(-20.0, 20.0));
+        assert_eq!(q[&zero].sampled, Interval::new(0.0, 0.0));
+        assert_eq!(q[&zero].looseness(), 1.0);
+
+        // Inputs are exact
+        assert_eq!(q[&x].looseness(), 0.0);
+        assert_eq!(q[&y].looseness(), 0.0);
+
+        // The looseness propagates to the output
+        assert_eq!(q[&out].interval, Interval::new(-30.0, 30.0));
+        assert_eq!(q[&out].sampled, Interval::new(-10.0, 10.0));
+        assert!((q[&out].looseness() - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_interval_choices() {
+        // Union of two spheres, plus a `max` with a plane
+        let mut ctx = Context::new();
+        let a = test_sphere(&mut ctx, [-0.5, 0.0, 0.0], 0.5);
+        let b = test_sphere(&mut ctx, [0.5, 0.0, 0.0], 0.5);
+        let union = ctx.min(a, b).unwrap();
+        let z = ctx.z();
+        let out = ctx.max(union, z).unwrap();
+
+        let (rx, ry, rz) = (
+            Interval::new(-0.8, 0.2),
+            Interval::new(-0.5, 0.5),
+            Interval::new(-0.25, 0.75),
+        );
+        let choices = ctx.interval_choices(out, rx, ry, rz).unwrap();
+        assert_eq!(choices.len(), 2);
+        assert_eq!(choices[0].0, union);
+        assert_eq!(choices[1].0, out);
+
+        // Each recorded interval bounds its branch's values within the region
+        for (n, ia, ib) in &choices {
+            let Op::Binary(_, a, b) = *ctx.get_op(*n).unwrap() else {
+                panic!("expected a binary op");
+            };
+            let mut seen = [Interval::from(f32::NAN); 2];
+            for i in 0..=4 {
+                for j in 0..=4 {
+                    for k in 0..=4 {
+                        let p = [(rx, i), (ry, j), (rz, k)]
+                            .map(|(r, t)| r.lerp(t as f32 / 4.0) as f64);
+                        for (n, s) in [a, b].into_iter().zip(&mut seen) {
+                            let v = ctx.eval_xyz(n, p[0], p[1], p[2]).unwrap();
+                            let v = v as f32;
+                            *s = if s.has_nan() {
+                                Interval::from(v)
+                            } else {
+                                Interval::new(
+                                    s.lower().min(v),
+                                    s.upper().max(v),
+                                )
+                            };
+                        }
+                    }
+                }
+            }
+            for (i, s) in [ia, ib].into_iter().zip(seen) {
+                assert!(
+                    i.lower() <= s.lower() && s.upper() <= i.upper(),
+                    "{i:?} does not contain {s:?} at {n:?}"
+                );
+            }
+        }
+
+        // Neither side of the union is always lower within the region, so its
+        // sub-intervals overlap
+        let (_, ia, ib) = choices[0];
+        assert!(ia.lower() < ib.upper());
+        assert!(ib.lower() < ia.upper());
+    }
+
+    #[test]
+    fn test_is_empty_in() {
+        // Unit sphere
+        let mut ctx = Context::new();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 1.0);
+
+        let far = [3.0, 4.0];
+        assert!(ctx.is_empty_in(sphere, far, far, far, 0).unwrap());
+        let inside = [-0.1, 0.1];
+        assert!(ctx.is_empty_in(sphere, inside, inside, inside, 0).unwrap());
+        let touching = [0.5, 2.0];
+        for depth in [0, 3] {
+            assert!(!ctx
+                .is_empty_in(sphere, touching, inside, inside, depth)
+                .unwrap());
+        }
+
+        // x * (x - 2) + 1.75 = (x - 1)² + 0.75 is always positive, but
+        // interval multiplication doesn't know that both sides depend on `x`,
+        // so proving that this region is empty requires subdivision
+        let x = ctx.x();
+        let xm = ctx.sub(x, 2.0).unwrap();
+        let f = ctx.mul(x, xm).unwrap();
+        let f = ctx.add(f, 1.75).unwrap();
+        let rx = [0.0, 2.0];
+        for depth in [0, 1] {
+            assert!(!ctx.is_empty_in(f, rx, inside, inside, depth).unwrap());
+        }
+        assert!(ctx.is_empty_in(f, rx, inside, inside, 2).unwrap());
+
+        // Reversed and non-finite regions are never proven empty
+        assert!(!ctx.is_empty_in(sphere, [4.0, 3.0], far, far, 0).unwrap());
+        for bad in [f64::NAN, f64::INFINITY] {
+            assert!(!ctx.is_empty_in(sphere, [3.0, bad], far, far, 2).unwrap());
+        }
+
+        // Huge regions are clamped to the range of `f32`
+        let huge = [-1e300, 1e300];
+        assert!(!ctx.is_empty_in(sphere, huge, huge, huge, 3).unwrap());
+        let beyond = [3.0, 1e300];
+        assert!(ctx.is_empty_in(sphere, beyond, beyond, beyond, 3).unwrap());
+
+        let a = ctx.var("a").unwrap();
+        assert!(matches!(
+            ctx.is_empty_in(a, far, far, far, 0),
+            Err(Error::UnknownVariable(..))
+        ));
+    }
+
+    #[test]
+    fn test_is_empty_in_parallel() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let xx = ctx.mul(x, x).unwrap();
+        let yy = ctx.mul(y, y).unwrap();
+        let zz = ctx.mul(z, z).unwrap();
+        let r = ctx.add(xx, yy).unwrap();
+        let r = ctx.add(r, zz).unwrap();
+        let sphere = ctx.sub(r, 1.0).unwrap();
+
+        // `x - x + 0.3` is positive, but the bounds are only tight enough to
+        // prove it after three levels of subdivision
+        let zero = ctx.sub(x, x).unwrap();
+        let loose = ctx.add(zero, 0.3).unwrap();
+
+        let regions = [
+            [[-0.9, 0.9], [1.2, 2.0], [-0.1, 0.1]],
+            [[0.5, 2.0], [-0.1, 0.1], [-0.1, 0.1]],
+            [[-1.0, 1.0], [-1.0, 1.0], [-1.0, 1.0]],
+            [[3.0, 4.0], [3.0, 4.0], [3.0, 4.0]],
+            [[1.0, -1.0], [-1.0, 1.0], [-1.0, 1.0]],
+            [[-1e300, 1e300], [-1e300, 1e300], [-1e300, 1e300]],
+            [[3.0, 1e300], [3.0, 1e300], [3.0, 1e300]],
+        ];
+        for (root, [x, y, z]) in regions
+            .iter()
+            .map(|r| (sphere, *r))
+            .chain([(loose, regions[2])])
+        {
+            for depth in 0..4 {
+                let serial = ctx.is_empty_in(root, x, y, z, depth).unwrap();
+                for threads in [0, 1, 2, 8] {
+                    let parallel = ctx
+                        .is_empty_in_parallel(root, x, y, z, depth, threads)
+                        .unwrap();
+                    assert_eq!(
+                        serial, parallel,
+                        "mismatch at depth {depth} with {threads} threads"
+                    );
+                }
+            }
+        }
+        let r = [-1.0, 1.0];
+        assert!(!ctx.is_empty_in_parallel(loose, r, r, r, 2, 4).unwrap());
+        assert!(ctx.is_empty_in_parallel(loose, r, r, r, 3, 4).unwrap());
+
+        let a = ctx.var("a").unwrap();
+        assert!(matches!(
+            ctx.is_empty_in_parallel(a, r, r, r, 2, 4),
+            Err(Error::UnknownVariable(..))
+        ));
+    }
+
+    #[test]
+    fn test_interval_quality_vars() {
+        let mut ctx = Context::new();
+        let a = ctx.var("a").unwrap();
+        let r = Interval::new(-1.0, 1.0);
+        assert!(matches!(
+            ctx.interval_quality(a, r, r, r, 2),
+            Err(Error::UnknownVariable(..))
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+//! Deterministic generation of random models
+use super::{BinaryOpcode, Context, Node};
+
+/// Small, seedable pseudo-random number generator (xorshift64*)
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // The state must be nonzero, so mix the seed with an odd constant
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    /// Returns a value in `[lo, hi)`
+    pub(crate) fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        let t = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        lo + (hi - lo) * t
+    }
+    /// Returns an index in `0..n`
+    pub(crate) fn index(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+impl Context {
+    /// Builds a pseudo-random model, for benchmarks and fuzzing
+    ///
+    /// The model is a tree of spheres (with random centers in `[-1, 1]³` and
+    /// radii in `[0.1, 0.5]`), combined pairwise with operations chosen from
+    /// `ops`; with `min` and `max`, this is a CSG model of unions and
+    /// intersections.  Spheres and combinations are added until the graph
+    /// has at least `size` nodes, so the result is slightly larger than
+    /// `size`.
+    ///
+    /// The same `seed`, `size`, and `ops` always produce the same graph.
+    ///
+    /// ```
+    /// # use fidget::context::{BinaryOpcode, Context};
+    /// let ops = [BinaryOpcode::Min, BinaryOpcode::Max];
+    /// let (ctx, root) = Context::random(1, 200, &ops);
+    /// assert!(ctx.len() >= 200);
+    /// assert!(ctx.eval_xyz(root, 0.0, 0.0, 0.0).unwrap().is_finite());
+    /// ```
+    ///
+    /// # Panics
+    /// If `ops` is empty
+    pub fn random(
+        seed: u64,
+        size: usize,
+        ops: &[BinaryOpcode],
+    ) -> (Self, Node) {
+        assert!(!ops.is_empty(), "`ops` must not be empty");
+        let mut rng = Rng::new(seed);
+        let mut ctx = Self::new();
+        let sphere = |ctx: &mut Self, rng: &mut Rng| -> Node {
+            let mut sum = None;
+            for axis in [ctx.x(), ctx.y(), ctx.z()] {
+                let d = ctx.sub(axis, rng.range(-1.0, 1.0)).unwrap();
+                let d = ctx.square(d).unwrap();
+                sum = Some(match sum {
+                    Some(s) => ctx.add(s, d).unwrap(),
+                    None => d,
+                });
+            }
+            let r = ctx.sqrt(sum.unwrap()).unwrap();
+            ctx.sub(r, rng.range(0.1, 0.5)).unwrap()
+        };
+
+        // Grow a pool of subtrees, either adding a new sphere or merging two
+        // existing subtrees, until the graph is large enough
+        let mut pool = vec![sphere(&mut ctx, &mut rng)];
+        let combine = |ctx: &mut Self, rng: &mut Rng, pool: &mut Vec<_>| {
+            let a = pool.swap_remove(rng.index(pool.len()));
+            let b = pool.swap_remove(rng.index(pool.len()));
+            let op = ops[rng.index(ops.len())];
+            pool.push(ctx.op_binary(a, b, op).unwrap());
+        };
+        while ctx.len() < size {
+            if pool.len() >= 2 && rng.index(2) == 0 {
+                combine(&mut ctx, &mut rng, &mut pool);
+            } else {
+                pool.push(sphere(&mut ctx, &mut rng));
+            }
+        }
+        while pool.len() > 1 {
+            combine(&mut ctx, &mut rng, &mut pool);
+        }
+        let root = pool[0];
+        (ctx, root)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::Op;
+
+    #[test]
+    fn test_random() {
+        let ops = [BinaryOpcode::Min, BinaryOpcode::Max];
+        let save = |(ctx, root): (Context, Node)| {
+            let mut out = vec![];
+            ctx.save(root, &mut out).unwrap();
+            out
+        };
+        let a = save(Context::random(123, 500, &ops));
+        let b = save(Context::random(123, 500, &ops));
+        let c = save(Context::random(124, 500, &ops));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        for seed in 0..8 {
+            let (ctx, root) = Context::random(seed, 300, &ops);
+            assert!(ctx.len() >= 300);
+            assert!(ctx.len() < 350, "too many nodes: {}", ctx.len());
+            for p in [[0.0; 3], [0.5, -0.25, 1.0], [2.0, 2.0, 2.0]] {
+                let v = ctx.eval_xyz(root, p[0], p[1], p[2]).unwrap();
+                assert!(v.is_finite());
+            }
+        }
+
+        // Every operation which is allowed is used (given enough nodes)
+        let ops = [BinaryOpcode::Min, BinaryOpcode::Mul];
+        let (ctx, root) = Context::random(5, 2000, &ops);
+        let (order, _) = ctx.topological_order(root).unwrap();
+        let used: Vec<_> = order
+            .into_iter()
+            .filter_map(|n| match ctx.get_op(n).unwrap() {
+                Op::Binary(op, ..) => Some(*op),
+                _ => None,
+            })
+            .collect();
+        assert!(used.contains(&BinaryOpcode::Min));
+        assert!(used.contains(&BinaryOpcode::Mul));
+        assert!(!used.contains(&BinaryOpcode::Max));
+    }
+}
@@ -0,0 +1,727 @@
+//! Parser for a subset of OpenSCAD's CSG language
+use super::{Context, Node, TextLine};
+use crate::Error;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Punct(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "`{s}`"),
+            Token::Number(n) => write!(f, "`{n}`"),
+            Token::Punct(c) => write!(f, "`{c}`"),
+        }
+    }
+}
+
+/// Literal value passed as an argument
+#[derive(Clone, Debug)]
+enum Value {
+    Number(f64),
+    Bool(bool),
+    Vector(Vec<f64>),
+}
+
+/// Error along with its byte offset in the input
+type Located = (usize, Error);
+
+/// Splits the input into tokens, each with its byte offset
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, Located> {
+    let mut out = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        } else if c == '/' && matches!(chars.peek(), Some((_, '/'))) {
+            for (_, c) in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+        } else if c == '/' && matches!(chars.peek(), Some((_, '*'))) {
+            chars.next();
+            let mut prev = ' ';
+            loop {
+                match chars.next() {
+                    Some((_, '/')) if prev == '*' => break,
+                    Some((_, c)) => prev = c,
+                    None => {
+                        let e =
+                            Error::ExpectedToken("`*/`", "end of file".into());
+                        return Err((i, e));
+                    }
+                }
+            }
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = i + 1;
+            while let Some(&(j, d)) = chars.peek() {
+                let exponent_sign = (d == '-' || d == '+')
+                    && matches!(text[..j].chars().last(), Some('e' | 'E'));
+                if d.is_ascii_digit()
+                    || matches!(d, '.' | 'e' | 'E')
+                    || exponent_sign
+                {
+                    end = j + 1;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let s = &text[i..end];
+            let v = s.parse().map_err(|_| (i, Error::BadConstant(s.into())))?;
+            out.push((Token::Number(v), i));
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, d)) = chars.peek() {
+                if d.is_alphanumeric() || d == '_' {
+                    end = j + d.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            out.push((Token::Ident(text[i..end].to_owned()), i));
+        } else if "(){}[],;=+-".contains(c) {
+            out.push((Token::Punct(c), i));
+        } else {
+            let e = Error::ExpectedToken("a token", format!("`{c}`"));
+            return Err((i, e));
+        }
+    }
+    Ok(out)
+}
+
+/// Arguments to a module call, bound to their names
+struct Args {
+    module: String,
+    values: BTreeMap<String, (Value, usize)>,
+}
+
+impl Args {
+    /// Returns a numeric argument
+    fn number(&self, name: &str) -> Result<Option<f64>, Located> {
+        match self.values.get(name) {
+            None => Ok(None),
+            Some((Value::Number(v), _)) => Ok(Some(*v)),
+            Some((_, offset)) => Err((*offset, Error::BadScadArg(name.into()))),
+        }
+    }
+
+    /// Returns a boolean argument
+    fn bool(&self, name: &str) -> Result<Option<bool>, Located> {
+        match self.values.get(name) {
+            None => Ok(None),
+            Some((Value::Bool(v), _)) => Ok(Some(*v)),
+            Some((_, offset)) => Err((*offset, Error::BadScadArg(name.into()))),
+        }
+    }
+
+    /// Returns a vector argument
+    ///
+    /// A single number is used for every axis, and a 2-element vector has its
+    /// Z component set to `z`.
+    fn vec3(&self, name: &str, z: f64) -> Result<Option<[f64; 3]>, Located> {
+        match self.values.get(name) {
+            None => Ok(None),
+            Some((Value::Number(v), _)) => Ok(Some([*v; 3])),
+            Some((Value::Vector(v), _)) if v.len() == 2 => {
+                Ok(Some([v[0], v[1], z]))
+            }
+            Some((Value::Vector(v), _)) if v.len() == 3 => {
+                Ok(Some([v[0], v[1], v[2]]))
+            }
+            Some((_, offset)) => Err((*offset, Error::BadScadArg(name.into()))),
+        }
+    }
+
+    /// Returns a radius, given either directly or as a diameter
+    fn radius(&self, r: &str, d: &str) -> Result<Option<f64>, Located> {
+        Ok(match self.number(r)? {
+            Some(r) => Some(r),
+            None => self.number(d)?.map(|d| d / 2.0),
+        })
+    }
+}
+
+struct Parser<'a> {
+    ctx: &'a mut Context,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Byte offset of the end of the input
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    /// Returns the offset of the next token (or the end of the input)
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(_, o)| *o)
+            .unwrap_or(self.end)
+    }
+
+    /// Consumes the given punctuation, returning `true` if it was present
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char, what: &'static str) -> Result<(), Located> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected(what))
+        }
+    }
+
+    /// Builds an error for an unexpected token at the current position
+    fn unexpected(&self, what: &'static str) -> Located {
+        let found = match self.peek() {
+            Some(t) => t.to_string(),
+            None => "end of file".to_owned(),
+        };
+        (self.offset(), Error::ExpectedToken(what, found))
+    }
+
+    /// Parses statements until the end of input or a closing brace
+    fn block(&mut self) -> Result<Vec<Option<Node>>, Located> {
+        let mut out = vec![];
+        while !matches!(self.peek(), None | Some(Token::Punct('}'))) {
+            out.push(self.statement()?);
+        }
+        Ok(out)
+    }
+
+    /// Parses a single statement, returning `None` if it has no geometry
+    fn statement(&mut self) -> Result<Option<Node>, Located> {
+        if self.eat(';') {
+            return Ok(None);
+        } else if self.eat('{') {
+            let children = self.block()?;
+            self.expect('}', "`}`")?;
+            return self.union(children);
+        }
+
+        let offset = self.offset();
+        let Some(Token::Ident(name)) = self.peek().cloned() else {
+            return Err(self.unexpected("a module name"));
+        };
+        let (positional, named): (&[&str], &[&str]) = match name.as_str() {
+            "sphere" => (&["r"], &["d"]),
+            "cube" => (&["size", "center"], &[]),
+            "cylinder" => {
+                (&["h", "r1", "r2", "center"], &["r", "d", "d1", "d2"])
+            }
+            "translate" | "scale" => (&["v"], &[]),
+            "union" | "difference" | "intersection" => (&[], &[]),
+            _ => {
+                return Err((offset, Error::UnsupportedScad(name)));
+            }
+        };
+        self.pos += 1;
+        let args = self.args(name, positional, named)?;
+
+        let out = match args.module.as_str() {
+            "sphere" => {
+                let r = args.radius("r", "d")?.unwrap_or(1.0);
+                self.expect(';', "`;`")?;
+                Some(sphere(self.ctx, r).map_err(|e| (offset, e))?)
+            }
+            "cube" => {
+                let size = args.vec3("size", 1.0)?.unwrap_or([1.0; 3]);
+                let center = args.bool("center")?.unwrap_or(false);
+                self.expect(';', "`;`")?;
+                Some(cube(self.ctx, size, center).map_err(|e| (offset, e))?)
+            }
+            "cylinder" => {
+                let h = args.number("h")?.unwrap_or(1.0);
+                let r = args.radius("r", "d")?;
+                let r1 = args.radius("r1", "d1")?.or(r).unwrap_or(1.0);
+                let r2 = args.radius("r2", "d2")?.or(r).unwrap_or(1.0);
+                let center = args.bool("center")?.unwrap_or(false);
+                self.expect(';', "`;`")?;
+                let c = cylinder(self.ctx, h, [r1, r2], center)
+                    .map_err(|e| (offset, e))?;
+                Some(c)
+            }
+            "translate" | "scale" => {
+                let children = self.children()?;
+                let v = args
+                    .vec3("v", if args.module == "scale" { 1.0 } else { 0.0 })?
+                    .ok_or((offset, Error::BadScadArg("v".into())))?;
+                match self.union(children)? {
+                    Some(c) => Some(
+                        transform(self.ctx, c, &args.module, v)
+                            .map_err(|e| (offset, e))?,
+                    ),
+                    None => None,
+                }
+            }
+            "union" => {
+                let children = self.children()?;
+                self.union(children)?
+            }
+            "intersection" => {
+                let children = self.children()?;
+                let mut out = None;
+                for c in children {
+                    let Some(c) = c else {
+                        return Ok(None);
+                    };
+                    out = Some(match out {
+                        Some(o) => {
+                            self.ctx.max(o, c).map_err(|e| (offset, e))?
+                        }
+                        None => c,
+                    });
+                }
+                out
+            }
+            "difference" => {
+                let children = self.children()?;
+                let mut iter = children.into_iter();
+                match iter.next().flatten() {
+                    Some(body) => {
+                        let tools = iter.flatten().collect::<Vec<_>>();
+                        Some(
+                            self.ctx
+                                .difference_all(body, &tools)
+                                .map_err(|e| (offset, e))?,
+                        )
+                    }
+                    None => None,
+                }
+            }
+            _ => unreachable!(),
+        };
+        Ok(out)
+    }
+
+    /// Parses the children of a module: a block, a statement, or `;`
+    fn children(&mut self) -> Result<Vec<Option<Node>>, Located> {
+        if self.eat(';') {
+            Ok(vec![])
+        } else if self.eat('{') {
+            let out = self.block()?;
+            self.expect('}', "`}`")?;
+            Ok(out)
+        } else {
+            Ok(vec![self.statement()?])
+        }
+    }
+
+    /// Parses an argument list, binding arguments to their names
+    ///
+    /// Special variables (e.g. `$fn`) control OpenSCAD's tessellation, so they
+    /// are accepted and ignored.
+    fn args(
+        &mut self,
+        module: String,
+        positional: &[&str],
+        named: &[&str],
+    ) -> Result<Args, Located> {
+        self.expect('(', "`(`")?;
+        let mut values = BTreeMap::new();
+        let mut index = 0;
+        while !self.eat(')') {
+            let offset = self.offset();
+            let name = match (self.peek(), self.tokens.get(self.pos + 1)) {
+                (Some(Token::Ident(s)), Some((Token::Punct('='), _))) => {
+                    let s = s.clone();
+                    self.pos += 2;
+                    if !s.starts_with('$')
+                        && !positional.contains(&s.as_str())
+                        && !named.contains(&s.as_str())
+                    {
+                        let e = Error::UnknownScadArg(s, module);
+                        return Err((offset, e));
+                    }
+                    s
+                }
+                _ => {
+                    let Some(s) = positional.get(index) else {
+                        let e = Error::UnknownScadArg(
+                            format!("#{}", index + 1),
+                            module,
+                        );
+                        return Err((offset, e));
+                    };
+                    index += 1;
+                    s.to_string()
+                }
+            };
+            let v = self.value()?;
+            values.insert(name, (v, offset));
+            if !self.eat(',') && self.peek() != Some(&Token::Punct(')')) {
+                return Err(self.unexpected("`,` or `)`"));
+            }
+        }
+        Ok(Args { module, values })
+    }
+
+    fn number(&mut self) -> Result<f64, Located> {
+        let sign = if self.eat('-') {
+            -1.0
+        } else {
+            self.eat('+');
+            1.0
+        };
+        match self.peek() {
+            Some(Token::Number(v)) => {
+                let v = *v;
+                self.pos += 1;
+                Ok(sign * v)
+            }
+            _ => Err(self.unexpected("a number")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Located> {
+        match self.peek() {
+            Some(Token::Punct('[')) => {
+                self.pos += 1;
+                let mut out = vec![];
+                while !self.eat(']') {
+                    out.push(self.number()?);
+                    if !self.eat(',') && self.peek() != Some(&Token::Punct(']'))
+                    {
+                        return Err(self.unexpected("`,` or `]`"));
+                    }
+                }
+                Ok(Value::Vector(out))
+            }
+            Some(Token::Ident(s)) if s == "true" || s == "false" => {
+                let v = s == "true";
+                self.pos += 1;
+                Ok(Value::Bool(v))
+            }
+            _ => self.number().map(Value::Number),
+        }
+    }
+
+    fn union(
+        &mut self,
+        nodes: Vec<Option<Node>>,
+    ) -> Result<Option<Node>, Located> {
+        let mut out = None;
+        for n in nodes.into_iter().flatten() {
+            out = Some(match out {
+                Some(o) => self.ctx.min(o, n).map_err(|e| (self.end, e))?,
+                None => n,
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// Returns the distance from the origin
+fn length(ctx: &mut Context, axes: &[Node]) -> Result<Node, Error> {
+    let mut sum = None;
+    for &a in axes {
+        let a2 = ctx.square(a)?;
+        sum = Some(match sum {
+            Some(s) => ctx.add(s, a2)?,
+            None => a2,
+        });
+    }
+    ctx.sqrt(sum.unwrap())
+}
+
+/// Builds a sphere of radius `r`, centered at the origin
+fn sphere(ctx: &mut Context, r: f64) -> Result<Node, Error> {
+    let axes = [ctx.x(), ctx.y(), ctx.z()];
+    let len = length(ctx, &axes)?;
+    ctx.sub(len, r)
+}
+
+/// Builds an axis-aligned box with one corner at the origin (or centered)
+fn cube(
+    ctx: &mut Context,
+    size: [f64; 3],
+    center: bool,
+) -> Result<Node, Error> {
+    let axes = [ctx.x(), ctx.y(), ctx.z()];
+    let mut out = None;
+    for (p, s) in axes.into_iter().zip(size) {
+        let c = if center { 0.0 } else { s / 2.0 };
+        let d = ctx.sub(p, c)?;
+        let d = ctx.abs(d)?;
+        let d = ctx.sub(d, s / 2.0)?;
+        out = Some(match out {
+            Some(o) => ctx.max(o, d)?,
+            None => d,
+        });
+    }
+    Ok(out.unwrap())
+}
+
+/// Builds a cylinder (or cone) along the Z axis
+///
+/// The radius varies linearly from `r[0]` at the bottom to `r[1]` at the
+/// top; the bottom is at Z = 0, unless the cylinder is centered.
+fn cylinder(
+    ctx: &mut Context,
+    h: f64,
+    r: [f64; 2],
+    center: bool,
+) -> Result<Node, Error> {
+    let z0 = if center { -h / 2.0 } else { 0.0 };
+    let (x, y, z) = (ctx.x(), ctx.y(), ctx.z());
+    let radial = length(ctx, &[x, y])?;
+    let t = ctx.sub(z, z0)?;
+    let t = ctx.mul(t, (r[1] - r[0]) / h)?;
+    let t = ctx.add(t, r[0])?;
+    let side = ctx.sub(radial, t)?;
+
+    let caps = ctx.sub(z, z0 + h / 2.0)?;
+    let caps = ctx.abs(caps)?;
+    let caps = ctx.sub(caps, h / 2.0)?;
+    ctx.max(side, caps)
+}
+
+/// Applies a `translate` or `scale` transform to a shape
+fn transform(
+    ctx: &mut Context,
+    node: Node,
+    module: &str,
+    v: [f64; 3],
+) -> Result<Node, Error> {
+    let mut xyz = [ctx.x(), ctx.y(), ctx.z()];
+    for (p, v) in xyz.iter_mut().zip(v) {
+        *p = match module {
+            "translate" => ctx.sub(*p, v)?,
+            "scale" => ctx.div(*p, v)?,
+            m => unreachable!("unexpected module {m}"),
+        };
+    }
+    ctx.remap_xyz(node, xyz)
+}
+
+/// Converts an error at a byte offset into an [`Error::ParseError`]
+fn locate(text: &str, (offset, e): Located) -> Error {
+    let start = text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let end = text[offset..]
+        .find('\n')
+        .map(|i| offset + i)
+        .unwrap_or(text.len());
+    let line = TextLine {
+        text: text[start..end].to_owned(),
+        line: text[..start].matches('\n').count() + 1,
+        offset: start,
+    };
+    line.error(offset - start, e)
+}
+
+impl Context {
+    /// Parses a model written in a subset of OpenSCAD's CSG language
+    ///
+    /// The supported modules are the primitives `sphere`, `cube`, and
+    /// `cylinder`; the transforms `translate` and `scale`; and the boolean
+    /// operations `union`, `difference`, and `intersection`.  Arguments must
+    /// be literal numbers, vectors, or booleans, and special variables such
+    /// as `$fn` are ignored (since the result is an implicit surface rather
+    /// than a tessellation).  Top-level statements are combined with a union.
+    ///
+    /// Primitives are built as implicit surfaces; spheres are exact distance
+    /// fields, while boxes and cylinders are bounds on the distance.
+    ///
+    /// ```
+    /// # use fidget::context::Context;
+    /// let scad = "
+    /// difference() {
+    ///     cube(2, center = true);
+    ///     translate([1, 0, 0]) sphere(r = 0.5);
+    /// }
+    /// ";
+    /// let (ctx, root) = Context::from_scad(scad.as_bytes()).unwrap();
+    /// assert!(ctx.eval_xyz(root, -0.5, 0.0, 0.0).unwrap() < 0.0);
+    /// assert!(ctx.eval_xyz(root, 0.9, 0.0, 0.0).unwrap() > 0.0);
+    /// ```
+    ///
+    /// Anything outside of this subset is an [`Error::ParseError`], rather
+    /// than being silently dropped:
+    /// ```
+    /// # use fidget::context::Context;
+    /// let scad = "union() {\n  sphere(1);\n  rotate([0, 0, 45]) cube(1);\n}";
+    /// let Err(e) = Context::from_scad(scad.as_bytes()) else { panic!() };
+    /// assert_eq!(
+    ///     e.to_string(),
+    ///     "line 3, column 3: unsupported OpenSCAD module `rotate`\n  \
+    ///      rotate([0, 0, 45]) cube(1);\n  ^"
+    /// );
+    /// ```
+    pub fn from_scad<R: Read>(mut r: R) -> Result<(Self, Node), Error> {
+        let mut text = String::new();
+        r.read_to_string(&mut text)?;
+
+        let tokens = tokenize(&text).map_err(|e| locate(&text, e))?;
+        let mut ctx = Self::new();
+        let mut parser = Parser {
+            ctx: &mut ctx,
+            tokens,
+            pos: 0,
+            end: text.len(),
+        };
+        let root = parser
+            .block()
+            .and_then(|nodes| {
+                if parser.peek().is_some() {
+                    Err(parser.unexpected("a statement"))
+                } else {
+                    parser.union(nodes)
+                }
+            })
+            .map_err(|e| locate(&text, e))?;
+        let root = root.ok_or(Error::EmptyFile)?;
+        Ok((ctx, root))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(text: &str) -> (Context, Node) {
+        Context::from_scad(text.as_bytes()).unwrap()
+    }
+
+    fn inner(text: &str) -> Error {
+        match Context::from_scad(text.as_bytes()) {
+            Err(Error::ParseError { source, .. }) => *source,
+            r => panic!("expected a parse error, got {r:?}"),
+        }
+    }
+
+    #[test]
+    fn test_scad_primitives() {
+        let (ctx, root) = parse("sphere(r = 2);");
+        assert_eq!(ctx.eval_xyz(root, 3.0, 0.0, 0.0).unwrap(), 1.0);
+        let (ctx, root) = parse("sphere(d = 2, $fn = 64);");
+        assert_eq!(ctx.eval_xyz(root, 0.0, 0.0, 0.0).unwrap(), -1.0);
+        let (ctx, root) = parse("sphere(0.5);");
+        assert_eq!(ctx.eval_xyz(root, 0.0, 1.0, 0.0).unwrap(), 0.5);
+
+        // Cubes are placed in the positive octant unless centered
+        let (ctx, root) = parse("cube([2, 4, 6]);");
+        assert_eq!(ctx.eval_xyz(root, 1.0, 2.0, 3.0).unwrap(), -1.0);
+        assert!(ctx.eval_xyz(root, -0.5, 2.0, 3.0).unwrap() > 0.0);
+        let (ctx, root) = parse("cube(size = 2, center = true);");
+        assert_eq!(ctx.eval_xyz(root, 0.0, 0.0, 0.0).unwrap(), -1.0);
+        assert_eq!(ctx.eval_xyz(root, 0.0, 0.0, 1.5).unwrap(), 0.5);
+
+        let (ctx, root) = parse("cylinder(h = 4, r = 1);");
+        assert_eq!(ctx.eval_xyz(root, 0.0, 0.0, 2.0).unwrap(), -1.0);
+        assert_eq!(ctx.eval_xyz(root, 2.0, 0.0, 2.0).unwrap(), 1.0);
+        assert_eq!(ctx.eval_xyz(root, 0.0, 0.0, 5.0).unwrap(), 1.0);
+        assert!(ctx.eval_xyz(root, 0.0, 0.0, -0.5).unwrap() > 0.0);
+
+        // A cone narrows from r1 to r2
+        let (ctx, root) = parse("cylinder(2, 1, 0, center = true);");
+        assert!(ctx.eval_xyz(root, 0.9, 0.0, -0.9).unwrap() < 0.0);
+        assert!(ctx.eval_xyz(root, 0.9, 0.0, 0.9).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_scad_csg() {
+        let (ctx, root) = parse(
+            "
+            // Two spheres, with a cube cut out
+            difference() {
+                union() {
+                    translate([-1, 0, 0]) sphere(1);
+                    translate([1, 0]) { sphere(1); }
+                }
+                /* the cube is centered at
+                   (0, 0, 1) */
+                translate([0, 0, 1]) cube(1, true);
+                ;
+            }
+            ",
+        );
+        let eval = |x, y, z| ctx.eval_xyz(root, x, y, z).unwrap();
+        assert!(eval(-1.0, 0.0, 0.0) < 0.0);
+        assert!(eval(1.0, 0.0, -0.5) < 0.0);
+        assert!(eval(0.0, 0.0, 0.75) > 0.0);
+        assert!(eval(3.0, 0.0, 0.0) > 0.0);
+
+        let (ctx, root) = parse(
+            "intersection() { sphere(1); translate([1, 0, 0]) sphere(1); }",
+        );
+        assert!(ctx.eval_xyz(root, 0.5, 0.0, 0.0).unwrap() < 0.0);
+        assert!(ctx.eval_xyz(root, -0.5, 0.0, 0.0).unwrap() > 0.0);
+
+        // Top-level statements are combined, and scaling takes one or three
+        // values
+        let (ctx, root) =
+            parse("scale(2) sphere(1);\nscale([1, 1, 4]) sphere(1);");
+        assert!(ctx.eval_xyz(root, 1.9, 0.0, 0.0).unwrap() < 0.0);
+        assert!(ctx.eval_xyz(root, 0.0, 0.0, 3.9).unwrap() < 0.0);
+        assert!(ctx.eval_xyz(root, 0.0, 0.0, 4.1).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_scad_errors() {
+        let e = Context::from_scad("sphere(1);\nhull() cube(1);".as_bytes());
+        match e {
+            Err(Error::ParseError {
+                line,
+                column,
+                source,
+                ..
+            }) => {
+                assert_eq!((line, column), (2, 1));
+                assert!(matches!(
+                    *source,
+                    Error::UnsupportedScad(ref s) if s == "hull"
+                ));
+            }
+            r => panic!("unexpected result {r:?}"),
+        }
+
+        assert!(matches!(
+            inner("sphere(radius = 1);"),
+            Error::UnknownScadArg(a, m) if a == "radius" && m == "sphere"
+        ));
+        assert!(matches!(
+            inner("sphere(1, 2);"),
+            Error::UnknownScadArg(a, _) if a == "#2"
+        ));
+        assert!(matches!(
+            inner("translate(true) sphere(1);"),
+            Error::BadScadArg(a) if a == "v"
+        ));
+        assert!(matches!(
+            inner("translate() sphere(1);"),
+            Error::BadScadArg(a) if a == "v"
+        ));
+        assert!(matches!(inner("sphere(1)"), Error::ExpectedToken("`;`", _)));
+        assert!(matches!(
+            inner("union() { sphere(1);"),
+            Error::ExpectedToken("`}`", _)
+        ));
+        assert!(matches!(
+            inner("sphere(r = 1 + 2);"),
+            Error::ExpectedToken(..)
+        ));
+        assert!(matches!(inner("}"), Error::ExpectedToken(..)));
+        assert!(matches!(
+            Context::from_scad("// nothing here".as_bytes()),
+            Err(Error::EmptyFile)
+        ));
+    }
+}
@@ -20,6 +20,15 @@
 //! assert_eq!(value, 0.25);
 //! # Ok::<(), fidget::Error>(())
 //! ```
+//!
+//! # Indeterminate forms
+//! Every evaluator (the interpreter, the JIT backends, and their interval
+//! variants) uses the same policy for indeterminate forms: `0 / 0`, `∞ / ∞`,
+//! and `∞ - ∞` produce `NaN`, which then propagates through arithmetic.  An
+//! interval evaluator returns `[NaN, NaN]` if either bound is indeterminate.
+//!
+//! Note that the [`Context`] simplifies some expressions as the graph is built
+//! (e.g. `0 / x` becomes `0`), so such expressions never reach an evaluator.
 use crate::{
     context::Node,
     types::{Grad, Interval},
@@ -31,16 +40,20 @@ use std::collections::HashMap;
 pub mod test;
 
 mod bulk;
+mod pool;
 mod tracing;
 mod transform;
 
 mod vars;
+mod verify;
 
 // Re-export a few things
 pub use bulk::BulkEvaluator;
+pub use pool::{PooledWorkspace, WorkspacePool};
 pub use tracing::TracingEvaluator;
 pub use transform::TransformedShape;
 pub use vars::Vars;
+pub use verify::{verify, Mismatch, ShapeBackend, VerifyBackend};
 
 /// A shape represents an implicit surface
 ///
@@ -219,6 +232,16 @@ pub trait EzShape: Shape {
     fn ez_simplify(&self, trace: &Self::Trace) -> Result<Self, Error>
     where
         Self: Sized;
+
+    /// Runs a few throwaway evaluations with every evaluator type
+    ///
+    /// The first evaluation of a shape may pay one-time costs (e.g. paging in
+    /// JIT-compiled code), which skew benchmarks; calling this before starting
+    /// the clock moves those costs out of the measurement.  Any variables are
+    /// bound to zero.
+    fn warmup(&self) -> Result<(), Error>
+    where
+        Self: ShapeVars;
 }
 
 impl<S: Shape> EzShape for S {
@@ -248,6 +271,43 @@ impl<S: Shape> EzShape for S {
         let mut workspace = Default::default();
         self.simplify(trace, Default::default(), &mut workspace)
     }
+
+    fn warmup(&self) -> Result<(), Error>
+    where
+        Self: ShapeVars,
+    {
+        const N: usize = 64;
+        let vars = vec![0.0; self.vars().len()];
+        let xs: Vec<f32> =
+            (0..N).map(|i| i as f32 / N as f32 * 2.0 - 1.0).collect();
+        let ys: Vec<f32> = xs.iter().rev().cloned().collect();
+        let zs = vec![0.0; N];
+
+        let point = self.ez_point_tape();
+        let interval = self.ez_interval_tape();
+        let float_slice = self.ez_float_slice_tape();
+        let grad_slice = self.ez_grad_slice_tape();
+
+        let mut point_eval = Self::new_point_eval();
+        let mut interval_eval = Self::new_interval_eval();
+        let mut float_eval = Self::new_float_slice_eval();
+        let mut grad_eval = Self::new_grad_slice_eval();
+        for _ in 0..4 {
+            for (x, y) in xs.iter().zip(&ys) {
+                point_eval.eval(&point, *x, *y, 0.0, &vars)?;
+            }
+            interval_eval.eval(
+                &interval,
+                [-1.0, 1.0],
+                [-1.0, 1.0],
+                [-1.0, 1.0],
+                &vars,
+            )?;
+            float_eval.eval(&float_slice, &xs, &ys, &zs, &vars)?;
+            grad_eval.eval(&grad_slice, &xs, &ys, &zs, &vars)?;
+        }
+        Ok(())
+    }
 }
 
 /// A [`Shape`] which contains named variables
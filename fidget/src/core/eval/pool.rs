@@ -0,0 +1,191 @@
+//! Shared pool of simplification workspaces
+use crate::{eval::Shape, Error};
+use std::sync::Mutex;
+
+/// A thread-safe pool of [`Shape::Workspace`] objects, tied to a single shape
+///
+/// Simplifying a shape requires a workspace, which is expensive to build from
+/// scratch (it contains a register allocator and per-node bindings).  When
+/// many short-lived evaluations are spawned (e.g. by a renderer's worker
+/// threads), it's useful to recycle those workspaces instead of creating a new
+/// one for each evaluation.
+///
+/// Workspaces are checked out with [`WorkspacePool::get`] and returned to the
+/// pool when the [`PooledWorkspace`] guard is dropped.  Each simplification
+/// fully resets the workspace, so no state leaks between uses.
+///
+/// ```
+/// use fidget::{
+///     eval::{EzShape, MathShape, Shape, TracingEvaluator, WorkspacePool},
+///     vm::VmShape,
+/// };
+///
+/// let (node, ctx) = fidget::rhai::eval("min(x, y)")?;
+/// let shape = VmShape::new(&ctx, node)?;
+/// let pool = WorkspacePool::new(shape.clone());
+///
+/// let tape = shape.ez_point_tape();
+/// let mut eval = VmShape::new_point_eval();
+/// let (_, trace) = eval.eval(&tape, 1.0, 2.0, 0.0, &[])?;
+/// let simple = pool.simplify(trace.unwrap(), Default::default())?;
+/// assert_eq!(simple.size(), 1);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub struct WorkspacePool<S: Shape> {
+    shape: S,
+    workspaces: Mutex<Vec<S::Workspace>>,
+}
+
+impl<S: Shape> WorkspacePool<S> {
+    /// Builds a new (empty) pool for the given shape
+    pub fn new(shape: S) -> Self {
+        Self {
+            shape,
+            workspaces: Mutex::new(vec![]),
+        }
+    }
+
+    /// Returns the shape associated with this pool
+    pub fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    /// Checks out a workspace, building a new one if the pool is empty
+    pub fn get(&self) -> PooledWorkspace<'_, S> {
+        let workspace = self.workspaces.lock().unwrap().pop();
+        PooledWorkspace {
+            pool: self,
+            workspace: Some(workspace.unwrap_or_default()),
+        }
+    }
+
+    /// Returns the number of idle workspaces in the pool
+    pub fn len(&self) -> usize {
+        self.workspaces.lock().unwrap().len()
+    }
+
+    /// Checks whether the pool has no idle workspaces
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Simplifies the pool's shape with the given trace
+    ///
+    /// This is equivalent to calling [`Shape::simplify`] with a workspace
+    /// borrowed from the pool.
+    pub fn simplify(
+        &self,
+        trace: &S::Trace,
+        storage: S::Storage,
+    ) -> Result<S, Error>
+    where
+        S: Sized,
+    {
+        let mut workspace = self.get();
+        self.shape.simplify(trace, storage, &mut workspace)
+    }
+}
+
+/// A workspace checked out from a [`WorkspacePool`]
+///
+/// The workspace is returned to the pool when this object is dropped.
+pub struct PooledWorkspace<'a, S: Shape> {
+    pool: &'a WorkspacePool<S>,
+    workspace: Option<S::Workspace>,
+}
+
+impl<S: Shape> std::ops::Deref for PooledWorkspace<'_, S> {
+    type Target = S::Workspace;
+    fn deref(&self) -> &Self::Target {
+        self.workspace.as_ref().unwrap()
+    }
+}
+
+impl<S: Shape> std::ops::DerefMut for PooledWorkspace<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.workspace.as_mut().unwrap()
+    }
+}
+
+impl<S: Shape> Drop for PooledWorkspace<'_, S> {
+    fn drop(&mut self) {
+        if let Some(w) = self.workspace.take() {
+            self.pool.workspaces.lock().unwrap().push(w);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        eval::{EzShape, MathShape, TracingEvaluator},
+        vm::VmShape,
+        Context,
+    };
+
+    const COLONNADE: &str = include_str!("../../../../models/colonnade.vm");
+
+    fn check_pool<S: Shape + MathShape + Clone + Send + Sync>() {
+        let (ctx, root) = Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let shape = S::new(&ctx, root).unwrap();
+        let pool = WorkspacePool::new(shape.clone());
+        let tape = shape.ez_point_tape();
+
+        // Points with different traces, so that a stale binding from the
+        // previous simplification would produce a different result
+        let pts: Vec<[f32; 3]> = (0..64)
+            .map(|i| {
+                let t = i as f32 / 64.0;
+                [(t * 7.0).sin(), (t * 11.0).cos(), t * 2.0 - 1.0]
+            })
+            .collect();
+
+        let check = |eval: &mut S::PointEval, [x, y, z]: [f32; 3]| {
+            let (v, trace) = eval.eval(&tape, x, y, z, &[]).unwrap();
+            let Some(trace) = trace else {
+                return;
+            };
+            let fresh = shape.ez_simplify(trace).unwrap();
+            let pooled = pool.simplify(trace, Default::default()).unwrap();
+            assert_eq!(fresh.size(), pooled.size());
+
+            let mut e = S::new_point_eval();
+            let (a, _) = e.eval(&fresh.ez_point_tape(), x, y, z, &[]).unwrap();
+            let (b, _) = e.eval(&pooled.ez_point_tape(), x, y, z, &[]).unwrap();
+            assert_eq!(a.to_bits(), b.to_bits());
+            assert_eq!(a.to_bits(), v.to_bits());
+        };
+
+        let mut eval = S::new_point_eval();
+        for p in &pts {
+            check(&mut eval, *p);
+        }
+        assert_eq!(pool.len(), 1);
+
+        std::thread::scope(|s| {
+            for chunk in pts.chunks(16) {
+                let check = &check;
+                s.spawn(move || {
+                    let mut eval = S::new_point_eval();
+                    for p in chunk {
+                        check(&mut eval, *p);
+                    }
+                });
+            }
+        });
+        assert!(!pool.is_empty());
+        assert!(pool.len() <= 4);
+    }
+
+    #[test]
+    fn test_workspace_pool_vm() {
+        check_pool::<VmShape>();
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn test_workspace_pool_jit() {
+        check_pool::<crate::jit::JitShape>();
+    }
+}
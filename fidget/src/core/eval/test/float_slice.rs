@@ -3,7 +3,10 @@
 //! If the `eval-tests` feature is set, then this exposes a standard test suite
 //! for such evaluators; otherwise, the module has no public exports.
 
-use super::{build_stress_fn, test_args, CanonicalBinaryOp, CanonicalUnaryOp};
+use super::{
+    build_indeterminate_fns, build_stress_fn, test_args, CanonicalBinaryOp,
+    CanonicalUnaryOp,
+};
 use crate::{
     context::Context,
     eval::{BulkEvaluator, EzShape, MathShape, Shape, ShapeVars, Vars},
@@ -264,6 +267,17 @@ where
         }
     }
 
+    pub fn test_f_indeterminate() {
+        let (ctx, cases) = build_indeterminate_fns();
+        let mut eval = S::new_float_slice_eval();
+        for (node, x, y) in cases {
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_float_slice_tape();
+            let out = eval.eval(&tape, &[x], &[y], &[0.0], &[]).unwrap();
+            assert!(out[0].is_nan(), "expected NaN at ({x}, {y}), got {out:?}");
+        }
+    }
+
     pub fn test_unary<C: CanonicalUnaryOp>() {
         let args = test_args();
         let zero = vec![0.0; args.len()];
@@ -467,6 +481,7 @@ macro_rules! float_slice_tests {
         $crate::float_slice_test!(test_f_var, $t);
         $crate::float_slice_test!(test_f_sin, $t);
         $crate::float_slice_test!(test_f_stress, $t);
+        $crate::float_slice_test!(test_f_indeterminate, $t);
 
         mod f_unary {
             use super::*;
@@ -2,7 +2,10 @@
 //!
 //! If the `eval-tests` feature is set, then this exposes a standard test suite
 //! for interval evaluators; otherwise, the module has no public exports.
-use super::{build_stress_fn, test_args, CanonicalBinaryOp, CanonicalUnaryOp};
+use super::{
+    build_indeterminate_fns, build_stress_fn, test_args, CanonicalBinaryOp,
+    CanonicalUnaryOp,
+};
 use crate::{
     context::Context,
     eval::{BulkEvaluator, EzShape, MathShape, Shape, ShapeVars, Vars},
@@ -270,6 +273,46 @@ where
         );
     }
 
+    /// Checks the subgradient convention documented on [`Grad`]
+    pub fn test_g_kinks() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+
+        let abs = ctx.abs(x).unwrap();
+        let shape = S::new(&ctx, abs).unwrap();
+        let mut eval = S::new_grad_slice_eval();
+        let tape = shape.ez_grad_slice_tape();
+        let out = eval.eval(&tape, &[0.0, -0.0], &[0.0; 2], &[0.0; 2], &[]);
+        for g in out.unwrap() {
+            assert_eq!(*g, Grad::new(0.0, 0.0, 0.0, 0.0));
+        }
+
+        // `x` is the left-hand argument, regardless of argument order
+        for (a, b) in [(x, y), (y, x)] {
+            for f in [Context::min, Context::max] {
+                let node = f(&mut ctx, a, b).unwrap();
+                let shape = S::new(&ctx, node).unwrap();
+                let tape = shape.ez_grad_slice_tape();
+                assert_eq!(
+                    eval.eval(&tape, &[1.5], &[1.5], &[0.0], &[]).unwrap()[0],
+                    Grad::new(1.5, 1.0, 0.0, 0.0)
+                );
+            }
+        }
+
+        // Constants are always on the right
+        for f in [Context::min, Context::max] {
+            let node = f(&mut ctx, 0.5, x).unwrap();
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_grad_slice_tape();
+            assert_eq!(
+                eval.eval(&tape, &[0.5], &[0.0], &[0.0], &[]).unwrap()[0],
+                Grad::new(0.5, 1.0, 0.0, 0.0)
+            );
+        }
+    }
+
     pub fn test_g_not() {
         let mut ctx = Context::new();
         let x = ctx.x();
@@ -440,12 +483,54 @@ where
         }
     }
 
+    /// Checks that a batch of gradients matches single-point evaluation
+    pub fn test_g_batch() {
+        let (ctx, node) = build_stress_fn(8);
+        let shape = S::new(&ctx, node).unwrap();
+        let mut eval = S::new_grad_slice_eval();
+        let tape = shape.ez_grad_slice_tape();
+
+        // A 7×7 tile, so that the batch isn't a multiple of the SIMD width
+        let mut xs = vec![];
+        let mut ys = vec![];
+        let mut zs = vec![];
+        for i in 0..7 {
+            for j in 0..7 {
+                xs.push(i as f32 / 7.0 - 0.5);
+                ys.push(j as f32 / 7.0 - 0.5);
+                zs.push((i + j) as f32 / 14.0);
+            }
+        }
+        let batch = eval.eval(&tape, &xs, &ys, &zs, &[]).unwrap().to_vec();
+        assert_eq!(batch.len(), xs.len());
+        for (i, b) in batch.iter().enumerate() {
+            let v = eval
+                .eval(&tape, &xs[i..=i], &ys[i..=i], &zs[i..=i], &[])
+                .unwrap()[0];
+            b.compare_eq(v);
+        }
+    }
+
     pub fn test_g_stress() {
         for n in [1, 2, 4, 8, 12, 16, 32] {
             Self::test_g_stress_n(n);
         }
     }
 
+    pub fn test_g_indeterminate() {
+        let (ctx, cases) = build_indeterminate_fns();
+        let mut eval = S::new_grad_slice_eval();
+        for (node, x, y) in cases {
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_grad_slice_tape();
+            let out = eval.eval(&tape, &[x], &[y], &[0.0], &[]).unwrap();
+            assert!(
+                out[0].v.is_nan(),
+                "expected NaN at ({x}, {y}), got {out:?}"
+            );
+        }
+    }
+
     pub fn test_unary<C: CanonicalUnaryOp>() {
         let args = test_args();
         let zero = vec![0.0; args.len()];
@@ -760,11 +845,14 @@ macro_rules! grad_slice_tests {
         $crate::grad_test!(test_g_min, $t);
         $crate::grad_test!(test_g_max, $t);
         $crate::grad_test!(test_g_min_max, $t);
+        $crate::grad_test!(test_g_kinks, $t);
         $crate::grad_test!(test_g_not, $t);
         $crate::grad_test!(test_g_div, $t);
         $crate::grad_test!(test_g_recip, $t);
         $crate::grad_test!(test_g_var, $t);
         $crate::grad_test!(test_g_stress, $t);
+        $crate::grad_test!(test_g_batch, $t);
+        $crate::grad_test!(test_g_indeterminate, $t);
 
         mod g_unary {
             use super::*;
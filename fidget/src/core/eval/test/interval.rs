@@ -4,8 +4,8 @@
 //! for interval evaluators; otherwise, the module has no public exports.
 
 use super::{
-    build_stress_fn, test_args, test_args_n, CanonicalBinaryOp,
-    CanonicalUnaryOp,
+    build_indeterminate_fns, build_stress_fn, test_args, test_args_n,
+    CanonicalBinaryOp, CanonicalUnaryOp,
 };
 use crate::{
     context::Context,
@@ -159,7 +159,9 @@ where
         let tape = shape.ez_interval_tape();
 
         let mut eval = S::new_interval_eval();
-        assert_eq!(eval.eval_x(&tape, [0.0, 1.0]), [-1.0, 1.0].into());
+        assert_eq!(eval.eval_x(&tape, [0.0, 1.0]), [0.0, 1f32.sin()].into());
+        assert_eq!(eval.eval_x(&tape, [1.0, 2.0]), [1f32.sin(), 1.0].into());
+        assert_eq!(eval.eval_x(&tape, [-7.0, 0.0]), [-1.0, 1.0].into());
 
         let y = ctx.y();
         let y = ctx.mul(y, 2.0).unwrap();
@@ -168,7 +170,16 @@ where
         let shape = S::new(&ctx, s).unwrap();
         let tape = shape.ez_interval_tape();
 
-        assert_eq!(eval.eval_x(&tape, [0.0, 3.0]), [-1.0, 4.0].into());
+        assert_eq!(eval.eval_x(&tape, [0.0, 3.0]), [0.0, 3.0].into());
+        assert_eq!(
+            eval.eval_xy(&tape, [0.0, 3.0], [0.0, 3.0]),
+            [-1.0, 4.0].into()
+        );
+
+        let c = ctx.cos(x).unwrap();
+        let shape = S::new(&ctx, c).unwrap();
+        let tape = shape.ez_interval_tape();
+        assert_eq!(eval.eval_x(&tape, [3.0, 3.5]), [-1.0, 3.5f32.cos()].into());
     }
 
     pub fn test_i_neg() {
@@ -790,6 +801,40 @@ where
         out
     }
 
+    pub fn test_i_indeterminate() {
+        let (ctx, cases) = build_indeterminate_fns();
+        let mut eval = S::new_interval_eval();
+        for (node, x, y) in cases {
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_interval_tape();
+            let out = eval.eval_xy(&tape, x, y);
+            assert!(
+                out.lower().is_nan() && out.upper().is_nan(),
+                "expected NaN at ({x}, {y}), got {out:?}"
+            );
+        }
+
+        // An indeterminate form at one end of the interval should still make
+        // the entire result NaN
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let inf = f32::INFINITY;
+        for node in [ctx.add(x, y).unwrap(), ctx.sub(x, y).unwrap()] {
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_interval_tape();
+            for (x, y) in
+                [([-inf, inf], [inf, inf]), ([-inf, inf], [-inf, -inf])]
+            {
+                let out = eval.eval_xy(&tape, x, y);
+                assert!(
+                    out.lower().is_nan() && out.upper().is_nan(),
+                    "expected NaN for {x:?}, {y:?}, got {out:?}"
+                );
+            }
+        }
+    }
+
     pub fn test_unary<C: CanonicalUnaryOp>() {
         let args = Self::interval_test_args();
 
@@ -1066,6 +1111,7 @@ macro_rules! interval_tests {
         $crate::interval_test!(test_i_simplify, $t);
         $crate::interval_test!(test_i_var, $t);
         $crate::interval_test!(test_i_stress, $t);
+        $crate::interval_test!(test_i_indeterminate, $t);
 
         mod i_unary {
             use super::*;
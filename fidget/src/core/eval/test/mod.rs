@@ -34,6 +34,45 @@ pub fn build_stress_fn(n: usize) -> (Context, Node) {
     (ctx, sum)
 }
 
+/// Builds expressions which hit an indeterminate form (`0 / 0` or `∞ - ∞`)
+///
+/// Returns a context and a list of `(node, x, y)` tuples; evaluating each node
+/// at the given position should produce `NaN` in every evaluator.
+///
+/// (`0 / y` isn't tested here, because the context simplifies it to `0` when
+/// the graph is built)
+pub fn build_indeterminate_fns() -> (Context, Vec<(Node, f32, f32)>) {
+    let mut ctx = Context::new();
+    let x = ctx.x();
+    let y = ctx.y();
+    let inf = f32::INFINITY;
+
+    let mut out = vec![
+        (ctx.div(x, y).unwrap(), 0.0, 0.0),
+        (ctx.div(x, 0.0).unwrap(), 0.0, 1.0),
+        (ctx.div(f64::INFINITY, y).unwrap(), 0.0, inf),
+        (ctx.div(x, y).unwrap(), inf, -inf),
+        (ctx.sub(x, y).unwrap(), inf, inf),
+        (ctx.sub(x, y).unwrap(), -inf, -inf),
+        (ctx.add(x, y).unwrap(), inf, -inf),
+        (ctx.sub(x, f64::INFINITY).unwrap(), inf, 0.0),
+        (ctx.sub(f64::INFINITY, y).unwrap(), 0.0, inf),
+        (ctx.add(x, f64::NEG_INFINITY).unwrap(), inf, 0.0),
+    ];
+
+    // The NaN should propagate through subsequent arithmetic
+    let sub = ctx.sub(x, y).unwrap();
+    let sub = ctx.mul(sub, 2.0).unwrap();
+    let sub = ctx.add(sub, 1.0).unwrap();
+    out.push((sub, inf, inf));
+    let div = ctx.div(x, y).unwrap();
+    let div = ctx.square(div).unwrap();
+    let div = ctx.sub(div, x).unwrap();
+    out.push((div, 0.0, 0.0));
+
+    (ctx, out)
+}
+
 /// Pick a bunch of arguments, some of which are spicy
 fn test_args_n(n: i64) -> Vec<f32> {
     let mut args = (-n..=n)
@@ -190,7 +229,7 @@ pub mod canonical {
 
     declare_canonical_unary!(Context::neg, |a| -a);
     declare_canonical_unary!(Context::recip, |a| 1.0 / a);
-    declare_canonical_unary!(Context::abs, |a| a.abs());
+    declare_canonical_unary!(Context::abs, |a| a.abs(), |a| a == 0.0);
     declare_canonical_unary!(Context::sin, |a| a.sin());
     declare_canonical_unary!(Context::cos, |a| a.cos());
     declare_canonical_unary!(Context::tan, |a| a.tan());
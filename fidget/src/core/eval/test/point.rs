@@ -2,7 +2,10 @@
 //!
 //! If the `eval-tests` feature is set, then this exposes a standard test suite
 //! for point evaluators; otherwise, the module has no public exports.
-use super::{build_stress_fn, test_args, CanonicalBinaryOp, CanonicalUnaryOp};
+use super::{
+    build_indeterminate_fns, build_stress_fn, test_args, CanonicalBinaryOp,
+    CanonicalUnaryOp,
+};
 use crate::{
     context::Context,
     eval::{EzShape, MathShape, Shape, ShapeVars, TracingEvaluator, Vars},
@@ -202,6 +205,36 @@ where
         assert_eq!(trace.unwrap().as_ref(), &[Choice::Left]);
     }
 
+    pub fn test_p_choices()
+    where
+        <S as Shape>::Trace: AsRef<[Choice]>,
+    {
+        // Each choice must be written to its own slot in the trace
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let a = ctx.min(x, y).unwrap();
+        let b = ctx.max(a, z).unwrap();
+        let c = ctx.and(b, x).unwrap();
+        let d = ctx.or(c, y).unwrap();
+
+        let shape = S::new(&ctx, d).unwrap();
+        let tape = shape.ez_point_tape();
+        let mut eval = S::new_point_eval();
+        let (r, trace) = eval.eval(&tape, 1.0, 2.0, 3.0, &[]).unwrap();
+        assert_eq!(r, 1.0);
+        let trace = trace.unwrap().as_ref().to_vec();
+
+        let vm = crate::vm::VmShape::new(&ctx, d).unwrap();
+        let vm_tape = vm.ez_point_tape();
+        let mut vm_eval = crate::vm::VmShape::new_point_eval();
+        let (_, expected) = vm_eval.eval(&vm_tape, 1.0, 2.0, 3.0, &[]).unwrap();
+        assert_eq!(trace, expected.unwrap().as_ref());
+        assert_eq!(trace.len(), 4);
+        assert!(!trace.contains(&Choice::Unknown));
+    }
+
     pub fn test_p_sin()
     where
         <S as Shape>::Trace: AsRef<[Choice]>,
@@ -401,6 +434,21 @@ where
         );
     }
 
+    pub fn test_warmup() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let a = ctx.var("a").unwrap();
+        let sum = ctx.add(x, a).unwrap();
+        let shape = S::new(&ctx, sum).unwrap();
+        shape.warmup().unwrap();
+
+        let tape = shape.ez_point_tape();
+        let mut vars = Vars::new(shape.vars());
+        let mut eval = S::new_point_eval();
+        let v = vars.bind([("a", 2.0)].into_iter());
+        assert_eq!(eval.eval(&tape, 1.0, 0.0, 0.0, v).unwrap().0, 3.0);
+    }
+
     pub fn test_p_stress_n(depth: usize) {
         let (ctx, node) = build_stress_fn(depth);
 
@@ -458,6 +506,17 @@ where
         }
     }
 
+    pub fn test_p_indeterminate() {
+        let (ctx, cases) = build_indeterminate_fns();
+        let mut eval = S::new_point_eval();
+        for (node, x, y) in cases {
+            let shape = S::new(&ctx, node).unwrap();
+            let tape = shape.ez_point_tape();
+            let (v, _) = eval.eval(&tape, x, y, 0.0, &[]).unwrap();
+            assert!(v.is_nan(), "expected NaN at ({x}, {y}), got {v}");
+        }
+    }
+
     pub fn test_unary<C: CanonicalUnaryOp>() {
         // Pick a bunch of arguments, some of which are spicy
         let args = test_args();
@@ -656,11 +715,14 @@ macro_rules! point_tests {
         $crate::point_test!(test_p_sin, $t);
         $crate::point_test!(test_p_and, $t);
         $crate::point_test!(test_p_or, $t);
+        $crate::point_test!(test_p_choices, $t);
         $crate::point_test!(basic_interpreter, $t);
         $crate::point_test!(test_push, $t);
         $crate::point_test!(test_var, $t);
+        $crate::point_test!(test_warmup, $t);
         $crate::point_test!(test_basic, $t);
         $crate::point_test!(test_p_stress, $t);
+        $crate::point_test!(test_p_indeterminate, $t);
 
         mod p_unary {
             use super::*;
@@ -0,0 +1,232 @@
+//! Consistency checks between evaluator backends
+use crate::{
+    context::{Context, Node, Rng},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape},
+    Error,
+};
+
+/// Relative tolerance when comparing values from different backends
+const TOLERANCE: f32 = 1e-5;
+
+/// An evaluator backend which can be checked with [`verify`]
+pub trait VerifyBackend {
+    /// Returns the name of this backend, used in [`Mismatch`] reports
+    fn name(&self) -> &str;
+
+    /// Evaluates the given node at every point
+    fn eval(
+        &self,
+        ctx: &Context,
+        node: Node,
+        xs: &[f32],
+        ys: &[f32],
+        zs: &[f32],
+    ) -> Result<Vec<f32>, Error>;
+}
+
+/// [`VerifyBackend`] which uses a shape's float slice evaluator
+///
+/// ```
+/// use fidget::{eval::ShapeBackend, vm::VmShape};
+/// let vm = ShapeBackend::<VmShape>::new("vm");
+/// ```
+pub struct ShapeBackend<S> {
+    name: String,
+    _marker: std::marker::PhantomData<fn() -> S>,
+}
+
+impl<S> ShapeBackend<S> {
+    /// Builds a new backend with the given name
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<S: Shape + MathShape> VerifyBackend for ShapeBackend<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn eval(
+        &self,
+        ctx: &Context,
+        node: Node,
+        xs: &[f32],
+        ys: &[f32],
+        zs: &[f32],
+    ) -> Result<Vec<f32>, Error> {
+        let shape = S::new(ctx, node)?;
+        let tape = shape.ez_float_slice_tape();
+        let mut eval = S::new_float_slice_eval();
+        Ok(eval.eval(&tape, xs, ys, zs, &[])?.to_vec())
+    }
+}
+
+/// Failure reported by [`verify`]
+#[derive(Debug)]
+pub enum Mismatch {
+    /// Backends returned different values at a point
+    Values {
+        /// Position at which the backends disagree
+        point: [f32; 3],
+        /// Name and result of every backend at that point
+        values: Vec<(String, f32)>,
+    },
+    /// A backend failed to evaluate the model
+    Error {
+        /// Name of the failing backend
+        backend: String,
+        /// Error returned by the backend
+        error: Error,
+    },
+}
+
+/// Checks that several backends agree when evaluating a model
+///
+/// The node is evaluated by each backend at `samples` pseudo-random points in
+/// the region `[-1, 1]³` (use [`Context::remap_xyz`] to check a different
+/// region).  Points are generated from a fixed seed, so results are
+/// reproducible.  Values are compared against the first backend, with a small
+/// relative tolerance; `NaN` values are considered equal to each other.
+///
+/// Returns the first disagreement (or evaluation failure), if any.
+///
+/// ```
+/// use fidget::{
+///     context::Context,
+///     eval::{verify, ShapeBackend},
+///     vm::VmShape,
+/// };
+///
+/// let mut ctx = Context::new();
+/// let x = ctx.x();
+/// let y = ctx.y();
+/// let node = ctx.mul(x, y)?;
+/// let a = ShapeBackend::<VmShape>::new("vm");
+/// let b = ShapeBackend::<VmShape>::new("vm (again)");
+/// assert!(verify(&ctx, node, &[&a, &b], 256).is_ok());
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub fn verify(
+    ctx: &Context,
+    node: Node,
+    backends: &[&dyn VerifyBackend],
+    samples: usize,
+) -> Result<(), Mismatch> {
+    let mut rng = Rng::new(0);
+    let mut rand = || rng.range(-1.0, 1.0) as f32;
+    let mut xs = Vec::with_capacity(samples);
+    let mut ys = Vec::with_capacity(samples);
+    let mut zs = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        xs.push(rand());
+        ys.push(rand());
+        zs.push(rand());
+    }
+
+    let results = backends
+        .iter()
+        .map(|b| {
+            b.eval(ctx, node, &xs, &ys, &zs)
+                .map_err(|error| Mismatch::Error {
+                    backend: b.name().to_owned(),
+                    error,
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let agree = |a: f32, b: f32| {
+        (a.is_nan() && b.is_nan())
+            || a == b
+            || (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.0)
+    };
+    let Some((first, rest)) = results.split_first() else {
+        return Ok(());
+    };
+    for i in 0..samples {
+        if rest.iter().any(|r| !agree(first[i], r[i])) {
+            return Err(Mismatch::Values {
+                point: [xs[i], ys[i], zs[i]],
+                values: backends
+                    .iter()
+                    .zip(&results)
+                    .map(|(b, r)| (b.name().to_owned(), r[i]))
+                    .collect(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{context::test_sphere, vm::VmShape};
+
+    /// Backend which adds an offset to a shape's values for `x > 0.5`
+    struct Stub(f32);
+    impl VerifyBackend for Stub {
+        fn name(&self) -> &str {
+            "stub"
+        }
+        fn eval(
+            &self,
+            ctx: &Context,
+            node: Node,
+            xs: &[f32],
+            ys: &[f32],
+            zs: &[f32],
+        ) -> Result<Vec<f32>, Error> {
+            let mut out = ShapeBackend::<VmShape>::new("vm")
+                .eval(ctx, node, xs, ys, zs)?;
+            for (o, x) in out.iter_mut().zip(xs) {
+                if *x > 0.5 {
+                    *o += self.0;
+                }
+            }
+            Ok(out)
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut ctx = Context::new();
+        let circle = test_sphere(&mut ctx, [0.0; 2], 0.5);
+
+        let vm = ShapeBackend::<VmShape>::new("vm");
+        assert!(verify(&ctx, circle, &[&vm], 100).is_ok());
+        assert!(verify(&ctx, circle, &[], 100).is_ok());
+
+        // Errors smaller than the tolerance are ignored
+        let close = Stub(1e-7);
+        assert!(verify(&ctx, circle, &[&vm, &close], 100).is_ok());
+
+        #[cfg(feature = "jit")]
+        {
+            let jit = ShapeBackend::<crate::jit::JitShape>::new("jit");
+            assert!(verify(&ctx, circle, &[&vm, &jit], 1000).is_ok());
+        }
+
+        let bad = Stub(0.25);
+        match verify(&ctx, circle, &[&vm, &bad], 100) {
+            Err(Mismatch::Values { point, values }) => {
+                assert!(point[0] > 0.5);
+                assert_eq!(values.len(), 2);
+                assert_eq!(values[0].0, "vm");
+                assert_eq!(values[1].0, "stub");
+                assert!((values[1].1 - values[0].1 - 0.25).abs() < 1e-6);
+            }
+            r => panic!("expected a mismatch, got {r:?}"),
+        }
+
+        // Evaluation failures are reported with the backend's name
+        let v = ctx.var("v").unwrap();
+        match verify(&ctx, v, &[&vm], 10) {
+            Err(Mismatch::Error { backend, .. }) => assert_eq!(backend, "vm"),
+            r => panic!("expected an error, got {r:?}"),
+        }
+    }
+}
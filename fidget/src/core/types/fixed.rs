@@ -0,0 +1,229 @@
+/// A signed fixed-point number in Q16.16 format
+///
+/// Values are stored as an `i32` scaled by 2<sup>16</sup>, so the
+/// representable range is `[-32768, 32768 - 2^-16]` with a uniform resolution
+/// of 2<sup>-16</sup> (about 1.5e-5).
+///
+/// All arithmetic saturates at the ends of that range instead of overflowing.
+/// There is no representation for infinity or `NaN`, so operations which
+/// would produce them in floating-point are mapped onto finite values:
+///
+/// - Division by zero saturates to [`Fixed::MAX`] or [`Fixed::MIN`] (based on
+///   the sign of the dividend), and `0 / 0` is zero
+/// - The square root of a negative number is zero
+/// - Converting a `NaN` from `f32` produces zero
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i32);
+
+impl std::fmt::Display for Fixed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", f32::from(*self))
+    }
+}
+
+impl Fixed {
+    /// Number of fractional bits
+    pub const FRAC_BITS: u32 = 16;
+
+    /// The value 0
+    pub const ZERO: Self = Self(0);
+    /// The value 1
+    pub const ONE: Self = Self(1 << Self::FRAC_BITS);
+    /// Largest representable value
+    pub const MAX: Self = Self(i32::MAX);
+    /// Smallest (most negative) representable value
+    pub const MIN: Self = Self(i32::MIN);
+    /// Smallest positive value
+    pub const EPSILON: Self = Self(1);
+
+    /// Builds a value from its raw bit representation
+    pub const fn from_bits(bits: i32) -> Self {
+        Self(bits)
+    }
+
+    /// Builds a value from an integer
+    pub const fn from_int(i: i16) -> Self {
+        Self((i as i32) << Self::FRAC_BITS)
+    }
+
+    /// Returns the raw bit representation
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    /// Saturating conversion from a wider intermediate value
+    fn saturate(v: i64) -> Self {
+        Self(v.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+
+    /// Absolute value
+    pub fn abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+
+    /// Square root
+    ///
+    /// This is computed with integer arithmetic and rounds down; negative
+    /// inputs return zero.
+    pub fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+        // sqrt(v / 2^16) * 2^16 = sqrt(v * 2^16)
+        let n = (self.0 as u64) << Self::FRAC_BITS;
+
+        // Bit-by-bit integer square root
+        let mut root = 0u64;
+        let mut rem = n;
+        let mut bit = 1u64 << 62;
+        while bit > n {
+            bit >>= 2;
+        }
+        while bit != 0 {
+            if rem >= root + bit {
+                rem -= root + bit;
+                root = (root >> 1) + bit;
+            } else {
+                root >>= 1;
+            }
+            bit >>= 2;
+        }
+        Self::saturate(root as i64)
+    }
+
+    /// Square
+    pub fn square(self) -> Self {
+        self * self
+    }
+
+    /// Reciprocal
+    pub fn recip(self) -> Self {
+        Self::ONE / self
+    }
+
+    /// Minimum of two values
+    pub fn min(self, rhs: Self) -> Self {
+        Ord::min(self, rhs)
+    }
+
+    /// Maximum of two values
+    pub fn max(self, rhs: Self) -> Self {
+        Ord::max(self, rhs)
+    }
+
+    /// Least non-negative remainder of `self (mod rhs)`
+    ///
+    /// Returns zero if `rhs` is zero.
+    pub fn rem_euclid(self, rhs: Self) -> Self {
+        if rhs.0 == 0 {
+            Self::ZERO
+        } else {
+            // The remainder is bounded by `rhs`, so this can't overflow
+            Self((self.0 as i64).rem_euclid(rhs.0 as i64) as i32)
+        }
+    }
+}
+
+impl From<f32> for Fixed {
+    fn from(v: f32) -> Self {
+        // Float-to-int `as` casts saturate (and map NaN to 0)
+        Self((v * Self::ONE.0 as f32).round() as i32)
+    }
+}
+
+impl From<Fixed> for f32 {
+    fn from(v: Fixed) -> Self {
+        v.0 as f32 / Fixed::ONE.0 as f32
+    }
+}
+
+impl std::ops::Add<Fixed> for Fixed {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl std::ops::Sub<Fixed> for Fixed {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl std::ops::Mul<Fixed> for Fixed {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::saturate((self.0 as i64 * rhs.0 as i64) >> Self::FRAC_BITS)
+    }
+}
+
+impl std::ops::Div<Fixed> for Fixed {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        match (self.0.signum(), rhs.0) {
+            (0, 0) => Self::ZERO,
+            (1, 0) => Self::MAX,
+            (-1, 0) => Self::MIN,
+            _ => Self::saturate(
+                ((self.0 as i64) << Self::FRAC_BITS) / rhs.0 as i64,
+            ),
+        }
+    }
+}
+
+impl std::ops::Neg for Fixed {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fixed_arithmetic() {
+        let a = Fixed::from(1.5);
+        let b = Fixed::from(-0.25);
+        assert_eq!(f32::from(a + b), 1.25);
+        assert_eq!(f32::from(a - b), 1.75);
+        assert_eq!(f32::from(a * b), -0.375);
+        assert_eq!(f32::from(a / b), -6.0);
+        assert_eq!(f32::from(-a), -1.5);
+        assert_eq!(f32::from(b.abs()), 0.25);
+        assert_eq!(f32::from(Fixed::from(-3.0).rem_euclid(a)), 0.0);
+        assert_eq!(f32::from(Fixed::from(-1.0).rem_euclid(a)), 0.5);
+    }
+
+    #[test]
+    fn test_fixed_saturation() {
+        let big = Fixed::from(30000.0);
+        assert_eq!(big + big, Fixed::MAX);
+        assert_eq!(-big - big, Fixed::MIN);
+        assert_eq!(big * big, Fixed::MAX);
+        assert_eq!(big * -big, Fixed::MIN);
+        assert_eq!(big / Fixed::from(0.01), Fixed::MAX);
+        assert_eq!(Fixed::MIN.abs(), Fixed::MAX);
+        assert_eq!(-Fixed::MIN, Fixed::MAX);
+
+        assert_eq!(Fixed::ONE / Fixed::ZERO, Fixed::MAX);
+        assert_eq!(-Fixed::ONE / Fixed::ZERO, Fixed::MIN);
+        assert_eq!(Fixed::ZERO / Fixed::ZERO, Fixed::ZERO);
+
+        assert_eq!(Fixed::from(1e10), Fixed::MAX);
+        assert_eq!(Fixed::from(-1e10), Fixed::MIN);
+        assert_eq!(Fixed::from(f32::NAN), Fixed::ZERO);
+    }
+
+    #[test]
+    fn test_fixed_sqrt() {
+        for v in [0.0, 0.25, 1.0, 2.0, 10.0, 12345.678, 32767.0] {
+            let s = f32::from(Fixed::from(v).sqrt());
+            assert!((s - v.sqrt()).abs() < 1e-4, "bad sqrt({v}): {s}");
+        }
+        assert_eq!(Fixed::from(-1.0).sqrt(), Fixed::ZERO);
+        assert_eq!(Fixed::EPSILON.sqrt(), Fixed::from(1.0 / 256.0));
+    }
+}
@@ -1,4 +1,13 @@
 /// A point in space with associated partial derivatives.
+///
+/// Some operations aren't differentiable everywhere, so their gradients follow
+/// a fixed (sub)gradient convention, which every evaluator shares:
+/// - `abs` has a zero gradient when its argument is exactly zero
+/// - `min` and `max` use the gradient of their left-hand argument when both
+///   arguments are equal.  Note that this is the argument's position in the
+///   evaluation tape: operands of commutative operations are sorted when
+///   building a [`Context`](crate::context::Context), and constant operands
+///   are always on the right.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
 #[repr(C)]
 pub struct Grad {
@@ -53,6 +62,8 @@ impl Grad {
     }
 
     /// Absolute value
+    ///
+    /// At zero, the gradient is zero.
     pub fn abs(self) -> Self {
         if self.v < 0.0 {
             Grad {
@@ -61,6 +72,8 @@ impl Grad {
                 dy: -self.dy,
                 dz: -self.dz,
             }
+        } else if self.v == 0.0 {
+            Grad::new(0.0, 0.0, 0.0, 0.0)
         } else {
             self
         }
@@ -169,8 +182,10 @@ impl Grad {
     }
 
     /// Minimum of two values
+    ///
+    /// If the values are equal, `self` is returned.
     pub fn min(self, rhs: Self) -> Self {
-        if self.v < rhs.v {
+        if self.v <= rhs.v {
             self
         } else {
             rhs
@@ -178,8 +193,10 @@ impl Grad {
     }
 
     /// Maximum of two values
+    ///
+    /// If the values are equal, `self` is returned.
     pub fn max(self, rhs: Self) -> Self {
-        if self.v > rhs.v {
+        if self.v >= rhs.v {
             self
         } else {
             rhs
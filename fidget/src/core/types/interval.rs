@@ -41,6 +41,19 @@ impl Interval {
         );
         Self { lower, upper }
     }
+    /// Builds a new interval, returning `[NaN, NaN]` if either bound is `NaN`
+    ///
+    /// This is used for operations where an indeterminate form (e.g. `∞ - ∞`)
+    /// can make one of the bounds `NaN`; in that case, the whole interval
+    /// becomes `NaN` (matching the point evaluators, which would return `NaN`).
+    #[inline]
+    fn new_or_nan(lower: f32, upper: f32) -> Self {
+        if lower.is_nan() || upper.is_nan() {
+            f32::NAN.into()
+        } else {
+            Interval::new(lower, upper)
+        }
+    }
     /// Returns the lower bound of the interval
     #[inline]
     pub fn lower(&self) -> f32 {
@@ -60,6 +73,46 @@ impl Interval {
     pub fn has_nan(&self) -> bool {
         self.lower.is_nan() || self.upper.is_nan()
     }
+    /// Margin (in ulps) for the results of transcendental functions
+    ///
+    /// Unlike basic arithmetic, `sin`, `exp`, etc. aren't required to be
+    /// correctly rounded.  Common math libraries are accurate to within one or
+    /// two ulps for `f32`; this leaves some headroom, but isn't a guarantee.
+    pub const TRANSCENDENTAL_ULPS: u32 = 4;
+
+    /// Widens the interval by one ulp in each direction
+    ///
+    /// Applying this to the result of a correctly-rounded operation (IEEE 754
+    /// `+`, `-`, `*`, `/`, and `sqrt`) gives an interval which contains the
+    /// exact result, without changing the floating-point rounding mode.  It
+    /// makes no such promise for functions which may be off by more than an
+    /// ulp; see [`widen_by`](Self::widen_by).
+    #[inline]
+    pub fn widen(self) -> Self {
+        self.widen_by(1)
+    }
+
+    /// Widens the interval by the given number of ulps in each direction
+    ///
+    /// A lower bound of `+0.0` or an upper bound of `-0.0` is left unchanged:
+    /// IEEE 754 preserves the sign of a result which rounds to zero, so such a
+    /// bound is already on the correct side of the exact value.  This keeps
+    /// (for example) a widened `square` from dipping below zero.
+    pub fn widen_by(self, ulps: u32) -> Self {
+        let mut lower = self.lower;
+        if !(lower == 0.0 && lower.is_sign_positive()) {
+            for _ in 0..ulps {
+                lower = lower.next_down();
+            }
+        }
+        let mut upper = self.upper;
+        if !(upper == 0.0 && upper.is_sign_negative()) {
+            for _ in 0..ulps {
+                upper = upper.next_up();
+            }
+        }
+        Self { lower, upper }
+    }
     /// Calculates the absolute value of the interval
     pub fn abs(self) -> Self {
         if self.lower < 0.0 {
@@ -89,25 +142,37 @@ impl Interval {
     }
     /// Computes the sine of the interval
     ///
-    /// Right now, this always returns the maximum range of `[-1, 1]`
+    /// Intervals spanning a full period return `[-1, 1]`; otherwise, the
+    /// result is bounded by the sine at either end, extended to `±1` if the
+    /// interval contains a peak (`π/2 + 2πk`) or trough (`-π/2 + 2πk`).
     pub fn sin(self) -> Self {
-        if self.has_nan() {
-            f32::NAN.into()
-        } else {
-            // TODO: make this smarter
-            Interval::new(-1.0, 1.0)
-        }
+        self.sinusoid(f32::sin, std::f64::consts::FRAC_PI_2)
     }
     /// Computes the cosine of the interval
     ///
-    /// Right now, this always returns the maximum range of `[-1, 1]`
+    /// This is bounded like [`sin`](Self::sin), with peaks at `2πk` and
+    /// troughs at `π + 2πk`.
     pub fn cos(self) -> Self {
+        self.sinusoid(f32::cos, 0.0)
+    }
+    /// Bounds a sinusoid `f`, which has its peaks at `peak + 2πk`
+    fn sinusoid(self, f: fn(f32) -> f32, peak: f64) -> Self {
+        use std::f64::consts::{PI, TAU};
         if self.has_nan() {
-            f32::NAN.into()
-        } else {
-            // TODO: make this smarter
-            Interval::new(-1.0, 1.0)
+            return f32::NAN.into();
+        }
+        // Critical points are found in f64, to keep them accurate far from 0
+        let (lo, hi) = (self.lower as f64, self.upper as f64);
+        let width = hi - lo;
+        if width >= TAU || width.is_nan() {
+            // A full period (or infinite bounds)
+            return Interval::new(-1.0, 1.0);
         }
+        let contains = |c: f64| c + ((lo - c) / TAU).ceil() * TAU <= hi;
+        let (a, b) = (f(self.lower), f(self.upper));
+        let lower = if contains(peak + PI) { -1.0 } else { a.min(b) };
+        let upper = if contains(peak) { 1.0 } else { a.max(b) };
+        Interval::new(lower, upper)
     }
     /// Computes the tangent of the interval
     ///
@@ -370,7 +435,7 @@ impl From<f32> for Interval {
 impl std::ops::Add<Interval> for Interval {
     type Output = Self;
     fn add(self, rhs: Self) -> Self {
-        Interval::new(self.lower + rhs.lower, self.upper + rhs.upper)
+        Interval::new_or_nan(self.lower + rhs.lower, self.upper + rhs.upper)
     }
 }
 
@@ -442,7 +507,7 @@ impl std::ops::Div<Interval> for Interval {
 impl std::ops::Sub<Interval> for Interval {
     type Output = Self;
     fn sub(self, rhs: Self) -> Self {
-        Interval::new(self.lower - rhs.upper, self.upper - rhs.lower)
+        Interval::new_or_nan(self.lower - rhs.upper, self.upper - rhs.lower)
     }
 }
 
@@ -465,4 +530,61 @@ mod test {
         assert_eq!(v, [0.0, 1.0].into());
         assert_eq!(c, Choice::Both);
     }
+
+    #[test]
+    fn test_widen() {
+        let i = Interval::new(1.0, 2.0).widen();
+        assert_eq!(i, Interval::new(1f32.next_down(), 2f32.next_up()));
+        let i = Interval::new(1.0, 2.0).widen_by(2);
+        assert_eq!(i.lower(), 1f32.next_down().next_down());
+        assert_eq!(i.upper(), 2f32.next_up().next_up());
+
+        // Signed zeros on the inside of the exact value are kept
+        assert_eq!(Interval::new(0.0, 1.0).widen().lower(), 0.0);
+        assert_eq!(Interval::new(-1.0, -0.0).widen().upper(), 0.0);
+        assert!(Interval::new(-0.0, 1.0).widen().lower() < 0.0);
+        assert!(Interval::new(-1.0, 0.0).widen().upper() > 0.0);
+    }
+
+    #[test]
+    fn test_sin_cos() {
+        let i = |lo: f32, hi: f32| Interval::new(lo, hi);
+
+        // Monotonic pieces are bounded by their endpoints
+        assert_eq!(i(0.0, 1.0).sin(), i(0.0, 1f32.sin()));
+        assert_eq!(i(6.0, 7.0).sin(), i(6f32.sin(), 7f32.sin()));
+        assert_eq!(i(0.5, 3.0).cos(), i(3f32.cos(), 0.5f32.cos()));
+
+        // Peaks and troughs, including across the wrap-around at 2π
+        assert_eq!(i(1.0, 2.0).sin(), i(1f32.sin(), 1.0));
+        assert_eq!(i(4.0, 5.0).sin(), i(-1.0, 4f32.sin()));
+        assert_eq!(i(-2.0, -1.0).sin(), i(-1.0, (-1f32).sin()));
+        assert_eq!(i(7.0, 8.0).sin(), i(7f32.sin(), 1.0));
+        assert_eq!(i(-0.5, 0.25).cos(), i((-0.5f32).cos(), 1.0));
+        assert_eq!(i(3.0, 3.5).cos(), i(-1.0, 3.5f32.cos()));
+        assert_eq!(i(1.0, 5.0).sin(), i(-1.0, 1.0));
+
+        // Far from the origin
+        let k = 200.0 * std::f32::consts::PI;
+        assert_eq!(i(k + 1.0, k + 2.0).sin().upper(), 1.0);
+        assert!(i(k + 0.1, k + 1.0).sin().upper() < 1.0);
+
+        // Full periods saturate
+        assert_eq!(i(0.0, 7.0).sin(), i(-1.0, 1.0));
+        assert_eq!(i(f32::NEG_INFINITY, 0.0).cos(), i(-1.0, 1.0));
+        assert!(Interval::from(f32::NAN).sin().has_nan());
+
+        // Results contain every sampled value
+        for lo in (-40..40).map(|i| i as f32 * 0.37) {
+            for width in [0.0, 0.1, 0.9, 2.5, 4.0, 6.0] {
+                let x = i(lo, lo + width);
+                let (s, c) = (x.sin(), x.cos());
+                for j in 0..=64 {
+                    let v = lo + width * j as f32 / 64.0;
+                    assert!(s.lower() <= v.sin() && v.sin() <= s.upper());
+                    assert!(c.lower() <= v.cos() && v.cos() <= c.upper());
+                }
+            }
+        }
+    }
 }
@@ -1,6 +1,8 @@
 //! Custom types used during evaluation
 
+mod fixed;
 mod grad;
 mod interval;
+pub use fixed::Fixed;
 pub use grad::Grad;
 pub use interval::Interval;
@@ -1,3 +1,10 @@
+/// Index of a choice within a tape
+///
+/// Choices are numbered in evaluation order, matching their positions in the
+/// trace returned by a [`TracingEvaluator`](crate::eval::TracingEvaluator).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChoiceIndex(pub usize);
+
 /// A single choice made at a min/max node.
 ///
 /// Explicitly stored in a `u8` so that this can be written by JIT functions,
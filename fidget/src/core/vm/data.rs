@@ -68,6 +68,16 @@ impl<const N: usize> VmData<N> {
         &self.ssa.vars
     }
 
+    /// Returns constants which overflowed when converted to `f32`
+    ///
+    /// If this is not empty, evaluation results may disagree with
+    /// [`Context::eval`](crate::Context::eval), which uses `f64`; see
+    /// [`Context::clamp_to_f32`](crate::Context::clamp_to_f32) to clamp them.
+    /// Simplified tapes report the overflows of their original tape.
+    pub fn f32_overflows(&self) -> &[f64] {
+        &self.ssa.f32_overflows
+    }
+
     /// Returns the length of the internal VM tape
     pub fn len(&self) -> usize {
         self.asm.len()
@@ -100,6 +110,12 @@ impl<const N: usize> VmData<N> {
     ///
     /// To minimize allocations, this function takes a [`VmWorkspace`] and
     /// spare [`VmData`]; it will reuse those allocations.
+    ///
+    /// The tape is walked backwards from its output, and operations are only
+    /// kept if their result is used by an operation that was already kept.
+    /// This means that simplification also performs dead code elimination:
+    /// operations which only fed into pruned branches are removed, and
+    /// registers are reallocated for the remaining operations.
     pub fn simplify(
         &self,
         choices: &[Choice],
@@ -163,7 +179,8 @@ impl<const N: usize> VmData<N> {
                 | SsaOp::AtanReg(index, arg)
                 | SsaOp::ExpReg(index, arg)
                 | SsaOp::LnReg(index, arg)
-                | SsaOp::NotReg(index, arg) => {
+                | SsaOp::NotReg(index, arg)
+                | SsaOp::CustomReg(index, arg, _) => {
                     *index = new_index;
                     *arg = workspace.get_or_insert_active(*arg);
                 }
@@ -277,6 +294,7 @@ impl<const N: usize> VmData<N> {
                 tape: ops_out,
                 choice_count,
                 vars: self.ssa.vars.clone(),
+                f32_overflows: self.ssa.f32_overflows.clone(),
             },
             asm: asm_tape,
         })
@@ -6,7 +6,7 @@ use crate::{
         BulkEvaluator, MathShape, Shape, ShapeVars, Tape, Trace,
         TracingEvaluator, TransformedShape,
     },
-    types::{Grad, Interval},
+    types::{Fixed, Grad, Interval},
     Context, Error,
 };
 use nalgebra::Matrix4;
@@ -15,7 +15,7 @@ use std::{collections::HashMap, sync::Arc};
 mod choice;
 mod data;
 
-pub use choice::Choice;
+pub use choice::{Choice, ChoiceIndex};
 pub use data::{VmData, VmWorkspace};
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -230,6 +230,8 @@ impl<T> std::ops::IndexMut<u32> for SlotArray<'_, T> {
 struct TracingVmEval<T> {
     slots: Vec<T>,
     choices: VmTrace,
+    /// Number of operations executed by the most recent evaluation
+    ops: usize,
 }
 
 impl<T> Default for TracingVmEval<T> {
@@ -237,6 +239,7 @@ impl<T> Default for TracingVmEval<T> {
         Self {
             slots: vec![],
             choices: VmTrace::default(),
+            ops: 0,
         }
     }
 }
@@ -246,12 +249,247 @@ impl<T: From<f32> + Clone> TracingVmEval<T> {
         self.slots.resize(tape.slot_count(), f32::NAN.into());
         self.choices.resize(tape.choice_count(), Choice::Unknown);
         self.choices.fill(Choice::Unknown);
+        self.ops = 0;
     }
 }
 
+/// Returns the margin (in ulps) by which an operation's result must be widened
+///
+/// Operations which only copy, negate, or select values are exact, so they
+/// return 0.  Basic arithmetic is correctly rounded, so it returns 1;
+/// transcendental functions return [`Interval::TRANSCENDENTAL_ULPS`].
+fn rounding_ulps(op: RegOp) -> u32 {
+    match op {
+        RegOp::SinReg(..)
+        | RegOp::CosReg(..)
+        | RegOp::TanReg(..)
+        | RegOp::AsinReg(..)
+        | RegOp::AcosReg(..)
+        | RegOp::AtanReg(..)
+        | RegOp::ExpReg(..)
+        | RegOp::LnReg(..) => Interval::TRANSCENDENTAL_ULPS,
+
+        RegOp::RecipReg(..)
+        | RegOp::SqrtReg(..)
+        | RegOp::SquareReg(..)
+        | RegOp::CustomReg(..)
+        | RegOp::AddRegImm(..)
+        | RegOp::MulRegImm(..)
+        | RegOp::DivRegImm(..)
+        | RegOp::DivImmReg(..)
+        | RegOp::SubImmReg(..)
+        | RegOp::SubRegImm(..)
+        | RegOp::ModRegImm(..)
+        | RegOp::ModImmReg(..)
+        | RegOp::AddRegReg(..)
+        | RegOp::MulRegReg(..)
+        | RegOp::DivRegReg(..)
+        | RegOp::SubRegReg(..)
+        | RegOp::ModRegReg(..) => 1,
+
+        RegOp::Input(..)
+        | RegOp::Var(..)
+        | RegOp::CopyImm(..)
+        | RegOp::CopyReg(..)
+        | RegOp::NegReg(..)
+        | RegOp::AbsReg(..)
+        | RegOp::NotReg(..)
+        | RegOp::MinRegImm(..)
+        | RegOp::MaxRegImm(..)
+        | RegOp::AndRegImm(..)
+        | RegOp::OrRegImm(..)
+        | RegOp::CompareRegImm(..)
+        | RegOp::CompareImmReg(..)
+        | RegOp::MinRegReg(..)
+        | RegOp::MaxRegReg(..)
+        | RegOp::AndRegReg(..)
+        | RegOp::OrRegReg(..)
+        | RegOp::CompareRegReg(..)
+        | RegOp::Load(..)
+        | RegOp::Store(..) => 0,
+    }
+}
+
+/// Returns the argument intervals of a choice operation
+///
+/// Immediate arguments are always returned on the right-hand side; other
+/// operations return `None`.
+fn choice_args(
+    op: RegOp,
+    reg: impl Fn(u8) -> Interval,
+) -> Option<(Interval, Interval)> {
+    match op {
+        RegOp::MinRegReg(_, lhs, rhs)
+        | RegOp::MaxRegReg(_, lhs, rhs)
+        | RegOp::AndRegReg(_, lhs, rhs)
+        | RegOp::OrRegReg(_, lhs, rhs) => Some((reg(lhs), reg(rhs))),
+        RegOp::MinRegImm(_, arg, imm)
+        | RegOp::MaxRegImm(_, arg, imm)
+        | RegOp::AndRegImm(_, arg, imm)
+        | RegOp::OrRegImm(_, arg, imm) => Some((reg(arg), imm.into())),
+        _ => None,
+    }
+}
+
+/// Evaluates a single interval operation
+///
+/// `reg` reads the current value of a register.  Returns the output register,
+/// its new value, and (for `min`, `max`, `and`, and `or`) the choice made by
+/// the operation.  If `round` is set, the result is widened outwards by
+/// [`rounding_ulps`].
+///
+/// `Input`, `Var`, `Load`, and `Store` depend on evaluator state and must be
+/// handled by the caller.
+fn interval_op(
+    op: RegOp,
+    reg: impl Fn(u8) -> Interval,
+    round: bool,
+) -> (u8, Interval, Option<Choice>) {
+    let compare = |a: Interval, b: Interval| {
+        if a.has_nan() || b.has_nan() {
+            f32::NAN.into()
+        } else if a.upper() < b.lower() {
+            Interval::from(-1.0)
+        } else if a.lower() > b.upper() {
+            Interval::from(1.0)
+        } else {
+            Interval::new(-1.0, 1.0)
+        }
+    };
+    let (out, value, choice) = match op {
+        RegOp::NegReg(out, arg) => (out, -reg(arg), None),
+        RegOp::AbsReg(out, arg) => (out, reg(arg).abs(), None),
+        RegOp::RecipReg(out, arg) => (out, reg(arg).recip(), None),
+        RegOp::SqrtReg(out, arg) => (out, reg(arg).sqrt(), None),
+        RegOp::SquareReg(out, arg) => (out, reg(arg).square(), None),
+        RegOp::SinReg(out, arg) => (out, reg(arg).sin(), None),
+        RegOp::CosReg(out, arg) => (out, reg(arg).cos(), None),
+        RegOp::TanReg(out, arg) => (out, reg(arg).tan(), None),
+        RegOp::AsinReg(out, arg) => (out, reg(arg).asin(), None),
+        RegOp::AcosReg(out, arg) => (out, reg(arg).acos(), None),
+        RegOp::AtanReg(out, arg) => (out, reg(arg).atan(), None),
+        RegOp::ExpReg(out, arg) => (out, reg(arg).exp(), None),
+        RegOp::LnReg(out, arg) => (out, reg(arg).ln(), None),
+        RegOp::NotReg(out, arg) => {
+            let a = reg(arg);
+            let v = if !a.contains(0.0) {
+                Interval::new(0.0, 0.0)
+            } else if a.lower() == 0.0 && a.upper() == 0.0 {
+                Interval::new(1.0, 1.0)
+            } else {
+                Interval::new(0.0, 1.0)
+            };
+            (out, v, None)
+        }
+        RegOp::CustomReg(out, arg, id) => {
+            (out, id.get().eval_interval(reg(arg)), None)
+        }
+        RegOp::CopyReg(out, arg) => (out, reg(arg), None),
+        RegOp::AddRegImm(out, arg, imm) => (out, reg(arg) + imm.into(), None),
+        RegOp::MulRegImm(out, arg, imm) => (out, reg(arg) * imm, None),
+        RegOp::DivRegImm(out, arg, imm) => (out, reg(arg) / imm.into(), None),
+        RegOp::DivImmReg(out, arg, imm) => {
+            (out, Interval::from(imm) / reg(arg), None)
+        }
+        RegOp::SubImmReg(out, arg, imm) => {
+            (out, Interval::from(imm) - reg(arg), None)
+        }
+        RegOp::SubRegImm(out, arg, imm) => (out, reg(arg) - imm.into(), None),
+        RegOp::MinRegImm(out, arg, imm) => {
+            let (v, c) = reg(arg).min_choice(imm.into());
+            (out, v, Some(c))
+        }
+        RegOp::MaxRegImm(out, arg, imm) => {
+            let (v, c) = reg(arg).max_choice(imm.into());
+            (out, v, Some(c))
+        }
+        RegOp::AndRegReg(out, lhs, rhs) => {
+            let (v, c) = reg(lhs).and_choice(reg(rhs));
+            (out, v, Some(c))
+        }
+        RegOp::AndRegImm(out, arg, imm) => {
+            let (v, c) = reg(arg).and_choice(imm.into());
+            (out, v, Some(c))
+        }
+        RegOp::OrRegReg(out, lhs, rhs) => {
+            let (v, c) = reg(lhs).or_choice(reg(rhs));
+            (out, v, Some(c))
+        }
+        RegOp::OrRegImm(out, arg, imm) => {
+            let (v, c) = reg(arg).or_choice(imm.into());
+            (out, v, Some(c))
+        }
+        RegOp::ModRegReg(out, lhs, rhs) => {
+            (out, reg(lhs).rem_euclid(reg(rhs)), None)
+        }
+        RegOp::ModRegImm(out, arg, imm) => {
+            (out, reg(arg).rem_euclid(imm.into()), None)
+        }
+        RegOp::ModImmReg(out, arg, imm) => {
+            (out, Interval::from(imm).rem_euclid(reg(arg)), None)
+        }
+        RegOp::AddRegReg(out, lhs, rhs) => (out, reg(lhs) + reg(rhs), None),
+        RegOp::MulRegReg(out, lhs, rhs) => (out, reg(lhs) * reg(rhs), None),
+        RegOp::DivRegReg(out, lhs, rhs) => (out, reg(lhs) / reg(rhs), None),
+        RegOp::SubRegReg(out, lhs, rhs) => (out, reg(lhs) - reg(rhs), None),
+        RegOp::CompareRegReg(out, lhs, rhs) => {
+            (out, compare(reg(lhs), reg(rhs)), None)
+        }
+        RegOp::CompareRegImm(out, arg, imm) => {
+            (out, compare(reg(arg), imm.into()), None)
+        }
+        RegOp::CompareImmReg(out, arg, imm) => {
+            (out, compare(imm.into(), reg(arg)), None)
+        }
+        RegOp::MinRegReg(out, lhs, rhs) => {
+            let (v, c) = reg(lhs).min_choice(reg(rhs));
+            (out, v, Some(c))
+        }
+        RegOp::MaxRegReg(out, lhs, rhs) => {
+            let (v, c) = reg(lhs).max_choice(reg(rhs));
+            (out, v, Some(c))
+        }
+        RegOp::CopyImm(out, imm) => (out, imm.into(), None),
+        RegOp::Input(..)
+        | RegOp::Var(..)
+        | RegOp::Load(..)
+        | RegOp::Store(..) => unreachable!("must be handled by the caller"),
+    };
+    if round {
+        (out, value.widen_by(rounding_ulps(op)), choice)
+    } else {
+        (out, value, choice)
+    }
+}
+
+/// Argument intervals at a single choice, recorded by
+/// [`VmIntervalEval::eval_with_choices`]
+pub type ChoiceArgs = (ChoiceIndex, [f32; 2], [f32; 2]);
+
 /// VM-based tracing evaluator for intervals
+///
+/// In addition to the [`TracingEvaluator`] interface, this evaluator can
+/// evaluate many boxes at once with
+/// [`eval_interval_array`](Self::eval_interval_array), and can record the
+/// argument intervals at every choice with
+/// [`eval_with_choices`](Self::eval_with_choices).
+///
+/// By default, interval bounds are computed with ordinary floating-point
+/// arithmetic, so a result may be off by an ulp or so.  Call
+/// [`set_outward_rounding`](Self::set_outward_rounding) to widen every
+/// rounded result (see [`Interval::widen_by`]) so that it contains the true
+/// range.
 #[derive(Default)]
-pub struct VmIntervalEval<const N: usize>(TracingVmEval<Interval>);
+pub struct VmIntervalEval<const N: usize> {
+    /// Evaluator for single intervals
+    tracing: TracingVmEval<Interval>,
+    /// Evaluator for arrays of intervals
+    bulk: BulkVmEval<Interval>,
+    /// Enables outward rounding
+    outward_rounding: bool,
+    /// Argument intervals at each choice, if recording is enabled
+    choice_args: Option<Vec<ChoiceArgs>>,
+}
 impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
     type Data = Interval;
     type Tape = GenericVmShape<N>;
@@ -271,12 +509,15 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
         let z = z.into();
         let tape = tape.0.as_ref();
         self.check_arguments(vars, tape.var_count())?;
-        self.0.resize_slots(tape);
+        self.tracing.resize_slots(tape);
         assert_eq!(vars.len(), tape.var_count());
 
         let mut simplify = false;
-        let mut v = SlotArray(&mut self.0.slots);
-        let mut choices = self.0.choices.as_mut_slice().iter_mut();
+        let round = self.outward_rounding;
+        let mut v = SlotArray(&mut self.tracing.slots);
+        let mut choices = self.tracing.choices.as_mut_slice().iter_mut();
+        let mut index = 0;
+        let mut record = self.choice_args.as_mut();
         for op in tape.iter_asm() {
             match op {
                 RegOp::Input(out, i) => {
@@ -290,183 +531,34 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
                 RegOp::Var(out, i) => {
                     v[out] = vars[i as usize].into();
                 }
-                RegOp::NegReg(out, arg) => {
-                    v[out] = -v[arg];
-                }
-                RegOp::AbsReg(out, arg) => {
-                    v[out] = v[arg].abs();
-                }
-                RegOp::RecipReg(out, arg) => {
-                    v[out] = v[arg].recip();
-                }
-                RegOp::SqrtReg(out, arg) => {
-                    v[out] = v[arg].sqrt();
-                }
-                RegOp::SquareReg(out, arg) => {
-                    v[out] = v[arg].square();
-                }
-                RegOp::SinReg(out, arg) => {
-                    v[out] = v[arg].sin();
-                }
-                RegOp::CosReg(out, arg) => {
-                    v[out] = v[arg].cos();
-                }
-                RegOp::TanReg(out, arg) => {
-                    v[out] = v[arg].tan();
-                }
-                RegOp::AsinReg(out, arg) => {
-                    v[out] = v[arg].asin();
-                }
-                RegOp::AcosReg(out, arg) => {
-                    v[out] = v[arg].acos();
-                }
-                RegOp::AtanReg(out, arg) => {
-                    v[out] = v[arg].atan();
-                }
-                RegOp::ExpReg(out, arg) => {
-                    v[out] = v[arg].exp();
-                }
-                RegOp::LnReg(out, arg) => {
-                    v[out] = v[arg].ln();
-                }
-                RegOp::NotReg(out, arg) => {
-                    v[out] = if !v[arg].contains(0.0) {
-                        Interval::new(0.0, 0.0)
-                    } else if v[arg].lower() == 0.0 && v[arg].upper() == 0.0 {
-                        Interval::new(1.0, 1.0)
-                    } else {
-                        Interval::new(0.0, 1.0)
-                    };
-                }
-                RegOp::CopyReg(out, arg) => v[out] = v[arg],
-                RegOp::AddRegImm(out, arg, imm) => {
-                    v[out] = v[arg] + imm.into();
-                }
-                RegOp::MulRegImm(out, arg, imm) => {
-                    v[out] = v[arg] * imm;
-                }
-                RegOp::DivRegImm(out, arg, imm) => {
-                    v[out] = v[arg] / imm.into();
-                }
-                RegOp::DivImmReg(out, arg, imm) => {
-                    let imm: Interval = imm.into();
-                    v[out] = imm / v[arg];
-                }
-                RegOp::SubImmReg(out, arg, imm) => {
-                    v[out] = Interval::from(imm) - v[arg];
-                }
-                RegOp::SubRegImm(out, arg, imm) => {
-                    v[out] = v[arg] - imm.into();
-                }
-                RegOp::MinRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].min_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].max_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].and_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].and_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].or_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegImm(out, arg, imm) => {
-                    let (value, choice) = v[arg].or_choice(imm.into());
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::ModRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].rem_euclid(v[rhs]);
-                }
-                RegOp::ModRegImm(out, arg, imm) => {
-                    v[out] = v[arg].rem_euclid(imm.into());
-                }
-                RegOp::ModImmReg(out, arg, imm) => {
-                    v[out] = Interval::from(imm).rem_euclid(v[arg]);
-                }
-                RegOp::AddRegReg(out, lhs, rhs) => v[out] = v[lhs] + v[rhs],
-                RegOp::MulRegReg(out, lhs, rhs) => v[out] = v[lhs] * v[rhs],
-                RegOp::DivRegReg(out, lhs, rhs) => v[out] = v[lhs] / v[rhs],
-                RegOp::SubRegReg(out, lhs, rhs) => v[out] = v[lhs] - v[rhs],
-                RegOp::CompareRegReg(out, lhs, rhs) => {
-                    v[out] = if v[lhs].has_nan() || v[rhs].has_nan() {
-                        f32::NAN.into()
-                    } else if v[lhs].upper() < v[rhs].lower() {
-                        Interval::from(-1.0)
-                    } else if v[lhs].lower() > v[rhs].upper() {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
-                }
-                RegOp::CompareRegImm(out, arg, imm) => {
-                    v[out] = if v[arg].has_nan() || imm.is_nan() {
-                        f32::NAN.into()
-                    } else if v[arg].upper() < imm {
-                        Interval::from(-1.0)
-                    } else if v[arg].lower() > imm {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
-                }
-                RegOp::CompareImmReg(out, arg, imm) => {
-                    v[out] = if v[arg].has_nan() || imm.is_nan() {
-                        f32::NAN.into()
-                    } else if imm < v[arg].lower() {
-                        Interval::from(-1.0)
-                    } else if imm > v[arg].upper() {
-                        Interval::from(1.0)
-                    } else {
-                        Interval::new(-1.0, 1.0)
-                    };
-                }
-                RegOp::MinRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].min_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegReg(out, lhs, rhs) => {
-                    let (value, choice) = v[lhs].max_choice(v[rhs]);
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::CopyImm(out, imm) => {
-                    v[out] = imm.into();
-                }
                 RegOp::Load(out, mem) => {
                     v[out] = v[mem];
                 }
                 RegOp::Store(out, mem) => {
                     v[mem] = v[out];
                 }
+                _ => {
+                    if let Some(record) = record.as_mut() {
+                        if let Some((a, b)) = choice_args(op, |r| v[r]) {
+                            let a = [a.lower(), a.upper()];
+                            let b = [b.lower(), b.upper()];
+                            record.push((ChoiceIndex(index), a, b));
+                        }
+                    }
+                    let (out, value, choice) = interval_op(op, |r| v[r], round);
+                    v[out] = value;
+                    if let Some(choice) = choice {
+                        *choices.next().unwrap() |= choice;
+                        simplify |= choice != Choice::Both;
+                        index += 1;
+                    }
+                }
             }
         }
         Ok((
-            self.0.slots[0],
+            self.tracing.slots[0],
             if simplify {
-                Some(&self.0.choices)
+                Some(&self.tracing.choices)
             } else {
                 None
             },
@@ -474,6 +566,424 @@ impl<const N: usize> TracingEvaluator for VmIntervalEval<N> {
     }
 }
 
+impl<const N: usize> VmIntervalEval<N> {
+    /// Enables or disables outward rounding
+    ///
+    /// When enabled, the result of every operation which may round is widened
+    /// outwards (at the cost of slightly looser bounds):
+    ///
+    /// - Basic arithmetic and `sqrt` are correctly rounded, so widening their
+    ///   results by one ulp is enough to contain the true range.
+    /// - Transcendental functions (`sin`, `exp`, etc) come from the platform's
+    ///   math library, which isn't required to be correctly rounded; their
+    ///   results are widened by [`Interval::TRANSCENDENTAL_ULPS`], which is
+    ///   only sound if the library's error is within that margin.
+    /// - Custom ops are widened by one ulp, so they're only as sound as their
+    ///   own [`eval_interval`](crate::context::CustomOp::eval_interval).
+    pub fn set_outward_rounding(&mut self, enabled: bool) {
+        self.outward_rounding = enabled;
+    }
+
+    /// Checks whether outward rounding is enabled
+    pub fn outward_rounding(&self) -> bool {
+        self.outward_rounding
+    }
+
+    /// Evaluates an interval, recording the arguments of every choice
+    ///
+    /// In addition to the result of [`eval`](TracingEvaluator::eval), this
+    /// returns the intervals of the left- and right-hand arguments at each
+    /// `min`, `max`, `and`, and `or` operation in the tape, tagged with its
+    /// [`ChoiceIndex`] (i.e. its position in the trace).  This is useful for
+    /// an adaptive renderer, which may want to decide how to subdivide a
+    /// region based on which branches are active within it.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     eval::{EzShape, MathShape, Shape},
+    ///     vm::{ChoiceIndex, VmShape},
+    /// };
+    ///
+    /// let (min, ctx) = fidget::rhai::eval("min(x, y)")?;
+    /// let shape = VmShape::new(&ctx, min)?;
+    /// let tape = shape.ez_interval_tape();
+    /// let mut eval = VmShape::new_interval_eval();
+    /// let (out, choices) = eval.eval_with_choices(
+    ///     &tape,
+    ///     [0.0, 1.0],
+    ///     [2.0, 3.0],
+    ///     [0.0, 0.0],
+    ///     &[],
+    /// )?;
+    /// assert_eq!(out, [0.0, 1.0].into());
+    /// assert_eq!(choices, vec![(ChoiceIndex(0), [0.0, 1.0], [2.0, 3.0])]);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn eval_with_choices<F: Into<Interval>>(
+        &mut self,
+        tape: &GenericVmShape<N>,
+        x: F,
+        y: F,
+        z: F,
+        vars: &[f32],
+    ) -> Result<(Interval, Vec<ChoiceArgs>), Error> {
+        self.choice_args = Some(vec![]);
+        let out = self.eval(tape, x, y, z, vars).map(|(v, _)| v);
+        let choices = self.choice_args.take().unwrap();
+        out.map(|v| (v, choices))
+    }
+
+    /// Evaluates a batch of boxes, returning an interval for each one
+    ///
+    /// Each box is given as `[x, y, z]` ranges, e.g. `[[x_min, x_max], ...]`.
+    /// The tape is walked once for the whole batch, with each operation
+    /// applied to every box before moving on to the next, which amortizes the
+    /// cost of dispatch across (for example) the 8 children of an octree cell.
+    ///
+    /// Results are identical to calling [`eval`](TracingEvaluator::eval) on
+    /// each box in turn; no trace is captured.
+    ///
+    /// ```
+    /// use fidget::{
+    ///     eval::{EzShape, MathShape, Shape},
+    ///     vm::VmShape,
+    /// };
+    ///
+    /// let (sum, ctx) = fidget::rhai::eval("x + y")?;
+    /// let shape = VmShape::new(&ctx, sum)?;
+    /// let tape = shape.ez_interval_tape();
+    /// let mut eval = VmShape::new_interval_eval();
+    /// let out = eval.eval_interval_array(
+    ///     &tape,
+    ///     &[
+    ///         [[0.0, 1.0], [0.0, 1.0], [0.0, 0.0]],
+    ///         [[-1.0, 0.0], [2.0, 3.0], [0.0, 0.0]],
+    ///     ],
+    ///     &[],
+    /// )?;
+    /// assert_eq!(out, vec![[0.0, 2.0], [1.0, 3.0]]);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn eval_interval_array(
+        &mut self,
+        tape: &GenericVmShape<N>,
+        boxes: &[[[f32; 2]; 3]],
+        vars: &[f32],
+    ) -> Result<Vec<[f32; 2]>, Error> {
+        let tape = tape.0.as_ref();
+        self.check_arguments(vars, tape.var_count())?;
+        let size = boxes.len();
+        self.bulk.resize_slots(tape, size);
+
+        let round = self.outward_rounding;
+        let mut v = SlotArray(&mut self.bulk.slots);
+        for op in tape.iter_asm() {
+            match op {
+                RegOp::Input(out, i) => {
+                    for (o, b) in v[out].iter_mut().zip(boxes) {
+                        *o = b[i as usize].into();
+                    }
+                }
+                RegOp::Var(out, i) => v[out].fill(vars[i as usize].into()),
+                RegOp::Load(out, mem) => {
+                    for i in 0..size {
+                        v[out][i] = v[mem][i];
+                    }
+                }
+                RegOp::Store(out, mem) => {
+                    for i in 0..size {
+                        v[mem][i] = v[out][i];
+                    }
+                }
+                _ => {
+                    for i in 0..size {
+                        let (out, value, _) =
+                            interval_op(op, |r| v[r][i], round);
+                        v[out][i] = value;
+                    }
+                }
+            }
+        }
+        Ok(self.bulk.slots[0]
+            .iter()
+            .map(|i| [i.lower(), i.upper()])
+            .collect())
+    }
+}
+
+/// Value type for single-point evaluation in the VM
+///
+/// This lets floating-point and fixed-point evaluation share a single
+/// interpreter loop; see [`eval_point`].
+trait PointValue:
+    Copy
+    + PartialEq
+    + From<f32>
+    + std::ops::Add<Output = Self>
+    + std::ops::Sub<Output = Self>
+    + std::ops::Mul<Output = Self>
+    + std::ops::Div<Output = Self>
+    + std::ops::Neg<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn abs(self) -> Self;
+    fn recip(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn square(self) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+    fn min_choice(self, rhs: Self) -> (Self, Choice);
+    fn max_choice(self, rhs: Self) -> (Self, Choice);
+    /// Returns -1, 0, or 1 based on the ordering of `self` and `rhs`
+    fn compare(self, rhs: Self) -> Self;
+
+    /// Evaluates a transcendental or custom operation on `arg`
+    ///
+    /// Returns [`Error::UnsupportedOp`] if this type can't evaluate it.
+    fn transcendental(op: RegOp, arg: Self) -> Result<Self, Error>;
+}
+
+impl PointValue for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn abs(self) -> Self {
+        self.abs()
+    }
+    fn recip(self) -> Self {
+        1.0 / self
+    }
+    fn sqrt(self) -> Self {
+        self.sqrt()
+    }
+    fn square(self) -> Self {
+        self * self
+    }
+    fn rem_euclid(self, rhs: Self) -> Self {
+        self.rem_euclid(rhs)
+    }
+    fn min_choice(self, rhs: Self) -> (Self, Choice) {
+        if self < rhs {
+            (self, Choice::Left)
+        } else if rhs < self {
+            (rhs, Choice::Right)
+        } else if self.is_nan() || rhs.is_nan() {
+            (f32::NAN, Choice::Both)
+        } else {
+            (rhs, Choice::Both)
+        }
+    }
+    fn max_choice(self, rhs: Self) -> (Self, Choice) {
+        if self > rhs {
+            (self, Choice::Left)
+        } else if rhs > self {
+            (rhs, Choice::Right)
+        } else if self.is_nan() || rhs.is_nan() {
+            (f32::NAN, Choice::Both)
+        } else {
+            (rhs, Choice::Both)
+        }
+    }
+    fn compare(self, rhs: Self) -> Self {
+        self.partial_cmp(&rhs)
+            .map(|c| c as i8 as f32)
+            .unwrap_or(f32::NAN)
+    }
+    fn transcendental(op: RegOp, arg: Self) -> Result<Self, Error> {
+        Ok(match op {
+            RegOp::SinReg(..) => arg.sin(),
+            RegOp::CosReg(..) => arg.cos(),
+            RegOp::TanReg(..) => arg.tan(),
+            RegOp::AsinReg(..) => arg.asin(),
+            RegOp::AcosReg(..) => arg.acos(),
+            RegOp::AtanReg(..) => arg.atan(),
+            RegOp::ExpReg(..) => arg.exp(),
+            RegOp::LnReg(..) => arg.ln(),
+            RegOp::CustomReg(_, _, id) => id.get().eval(arg as f64) as f32,
+            _ => unreachable!("not a transcendental op: {op:?}"),
+        })
+    }
+}
+
+impl PointValue for Fixed {
+    const ZERO: Self = Fixed::ZERO;
+    const ONE: Self = Fixed::ONE;
+
+    fn abs(self) -> Self {
+        self.abs()
+    }
+    fn recip(self) -> Self {
+        self.recip()
+    }
+    fn sqrt(self) -> Self {
+        self.sqrt()
+    }
+    fn square(self) -> Self {
+        self.square()
+    }
+    fn rem_euclid(self, rhs: Self) -> Self {
+        self.rem_euclid(rhs)
+    }
+    fn min_choice(self, rhs: Self) -> (Self, Choice) {
+        match self.cmp(&rhs) {
+            std::cmp::Ordering::Less => (self, Choice::Left),
+            std::cmp::Ordering::Greater => (rhs, Choice::Right),
+            std::cmp::Ordering::Equal => (rhs, Choice::Both),
+        }
+    }
+    fn max_choice(self, rhs: Self) -> (Self, Choice) {
+        match self.cmp(&rhs) {
+            std::cmp::Ordering::Greater => (self, Choice::Left),
+            std::cmp::Ordering::Less => (rhs, Choice::Right),
+            std::cmp::Ordering::Equal => (rhs, Choice::Both),
+        }
+    }
+    fn compare(self, rhs: Self) -> Self {
+        Fixed::from_int(self.cmp(&rhs) as i16)
+    }
+    fn transcendental(op: RegOp, _arg: Self) -> Result<Self, Error> {
+        Err(Error::UnsupportedOp(match op {
+            RegOp::SinReg(..) => "sin",
+            RegOp::CosReg(..) => "cos",
+            RegOp::TanReg(..) => "tan",
+            RegOp::AsinReg(..) => "asin",
+            RegOp::AcosReg(..) => "acos",
+            RegOp::AtanReg(..) => "atan",
+            RegOp::ExpReg(..) => "exp",
+            RegOp::LnReg(..) => "ln",
+            RegOp::CustomReg(..) => "custom",
+            _ => unreachable!("not a transcendental op: {op:?}"),
+        }))
+    }
+}
+
+/// Evaluates a tape at a single point
+///
+/// Each immediate is converted to the value type by `imm`, which is called
+/// exactly once per immediate, in tape order.  Returns `true` if any choice
+/// picked a single branch (i.e. the tape could be simplified).
+fn eval_point<T: PointValue, const N: usize>(
+    eval: &mut TracingVmEval<T>,
+    tape: &VmData<N>,
+    [x, y, z]: [T; 3],
+    vars: &[f32],
+    mut imm: impl FnMut(f32) -> T,
+) -> Result<bool, Error> {
+    let mut choices = eval.choices.as_mut_slice().iter_mut();
+    let mut simplify = false;
+    let mut choose = |(value, choice): (T, Choice)| {
+        *choices.next().unwrap() |= choice;
+        simplify |= choice != Choice::Both;
+        value
+    };
+    let and = |a: T, b: T| {
+        if a == T::ZERO {
+            (a, Choice::Left)
+        } else {
+            (b, Choice::Right)
+        }
+    };
+    let or = |a: T, b: T| {
+        if a != T::ZERO {
+            (a, Choice::Left)
+        } else {
+            (b, Choice::Right)
+        }
+    };
+    let mut v = SlotArray(&mut eval.slots);
+    for op in tape.iter_asm() {
+        eval.ops += 1;
+        match op {
+            RegOp::Input(out, i) => {
+                v[out] = match i {
+                    0 => x,
+                    1 => y,
+                    2 => z,
+                    _ => panic!("Invalid input: {}", i),
+                }
+            }
+            RegOp::Var(out, i) => v[out] = vars[i as usize].into(),
+            RegOp::NegReg(out, arg) => v[out] = -v[arg],
+            RegOp::AbsReg(out, arg) => v[out] = v[arg].abs(),
+            RegOp::RecipReg(out, arg) => v[out] = v[arg].recip(),
+            RegOp::SqrtReg(out, arg) => v[out] = v[arg].sqrt(),
+            RegOp::SquareReg(out, arg) => v[out] = v[arg].square(),
+            RegOp::SinReg(out, arg)
+            | RegOp::CosReg(out, arg)
+            | RegOp::TanReg(out, arg)
+            | RegOp::AsinReg(out, arg)
+            | RegOp::AcosReg(out, arg)
+            | RegOp::AtanReg(out, arg)
+            | RegOp::ExpReg(out, arg)
+            | RegOp::LnReg(out, arg)
+            | RegOp::CustomReg(out, arg, ..) => {
+                v[out] = T::transcendental(op, v[arg])?;
+            }
+            RegOp::NotReg(out, arg) => {
+                v[out] = if v[arg] == T::ZERO { T::ONE } else { T::ZERO };
+            }
+            RegOp::CopyReg(out, arg) => v[out] = v[arg],
+            RegOp::AddRegImm(out, arg, i) => v[out] = v[arg] + imm(i),
+            RegOp::MulRegImm(out, arg, i) => v[out] = v[arg] * imm(i),
+            RegOp::DivRegImm(out, arg, i) => v[out] = v[arg] / imm(i),
+            RegOp::DivImmReg(out, arg, i) => v[out] = imm(i) / v[arg],
+            RegOp::SubImmReg(out, arg, i) => v[out] = imm(i) - v[arg],
+            RegOp::SubRegImm(out, arg, i) => v[out] = v[arg] - imm(i),
+            RegOp::MinRegImm(out, arg, i) => {
+                v[out] = choose(v[arg].min_choice(imm(i)));
+            }
+            RegOp::MaxRegImm(out, arg, i) => {
+                v[out] = choose(v[arg].max_choice(imm(i)));
+            }
+            RegOp::AndRegImm(out, arg, i) => {
+                v[out] = choose(and(v[arg], imm(i)));
+            }
+            RegOp::OrRegImm(out, arg, i) => {
+                v[out] = choose(or(v[arg], imm(i)));
+            }
+            RegOp::ModRegReg(out, lhs, rhs) => {
+                v[out] = v[lhs].rem_euclid(v[rhs]);
+            }
+            RegOp::ModRegImm(out, arg, i) => {
+                v[out] = v[arg].rem_euclid(imm(i));
+            }
+            RegOp::ModImmReg(out, arg, i) => {
+                v[out] = imm(i).rem_euclid(v[arg]);
+            }
+            RegOp::AddRegReg(out, lhs, rhs) => v[out] = v[lhs] + v[rhs],
+            RegOp::MulRegReg(out, lhs, rhs) => v[out] = v[lhs] * v[rhs],
+            RegOp::DivRegReg(out, lhs, rhs) => v[out] = v[lhs] / v[rhs],
+            RegOp::SubRegReg(out, lhs, rhs) => v[out] = v[lhs] - v[rhs],
+            RegOp::CompareRegReg(out, lhs, rhs) => {
+                v[out] = v[lhs].compare(v[rhs]);
+            }
+            RegOp::CompareRegImm(out, arg, i) => {
+                v[out] = v[arg].compare(imm(i));
+            }
+            RegOp::CompareImmReg(out, arg, i) => {
+                v[out] = imm(i).compare(v[arg]);
+            }
+            RegOp::MinRegReg(out, lhs, rhs) => {
+                v[out] = choose(v[lhs].min_choice(v[rhs]));
+            }
+            RegOp::MaxRegReg(out, lhs, rhs) => {
+                v[out] = choose(v[lhs].max_choice(v[rhs]));
+            }
+            RegOp::AndRegReg(out, lhs, rhs) => {
+                v[out] = choose(and(v[lhs], v[rhs]));
+            }
+            RegOp::OrRegReg(out, lhs, rhs) => {
+                v[out] = choose(or(v[lhs], v[rhs]));
+            }
+            RegOp::CopyImm(out, i) => v[out] = imm(i),
+            RegOp::Load(out, mem) => v[out] = v[mem],
+            RegOp::Store(out, mem) => v[mem] = v[out],
+        }
+    }
+    Ok(simplify)
+}
+
 /// VM-based tracing evaluator for single points
 #[derive(Default)]
 pub struct VmPointEval<const N: usize>(TracingVmEval<f32>);
@@ -491,267 +1001,148 @@ impl<const N: usize> TracingEvaluator for VmPointEval<N> {
         z: F,
         vars: &[f32],
     ) -> Result<(f32, Option<&VmTrace>), Error> {
-        let x = x.into();
-        let y = y.into();
-        let z = z.into();
         let tape = tape.0.as_ref();
         self.check_arguments(vars, tape.var_count())?;
         self.0.resize_slots(tape);
 
-        let mut choices = self.0.choices.as_mut_slice().iter_mut();
-        let mut simplify = false;
-        let mut v = SlotArray(&mut self.0.slots);
-        for op in tape.iter_asm() {
-            match op {
-                RegOp::Input(out, i) => {
-                    v[out] = match i {
-                        0 => x,
-                        1 => y,
-                        2 => z,
-                        _ => panic!("Invalid input: {}", i),
-                    }
-                }
-                RegOp::Var(out, i) => v[out] = vars[i as usize],
-                RegOp::NegReg(out, arg) => {
-                    v[out] = -v[arg];
-                }
-                RegOp::AbsReg(out, arg) => {
-                    v[out] = v[arg].abs();
-                }
-                RegOp::RecipReg(out, arg) => {
-                    v[out] = 1.0 / v[arg];
-                }
-                RegOp::SqrtReg(out, arg) => {
-                    v[out] = v[arg].sqrt();
-                }
-                RegOp::SquareReg(out, arg) => {
-                    let s = v[arg];
-                    v[out] = s * s;
-                }
-                RegOp::SinReg(out, arg) => {
-                    v[out] = v[arg].sin();
-                }
-                RegOp::CosReg(out, arg) => {
-                    v[out] = v[arg].cos();
-                }
-                RegOp::TanReg(out, arg) => {
-                    v[out] = v[arg].tan();
-                }
-                RegOp::AsinReg(out, arg) => {
-                    v[out] = v[arg].asin();
-                }
-                RegOp::AcosReg(out, arg) => {
-                    v[out] = v[arg].acos();
-                }
-                RegOp::AtanReg(out, arg) => {
-                    v[out] = v[arg].atan();
-                }
-                RegOp::ExpReg(out, arg) => {
-                    v[out] = v[arg].exp();
-                }
-                RegOp::LnReg(out, arg) => {
-                    v[out] = v[arg].ln();
-                }
-                RegOp::NotReg(out, arg) => v[out] = (v[arg] == 0.0).into(),
-                RegOp::CopyReg(out, arg) => {
-                    v[out] = v[arg];
-                }
-                RegOp::AddRegImm(out, arg, imm) => {
-                    v[out] = v[arg] + imm;
-                }
-                RegOp::MulRegImm(out, arg, imm) => {
-                    v[out] = v[arg] * imm;
-                }
-                RegOp::DivRegImm(out, arg, imm) => {
-                    v[out] = v[arg] / imm;
-                }
-                RegOp::DivImmReg(out, arg, imm) => {
-                    v[out] = imm / v[arg];
-                }
-                RegOp::SubImmReg(out, arg, imm) => {
-                    v[out] = imm - v[arg];
-                }
-                RegOp::SubRegImm(out, arg, imm) => {
-                    v[out] = v[arg] - imm;
-                }
-                RegOp::MinRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a < imm {
-                        (Choice::Left, a)
-                    } else if imm < a {
-                        (Choice::Right, imm)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || imm.is_nan() {
-                                f32::NAN
-                            } else {
-                                imm
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a > imm {
-                        (Choice::Left, a)
-                    } else if imm > a {
-                        (Choice::Right, imm)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || imm.is_nan() {
-                                f32::NAN
-                            } else {
-                                imm
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a == 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, imm)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegImm(out, arg, imm) => {
-                    let a = v[arg];
-                    let (choice, value) = if a != 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, imm)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::ModRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs].rem_euclid(v[rhs]);
-                }
-                RegOp::ModRegImm(out, arg, imm) => {
-                    v[out] = v[arg].rem_euclid(imm);
-                }
-                RegOp::ModImmReg(out, arg, imm) => {
-                    v[out] = imm.rem_euclid(v[arg]);
-                }
-                RegOp::AddRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] + v[rhs];
-                }
-                RegOp::MulRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] * v[rhs];
-                }
-                RegOp::DivRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] / v[rhs];
-                }
-                RegOp::CompareRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs]
-                        .partial_cmp(&v[rhs])
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::CompareRegImm(out, arg, imm) => {
-                    v[out] = v[arg]
-                        .partial_cmp(&imm)
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::CompareImmReg(out, arg, imm) => {
-                    v[out] = imm
-                        .partial_cmp(&v[arg])
-                        .map(|c| c as i8 as f32)
-                        .unwrap_or(f32::NAN)
-                }
-                RegOp::SubRegReg(out, lhs, rhs) => {
-                    v[out] = v[lhs] - v[rhs];
-                }
-                RegOp::MinRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a < b {
-                        (Choice::Left, a)
-                    } else if b < a {
-                        (Choice::Right, b)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || b.is_nan() {
-                                f32::NAN
-                            } else {
-                                b
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::MaxRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a > b {
-                        (Choice::Left, a)
-                    } else if b > a {
-                        (Choice::Right, b)
-                    } else {
-                        (
-                            Choice::Both,
-                            if a.is_nan() || b.is_nan() {
-                                f32::NAN
-                            } else {
-                                b
-                            },
-                        )
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::AndRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a == 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, b)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::OrRegReg(out, lhs, rhs) => {
-                    let a = v[lhs];
-                    let b = v[rhs];
-                    let (choice, value) = if a != 0.0 {
-                        (Choice::Left, a)
-                    } else {
-                        (Choice::Right, b)
-                    };
-                    v[out] = value;
-                    *choices.next().unwrap() |= choice;
-                    simplify |= choice != Choice::Both;
-                }
-                RegOp::CopyImm(out, imm) => {
-                    v[out] = imm;
-                }
-                RegOp::Load(out, mem) => {
-                    v[out] = v[mem];
-                }
-                RegOp::Store(out, mem) => {
-                    v[mem] = v[out];
-                }
-            }
+        let xyz = [x.into(), y.into(), z.into()];
+        let simplify = eval_point(&mut self.0, tape, xyz, vars, |i| i)?;
+        Ok((
+            self.0.slots[0],
+            if simplify {
+                Some(&self.0.choices)
+            } else {
+                None
+            },
+        ))
+    }
+}
+
+impl<const N: usize> VmPointEval<N> {
+    /// Evaluates a single point, returning the number of ops executed
+    ///
+    /// Every instruction run by the VM is counted, including register loads
+    /// and stores.  The VM executes both sides of each `min` and `max`;
+    /// branches are only skipped once the tape has been simplified, so
+    /// comparing the counts for a full and simplified tape shows how much work
+    /// simplification saves at a given point.
+    pub fn eval_counted(
+        &mut self,
+        tape: &GenericVmShape<N>,
+        x: f32,
+        y: f32,
+        z: f32,
+        vars: &[f32],
+    ) -> Result<(f32, usize), Error> {
+        let (v, _trace) = self.eval(tape, x, y, z, vars)?;
+        Ok((v, self.0.ops))
+    }
+}
+
+/// VM tape with its immediates converted to fixed-point
+///
+/// This is the tape type for [`VmFixedEval`].  Immediates are converted from
+/// `f32` once, when the tape is built, so evaluation only uses integer
+/// arithmetic (apart from converting inputs and variables).
+#[derive(Clone)]
+pub struct VmFixedTape<const N: usize> {
+    tape: GenericVmShape<N>,
+    imms: Arc<[Fixed]>,
+}
+
+impl<const N: usize> VmFixedTape<N> {
+    /// Builds a fixed-point tape from a VM tape
+    pub fn new(tape: &GenericVmShape<N>) -> Self {
+        let imms = tape
+            .0
+            .iter_asm()
+            .filter_map(|op| match op {
+                RegOp::AddRegImm(_, _, i)
+                | RegOp::MulRegImm(_, _, i)
+                | RegOp::DivRegImm(_, _, i)
+                | RegOp::DivImmReg(_, _, i)
+                | RegOp::SubImmReg(_, _, i)
+                | RegOp::SubRegImm(_, _, i)
+                | RegOp::MinRegImm(_, _, i)
+                | RegOp::MaxRegImm(_, _, i)
+                | RegOp::AndRegImm(_, _, i)
+                | RegOp::OrRegImm(_, _, i)
+                | RegOp::ModRegImm(_, _, i)
+                | RegOp::ModImmReg(_, _, i)
+                | RegOp::CompareRegImm(_, _, i)
+                | RegOp::CompareImmReg(_, _, i)
+                | RegOp::CopyImm(_, i) => Some(Fixed::from(i)),
+                _ => None,
+            })
+            .collect();
+        Self {
+            tape: tape.clone(),
+            imms,
         }
+    }
+}
+
+impl<const N: usize> Tape for VmFixedTape<N> {
+    type Storage = ();
+    fn recycle(self) -> Self::Storage {
+        // nothing to do here
+    }
+}
+
+/// VM-based tracing evaluator for single points, using fixed-point arithmetic
+///
+/// This evaluator uses [`Fixed`] (Q16.16) values, so it can run on targets
+/// without floating-point hardware; see that type's documentation for its
+/// dynamic range and how it handles saturation.  It evaluates a
+/// [`VmFixedTape`], whose immediates are converted to fixed-point when it's
+/// built.
+///
+/// Transcendental functions (`sin`, `cos`, `tan`, `asin`, `acos`, `atan`,
+/// `exp`, and `ln`) and [custom operations](crate::context::CustomOp) are not
+/// supported; evaluating a tape which uses them returns
+/// [`Error::UnsupportedOp`].
+///
+/// ```
+/// use fidget::{
+///     eval::{EzShape, MathShape, TracingEvaluator},
+///     types::Fixed,
+///     vm::{VmFixedEval, VmFixedTape, VmShape},
+/// };
+///
+/// let (sum, ctx) = fidget::rhai::eval("sqrt(x * x + y * y) - 1")?;
+/// let shape = VmShape::new(&ctx, sum)?;
+/// let tape = VmFixedTape::new(&shape.ez_point_tape());
+/// let mut eval = VmFixedEval::new();
+/// let (r, _trace) = eval.eval(&tape, 3.0, 4.0, 0.0, &[])?;
+/// assert_eq!(r, Fixed::from(4.0));
+/// # Ok::<(), fidget::Error>(())
+/// ```
+#[derive(Default)]
+pub struct VmFixedEval<const N: usize>(TracingVmEval<Fixed>);
+impl<const N: usize> TracingEvaluator for VmFixedEval<N> {
+    type Data = Fixed;
+    type Tape = VmFixedTape<N>;
+    type Trace = VmTrace;
+    type TapeStorage = ();
+
+    fn eval<F: Into<Fixed>>(
+        &mut self,
+        tape: &Self::Tape,
+        x: F,
+        y: F,
+        z: F,
+        vars: &[f32],
+    ) -> Result<(Fixed, Option<&VmTrace>), Error> {
+        let imms = &tape.imms;
+        let tape = tape.tape.0.as_ref();
+        self.check_arguments(vars, tape.var_count())?;
+        self.0.resize_slots(tape);
+
+        let xyz = [x.into(), y.into(), z.into()];
+        let mut imms = imms.iter();
+        let simplify = eval_point(&mut self.0, tape, xyz, vars, |_| {
+            *imms.next().unwrap()
+        })?;
         Ok((
             self.0.slots[0],
             if simplify {
@@ -766,12 +1157,17 @@ impl<const N: usize> TracingEvaluator for VmPointEval<N> {
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Bulk evaluator for VM tapes
-#[derive(Default)]
 struct BulkVmEval<T> {
     /// Workspace for data
     slots: Vec<Vec<T>>,
 }
 
+impl<T> Default for BulkVmEval<T> {
+    fn default() -> Self {
+        Self { slots: vec![] }
+    }
+}
+
 impl<T: From<f32> + Clone> BulkVmEval<T> {
     /// Reserves slots for the given tape and slice size
     fn resize_slots<const N: usize>(&mut self, tape: &VmData<N>, size: usize) {
@@ -891,6 +1287,12 @@ impl<const N: usize> BulkEvaluator for VmFloatSliceEval<N> {
                         v[out][i] = (v[arg][i] == 0.0).into();
                     }
                 }
+                RegOp::CustomReg(out, arg, id) => {
+                    let op = id.get();
+                    for i in 0..size {
+                        v[out][i] = op.eval(v[arg][i] as f64) as f32;
+                    }
+                }
                 RegOp::CopyReg(out, arg) => {
                     for i in 0..size {
                         v[out][i] = v[arg][i];
@@ -1192,6 +1594,12 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
                         v[out][i] = f32::from(v[arg][i].v == 0.0).into();
                     }
                 }
+                RegOp::CustomReg(out, arg, id) => {
+                    let op = id.get();
+                    for i in 0..size {
+                        v[out][i] = op.eval_grad(v[arg][i]);
+                    }
+                }
                 RegOp::CopyReg(out, arg) => {
                     for i in 0..size {
                         v[out][i] = v[arg][i];
@@ -1395,8 +1803,365 @@ impl<const N: usize> BulkEvaluator for VmGradSliceEval<N> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::context::test_sphere;
     crate::grad_slice_tests!(VmShape);
     crate::interval_tests!(VmShape);
     crate::float_slice_tests!(VmShape);
     crate::point_tests!(VmShape);
+
+    #[test]
+    fn test_eval_counted() {
+        use crate::eval::EzShape;
+
+        // max(x - 1, sqrt(x² + y² + z²) - 0.5), i.e. a sphere cut by a plane
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 0.5);
+        let plane = ctx.sub(x, 1.0).unwrap();
+        let root = ctx.max(plane, sphere).unwrap();
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_point_tape();
+        let mut eval = VmShape::new_point_eval();
+
+        let (v, full) = eval.eval_counted(&tape, 0.1, 0.2, 0.3, &[]).unwrap();
+        assert_eq!(full, tape.0.iter_asm().count());
+
+        let (w, trace) = eval.eval(&tape, 0.1, 0.2, 0.3, &[]).unwrap();
+        assert_eq!(v, w);
+        let simple = shape.ez_simplify(trace.unwrap()).unwrap();
+        let simple_tape = simple.ez_point_tape();
+        let (w, count) =
+            eval.eval_counted(&simple_tape, 0.1, 0.2, 0.3, &[]).unwrap();
+        assert_eq!(v, w);
+        assert!(count < full, "simplified tape executed {count} >= {full}");
+    }
+
+    #[test]
+    fn test_simplify_removes_dead_ops() {
+        use crate::eval::EzShape;
+
+        // min(x, sqrt(y² + z²) - 1), with z bound to a constant
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let y2 = ctx.square(y).unwrap();
+        let z2 = ctx.square(z).unwrap();
+        let r = ctx.add(y2, z2).unwrap();
+        let r = ctx.sqrt(r).unwrap();
+        let r = ctx.sub(r, 1.0).unwrap();
+        let root = ctx.min(x, r).unwrap();
+        let root = ctx.bind_var(root, z, 2.0).unwrap();
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_point_tape();
+        let mut eval = VmShape::new_point_eval();
+
+        // Only the `x` branch is taken, so every op in the other branch is
+        // dead after simplification and should be removed.
+        let (v, trace) = eval.eval(&tape, -5.0, 0.5, 0.0, &[]).unwrap();
+        let simple = shape.ez_simplify(trace.unwrap()).unwrap();
+        assert!(simple.size() < shape.size());
+        assert_eq!(simple.size(), 1);
+
+        let simple_tape = simple.ez_point_tape();
+        let (w, _) = eval.eval(&simple_tape, -5.0, 0.5, 0.0, &[]).unwrap();
+        assert_eq!(v, w);
+        for x in [-5.0, -4.0, -3.5] {
+            let (a, _) = eval.eval(&tape, x, 0.5, 0.0, &[]).unwrap();
+            let (b, _) = eval.eval(&simple_tape, x, 0.5, 0.0, &[]).unwrap();
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_fixed_eval() {
+        use crate::eval::EzShape;
+
+        // A rounded box unioned with a sphere, which stays well within the
+        // range of Q16.16 values on [-2, 2]
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let ax = ctx.abs(x).unwrap();
+        let ay = ctx.abs(y).unwrap();
+        let bx = ctx.sub(ax, 0.5).unwrap();
+        let by = ctx.sub(ay, 0.75).unwrap();
+        let bx = ctx.max(bx, 0.0).unwrap();
+        let by = ctx.max(by, 0.0).unwrap();
+        let bx2 = ctx.square(bx).unwrap();
+        let by2 = ctx.square(by).unwrap();
+        let b = ctx.add(bx2, by2).unwrap();
+        let b = ctx.sqrt(b).unwrap();
+        let b = ctx.sub(b, 0.1).unwrap();
+        let s = test_sphere(&mut ctx, [1.0, 0.0, 0.0], 0.0);
+        let s = ctx.div(s, 2.0).unwrap();
+        let s = ctx.sub(s, 0.25).unwrap();
+        let root = ctx.min(b, s).unwrap();
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_point_tape();
+        let fixed_tape = VmFixedTape::new(&tape);
+        let mut float_eval = VmShape::new_point_eval();
+        let mut fixed_eval = VmFixedEval::<255>::new();
+        for i in 0..=20 {
+            for j in 0..=20 {
+                let x = i as f32 / 5.0 - 2.0;
+                let y = j as f32 / 5.0 - 2.0;
+                let z = (x + y) / 4.0;
+                let (a, ta) = float_eval.eval(&tape, x, y, z, &[]).unwrap();
+                let ta = ta.map(|t| t.as_slice().to_vec());
+                let (b, tb) =
+                    fixed_eval.eval(&fixed_tape, x, y, z, &[]).unwrap();
+                let tb = tb.map(|t| t.as_slice().to_vec());
+                assert_eq!(ta, tb, "trace mismatch at ({x}, {y}, {z})");
+                let b = f32::from(b);
+                assert!(
+                    (a - b).abs() < 1e-3,
+                    "mismatch at ({x}, {y}, {z}): {a} != {b}"
+                );
+            }
+        }
+
+        let s = ctx.sin(x).unwrap();
+        let shape = VmShape::new(&ctx, s).unwrap();
+        let tape = VmFixedTape::new(&shape.ez_point_tape());
+        assert!(matches!(
+            fixed_eval.eval(&tape, 0.0, 0.0, 0.0, &[]),
+            Err(Error::UnsupportedOp("sin"))
+        ));
+    }
+
+    #[test]
+    fn test_eval_interval_array() {
+        use crate::eval::EzShape;
+
+        // max(sqrt(x² + y² + z²) - 1, -x), with a comparison mixed in
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 1.0);
+        let nx = ctx.neg(x).unwrap();
+        let cut = ctx.max(sphere, nx).unwrap();
+        let (y, z) = (ctx.y(), ctx.z());
+        let c = ctx.compare(y, z).unwrap();
+        let c = ctx.mul(c, 0.1).unwrap();
+        let root = ctx.min(cut, c).unwrap();
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let mut eval = VmShape::new_interval_eval();
+
+        let mut boxes = vec![];
+        for i in 0..4 {
+            for j in 0..4 {
+                let x = i as f32 / 2.0 - 1.0;
+                let y = j as f32 / 2.0 - 1.0;
+                boxes.push([[x, x + 0.5], [y, y + 0.5], [-0.25, 0.25]]);
+            }
+        }
+        boxes.push([[-2.0, 2.0], [-2.0, 2.0], [-2.0, 2.0]]);
+        boxes.push([[0.1, 0.1], [0.2, 0.2], [0.3, 0.3]]);
+
+        let out = eval.eval_interval_array(&tape, &boxes, &[]).unwrap();
+        assert_eq!(out.len(), boxes.len());
+        for (b, o) in boxes.iter().zip(&out) {
+            let (v, _) = eval
+                .eval(
+                    &tape,
+                    Interval::from(b[0]),
+                    Interval::from(b[1]),
+                    Interval::from(b[2]),
+                    &[],
+                )
+                .unwrap();
+            assert_eq!([v.lower(), v.upper()], *o, "mismatch for {b:?}");
+        }
+
+        assert!(eval
+            .eval_interval_array(&tape, &[], &[])
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            eval.eval_interval_array(&tape, &boxes, &[1.0]),
+            Err(Error::BadVarSlice(1, 0))
+        ));
+    }
+
+    #[test]
+    fn test_outward_rounding() {
+        use crate::eval::EzShape;
+
+        // x² - 2, evaluated at the closest `f32` to its root; the exact result
+        // is a tiny negative value, which isn't representable after rounding
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let x2 = ctx.square(x).unwrap();
+        let root = ctx.sub(x2, 2.0).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let mut eval = VmShape::new_interval_eval();
+
+        let s = std::f32::consts::SQRT_2;
+        let exact = (s as f64).powi(2) - 2.0;
+        let contains = |i: Interval| {
+            (i.lower() as f64) <= exact && exact <= (i.upper() as f64)
+        };
+        let zero = Interval::from(0.0);
+
+        assert!(!eval.outward_rounding());
+        let (v, _) = eval
+            .eval(&tape, Interval::from(s), zero, zero, &[])
+            .unwrap();
+        assert!(!contains(v), "{v:?} unexpectedly contains {exact}");
+
+        eval.set_outward_rounding(true);
+        let (v, _) = eval
+            .eval(&tape, Interval::from(s), zero, zero, &[])
+            .unwrap();
+        assert!(contains(v), "{v:?} does not contain {exact}");
+        assert!(v.upper() - v.lower() < 1e-6);
+
+        let out = eval
+            .eval_interval_array(&tape, &[[[s, s], [0.0; 2], [0.0; 2]]], &[])
+            .unwrap();
+        assert_eq!(out, vec![[v.lower(), v.upper()]]);
+
+        // Widening a non-negative result keeps it non-negative, so `sqrt`
+        // doesn't see a negative input and return `NaN`
+        let y = ctx.y();
+        let y2 = ctx.square(y).unwrap();
+        let r = ctx.add(x2, y2).unwrap();
+        let r = ctx.sqrt(r).unwrap();
+        let shape = VmShape::new(&ctx, r).unwrap();
+        let tape = shape.ez_interval_tape();
+        let i = Interval::new(-1.0, 1.0);
+        let (v, _) = eval.eval(&tape, i, i, zero, &[]).unwrap();
+        assert!(!v.has_nan());
+        assert_eq!(v.lower(), 0.0);
+        assert!(v.upper() > 2f32.sqrt());
+    }
+
+    #[test]
+    fn test_eval_with_choices() {
+        use crate::eval::EzShape;
+
+        // Union of two circles, centered at x = ±0.5
+        let mut ctx = Context::new();
+        let a = test_sphere(&mut ctx, [-0.5, 0.0], 0.5);
+        let b = test_sphere(&mut ctx, [0.5, 0.0], 0.5);
+        let root = ctx.min(a, b).unwrap();
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let tape = shape.ez_interval_tape();
+        let mut eval = VmShape::new_interval_eval();
+        let (x, y) = (Interval::new(-0.8, 0.2), Interval::new(-0.5, 0.5));
+        let z = Interval::from(0.0);
+        let (out, choices) =
+            eval.eval_with_choices(&tape, x, y, z, &[]).unwrap();
+        assert_eq!(out, eval.eval(&tape, x, y, z, &[]).unwrap().0);
+        assert_eq!(choices.len(), 1);
+        let (index, lhs, rhs) = choices[0];
+        assert_eq!(index, ChoiceIndex(0));
+
+        // The tape may order the arguments either way, so match each branch
+        // with the recorded interval that contains it
+        for branch in [a, b] {
+            let shape = VmShape::new(&ctx, branch).unwrap();
+            let tape = shape.ez_interval_tape();
+            let i = eval.eval(&tape, x, y, z, &[]).unwrap().0;
+            let i = [i.lower(), i.upper()];
+            assert!(i == lhs || i == rhs, "{i:?} not in {lhs:?}, {rhs:?}");
+
+            // Spot-check that the branch interval bounds sampled values
+            let mut point = VmShape::new_point_eval();
+            let tape = shape.ez_point_tape();
+            for j in 0..=8 {
+                for k in 0..=8 {
+                    let px = x.lerp(j as f32 / 8.0);
+                    let py = y.lerp(k as f32 / 8.0);
+                    let v = point.eval(&tape, px, py, 0.0, &[]).unwrap().0;
+                    assert!(i[0] <= v && v <= i[1]);
+                }
+            }
+        }
+
+        // Neither circle is always closer within the region
+        assert!(lhs[0] < rhs[1] && rhs[0] < lhs[1]);
+
+        // Recording is opt-in, so plain evaluation doesn't allocate
+        assert!(eval.choice_args.is_none());
+    }
+
+    #[test]
+    fn test_custom_op() {
+        use crate::{
+            context::{CustomOp, CustomOpId},
+            eval::EzShape,
+        };
+
+        /// `sin(x) / 2`, implemented outside of the built-in opcodes
+        struct HalfSin;
+        impl CustomOp for HalfSin {
+            fn name(&self) -> &str {
+                "half_sin"
+            }
+            fn eval(&self, x: f64) -> f64 {
+                x.sin() / 2.0
+            }
+            fn eval_interval(&self, x: Interval) -> Interval {
+                x.sin() * 0.5
+            }
+            fn eval_grad(&self, x: Grad) -> Grad {
+                x.sin() * Grad::from(0.5)
+            }
+        }
+        let id = CustomOpId::register(Box::new(HalfSin));
+
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let h = ctx.custom(id, x).unwrap();
+        let root = ctx.sub(y, h).unwrap();
+
+        // Custom ops are constant-folded like built-in ones
+        let c = ctx.custom(id, std::f64::consts::FRAC_PI_2).unwrap();
+        assert_eq!(ctx.const_value(c).unwrap(), Some(0.5));
+
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let mut point_eval = VmShape::new_point_eval();
+        let tape = shape.ez_point_tape();
+        for (x, y) in [(0.0f32, 0.0f32), (1.0, 2.0), (-2.5, 0.25)] {
+            let (v, _) = point_eval.eval(&tape, x, y, 0.0, &[]).unwrap();
+            let expected = y - x.sin() / 2.0;
+            assert!((v - expected).abs() < 1e-6, "{v} != {expected}");
+            let c = ctx.eval_xyz(root, x as f64, y as f64, 0.0).unwrap();
+            assert!((v as f64 - c).abs() < 1e-6);
+        }
+
+        let mut interval_eval = VmShape::new_interval_eval();
+        let tape = shape.ez_interval_tape();
+        let (i, _) = interval_eval
+            .eval(
+                &tape,
+                [0.0, std::f32::consts::FRAC_PI_2],
+                [1.0, 1.0],
+                [0.0; 2],
+                &[],
+            )
+            .unwrap();
+        // sin(x) is in [0, 1], so y - sin(x) / 2 is in [0.5, 1]
+        assert_eq!(i, Interval::new(0.5, 1.0));
+
+        let mut grad_eval = VmShape::new_grad_slice_eval();
+        let tape = shape.ez_grad_slice_tape();
+        let g = grad_eval.eval(&tape, &[0.0], &[1.0], &[0.0], &[]).unwrap();
+        assert_eq!(g[0], Grad::new(1.0, -0.5, 1.0, 0.0));
+
+        #[cfg(feature = "jit")]
+        assert!(matches!(
+            crate::jit::JitShape::new(&ctx, root),
+            Err(Error::UnsupportedOp("custom"))
+        ));
+    }
 }
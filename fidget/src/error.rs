@@ -29,6 +29,72 @@ pub enum Error {
     #[error("empty file")]
     EmptyFile,
 
+    /// Unexpected end of line
+    #[error("unexpected end of line")]
+    UnexpectedEndOfLine,
+
+    /// Invalid constant {0}
+    #[error("invalid constant {0}")]
+    BadConstant(String),
+
+    /// Error while parsing text, with its location in the input
+    #[error("line {line}, column {column}: {source}\n{snippet}")]
+    ParseError {
+        /// Byte offset of the error within the input
+        offset: usize,
+        /// Line number (1-indexed)
+        line: usize,
+        /// Column number (1-indexed, in characters)
+        column: usize,
+        /// The offending line, followed by a line with a caret under the error
+        snippet: String,
+        /// Underlying error
+        source: Box<Error>,
+    },
+
+    /// Macro has an empty body
+    #[error("macro {0} has an empty body")]
+    EmptyMacro(String),
+    /// Macro calls itself, directly or through other macros
+    #[error("macro {0} is recursive")]
+    RecursiveMacro(String),
+    /// Macro was called with the wrong number of arguments
+    #[error("macro {0} expects {1} arguments, but was given {2}")]
+    BadMacroArgs(String, usize, usize),
+    /// Opcode was called with the wrong number of arguments
+    #[error("opcode {0} can't take {1} arguments")]
+    BadOpArgs(String, usize),
+    /// Expected `=` in an output declaration
+    #[error("expected `=`, found {0}")]
+    ExpectedEquals(String),
+    /// Output was declared more than once
+    #[error("output {0} is declared more than once")]
+    DuplicateOutput(String),
+
+    /// Expected a particular token while parsing
+    #[error("expected {0}, found {1}")]
+    ExpectedToken(&'static str, String),
+    /// OpenSCAD module is not supported
+    #[error("unsupported OpenSCAD module `{0}`")]
+    UnsupportedScad(String),
+    /// Unknown argument to an OpenSCAD module
+    #[error("unknown argument `{0}` for `{1}`")]
+    UnknownScadArg(String, String),
+    /// OpenSCAD argument is missing or has the wrong type
+    #[error("missing or invalid value for argument `{0}`")]
+    BadScadArg(String),
+
+    /// Variable name can't be used in GLSL
+    #[error("variable name `{0}` is not a valid GLSL uniform name")]
+    BadGlslName(String),
+
+    /// Binary graph doesn't begin with the expected magic bytes
+    #[error("invalid header in binary graph")]
+    BadMagic,
+    /// Binary graph was written with an unsupported format version
+    #[error("unsupported binary graph version {0}")]
+    BadVersion(u8),
+
     /// Choice slice length does not match choice count
     #[error("choice slice length ({0}) does not match choice count ({1})")]
     BadChoiceSlice(usize, usize),
@@ -49,10 +115,23 @@ pub enum Error {
     #[error("this name has already been used")]
     DuplicateName,
 
+    /// Contour level is too far from the surface stored in an octree
+    #[error("contour level {0} moves the surface by more than a cell")]
+    ContourTooFar(f32),
+
+    /// Operation is not supported by this evaluator
+    #[error("operation `{0}` is not supported by this evaluator")]
+    UnsupportedOp(&'static str),
+
     /// io error; see inner code for details
     #[error("io error: {0}")]
     IoError(#[from] std::io::Error),
 
+    #[cfg(feature = "json")]
+    /// JSON error; see inner code for details
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
     #[cfg(feature = "rhai")]
     /// Rhai error; see inner code for details
     #[error("Rhai error: {0}")]
@@ -252,12 +252,16 @@ impl Assembler for GradSliceAssembler {
         // TODO: use two fcsel instead?
         dynasm!(self.0.ops
             ; fcmp S(reg(lhs_reg)), 0.0
-            ; b.lt 12 // -> neg
-            // Happy path: v >= 0, so we just copy the register
+            ; b.lt 16 // -> neg
+            ; b.eq 20 // -> zero
+            // Happy path: v > 0, so we just copy the register
             ; mov V(reg(out_reg)).b16, V(reg(lhs_reg)).b16
-            ; b 8 // -> end
+            ; b 16 // -> end
             // neg:
             ; fneg V(reg(out_reg)).s4, V(reg(lhs_reg)).s4
+            ; b 8 // -> end
+            // zero, which has a zero gradient by convention:
+            ; eor V(reg(out_reg)).b16, V(reg(out_reg)).b16, V(reg(out_reg)).b16
             // end:
         )
     }
@@ -366,7 +370,7 @@ impl Assembler for GradSliceAssembler {
         dynasm!(self.0.ops
             ; fcmp S(reg(lhs_reg)), S(reg(rhs_reg))
             ; b.vs 24 // -> NaN
-            ; b.gt 12 // -> lhs
+            ; b.ge 12 // -> lhs (ties use the left-hand gradient)
 
             // Happy path: v >= 0, so we just copy the register
             ; mov V(reg(out_reg)).b16, V(reg(rhs_reg)).b16
@@ -386,7 +390,7 @@ impl Assembler for GradSliceAssembler {
         dynasm!(self.0.ops
             ; fcmp S(reg(lhs_reg)), S(reg(rhs_reg))
             ; b.vs 24 // -> NaN
-            ; b.lt 12 // -> lhs
+            ; b.le 12 // -> lhs (ties use the left-hand gradient)
 
             // Happy path: v >= 0, so we just copy the register
             ; mov V(reg(out_reg)).b16, V(reg(rhs_reg)).b16
@@ -314,19 +314,22 @@ impl Assembler for IntervalAssembler {
     fn build_add(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; fadd V(reg(out_reg)).s2, V(reg(lhs_reg)).s2, V(reg(rhs_reg)).s2
-        )
+        );
+        self.build_nan_fixup(out_reg);
     }
     fn build_sub(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; rev64 v4.s2, V(reg(rhs_reg)).s2
             ; fsub V(reg(out_reg)).s2, V(reg(lhs_reg)).s2, v4.s2
-        )
+        );
+        self.build_nan_fixup(out_reg);
     }
     fn build_sub_reg_imm(&mut self, out_reg: u8, arg: u8, imm: f32) {
         let imm = self.load_imm(imm);
         dynasm!(self.0.ops
             ; fsub V(reg(out_reg)).s2, V(reg(arg)).s2, V(reg(imm)).s2
-        )
+        );
+        self.build_nan_fixup(out_reg);
     }
     fn build_mul(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
@@ -734,6 +737,25 @@ impl Assembler for IntervalAssembler {
 }
 
 impl IntervalAssembler {
+    /// Replaces `out_reg` with `[NaN, NaN]` if either of its bounds is `NaN`
+    ///
+    /// This is used after addition and subtraction, where `∞ - ∞` can make a
+    /// single bound `NaN`.
+    fn build_nan_fixup(&mut self, out_reg: u8) {
+        dynasm!(self.0.ops
+            // Each lane of v4 is all 1s if the matching bound is not NaN
+            ; fcmeq v4.s2, V(reg(out_reg)).s2, V(reg(out_reg)).s2
+            ; fmov x15, d4
+            ; cmn x15, 1
+            ; b.eq 12 // -> end
+
+            // Load NaN into out_reg
+            ; mov w15, f32::NAN.to_bits().into()
+            ; dup V(reg(out_reg)).s2, w15
+
+            // <- end
+        )
+    }
     fn call_fn_unary(
         &mut self,
         out_reg: u8,
@@ -41,10 +41,21 @@ impl Mmap {
         if ptr == libc::MAP_FAILED {
             Err(std::io::Error::last_os_error())
         } else {
+            #[cfg(test)]
+            LIVE_COUNT.with(|c| c.set(c.get() + 1));
             Ok(Self { ptr, len })
         }
     }
 
+    /// Returns the number of non-empty maps owned by the current thread
+    ///
+    /// The count is incremented when a map is created and decremented when it
+    /// is dropped, so it's only meaningful for maps which don't cross threads.
+    #[cfg(test)]
+    pub fn live_count() -> isize {
+        LIVE_COUNT.with(|c| c.get())
+    }
+
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.len
@@ -231,10 +242,19 @@ impl Drop for Mmap {
             unsafe {
                 libc::munmap(self.ptr, self.len as libc::size_t);
             }
+            #[cfg(test)]
+            LIVE_COUNT.with(|c| c.set(c.get() - 1));
         }
     }
 }
 
+#[cfg(test)]
+thread_local! {
+    /// Per-thread count of live maps, used to check for leaks in unit tests
+    static LIVE_COUNT: std::cell::Cell<isize> =
+        const { std::cell::Cell::new(0) };
+}
+
 #[cfg(target_os = "macos")]
 mod macos {
     /// Empty struct which switches the thread to execute mode when dropped
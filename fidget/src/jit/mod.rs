@@ -790,6 +790,9 @@ fn build_asm_fn_with_storage<A: Assembler>(
                 let reg = asm.load_imm(imm);
                 asm.build_compare(out, reg, arg);
             }
+            RegOp::CustomReg(..) => {
+                unreachable!("custom ops are rejected by JitShape::new")
+            }
         }
     }
 
@@ -1205,8 +1208,21 @@ impl BulkEvaluator for JitGradSliceEval {
 }
 
 impl MathShape for JitShape {
+    /// Builds a new shape, which must not use [custom
+    /// operations](crate::context::CustomOp)
+    ///
+    /// Generated machine code can't call back into user-defined operations, so
+    /// graphs which use them return [`Error::UnsupportedOp`].
     fn new(ctx: &Context, node: Node) -> Result<Self, Error> {
-        GenericVmShape::new(ctx, node).map(JitShape)
+        let shape = GenericVmShape::new(ctx, node)?;
+        if shape
+            .data()
+            .iter_asm()
+            .any(|op| matches!(op, RegOp::CustomReg(..)))
+        {
+            return Err(Error::UnsupportedOp("custom"));
+        }
+        Ok(JitShape(shape))
     }
 }
 
@@ -1225,4 +1241,41 @@ mod test {
     crate::interval_tests!(JitShape);
     crate::float_slice_tests!(JitShape);
     crate::point_tests!(JitShape);
+
+    #[test]
+    fn test_no_mmap_leaks() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let mut root = ctx.add(x, y).unwrap();
+        for i in 0..100 {
+            let c = ctx.sin(root).unwrap();
+            root = if i % 2 == 0 {
+                ctx.min(c, x).unwrap()
+            } else {
+                ctx.max(c, y).unwrap()
+            };
+        }
+        let shape = JitShape::new(&ctx, root).unwrap();
+
+        let start = Mmap::live_count();
+        for _ in 0..16 {
+            // Build every kind of function, then throw them away
+            let p = shape.point_tape(Mmap::empty());
+            let i = shape.interval_tape(Mmap::empty());
+            let f = shape.float_slice_tape(Mmap::empty());
+            let g = shape.grad_slice_tape(Mmap::empty());
+            assert!(Mmap::live_count() > start);
+            drop((p, i, f, g));
+            assert_eq!(Mmap::live_count(), start);
+
+            // Abandon an assembler partway through building a function
+            let mut asm = point::PointAssembler::init(Mmap::new(0).unwrap(), 0);
+            for _ in 0..1024 {
+                asm.build_add(0, 1, 2); // forces the mmap to be resized
+            }
+            drop(asm);
+            assert_eq!(Mmap::live_count(), start);
+        }
+    }
 }
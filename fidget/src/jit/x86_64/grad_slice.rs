@@ -206,15 +206,20 @@ impl Assembler for GradSliceAssembler {
 
             ; vcomiss Rx(reg(lhs_reg)), xmm0
             ; jb >N
+            ; je >Z
 
-            // Fallthrough: non-negative (or NaN) input
+            // Fallthrough: positive input
             ; vmovups Rx(reg(out_reg)), Rx(reg(lhs_reg))
             ; jmp >E
 
-            ; N: // negative
+            ; N: // negative (or NaN)
             ; vpcmpeqw xmm0, xmm0, xmm0
             ; vpslld xmm0, xmm0, 31 // set the sign bit
             ; vpxor Rx(reg(out_reg)), xmm0, Rx(reg(lhs_reg))
+            ; jmp >E
+
+            ; Z: // zero, which has a zero gradient by convention
+            ; vpxor Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(out_reg))
             // Fallthrough to end
 
             ; E:
@@ -345,7 +350,7 @@ impl Assembler for GradSliceAssembler {
         dynasm!(self.0.ops
             ; vcomiss Rx(reg(lhs_reg)), Rx(reg(rhs_reg))
             ; jp >N // Parity flag is set if result is NAN
-            ; ja >L
+            ; jae >L // Ties use the left-hand gradient
 
             // Fallthrough
             ; vmovups Rx(reg(out_reg)), Rx(reg(rhs_reg))
@@ -370,7 +375,7 @@ impl Assembler for GradSliceAssembler {
             ; jp >N // Parity flag is set if result is NAN
             ; ja >R
 
-            // Fallthrough
+            // Fallthrough (including ties, which use the left-hand gradient)
             ; vmovups Rx(reg(out_reg)), Rx(reg(lhs_reg))
             ; jmp >O
 
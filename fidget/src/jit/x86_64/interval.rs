@@ -314,12 +314,14 @@ impl Assembler for IntervalAssembler {
         dynasm!(self.0.ops
             ; vaddps Rx(reg(out_reg)), Rx(reg(lhs_reg)), Rx(reg(rhs_reg))
         );
+        self.build_nan_fixup(out_reg);
     }
     fn build_sub(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
             ; vpshufd xmm1, Rx(reg(rhs_reg)), 0b11110001u8 as i8
             ; vsubps Rx(reg(out_reg)), Rx(reg(lhs_reg)), xmm1
         );
+        self.build_nan_fixup(out_reg);
     }
     fn build_mul(&mut self, out_reg: u8, lhs_reg: u8, rhs_reg: u8) {
         dynasm!(self.0.ops
@@ -757,6 +759,27 @@ impl Assembler for IntervalAssembler {
 }
 
 impl IntervalAssembler {
+    /// Replaces `out_reg` with `[NaN, NaN]` if either of its bounds is `NaN`
+    ///
+    /// This is used after addition and subtraction, where `∞ - ∞` can make a
+    /// single bound `NaN`.
+    fn build_nan_fixup(&mut self, out_reg: u8) {
+        dynasm!(self.0.ops
+            // Check whether either of the lower two lanes is unordered (NaN)
+            ; vcmpps xmm1, Rx(reg(out_reg)), Rx(reg(out_reg)), 3
+            ; movmskps eax, xmm1
+            ; test eax, 0b11
+            ; jz >E
+
+            // Load NaN into out_reg
+            ; vpcmpeqw Rx(reg(out_reg)), Rx(reg(out_reg)), Rx(reg(out_reg))
+            ; vpslld Rx(reg(out_reg)), Rx(reg(out_reg)), 23
+            ; vpsrld Rx(reg(out_reg)), Rx(reg(out_reg)), 1
+
+            ; E:
+        );
+        self.0.ops.commit_local().unwrap();
+    }
     fn call_fn_unary(
         &mut self,
         out_reg: u8,
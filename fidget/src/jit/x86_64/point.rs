@@ -249,6 +249,7 @@ impl Assembler for PointAssembler {
             // fallthrough to out
 
             ; O:
+            ; add rsi, 1
         );
         self.0.ops.commit_local().unwrap()
     }
@@ -283,6 +284,7 @@ impl Assembler for PointAssembler {
             // fallthrough to out
 
             ; O:
+            ; add rsi, 1
         );
         self.0.ops.commit_local().unwrap()
     }
@@ -327,6 +329,7 @@ impl Assembler for PointAssembler {
             ; or [rsi], cl // write the choice flag, based on condition flags
             ; or [rdx], 1 // write the simplify bit
             ; movaps Rx(reg(out_reg)), xmm1
+            ; add rsi, 1
         );
         self.0.ops.commit_local().unwrap()
     }
@@ -348,6 +351,7 @@ impl Assembler for PointAssembler {
             ; or [rsi], al // write the choice flag, based on condition flags
             ; or [rdx], 1 // write the simplify bit
             ; movaps Rx(reg(out_reg)), xmm1
+            ; add rsi, 1
         );
         self.0.ops.commit_local().unwrap()
     }
@@ -0,0 +1,100 @@
+//! Ambient occlusion baking for meshes
+use super::Mesh;
+use crate::{
+    eval::{EzShape, Shape, TracingEvaluator},
+    Error,
+};
+use nalgebra::Vector3;
+
+/// Maximum number of sphere-tracing steps along each ray
+const MAX_STEPS: usize = 128;
+
+impl Mesh {
+    /// Bakes ambient occlusion at each vertex
+    ///
+    /// For each vertex, `samples` rays are cast over the hemisphere around the
+    /// vertex normal (computed from the area-weighted normals of neighboring
+    /// triangles), and are sphere-traced against `shape`.  The result is the
+    /// fraction of rays which hit the shape, from 0 (fully exposed) to 1
+    /// (fully occluded), in the same order as [`self.vertices`](Self::vertices).
+    ///
+    /// `shape` should be the shape from which the mesh was built (in the same
+    /// coordinate space as its vertices).  Sphere tracing assumes that it's a
+    /// distance field, or at least a lower bound on distance; rays start
+    /// slightly above the surface and travel up to the size of the mesh's
+    /// bounding box.
+    pub fn bake_ao<S: Shape>(
+        &self,
+        shape: &S,
+        samples: usize,
+    ) -> Result<Vec<f32>, Error> {
+        let normals = self.vertex_normals();
+
+        let (lo, hi) = self.vertices.iter().fold(
+            (
+                Vector3::repeat(f32::INFINITY),
+                Vector3::repeat(f32::NEG_INFINITY),
+            ),
+            |(lo, hi), v| (lo.inf(v), hi.sup(v)),
+        );
+        let size = (hi - lo).norm();
+        let max_dist = size;
+        let offset = size * 1e-2;
+        let epsilon = size * 1e-3;
+
+        // Directions in the local frame of a vertex (with +Z as its normal),
+        // spaced uniformly over the hemisphere on a Fibonacci spiral
+        let golden = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+        let dirs: Vec<Vector3<f32>> = (0..samples)
+            .map(|k| {
+                let z = 1.0 - (k as f32 + 0.5) / samples as f32;
+                let r = (1.0 - z * z).sqrt();
+                let phi = k as f32 * golden;
+                Vector3::new(r * phi.cos(), r * phi.sin(), z)
+            })
+            .collect();
+
+        let tape = shape.ez_point_tape();
+        let mut eval = S::new_point_eval();
+        let mut out = Vec::with_capacity(self.vertices.len());
+        for (v, n) in self.vertices.iter().zip(&normals) {
+            let Some(n) = n.try_normalize(0.0) else {
+                out.push(0.0);
+                continue;
+            };
+            // Build an orthonormal frame around the normal
+            let helper = if n.x.abs() < 0.9 {
+                Vector3::x()
+            } else {
+                Vector3::y()
+            };
+            let u = n.cross(&helper).normalize();
+            let w = n.cross(&u);
+
+            let mut hits = 0;
+            for d in &dirs {
+                let dir = u * d.x + w * d.y + n * d.z;
+                let start = v + n * offset;
+                let mut t = 0.0;
+                for _ in 0..MAX_STEPS {
+                    let p = start + dir * t;
+                    let (d, _) = eval.eval(&tape, p.x, p.y, p.z, &[])?;
+                    if d < epsilon {
+                        hits += 1;
+                        break;
+                    }
+                    t += d;
+                    if t > max_dist {
+                        break;
+                    }
+                }
+            }
+            out.push(if samples == 0 {
+                0.0
+            } else {
+                hits as f32 / samples as f32
+            });
+        }
+        Ok(out)
+    }
+}
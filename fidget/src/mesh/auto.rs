@@ -0,0 +1,259 @@
+//! Automatic selection of a meshing strategy
+use super::{Mesh, Octree, Settings};
+use crate::{
+    context::{Context, Node},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape},
+    types::Grad,
+    vm::VmShape,
+    Error,
+};
+use nalgebra::Vector3;
+
+/// Strategy used to position mesh vertices
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mesher {
+    /// Manifold dual contouring, which places vertices by solving a QEF
+    ///
+    /// This preserves sharp features (edges and corners), but can produce
+    /// noisier meshes on smooth surfaces.
+    DualContouring,
+
+    /// Surface nets, which places vertices at the mass point of their edge
+    /// intersections
+    ///
+    /// Like marching cubes, this produces smooth meshes, but rounds off sharp
+    /// features.
+    SurfaceNets,
+}
+
+/// Number of samples per axis when looking for sharp features
+const FEATURE_GRID_SIZE: usize = 16;
+
+/// Normals which differ by more than this angle (as a cosine) are suspicious
+const FEATURE_COS_ANGLE: f32 = 0.866; // 30°
+
+/// Number of bisection steps used to confirm a gradient discontinuity
+const FEATURE_BISECT_STEPS: usize = 12;
+
+impl Mesher {
+    /// Picks a meshing strategy for the given shape
+    ///
+    /// The shape is sampled on a coarse grid within `settings.bounds`.  If
+    /// neighboring samples near the surface have very different normals, we
+    /// bisect between them; if the normals stay different as the samples get
+    /// closer together, then the gradient is discontinuous (e.g. at the
+    /// boundary between two branches of a `min` or `max`), so the shape has
+    /// sharp features and we use [`Mesher::DualContouring`].  Otherwise, we
+    /// use [`Mesher::SurfaceNets`].
+    pub fn select<S: Shape>(shape: &S, settings: &Settings) -> Self {
+        if has_sharp_features(shape, settings) {
+            Mesher::DualContouring
+        } else {
+            Mesher::SurfaceNets
+        }
+    }
+
+    /// Meshes the given shape using this strategy
+    pub fn mesh<S: Shape + Clone>(self, shape: &S, settings: Settings) -> Mesh {
+        let mut octree = Octree::build(shape, settings);
+        if self == Mesher::SurfaceNets {
+            octree.use_mass_points();
+        }
+        octree.walk_dual(settings)
+    }
+}
+
+/// Meshes a node, automatically picking a meshing strategy
+///
+/// See [`Mesher::select`] for details on the heuristic; to override it, use
+/// [`Mesher::mesh`] with a specific strategy instead.
+///
+/// ```
+/// use fidget::mesh::{self, Settings};
+///
+/// let (node, ctx) = fidget::rhai::eval("sphere(0, 0, 0, 0.6).call(x, y, z)")?;
+/// let settings = Settings {
+///     min_depth: 4,
+///     max_depth: 4,
+///     ..Default::default()
+/// };
+/// let mesh = mesh::auto(&ctx, node, settings)?;
+/// assert!(!mesh.triangles.is_empty());
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub fn auto(
+    ctx: &Context,
+    node: Node,
+    settings: Settings,
+) -> Result<Mesh, Error> {
+    let shape = VmShape::new(ctx, node)?;
+    Ok(Mesher::select(&shape, &settings).mesh(&shape, settings))
+}
+
+/// Checks whether the shape has a gradient discontinuity near its surface
+fn has_sharp_features<S: Shape>(shape: &S, settings: &Settings) -> bool {
+    let n = FEATURE_GRID_SIZE;
+    let center = settings.bounds.center;
+    let size = settings.bounds.size;
+    let spacing = size * 2.0 / (n - 1) as f32;
+    let pos = |i, j, k| {
+        center + Vector3::new(i as f32, j as f32, k as f32) * spacing
+            - Vector3::repeat(size)
+    };
+
+    let mut xs = Vec::with_capacity(n.pow(3));
+    let mut ys = Vec::with_capacity(n.pow(3));
+    let mut zs = Vec::with_capacity(n.pow(3));
+    for k in 0..n {
+        for j in 0..n {
+            for i in 0..n {
+                let p = pos(i, j, k);
+                xs.push(p.x);
+                ys.push(p.y);
+                zs.push(p.z);
+            }
+        }
+    }
+    let tape = shape.ez_grad_slice_tape();
+    let mut eval = S::new_grad_slice_eval();
+    let grads = eval.eval(&tape, &xs, &ys, &zs, &[]).unwrap().to_vec();
+
+    // Returns the normal, if the sample is near the surface
+    let near_surface = |g: Grad| {
+        let n = Vector3::new(g.dx, g.dy, g.dz);
+        if g.v.abs() < spacing * n.norm() {
+            n.try_normalize(0.0)
+        } else {
+            None
+        }
+    };
+    let mut normal_at = |p: Vector3<f32>| {
+        let g = eval.eval(&tape, &[p.x], &[p.y], &[p.z], &[]).unwrap()[0];
+        Vector3::new(g.dx, g.dy, g.dz).try_normalize(0.0)
+    };
+
+    let index = |i, j, k| i + j * n + k * n * n;
+    for k in 0..n {
+        for j in 0..n {
+            for i in 0..n {
+                let Some(na) = near_surface(grads[index(i, j, k)]) else {
+                    continue;
+                };
+                for [di, dj, dk] in [[1, 0, 0], [0, 1, 0], [0, 0, 1]] {
+                    let (i2, j2, k2) = (i + di, j + dj, k + dk);
+                    if i2 >= n || j2 >= n || k2 >= n {
+                        continue;
+                    }
+                    let Some(nb) = near_surface(grads[index(i2, j2, k2)])
+                    else {
+                        continue;
+                    };
+                    if na.dot(&nb) >= FEATURE_COS_ANGLE {
+                        continue;
+                    }
+
+                    // Bisect towards the largest change in normal; on a smooth
+                    // surface, the change shrinks as the samples get closer.
+                    let (mut a, mut b) = (pos(i, j, k), pos(i2, j2, k2));
+                    let (mut na, mut nb) = (na, nb);
+                    for _ in 0..FEATURE_BISECT_STEPS {
+                        let m = (a + b) / 2.0;
+                        let Some(nm) = normal_at(m) else {
+                            break;
+                        };
+                        if na.dot(&nm) < nm.dot(&nb) {
+                            (b, nb) = (m, nm);
+                        } else {
+                            (a, na) = (m, nm);
+                        }
+                    }
+                    if na.dot(&nb) < FEATURE_COS_ANGLE {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::test_sphere;
+
+    fn cube(ctx: &mut Context, size: f32) -> Node {
+        let mut out = None;
+        for axis in [ctx.x(), ctx.y(), ctx.z()] {
+            let a = ctx.abs(axis).unwrap();
+            let a = ctx.sub(a, size).unwrap();
+            out = Some(match out {
+                Some(prev) => ctx.max(prev, a).unwrap(),
+                None => a,
+            });
+        }
+        out.unwrap()
+    }
+
+    #[test]
+    fn test_select_mesher() {
+        let settings = Settings {
+            min_depth: 4,
+            max_depth: 4,
+            threads: 0,
+            ..Default::default()
+        };
+
+        let mut ctx = Context::new();
+        for r in [0.2, 0.9] {
+            let s = test_sphere(&mut ctx, [0.0; 3], r);
+            let shape = VmShape::new(&ctx, s).unwrap();
+            assert_eq!(Mesher::select(&shape, &settings), Mesher::SurfaceNets);
+        }
+        let s = test_sphere(&mut ctx, [0.0; 3], 0.6);
+        let shape = VmShape::new(&ctx, s).unwrap();
+        assert_eq!(Mesher::select(&shape, &settings), Mesher::SurfaceNets);
+
+        let c = cube(&mut ctx, 0.4);
+        let shape = VmShape::new(&ctx, c).unwrap();
+        assert_eq!(Mesher::select(&shape, &settings), Mesher::DualContouring);
+
+        // Overlapping spheres have a crease where they meet
+        let x = ctx.x();
+        let dx = ctx.sub(x, 0.6).unwrap();
+        let (y, z) = (ctx.y(), ctx.z());
+        let s2 = ctx.remap_xyz(s, [dx, y, z]).unwrap();
+        let u = ctx.min(s, s2).unwrap();
+        let shape = VmShape::new(&ctx, u).unwrap();
+        assert_eq!(Mesher::select(&shape, &settings), Mesher::DualContouring);
+    }
+
+    #[test]
+    fn test_surface_nets() {
+        let mut ctx = Context::new();
+        let s = test_sphere(&mut ctx, [0.0; 3], 0.6);
+        let settings = Settings {
+            min_depth: 5,
+            max_depth: 5,
+            threads: 0,
+            ..Default::default()
+        };
+        let mesh = auto(&ctx, s, settings).unwrap();
+        assert!(!mesh.triangles.is_empty());
+        for v in &mesh.vertices {
+            // Mass points are within a cell of the surface
+            assert!((v.norm() - 0.6).abs() < 2.0 / 32.0, "bad vertex {v:?}");
+        }
+
+        // The cube's corners are only preserved by dual contouring
+        let c = cube(&mut ctx, 0.4);
+        let shape = VmShape::new(&ctx, c).unwrap();
+        let corner = |m: &Mesh| {
+            m.vertices.iter().map(|v| v.abs().min()).fold(0.0, f32::max)
+        };
+        let dc = Mesher::DualContouring.mesh(&shape, settings);
+        let sn = Mesher::SurfaceNets.mesh(&shape, settings);
+        assert!((corner(&dc) - 0.4).abs() < 1e-3);
+        assert!(corner(&sn) < 0.39);
+    }
+}
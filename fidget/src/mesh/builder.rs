@@ -71,3 +71,94 @@ impl DcBuilder for MeshBuilder {
         }
     }
 }
+
+/// Container used to build a polygon mesh from the dual of an octree
+///
+/// Unlike [`MeshBuilder`], this doesn't use edge intersection vertices:
+/// each triangle fan around an edge is merged into a single polygon through
+/// the surrounding leaf vertices.  The polygon is a quad, unless some of the
+/// cells around the edge are the same (larger) cell, in which case it's a
+/// triangle.
+#[derive(Default)]
+pub struct PolygonBuilder {
+    /// Map from indexes in [`Octree::verts`](super::Octree::verts) to
+    /// `vertices`
+    ///
+    /// `usize::MAX` is used a marker for an unmapped vertex
+    map: Vec<usize>,
+
+    /// Edges `(a, b)` of the current triangle fan, as absolute vertex indexes
+    fan: Vec<(usize, usize)>,
+
+    /// Absolute indexes of each output vertex
+    pub vertices: Vec<usize>,
+
+    /// Polygons, as indexes into `vertices`
+    pub faces: Vec<Vec<usize>>,
+}
+
+impl PolygonBuilder {
+    fn index(&mut self, v: usize) -> usize {
+        if v >= self.map.len() {
+            self.map.resize(v + 1, usize::MAX);
+        }
+        if self.map[v] == usize::MAX {
+            self.map[v] = self.vertices.len();
+            self.vertices.push(v);
+        }
+        self.map[v]
+    }
+}
+
+impl DcBuilder for PolygonBuilder {
+    type VertexIndex = usize;
+
+    fn cell(&mut self, octree: &Octree, cell: CellIndex) {
+        dc::dc_cell(octree, cell, self);
+    }
+    fn face<F: Frame>(&mut self, octree: &Octree, a: CellIndex, b: CellIndex) {
+        dc::dc_face::<F, _>(octree, a, b, self)
+    }
+    fn edge<F: Frame>(
+        &mut self,
+        octree: &Octree,
+        a: CellIndex,
+        b: CellIndex,
+        c: CellIndex,
+        d: CellIndex,
+    ) {
+        dc::dc_edge::<F, _>(octree, a, b, c, d, self)
+    }
+    fn triangle(&mut self, a: usize, b: usize, _i: usize) {
+        self.fan.push((a, b))
+    }
+
+    /// Chains the fan's outer edges into a single polygon
+    fn fan_done(&mut self) {
+        let fan = std::mem::take(&mut self.fan);
+        let mut poly = vec![];
+        if let Some(&(start, _)) = fan.first() {
+            let mut v = start;
+            while poly.len() < fan.len() {
+                poly.push(self.index(v));
+                match fan.iter().find(|(a, _)| *a == v) {
+                    Some(&(_, b)) if b != start => v = b,
+                    _ => break,
+                }
+            }
+        }
+        if poly.len() >= 3 {
+            self.faces.push(poly);
+        }
+    }
+
+    /// Returns the absolute vertex index, which is mapped in `fan_done`
+    fn vertex(
+        &mut self,
+        v: usize,
+        _cell: CellIndex,
+        _verts: &[CellVertex],
+    ) -> usize {
+        v
+    }
+}
@@ -113,12 +113,19 @@ impl Leaf {
 pub struct CellVertex {
     /// Position of this vertex
     pub pos: nalgebra::Vector3<f32>,
+
+    /// Surface normal (unnormalized gradient) at this position
+    ///
+    /// This is only populated for edge intersections; it's zero for vertices
+    /// positioned by the QEF solver.
+    pub normal: nalgebra::Vector3<f32>,
 }
 
 impl Default for CellVertex {
     fn default() -> Self {
         Self {
             pos: nalgebra::Vector3::new(f32::NAN, f32::NAN, f32::NAN),
+            normal: nalgebra::Vector3::zeros(),
         }
     }
 }
@@ -289,6 +296,15 @@ impl CellBounds {
     pub fn contains(&self, p: CellVertex) -> bool {
         [X, Y, Z].iter().all(|&i| self[i].contains(p[i]))
     }
+
+    /// Returns the center of the cell
+    pub fn center(&self) -> nalgebra::Vector3<f32> {
+        nalgebra::Vector3::new(
+            self.x.midpoint(),
+            self.y.midpoint(),
+            self.z.midpoint(),
+        )
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,211 @@
+//! Self-intersection checks for meshes
+use super::Mesh;
+use nalgebra::Vector3;
+
+/// Maximum number of triangles in a BVH leaf
+const LEAF_SIZE: usize = 4;
+
+/// Axis-aligned bounding box
+#[derive(Copy, Clone, Debug)]
+struct Aabb {
+    lower: Vector3<f32>,
+    upper: Vector3<f32>,
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Self {
+            lower: Vector3::repeat(f32::INFINITY),
+            upper: Vector3::repeat(f32::NEG_INFINITY),
+        }
+    }
+    fn of(pts: &[Vector3<f32>; 3]) -> Self {
+        let mut out = Self::empty();
+        for p in pts {
+            out.lower = out.lower.inf(p);
+            out.upper = out.upper.sup(p);
+        }
+        out
+    }
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            lower: self.lower.inf(&other.lower),
+            upper: self.upper.sup(&other.upper),
+        }
+    }
+    fn overlaps(&self, other: &Self) -> bool {
+        (0..3).all(|i| {
+            self.lower[i] <= other.upper[i] && other.lower[i] <= self.upper[i]
+        })
+    }
+}
+
+/// Node in a bounding volume hierarchy
+enum BvhNode {
+    /// Range of indexes into [`Bvh::order`]
+    Leaf(Aabb, std::ops::Range<usize>),
+    /// Indexes of child nodes in [`Bvh::nodes`]
+    Branch(Aabb, usize, usize),
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &Aabb {
+        match self {
+            BvhNode::Leaf(b, _) | BvhNode::Branch(b, ..) => b,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a mesh's triangles
+struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// Triangle indexes, sorted so that each leaf is a contiguous range
+    order: Vec<usize>,
+}
+
+impl Bvh {
+    fn new(boxes: &[Aabb]) -> Self {
+        let mut out = Self {
+            nodes: vec![],
+            order: (0..boxes.len()).collect(),
+        };
+        if !boxes.is_empty() {
+            out.build(boxes, 0..boxes.len());
+        }
+        out
+    }
+
+    /// Recursively builds a node for the given range, returning its index
+    fn build(
+        &mut self,
+        boxes: &[Aabb],
+        range: std::ops::Range<usize>,
+    ) -> usize {
+        let bounds = self.order[range.clone()]
+            .iter()
+            .fold(Aabb::empty(), |b, &i| b.union(&boxes[i]));
+        if range.len() <= LEAF_SIZE {
+            self.nodes.push(BvhNode::Leaf(bounds, range));
+            return self.nodes.len() - 1;
+        }
+
+        // Split at the median along the longest axis
+        let axis = (bounds.upper - bounds.lower).imax();
+        let center = |i: &usize| boxes[*i].lower[axis] + boxes[*i].upper[axis];
+        let mid = range.start + range.len() / 2;
+        self.order[range.clone()]
+            .select_nth_unstable_by(mid - range.start, |a, b| {
+                center(a).total_cmp(&center(b))
+            });
+
+        // Reserve our slot before building children
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode::Leaf(bounds, 0..0));
+        let lo = self.build(boxes, range.start..mid);
+        let hi = self.build(boxes, mid..range.end);
+        self.nodes[index] = BvhNode::Branch(bounds, lo, hi);
+        index
+    }
+
+    /// Calls `f` on every triangle whose bounding box overlaps `b`
+    ///
+    /// Stops early and returns `true` if `f` returns `true`.
+    fn any_overlapping<F: FnMut(usize) -> bool>(
+        &self,
+        b: &Aabb,
+        mut f: F,
+    ) -> bool {
+        let mut todo = vec![];
+        if !self.nodes.is_empty() {
+            todo.push(0);
+        }
+        while let Some(n) = todo.pop() {
+            let node = &self.nodes[n];
+            if !node.bounds().overlaps(b) {
+                continue;
+            }
+            match node {
+                BvhNode::Leaf(_, range) => {
+                    if self.order[range.clone()].iter().any(|&i| f(i)) {
+                        return true;
+                    }
+                }
+                BvhNode::Branch(_, lo, hi) => {
+                    todo.push(*lo);
+                    todo.push(*hi);
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Checks whether the segment `p`-`q` passes through the triangle `t`
+///
+/// This is a Möller–Trumbore intersection test, limited to the segment; it
+/// ignores segments which are parallel to the triangle's plane.
+fn segment_hits_triangle(
+    p: Vector3<f32>,
+    q: Vector3<f32>,
+    t: &[Vector3<f32>; 3],
+) -> bool {
+    let dir = q - p;
+    let e1 = t[1] - t[0];
+    let e2 = t[2] - t[0];
+    let h = dir.cross(&e2);
+    let det = e1.dot(&h);
+    if det.abs() <= f32::EPSILON * e1.norm() * e2.norm() * dir.norm() {
+        return false;
+    }
+    let s = p - t[0];
+    let u = s.dot(&h) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return false;
+    }
+    let r = s.cross(&e1);
+    let v = dir.dot(&r) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return false;
+    }
+    let w = e2.dot(&r) / det;
+    (0.0..=1.0).contains(&w)
+}
+
+/// Checks whether two triangles intersect
+///
+/// Two triangles which cross each other must have an edge of one passing
+/// through the other, so we check all six edges.  Coplanar overlaps are not
+/// detected.
+fn triangles_intersect(a: &[Vector3<f32>; 3], b: &[Vector3<f32>; 3]) -> bool {
+    let edges_hit = |s: &[Vector3<f32>; 3], t| {
+        (0..3).any(|i| segment_hits_triangle(s[i], s[(i + 1) % 3], t))
+    };
+    edges_hit(a, b) || edges_hit(b, a)
+}
+
+impl Mesh {
+    /// Checks whether any two triangles in the mesh intersect
+    ///
+    /// Triangles which share a vertex are adjacent, and are not tested against
+    /// each other; every other pair with overlapping bounding boxes (found
+    /// with a bounding volume hierarchy) is checked for a crossing.  Coplanar
+    /// overlaps are not detected.
+    ///
+    /// This is expensive, so it's not run when building a mesh; call it before
+    /// sending a mesh to tools which require it to be free of
+    /// self-intersections.
+    pub fn has_self_intersections(&self) -> bool {
+        let tris: Vec<_> =
+            self.triangles.iter().map(|t| self.corners(t)).collect();
+        let boxes: Vec<_> = tris.iter().map(Aabb::of).collect();
+        let bvh = Bvh::new(&boxes);
+        self.triangles.iter().enumerate().any(|(i, ti)| {
+            bvh.any_overlapping(&boxes[i], |j| {
+                let tj = &self.triangles[j];
+                j > i
+                    && !ti.iter().any(|v| tj.iter().any(|u| u == v))
+                    && triangles_intersect(&tris[i], &tris[j])
+            })
+        })
+    }
+}
@@ -13,6 +13,12 @@
 //! However, they may contain self-intersections, and are not guaranteed to
 //! catch thin features (below the sampling grid resolution).
 //!
+//! Octrees may be adaptive, with leaf cells at different depths (see
+//! [`Settings::max_depth`]).  Because the mesh is built by walking the dual
+//! of the octree, triangles connect the vertices of neighboring cells
+//! regardless of their sizes, so there are no T-junctions or cracks where a
+//! fine cell meets a coarse one, and no separate crack-patching is required.
+//!
 //! The resulting [`Mesh`] objects can be written out as STL files.
 //!
 //! Here's a full example:
@@ -43,13 +49,17 @@
 //! ```
 
 use crate::shape::Bounds;
+use std::collections::BTreeMap;
 
+mod ao;
+mod auto;
 mod builder;
 mod cell;
 mod dc;
 mod fixup;
 mod frame;
 mod gen;
+mod intersect;
 mod mt;
 mod octree;
 mod output;
@@ -61,6 +71,8 @@ pub mod types;
 // Re-export the main Octree type as public
 pub use octree::Octree;
 
+pub use auto::{auto, Mesher};
+
 ////////////////////////////////////////////////////////////////////////////////
 
 /// An indexed 3D mesh
@@ -70,6 +82,10 @@ pub struct Mesh {
     pub triangles: Vec<nalgebra::Vector3<usize>>,
     /// Vertex positions
     pub vertices: Vec<nalgebra::Vector3<f32>>,
+    /// Texture coordinates for each vertex, if they have been generated
+    ///
+    /// See [`Mesh::generate_uvs_triplanar`]
+    pub uvs: Option<Vec<[f32; 2]>>,
 }
 
 impl Mesh {
@@ -77,6 +93,220 @@ impl Mesh {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Returns the three corners of the given triangle
+    fn corners(
+        &self,
+        t: &nalgebra::Vector3<usize>,
+    ) -> [nalgebra::Vector3<f32>; 3] {
+        t.map(|i| self.vertices[i]).into()
+    }
+
+    /// Returns the (unnormalized) area-weighted normal at each vertex
+    fn vertex_normals(&self) -> Vec<nalgebra::Vector3<f32>> {
+        let mut normals = vec![nalgebra::Vector3::zeros(); self.vertices.len()];
+        for t in &self.triangles {
+            let [a, b, c] = self.corners(t);
+            let n = (b - a).cross(&(c - a));
+            for &i in t.iter() {
+                normals[i] += n;
+            }
+        }
+        normals
+    }
+
+    /// Computes the volume enclosed by the mesh
+    ///
+    /// This is the sum of signed volumes of tetrahedra formed by each triangle
+    /// and the origin, so it's only meaningful for closed meshes.  Triangles
+    /// are wound counter-clockwise when seen from outside the model, so the
+    /// volume is positive.
+    pub fn volume(&self) -> f32 {
+        self.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = self.corners(t);
+                a.dot(&b.cross(&c))
+            })
+            .sum::<f32>()
+            / 6.0
+    }
+
+    /// Computes the total area of the mesh's triangles
+    ///
+    /// Meshes of curved surfaces are slightly faceted, so this tends to
+    /// overestimate their true area by a few percent (regardless of
+    /// resolution).
+    pub fn surface_area(&self) -> f32 {
+        self.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = self.corners(t);
+                (b - a).cross(&(c - a)).norm()
+            })
+            .sum::<f32>()
+            / 2.0
+    }
+
+    /// Splits the mesh into connected components
+    ///
+    /// Triangles are connected if they share a vertex; each component is
+    /// returned as a separate mesh, containing only the vertices that its
+    /// triangles use.  Components are ordered by their first triangle in
+    /// [`self.triangles`](Self::triangles), and preserve triangle order.
+    pub fn connected_components(&self) -> Vec<Mesh> {
+        // Union-find over vertices, with path halving
+        let mut parent: Vec<usize> = (0..self.vertices.len()).collect();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        for t in &self.triangles {
+            let a = find(&mut parent, t[0]);
+            for &v in &[t[1], t[2]] {
+                let b = find(&mut parent, v);
+                parent[b] = a;
+            }
+        }
+
+        let mut out: Vec<Mesh> = vec![];
+        let mut component = vec![usize::MAX; self.vertices.len()];
+        let mut remap = vec![usize::MAX; self.vertices.len()];
+        for t in &self.triangles {
+            let root = find(&mut parent, t[0]);
+            if component[root] == usize::MAX {
+                component[root] = out.len();
+                out.push(Mesh::new());
+            }
+            let mesh = &mut out[component[root]];
+            let t = t.map(|v| {
+                if remap[v] == usize::MAX {
+                    remap[v] = mesh.vertices.len();
+                    mesh.vertices.push(self.vertices[v]);
+                }
+                remap[v]
+            });
+            mesh.triangles.push(t);
+        }
+        out
+    }
+
+    /// Generates texture coordinates by triplanar projection
+    ///
+    /// Each vertex is projected onto the axis-aligned plane which is most
+    /// perpendicular to its normal (the area-weighted average of its
+    /// triangles' normals): vertices facing ±X get `(y, z)`, vertices facing
+    /// ±Y get `(x, z)`, and vertices facing ±Z get `(x, y)`, multiplied by
+    /// `scale`.  The coordinates are stored in [`self.uvs`](Self::uvs), and a
+    /// reference is returned.
+    ///
+    /// Vertices which aren't used by any triangle have no normal, and are
+    /// projected onto the XY plane.  Texture coordinates are discontinuous
+    /// where the dominant axis changes, which is usually hidden by blending
+    /// the three projections in a shader.
+    pub fn generate_uvs_triplanar(&mut self, scale: f32) -> &[[f32; 2]] {
+        let uvs = self
+            .vertices
+            .iter()
+            .zip(self.vertex_normals())
+            .map(|(p, n)| {
+                let n = n.abs();
+                let [u, v] = if n.x > n.y && n.x > n.z {
+                    [p.y, p.z]
+                } else if n.y > n.z {
+                    [p.x, p.z]
+                } else {
+                    [p.x, p.y]
+                };
+                [u * scale, v * scale]
+            })
+            .collect();
+        self.uvs.insert(uvs)
+    }
+
+    /// Checks whether the mesh is closed and manifold
+    ///
+    /// Each undirected edge is classified by the number of triangles which use
+    /// it; see [`MeshReport`] for details.
+    pub fn validate(&self) -> MeshReport {
+        let mut edges: BTreeMap<[usize; 2], usize> = BTreeMap::new();
+        let mut used = vec![false; self.vertices.len()];
+        for t in &self.triangles {
+            for i in 0..3 {
+                let (a, b) = (t[i], t[(i + 1) % 3]);
+                *edges.entry([a.min(b), a.max(b)]).or_default() += 1;
+                used[a] = true;
+            }
+        }
+        let vertices = used.iter().filter(|u| **u).count();
+        MeshReport {
+            boundary_edges: edges.values().filter(|c| **c == 1).count(),
+            non_manifold_edges: edges.values().filter(|c| **c > 2).count(),
+            euler_characteristic: vertices as i64 - edges.len() as i64
+                + self.triangles.len() as i64,
+        }
+    }
+
+    /// Finds sharp edges, e.g. for drawing a wireframe overlay
+    ///
+    /// An edge is sharp if the angle between the normals of the two triangles
+    /// which share it is greater than `angle_threshold` (in radians), so a
+    /// threshold of 0 returns every non-flat edge.  Edges which don't have
+    /// exactly two triangles (i.e. boundaries of an open mesh, or non-manifold
+    /// edges) are always returned.
+    ///
+    /// Each edge is returned as a pair of indexes into
+    /// [`self.vertices`](Self::vertices), with the smaller index first; edges
+    /// are sorted and unique.
+    pub fn feature_edges(&self, angle_threshold: f32) -> Vec<[u32; 2]> {
+        let mut edges: BTreeMap<[usize; 2], Vec<nalgebra::Vector3<f32>>> =
+            BTreeMap::new();
+        for t in &self.triangles {
+            let [a, b, c] = self.corners(t);
+            let n = (b - a).cross(&(c - a));
+            for i in 0..3 {
+                let (a, b) = (t[i], t[(i + 1) % 3]);
+                edges.entry([a.min(b), a.max(b)]).or_default().push(n);
+            }
+        }
+        edges
+            .into_iter()
+            .filter(|(_, normals)| match normals.as_slice() {
+                // The normals aren't normalized, so use atan2 to find the
+                // angle between them (which is 0 for degenerate triangles)
+                [a, b] => a.cross(b).norm().atan2(a.dot(b)) > angle_threshold,
+                _ => true,
+            })
+            .map(|([a, b], _)| [a as u32, b as u32])
+            .collect()
+    }
+}
+
+/// Summary of a mesh's topology, from [`Mesh::validate`]
+///
+/// A closed, manifold mesh (e.g. for 3D printing) has no boundary or
+/// non-manifold edges; its Euler characteristic is then `2 - 2g` for a single
+/// surface with `g` holes through it (so 2 for a sphere).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MeshReport {
+    /// Number of edges which are used by only one triangle
+    pub boundary_edges: usize,
+    /// Number of edges which are used by more than two triangles
+    pub non_manifold_edges: usize,
+    /// Euler characteristic (`V - E + F`)
+    ///
+    /// Only vertices which are used by a triangle are counted.
+    pub euler_characteristic: i64,
+}
+
+impl MeshReport {
+    /// Checks whether every edge is shared by exactly two triangles
+    pub fn is_watertight(&self) -> bool {
+        self.boundary_edges == 0 && self.non_manifold_edges == 0
+    }
 }
 
 /// Settings when building an octree and mesh
@@ -102,6 +332,37 @@ pub struct Settings {
 
     /// Bounds for meshing
     pub bounds: Bounds<3>,
+
+    /// Maximum number of cells in the octree
+    ///
+    /// When a limit is set, the octree is built one level at a time (on a
+    /// single thread, regardless of [`Settings::threads`]).  If subdividing
+    /// every ambiguous cell at a given level would exceed the limit, they're
+    /// all treated as leaves instead, and the resulting octree is marked as
+    /// truncated (see [`Octree::is_truncated`]).  `None` means there is no
+    /// limit.
+    ///
+    /// The octree always contains its 8 root cells, so limits below 8 can't be
+    /// met; in that case, the root isn't subdivided at all.
+    pub max_cells: Option<usize>,
+
+    /// Grid step for snapping vertices
+    ///
+    /// If this is set, each vertex is rounded to the nearest multiple of the
+    /// step (along each axis) after it has been positioned within its leaf
+    /// cell.  This makes output meshes easier to compare across runs and
+    /// implementations, at the cost of up to half a step of error per axis.
+    pub vertex_quantization: Option<f32>,
+
+    /// Strength of the bias pulling each vertex toward its cell's center
+    ///
+    /// Vertices are positioned by solving a quadratic error function (QEF);
+    /// when the surface is nearly flat, the unconstrained solution can land
+    /// far outside of the cell.  A positive value adds `λ·|v - center|²` to
+    /// the QEF, trading sharp features for stability.  A value of 1 is
+    /// comparable to the weight of a single surface intersection; the default
+    /// of 0 disables the bias.
+    pub qef_bias: f32,
 }
 
 impl Default for Settings {
@@ -111,6 +372,9 @@ impl Default for Settings {
             min_depth: 3,
             max_depth: 3,
             bounds: Default::default(),
+            max_cells: None,
+            vertex_quantization: None,
+            qef_bias: 0.0,
         }
     }
 }
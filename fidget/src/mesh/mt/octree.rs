@@ -132,9 +132,9 @@ impl<S: Shape> OctreeWorker<S> {
             .map(|(thread_index, (queue, done))| OctreeWorker {
                 thread_index,
                 octree: if thread_index == 0 {
-                    OctreeBuilder::new()
+                    OctreeBuilder::new(&settings)
                 } else {
-                    OctreeBuilder::empty()
+                    OctreeBuilder::empty(&settings)
                 },
                 queue,
                 done,
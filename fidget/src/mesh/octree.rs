@@ -1,8 +1,8 @@
 //! An octree data structure and implementation of Manifold Dual Contouring
 
 use super::{
-    builder::MeshBuilder,
-    cell::{Cell, CellData, CellIndex, CellVertex, Leaf},
+    builder::{MeshBuilder, PolygonBuilder},
+    cell::{Cell, CellBounds, CellData, CellIndex, CellVertex, Leaf},
     dc::DcBuilder,
     fixup::DcFixup,
     frame::Frame,
@@ -10,10 +10,10 @@ use super::{
     mt::{DcWorker, OctreeWorker},
     qef::QuadraticErrorSolver,
     types::{Axis, Corner, Edge, EdgeMask, Face, FaceMask},
-    Mesh, Settings,
+    Mesh, MeshReport, Settings,
 };
 use crate::eval::{BulkEvaluator, Shape, Tape, TracingEvaluator};
-use std::{num::NonZeroUsize, sync::Arc, sync::OnceLock};
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, sync::OnceLock};
 
 /// Helper struct to contain a set of matched evaluators
 ///
@@ -80,11 +80,60 @@ pub struct Octree {
     /// This is indexed by cell leaf index; the exact shape depends heavily on
     /// the number of intersections and vertices within each leaf.
     pub(crate) verts: Vec<CellVertex>,
+
+    /// Marks whether subdivision was stopped early by
+    /// [`Settings::max_cells`](super::Settings::max_cells)
+    pub(crate) truncated: bool,
+
+    /// Size of one unit of the octree's `[-1, +1]` cube in model space
+    ///
+    /// This is `settings.bounds.size` for a built octree.
+    pub(crate) scale: f32,
+}
+
+/// Limit on the number of cells allocated during octree construction
+///
+/// Cells are reserved in blocks of 8 (one block per subdivision); once a
+/// reservation fails, the budget is marked as exhausted.
+struct CellBudget {
+    limit: Option<usize>,
+    used: usize,
+    exhausted: bool,
+}
+
+impl CellBudget {
+    /// Builds a new budget, accounting for the 8 root cells
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            used: 8,
+            exhausted: false,
+        }
+    }
+
+    /// Tries to reserve `blocks` blocks of 8 cells
+    ///
+    /// Returns `false` (reserving nothing) if they won't fit.
+    fn reserve(&mut self, blocks: usize) -> bool {
+        let used = self.used + blocks * 8;
+        if self.limit.is_some_and(|limit| used > limit) {
+            self.exhausted = true;
+            false
+        } else {
+            self.used = used;
+            true
+        }
+    }
 }
 
 impl Octree {
     /// Merges a set of octrees constructed across multiple workers
     ///
+    /// During construction, [`Cell::Branch`] references are tagged with the
+    /// worker thread which owns the target cells.  Merging rewrites every
+    /// branch into a single canonical index space and clears its `thread`
+    /// field, so the resulting octree has no per-thread references.
+    ///
     /// # Panics
     /// All cross-octree references must be valid
     pub(crate) fn merge(os: &[Octree]) -> Octree {
@@ -102,6 +151,8 @@ impl Octree {
         let mut out = Octree {
             cells: Vec::with_capacity(*cell_offsets.last().unwrap()),
             verts: Vec::with_capacity(*vert_offsets.last().unwrap()),
+            truncated: os.iter().any(|o| o.truncated),
+            scale: 1.0,
         };
 
         for (t, o) in os.iter().enumerate() {
@@ -127,38 +178,98 @@ impl Octree {
     /// Builds an octree to the given depth
     ///
     /// The shape is evaluated on the region specified by `settings.bounds`.
+    /// If `settings.vertex_quantization` is set, vertices are snapped to the
+    /// grid in model space.
     pub fn build<S: Shape + Clone>(shape: &S, settings: Settings) -> Self {
+        Self::build_from(shape, settings, None)
+    }
+
+    /// Refines an existing octree by subdividing it further
+    ///
+    /// `self` must have been built from `shape` with the same `settings`; the
+    /// result matches the octree built with `min_depth` and `max_depth`
+    /// increased by `extra_depth`.
+    ///
+    /// Branches in `self` are known to contain the surface, so they're
+    /// subdivided without re-evaluating them; only the remaining cells are
+    /// evaluated and built to the new depth.  Construction is single-threaded,
+    /// regardless of `settings.threads`.  If `settings.max_cells` is set, the
+    /// octree is rebuilt from scratch instead (see
+    /// [`Settings::max_cells`](super::Settings::max_cells)).
+    pub fn refine<S: Shape + Clone>(
+        &self,
+        extra_depth: u8,
+        shape: &S,
+        mut settings: Settings,
+    ) -> Self {
+        settings.min_depth += extra_depth;
+        settings.max_depth += extra_depth;
+        Self::build_from(shape, settings, Some(self))
+    }
+
+    /// Builds an octree, optionally following the branches of a prior octree
+    fn build_from<S: Shape + Clone>(
+        shape: &S,
+        settings: Settings,
+        prior: Option<&Octree>,
+    ) -> Self {
         // Transform the shape given our bounds
         let t = settings.bounds.transform();
-        if t == nalgebra::Transform::identity() {
-            Self::build_inner(shape, settings)
+        let mut out = if t == nalgebra::Transform::identity() {
+            Self::build_inner(shape, settings, prior)
         } else {
             let shape = shape.clone().apply_transform(t.into());
-            let mut out = Self::build_inner(&shape, settings);
+            let mut out = Self::build_inner(&shape, settings, prior);
 
-            // Apply the transform from [-1, +1] back to model space
+            // Apply the transform from [-1, +1] back to model space.  Normals
+            // are already model-space gradients, because the transformed
+            // evaluator seeds its partial derivatives after the transform.
             for v in &mut out.verts {
                 let p: nalgebra::Point3<f32> = v.pos.into();
                 let q = t.transform_point(&p);
                 v.pos = q.coords;
             }
+            out.scale = settings.bounds.size;
             out
+        };
+        if let Some(step) = settings.vertex_quantization {
+            for v in &mut out.verts {
+                v.pos = v.pos.map(|p| (p / step).round() * step);
+            }
         }
+        out
     }
 
-    fn build_inner<S: Shape + Clone>(shape: &S, settings: Settings) -> Self {
+    fn build_inner<S: Shape + Clone>(
+        shape: &S,
+        settings: Settings,
+        prior: Option<&Octree>,
+    ) -> Self {
         let eval = Arc::new(EvalGroup::new(shape.clone()));
 
-        let mut octree = if settings.threads == 0 {
-            let mut out = OctreeBuilder::new();
+        let mut budget = CellBudget::new(settings.max_cells);
+        let mut octree: Octree = if settings.max_cells.is_some() {
+            // Mixed-depth leaves are only safe to mesh if they were produced by
+            // collapsing cells, so a limited octree is built one level at a
+            // time (on a single thread), stopping at a uniform depth.
+            let mut out = OctreeBuilder::new(&settings);
+            out.recurse_limited(&eval, settings, &mut budget);
+            out.into()
+        } else if let Some(prior) = prior {
+            let mut out = OctreeBuilder::new(&settings);
+            out.recurse_from(&eval, CellIndex::default(), prior, 0, settings);
+            out.into()
+        } else if settings.threads == 0 {
+            let mut out = OctreeBuilder::new(&settings);
             out.recurse(&eval, CellIndex::default(), settings);
             out.into()
         } else {
             OctreeWorker::scheduler(eval.clone(), settings)
         };
+        octree.truncated = budget.exhausted;
 
         // If we can't refine any further, then return right away
-        if settings.min_depth == settings.max_depth {
+        if settings.min_depth == settings.max_depth || budget.exhausted {
             return octree;
         }
 
@@ -169,6 +280,11 @@ impl Octree {
             if num_fix == 0 {
                 break;
             }
+            // Refinement is all-or-nothing, to avoid partially-fixed octrees
+            if !budget.reserve(num_fix) {
+                octree.truncated = true;
+                break;
+            }
             // Translate from an Octree back to an OctreeBuilder; specifically,
             // the index field in a Cell::Leaf points into the `leafs` array,
             // rather than the `verts` array.
@@ -194,6 +310,8 @@ impl Octree {
                 o: Octree {
                     cells,
                     verts: octree.verts,
+                    truncated: false,
+                    scale: 1.0,
                 },
                 leafs,
                 hermite: vec![LeafHermiteData::default()],
@@ -204,6 +322,7 @@ impl Octree {
                 tape_storage: vec![],
                 shape_storage: vec![],
                 workspace: Default::default(),
+                qef_bias: settings.qef_bias,
             };
             b.refine(&eval, CellIndex::default(), &fixup.needs_fixing);
             octree = b.into();
@@ -211,6 +330,113 @@ impl Octree {
         octree
     }
 
+    /// Checks whether subdivision was stopped early
+    ///
+    /// This happens when the octree would otherwise exceed
+    /// [`Settings::max_cells`](super::Settings::max_cells).  In that case, the
+    /// octree stops at a shallower (uniform) depth and may skip refinement, so
+    /// the resulting mesh is still valid but coarser than requested.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns points on the surface of the model, with their normals
+    ///
+    /// These are the edge intersections found while building the octree, so
+    /// they're positioned precisely on the surface (unlike mesh vertices,
+    /// which are placed by the QEF solver).  Normals are normalized
+    /// gradients.
+    ///
+    /// Neighboring cells find the same intersection on shared edges, so points
+    /// within a small tolerance (relative to the size of the point cloud) are
+    /// deduplicated, keeping the first.
+    pub fn surface_points(&self) -> Vec<([f32; 3], [f32; 3])> {
+        let mut points: Vec<CellVertex> = vec![];
+        for Leaf { mask, index } in self.leafs() {
+            // Leaf vertices are followed by their edge intersections
+            let vs = &CELL_TO_VERT_TO_EDGES[mask as usize];
+            let start = index + vs.len();
+            let count: usize = vs.iter().map(|v| v.len()).sum();
+            points.extend(&self.verts[start..start + count]);
+        }
+
+        let (lo, hi) = points.iter().fold(
+            (
+                nalgebra::Vector3::repeat(f32::INFINITY),
+                nalgebra::Vector3::repeat(f32::NEG_INFINITY),
+            ),
+            |(lo, hi), v| (lo.inf(&v.pos), hi.sup(&v.pos)),
+        );
+        let tol = ((hi - lo).norm() * 1e-5).max(f32::MIN_POSITIVE);
+
+        // Bucket points into a grid with cells of size `tol`, so that we only
+        // have to check neighboring buckets for duplicates
+        let mut buckets: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut out: Vec<([f32; 3], [f32; 3])> = vec![];
+        for v in points {
+            let key = v.pos.map(|p| (p / tol).floor() as i64);
+            let mut dupe = false;
+            'outer: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let k = [key.x + dx, key.y + dy, key.z + dz];
+                        let Some(b) = buckets.get(&k) else {
+                            continue;
+                        };
+                        if b.iter().any(|&j| {
+                            let p: nalgebra::Vector3<f32> = out[j].0.into();
+                            (p - v.pos).norm() <= tol
+                        }) {
+                            dupe = true;
+                            break 'outer;
+                        }
+                    }
+                }
+            }
+            if !dupe {
+                buckets.entry(key.into()).or_default().push(out.len());
+                let n = v.normal.try_normalize(0.0).unwrap_or(v.normal);
+                out.push((v.pos.into(), n.into()));
+            }
+        }
+        out
+    }
+
+    /// Moves every leaf vertex to the mass point of its edge intersections
+    ///
+    /// This replaces the QEF solution, so the resulting mesh is smooth and
+    /// does not preserve sharp features (equivalent to Surface Nets).
+    pub(crate) fn use_mass_points(&mut self) {
+        for Leaf { mask, index } in self.leafs() {
+            let vs = &CELL_TO_VERT_TO_EDGES[mask as usize];
+            let mut start = index + vs.len();
+            for (i, edges) in vs.iter().enumerate() {
+                let sum: nalgebra::Vector3<f32> = self.verts
+                    [start..start + edges.len()]
+                    .iter()
+                    .map(|v| v.pos)
+                    .sum();
+                self.verts[index + i].pos = sum / edges.len() as f32;
+                start += edges.len();
+            }
+        }
+    }
+
+    /// Returns every leaf cell that's reachable from the root
+    fn leafs(&self) -> Vec<Leaf> {
+        let mut out = vec![];
+        let mut todo = vec![CellIndex::default().index];
+        while let Some(i) = todo.pop() {
+            match self.cells[i].into() {
+                Cell::Branch { index, .. } => todo.extend(index..index + 8),
+                Cell::Leaf(leaf) => out.push(leaf),
+                Cell::Empty | Cell::Full => (),
+                Cell::Invalid => panic!("invalid cell in octree"),
+            }
+        }
+        out
+    }
+
     /// Recursively walks the dual of the octree, building a mesh
     pub fn walk_dual(&self, settings: Settings) -> Mesh {
         let mut mesh = MeshBuilder::default();
@@ -223,6 +449,297 @@ impl Octree {
         }
     }
 
+    /// Computes the volume enclosed by this octree's surface
+    ///
+    /// This is computed from the mesh returned by [`Octree::walk_dual`]; see
+    /// [`Mesh::volume`] for details.
+    pub fn volume(&self) -> f32 {
+        self.mesh().volume()
+    }
+
+    /// Checks whether this octree's mesh is closed and manifold
+    ///
+    /// This is computed from the mesh returned by [`Octree::walk_dual`]; see
+    /// [`Mesh::validate`] for details.
+    pub fn validate(&self) -> MeshReport {
+        self.mesh().validate()
+    }
+
+    /// Computes the surface area of this octree's mesh
+    ///
+    /// This is computed from the mesh returned by [`Octree::walk_dual`]; see
+    /// [`Mesh::surface_area`] for details.
+    pub fn surface_area(&self) -> f32 {
+        self.mesh().surface_area()
+    }
+
+    /// Builds a mesh of the isosurface at `f = level`, reusing this octree
+    ///
+    /// The octree only stores the surface at `f = 0`, as edge intersections
+    /// with their gradients; each intersection is moved to the offset surface
+    /// with a first-order step along its gradient (`p + level * g / |g|²`),
+    /// and each leaf vertex is moved by the mean step of its intersections.
+    /// Cell topology is reused as-is, so this is only accurate for levels
+    /// which are small relative to the octree's cell size; for larger
+    /// offsets, build a new octree from the offset shape.
+    ///
+    /// With a `level` of 0, this returns the same mesh as
+    /// [`Octree::walk_dual`] (built on a single thread).
+    ///
+    /// Returns [`Error::ContourTooFar`](crate::Error::ContourTooFar) if any intersection would move by more
+    /// than the size of its leaf cell, since the offset surface's topology may
+    /// differ from the octree's at that point.
+    pub fn contour_at(&self, level: f32) -> Result<Mesh, crate::Error> {
+        let mut out = Octree {
+            cells: self.cells.clone(),
+            verts: self.verts.clone(),
+            truncated: self.truncated,
+            scale: self.scale,
+        };
+        let mut todo = vec![CellIndex::default()];
+        while let Some(cell) = todo.pop() {
+            let (mask, index) = match self.cells[cell.index].into() {
+                Cell::Branch { index, .. } => {
+                    todo.extend(Corner::iter().map(|i| cell.child(index, i)));
+                    continue;
+                }
+                Cell::Leaf(Leaf { mask, index }) => (mask, index),
+                Cell::Empty | Cell::Full => continue,
+                Cell::Invalid => panic!("invalid cell in octree"),
+            };
+            let size = cell.bounds.x.width() * self.scale;
+            let vs = &CELL_TO_VERT_TO_EDGES[mask as usize];
+            let mut start = index + vs.len();
+            for (i, edges) in vs.iter().enumerate() {
+                let mut sum = nalgebra::Vector3::zeros();
+                for v in &mut out.verts[start..start + edges.len()] {
+                    let g2 = v.normal.norm_squared();
+                    if g2 > 0.0 {
+                        let step = v.normal * (level / g2);
+                        if step.norm() > size {
+                            return Err(crate::Error::ContourTooFar(level));
+                        }
+                        v.pos += step;
+                        sum += step;
+                    }
+                }
+                out.verts[index + i].pos += sum / edges.len() as f32;
+                start += edges.len();
+            }
+        }
+        Ok(out.mesh())
+    }
+
+    /// Converts the octree into a dense occupancy grid
+    ///
+    /// The grid has `resolution` voxels along each axis, covering the region
+    /// on which the octree was built (`settings.bounds` in
+    /// [`Octree::build`]).  Values are returned in `x`-major order, i.e. the
+    /// voxel at `(i, j, k)` is at index `i + j * n + k * n * n`.
+    ///
+    /// Each voxel is sampled at a 3×3×3 grid of points, and is marked as
+    /// occupied if the majority of them are inside the model.  Points are
+    /// classified by descending the cell tree; within a leaf cell, the signs
+    /// of the cell's corners are blended with trilinear weights.
+    pub fn rasterize(&self, resolution: usize) -> Vec<bool> {
+        const SUBSAMPLES: usize = 3;
+        let n = resolution;
+        let mut out = Vec::with_capacity(n * n * n);
+        for k in 0..n {
+            for j in 0..n {
+                for i in 0..n {
+                    let mut count = 0;
+                    for s in 0..SUBSAMPLES.pow(3) {
+                        let sub = [
+                            s % SUBSAMPLES,
+                            s / SUBSAMPLES % SUBSAMPLES,
+                            s / SUBSAMPLES.pow(2),
+                        ];
+                        let p = [(i, 0), (j, 1), (k, 2)].map(|(v, a)| {
+                            let t = (v * SUBSAMPLES + sub[a]) as f32 + 0.5;
+                            t / (n * SUBSAMPLES) as f32 * 2.0 - 1.0
+                        });
+                        count += self.is_inside(p) as usize;
+                    }
+                    out.push(count * 2 > SUBSAMPLES.pow(3));
+                }
+            }
+        }
+        out
+    }
+
+    /// Checks whether a point (in the octree's `[-1, 1]` space) is inside
+    fn is_inside(&self, p: [f32; 3]) -> bool {
+        let mut cell = CellIndex::default();
+        loop {
+            match self.cells[cell.index].into() {
+                Cell::Branch { index, .. } => {
+                    let mut c = 0;
+                    for (axis, bounds) in
+                        [cell.bounds.x, cell.bounds.y, cell.bounds.z]
+                            .iter()
+                            .enumerate()
+                    {
+                        if p[axis] >= bounds.midpoint() {
+                            c |= 1 << axis;
+                        }
+                    }
+                    cell = cell.child(index, Corner::new(c));
+                }
+                Cell::Leaf(Leaf { mask, .. }) => {
+                    let b = cell.bounds;
+                    let t = [
+                        (p[0] - b.x.lower()) / b.x.width(),
+                        (p[1] - b.y.lower()) / b.y.width(),
+                        (p[2] - b.z.lower()) / b.z.width(),
+                    ];
+                    let mut sum = 0.0;
+                    for c in Corner::iter() {
+                        let w: f32 = (0..3)
+                            .map(|a| {
+                                if c.index() & (1 << a) != 0 {
+                                    t[a]
+                                } else {
+                                    1.0 - t[a]
+                                }
+                            })
+                            .product();
+                        let filled = mask & (1 << c.index()) != 0;
+                        sum += if filled { w } else { -w };
+                    }
+                    return sum > 0.0;
+                }
+                Cell::Empty => return false,
+                Cell::Full => return true,
+                Cell::Invalid => panic!("invalid cell in octree"),
+            }
+        }
+    }
+
+    /// Writes the cell tree as a GraphViz graph
+    ///
+    /// Each reachable cell is a node labeled with its type (`Empty`, `Full`,
+    /// `Leaf`, or `Branch`) and depth, with edges from each branch to its
+    /// eight children.  The output can be rendered with `dot`, e.g.
+    /// `dot -Tpng octree.dot > octree.png`.
+    pub fn to_dot<W: std::io::Write>(
+        &self,
+        mut w: W,
+    ) -> Result<(), crate::Error> {
+        writeln!(w, "digraph octree{{")?;
+        let mut todo = vec![(CellIndex::default().index, 0)];
+        while let Some((i, depth)) = todo.pop() {
+            let (label, color) = match self.cells[i].into() {
+                Cell::Branch { index, .. } => {
+                    for c in index..index + 8 {
+                        writeln!(w, "c{i} -> c{c}")?;
+                        todo.push((c, depth + 1));
+                    }
+                    ("Branch", "black")
+                }
+                Cell::Leaf(..) => ("Leaf", "green"),
+                Cell::Empty => ("Empty", "gray"),
+                Cell::Full => ("Full", "blue"),
+                Cell::Invalid => panic!("invalid cell in octree"),
+            };
+            writeln!(
+                w,
+                r#"c{i} [label = "{label}\ndepth {depth}" color="{color}"]"#
+            )?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    /// Writes the surface as a PLY file, with per-vertex normals
+    ///
+    /// The output is ASCII if `binary` is false, and little-endian binary
+    /// otherwise.  Each vertex has `x`, `y`, `z` positions and `nx`, `ny`,
+    /// `nz` normals; the normal is the average of the gradients at the edge
+    /// intersections (Hermite data) which positioned the vertex.
+    ///
+    /// Faces follow the dual contouring topology, with one quad per surface
+    /// edge, falling back to triangles where cells of different sizes meet.
+    /// Unlike [`walk_dual`](Self::walk_dual), edge intersections aren't used as
+    /// extra vertices, so each quad is a single (possibly non-planar) face.
+    pub fn to_ply<W: std::io::Write>(
+        &self,
+        mut w: W,
+        binary: bool,
+    ) -> Result<(), crate::Error> {
+        let mut b = PolygonBuilder::default();
+        b.cell(self, CellIndex::default());
+        let normals = self.leaf_vertex_normals();
+
+        writeln!(w, "ply")?;
+        if binary {
+            writeln!(w, "format binary_little_endian 1.0")?;
+        } else {
+            writeln!(w, "format ascii 1.0")?;
+        }
+        writeln!(w, "comment PLY file exported by Fidget")?;
+        writeln!(w, "element vertex {}", b.vertices.len())?;
+        for p in ["x", "y", "z", "nx", "ny", "nz"] {
+            writeln!(w, "property float {p}")?;
+        }
+        writeln!(w, "element face {}", b.faces.len())?;
+        writeln!(w, "property list uchar uint vertex_indices")?;
+        writeln!(w, "end_header")?;
+
+        for &v in &b.vertices {
+            let p = self.verts[v].pos;
+            let n = normals[&v].try_normalize(0.0).unwrap_or_default();
+            if binary {
+                for f in p.iter().chain(n.iter()) {
+                    w.write_all(&f.to_le_bytes())?;
+                }
+            } else {
+                writeln!(w, "{} {} {} {} {} {}", p.x, p.y, p.z, n.x, n.y, n.z)?;
+            }
+        }
+        for f in &b.faces {
+            if binary {
+                w.write_all(&[f.len() as u8])?;
+                for i in f {
+                    w.write_all(&(*i as u32).to_le_bytes())?;
+                }
+            } else {
+                write!(w, "{}", f.len())?;
+                for i in f {
+                    write!(w, " {i}")?;
+                }
+                writeln!(w)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the summed normals of each leaf vertex's edge intersections,
+    /// keyed by the vertex's index in `self.verts`
+    fn leaf_vertex_normals(&self) -> HashMap<usize, nalgebra::Vector3<f32>> {
+        let mut out = HashMap::new();
+        for Leaf { mask, index } in self.leafs() {
+            let vs = &CELL_TO_VERT_TO_EDGES[mask as usize];
+            let mut start = index + vs.len();
+            for (i, edges) in vs.iter().enumerate() {
+                let sum = self.verts[start..start + edges.len()]
+                    .iter()
+                    .map(|v| v.normal)
+                    .sum();
+                out.insert(index + i, sum);
+                start += edges.len();
+            }
+        }
+        out
+    }
+
+    /// Builds a mesh on a single thread
+    fn mesh(&self) -> Mesh {
+        let mut mesh = MeshBuilder::default();
+        mesh.cell(self, CellIndex::default());
+        mesh.take()
+    }
+
     pub(crate) fn is_leaf(&self, cell: CellIndex) -> bool {
         match self[cell].into() {
             Cell::Leaf(..) | Cell::Full | Cell::Empty => true,
@@ -408,11 +925,16 @@ pub(crate) struct OctreeBuilder<S: Shape> {
     pub tape_storage: Vec<S::TapeStorage>,
     pub shape_storage: Vec<S::Storage>,
     workspace: S::Workspace,
+
+    /// Weight of the bias toward the cell center when solving QEFs
+    ///
+    /// See [`Settings::qef_bias`] for details.
+    qef_bias: f32,
 }
 
 impl<S: Shape> Default for OctreeBuilder<S> {
     fn default() -> Self {
-        Self::new()
+        Self::new(&Settings::default())
     }
 }
 
@@ -438,17 +960,21 @@ impl<S: Shape> From<OctreeBuilder<S>> for Octree {
         Self {
             cells,
             verts: o.o.verts,
+            truncated: false,
+            scale: 1.0,
         }
     }
 }
 
 impl<S: Shape> OctreeBuilder<S> {
     /// Builds a new octree, which allocates data for 8 root cells
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(settings: &Settings) -> Self {
         Self {
             o: Octree {
                 cells: vec![Cell::Invalid.into(); 8],
                 verts: vec![],
+                truncated: false,
+                scale: 1.0,
             },
             leafs: vec![],
             hermite: vec![LeafHermiteData::default()],
@@ -459,17 +985,20 @@ impl<S: Shape> OctreeBuilder<S> {
             tape_storage: vec![],
             shape_storage: vec![],
             workspace: Default::default(),
+            qef_bias: settings.qef_bias,
         }
     }
 
     /// Builds a new empty octree
     ///
     /// This still allocates data to reserve the lowest slot in `hermite`
-    pub(crate) fn empty() -> Self {
+    pub(crate) fn empty(settings: &Settings) -> Self {
         Self {
             o: Octree {
                 cells: vec![],
                 verts: vec![],
+                truncated: false,
+                scale: 1.0,
             },
             leafs: vec![],
             hermite: vec![LeafHermiteData::default()],
@@ -482,6 +1011,7 @@ impl<S: Shape> OctreeBuilder<S> {
             tape_storage: vec![],
             shape_storage: vec![],
             workspace: Default::default(),
+            qef_bias: settings.qef_bias,
         }
     }
 
@@ -552,11 +1082,7 @@ impl<S: Shape> OctreeBuilder<S> {
             };
             if cell.depth == settings.min_depth as usize {
                 let eval = sub_tape.unwrap_or_else(|| eval.clone());
-                let out = CellResult::Done(self.leaf(&eval, cell));
-                if let Ok(t) = Arc::try_unwrap(eval) {
-                    self.reclaim(t);
-                }
-                out
+                CellResult::Done(self.leaf_and_reclaim(eval, cell))
             } else {
                 CellResult::Recurse(sub_tape.unwrap_or_else(|| eval.clone()))
             }
@@ -583,7 +1109,10 @@ impl<S: Shape> OctreeBuilder<S> {
         debug_assert_eq!(edges.len(), 1);
         for e in edges[0] {
             let i = hermite.intersections[e.to_undirected().index()];
-            self.o.verts.push(CellVertex { pos: i.pos.xyz() });
+            self.o.verts.push(CellVertex {
+                pos: i.pos.xyz(),
+                normal: i.grad.xyz(),
+            });
         }
 
         let leaf_index = self.leafs.len();
@@ -598,6 +1127,20 @@ impl<S: Shape> OctreeBuilder<S> {
         })
     }
 
+    /// Evaluates the given cell as a leaf, then reclaims its evaluators if
+    /// they're no longer shared
+    fn leaf_and_reclaim(
+        &mut self,
+        eval: Arc<EvalGroup<S>>,
+        cell: CellIndex,
+    ) -> Cell {
+        let out = self.leaf(&eval, cell);
+        if let Ok(t) = Arc::try_unwrap(eval) {
+            self.reclaim(t);
+        }
+        out
+    }
+
     /// Recurse down the octree, building the given cell
     fn recurse(
         &mut self,
@@ -621,21 +1164,116 @@ impl<S: Shape> OctreeBuilder<S> {
                     self.reclaim(t);
                 }
 
-                let r = self.check_done(cell, index).unwrap();
+                self.finish_branch(cell, index);
+            }
+        }
+    }
+
+    /// Recurse down the octree, following the branches of a prior octree
+    ///
+    /// Branches in `prior` (above `settings.min_depth`) are subdivided without
+    /// evaluation, because their interval results are already known to be
+    /// ambiguous; every other cell is built with [`recurse`](Self::recurse).
+    fn recurse_from(
+        &mut self,
+        eval: &Arc<EvalGroup<S>>,
+        cell: CellIndex,
+        prior: &Octree,
+        prior_index: usize,
+        settings: Settings,
+    ) {
+        let prior_children = match prior.cells[prior_index].into() {
+            Cell::Branch { index, .. }
+                if cell.depth < settings.min_depth as usize =>
+            {
+                index
+            }
+            _ => {
+                self.recurse(eval, cell, settings);
+                return;
+            }
+        };
+
+        let index = self.o.cells.len();
+        for _ in Corner::iter() {
+            self.o.cells.push(Cell::Invalid.into());
+        }
+        for i in Corner::iter() {
+            self.recurse_from(
+                eval,
+                cell.child(index, i),
+                prior,
+                prior_children + i.index(),
+                settings,
+            );
+        }
+        self.finish_branch(cell, index);
+    }
 
-                self.o[cell] = match r {
-                    BranchResult::Empty => Cell::Empty,
-                    BranchResult::Full => Cell::Full,
-                    BranchResult::Branch(index) => {
-                        Cell::Branch { index, thread: 0 }
+    /// Builds the octree one level at a time, within the given cell budget
+    ///
+    /// Each level is subdivided all-or-nothing: if the budget can't fit every
+    /// ambiguous cell at a given depth, then they all become leafs, so the
+    /// octree is truncated at a uniform depth.
+    fn recurse_limited(
+        &mut self,
+        eval: &Arc<EvalGroup<S>>,
+        settings: Settings,
+        budget: &mut CellBudget,
+    ) {
+        let mut level = vec![(CellIndex::default(), eval.clone())];
+        let mut branches = vec![];
+        while !level.is_empty() {
+            let mut ambiguous = vec![];
+            for (cell, eval) in level {
+                match self.eval_cell(&eval, cell, settings) {
+                    CellResult::Done(c) => self.o[cell] = c.into(),
+                    CellResult::Recurse(sub_eval) => {
+                        ambiguous.push((cell, sub_eval))
                     }
-                    BranchResult::Leaf(pos, hermite) => {
-                        self.record_leaf(pos, hermite)
+                }
+                if let Ok(t) = Arc::try_unwrap(eval) {
+                    self.reclaim(t);
+                }
+            }
+
+            let mut next = vec![];
+            if budget.reserve(ambiguous.len()) {
+                for (cell, sub_eval) in ambiguous {
+                    let index = self.o.cells.len();
+                    for i in Corner::iter() {
+                        self.o.cells.push(Cell::Invalid.into());
+                        next.push((cell.child(index, i), sub_eval.clone()));
                     }
+                    branches.push((cell, index));
+                }
+            } else {
+                for (cell, sub_eval) in ambiguous {
+                    self.o[cell] = self.leaf_and_reclaim(sub_eval, cell).into();
                 }
-                .into();
             }
+            level = next;
+        }
+
+        // Collapse branches from the bottom up, now that all cells are built
+        for (cell, index) in branches.into_iter().rev() {
+            self.finish_branch(cell, index);
+        }
+    }
+
+    /// Records a branch cell once its 8 children (at `index`) are built
+    ///
+    /// The branch is collapsed into a single cell if possible.
+    fn finish_branch(&mut self, cell: CellIndex, index: usize) {
+        let r = self.check_done(cell, index).unwrap();
+
+        self.o[cell] = match r {
+            BranchResult::Empty => Cell::Empty,
+            BranchResult::Full => Cell::Full,
+            BranchResult::Branch(index) => Cell::Branch { index, thread: 0 },
+            BranchResult::Leaf(pos, hermite) => self.record_leaf(pos, hermite),
         }
+        .into();
     }
 
     /// Evaluates the given leaf
@@ -838,6 +1476,7 @@ impl<S: Shape> OctreeBuilder<S> {
             .unwrap();
 
         let mut verts: arrayvec::ArrayVec<_, 4> = arrayvec::ArrayVec::new();
+        let mut normals: arrayvec::ArrayVec<_, 12> = arrayvec::ArrayVec::new();
         let mut i = 0;
         let mut hermite_cell = LeafHermiteData::new();
         hermite_cell.mask = mask;
@@ -848,6 +1487,7 @@ impl<S: Shape> OctreeBuilder<S> {
                 let grad: nalgebra::Vector4<f32> = grads[i].into();
 
                 qef.add_intersection(pos, grad);
+                normals.push(grad.xyz());
 
                 // Record this intersection in the Hermite data for the leaf
                 let edge_index = e.to_undirected().index();
@@ -858,6 +1498,9 @@ impl<S: Shape> OctreeBuilder<S> {
 
                 i += 1;
             }
+            if self.qef_bias > 0.0 {
+                qef.add_center_bias(cell.bounds.center(), self.qef_bias);
+            }
             let (pos, err) = qef.solve();
             verts.push(pos);
 
@@ -870,11 +1513,14 @@ impl<S: Shape> OctreeBuilder<S> {
         // TODO: use self.record_leaf here?
         let vert_index = self.o.verts.len();
         self.o.verts.extend(verts);
-        self.o.verts.extend(
-            intersections
-                .into_iter()
-                .map(|pos| CellVertex { pos: cell.pos(pos) }),
-        );
+        self.o
+            .verts
+            .extend(intersections.into_iter().zip(normals).map(
+                |(pos, normal)| CellVertex {
+                    pos: cell.pos(pos),
+                    normal,
+                },
+            ));
 
         let hermite_index = self.push_hermite(hermite_cell);
         debug_assert!(hermite_index > 0);
@@ -958,7 +1604,7 @@ impl<S: Shape> OctreeBuilder<S> {
             //   not be marked as collapsible.
             debug_assert!(hermite.mask != 0);
             debug_assert!(hermite.mask != 255);
-            let (pos, new_err) = hermite.solve();
+            let (pos, new_err) = hermite.solve(&cell.bounds, self.qef_bias);
             if new_err < hermite.qef_err * 2.0 && cell.bounds.contains(pos) {
                 hermite.qef_err = new_err;
                 BranchResult::Leaf(pos, hermite)
@@ -1344,8 +1990,8 @@ impl LeafHermiteData {
         out
     }
 
-    /// Solves the combined QEF
-    pub fn solve(&self) -> (CellVertex, f32) {
+    /// Solves the combined QEF, with an optional bias toward the cell center
+    pub fn solve(&self, bounds: &CellBounds, bias: f32) -> (CellVertex, f32) {
         let mut qef = self.center_qef;
         for &i in &self.intersections {
             qef += i.into();
@@ -1353,6 +1999,9 @@ impl LeafHermiteData {
         for &f in &self.face_qefs {
             qef += f;
         }
+        if bias > 0.0 {
+            qef.add_center_bias(bounds.center(), bias);
+        }
         qef.solve()
     }
 }
@@ -1363,7 +2012,10 @@ impl LeafHermiteData {
 mod test {
     use super::*;
     use crate::{
-        context::bound::{self, BoundContext, BoundNode},
+        context::{
+            bound::{self, BoundContext, BoundNode},
+            Context,
+        },
         eval::{EzShape, MathShape},
         mesh::types::{Edge, X, Y, Z},
         shape::Bounds,
@@ -1376,6 +2028,9 @@ mod test {
         min_depth: 0,
         max_depth: 0,
         threads: 0,
+        max_cells: None,
+        vertex_quantization: None,
+        qef_bias: 0.0,
         bounds: Bounds {
             center: Vector3::new(0.0, 0.0, 0.0),
             size: 1.0,
@@ -1385,6 +2040,9 @@ mod test {
         min_depth: 1,
         max_depth: 1,
         threads: 0,
+        max_cells: None,
+        vertex_quantization: None,
+        qef_bias: 0.0,
         bounds: Bounds {
             center: Vector3::new(0.0, 0.0, 0.0),
             size: 1.0,
@@ -1585,6 +2243,58 @@ mod test {
         }
     }
 
+    /// Adaptive octrees mix leafs at different depths, but the dual walk
+    /// should still produce a watertight mesh (without T-junction cracks)
+    #[test]
+    fn test_adaptive_manifold() {
+        let ctx = BoundContext::new();
+        for threads in [0, 8] {
+            for (name, shape) in [
+                ("sphere", sphere(&ctx, [0.1, 0.0, -0.2], 0.6)),
+                (
+                    "cube",
+                    cube(&ctx, [-0.31, 0.52], [-0.43, 0.27], [-0.6, 0.2]),
+                ),
+                (
+                    "spheres",
+                    sphere(&ctx, [-0.3, 0.0, 0.0], 0.35).min(sphere(
+                        &ctx,
+                        [0.3, 0.05, 0.0],
+                        0.35,
+                    )),
+                ),
+            ] {
+                let shape: VmShape = shape.convert();
+                let settings = Settings {
+                    min_depth: 3,
+                    max_depth: 6,
+                    threads,
+                    ..Default::default()
+                };
+                let octree = Octree::build(&shape, settings);
+                let mut depths = BTreeMap::<usize, usize>::new();
+                let mut todo = vec![CellIndex::default()];
+                while let Some(c) = todo.pop() {
+                    match octree[c].into() {
+                        Cell::Branch { index, .. } => todo
+                            .extend(Corner::iter().map(|i| c.child(index, i))),
+                        Cell::Leaf(..) => {
+                            *depths.entry(c.depth).or_insert(0) += 1
+                        }
+                        _ => (),
+                    }
+                }
+                // Surface cells should be at a mix of depths
+                assert!(depths.len() > 2, "{name} has leafs at {depths:?}");
+
+                let mesh = octree.walk_dual(settings);
+                if let Err(e) = check_for_edge_matching(&mesh) {
+                    panic!("{name} has {e} (with {threads} threads)");
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_cube_verts() {
         let ctx = BoundContext::new();
@@ -1759,7 +2469,7 @@ mod test {
         ) -> OctreeBuilder<VmShape> {
             let shape: VmShape = shape.convert();
             let eval = Arc::new(EvalGroup::new(shape));
-            let mut out = OctreeBuilder::new();
+            let mut out = OctreeBuilder::new(&settings);
             out.recurse(&eval, CellIndex::default(), settings);
             out
         }
@@ -1805,6 +2515,28 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_constant() {
+        for (value, cell) in [(1.0, Cell::Empty), (-1.0, Cell::Full)] {
+            let mut ctx = Context::new();
+            let root = ctx.constant(value);
+            let shape = VmShape::new(&ctx, root).unwrap();
+            for threads in [0, 4] {
+                let settings = Settings {
+                    min_depth: 3,
+                    max_depth: 3,
+                    threads,
+                    ..Default::default()
+                };
+                let octree = Octree::build(&shape, settings);
+                assert_eq!(cell, octree.cells[0].into());
+                let mesh = octree.walk_dual(settings);
+                assert!(mesh.triangles.is_empty());
+                assert!(mesh.vertices.is_empty());
+            }
+        }
+    }
+
     #[test]
     fn test_colonnade_manifold() {
         const COLONNADE: &str = include_str!("../../../models/colonnade.vm");
@@ -1827,22 +2559,67 @@ mod test {
         }
     }
 
-    fn check_for_vertex_dupes(mesh: &Mesh) -> Result<(), String> {
-        let mut verts = mesh.vertices.clone();
-        verts.sort_by_key(|k| (k.x.to_bits(), k.y.to_bits(), k.z.to_bits()));
-        for i in 1..verts.len() {
-            if verts[i - 1] == verts[i] {
-                return Err(format!("duplicate vertices at {}", verts[i]));
-            }
-        }
-        Ok(())
-    }
+    #[test]
+    fn test_max_cells() {
+        const COLONNADE: &str = include_str!("../../../models/colonnade.vm");
+        let (ctx, root) =
+            crate::Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let tape = VmShape::new(&ctx, root).unwrap();
+        for threads in [0, 8] {
+            let settings = Settings {
+                min_depth: 5,
+                max_depth: 5,
+                threads,
+                ..Default::default()
+            };
+            let full = Octree::build(&tape, settings);
+            assert!(!full.is_truncated());
 
-    fn check_for_edge_matching(mesh: &Mesh) -> Result<(), String> {
-        let mut edges: BTreeMap<_, usize> = BTreeMap::new();
-        for t in &mesh.triangles {
-            for edge in [(t.x, t.y), (t.y, t.z), (t.z, t.x)] {
-                if t.x == t.y || t.y == t.z || t.x == t.z {
+            let max_cells = full.cells.len() / 4;
+            let settings = Settings {
+                max_cells: Some(max_cells),
+                ..settings
+            };
+            let octree = Octree::build(&tape, settings);
+            assert!(octree.is_truncated());
+            assert!(
+                octree.cells.len() <= max_cells,
+                "too many cells ({} > {max_cells}) with {threads} threads",
+                octree.cells.len()
+            );
+            let mesh = octree.walk_dual(settings);
+            assert!(!mesh.triangles.is_empty());
+            if let Err(e) = check_for_edge_matching(&mesh) {
+                panic!("truncated colonnade model has {e}");
+            }
+
+            // Limits below the 8 root cells leave the root unsubdivided
+            let settings = Settings {
+                max_cells: Some(4),
+                ..settings
+            };
+            let octree = Octree::build(&tape, settings);
+            assert!(octree.is_truncated());
+            assert_eq!(octree.cells.len(), 8);
+        }
+    }
+
+    fn check_for_vertex_dupes(mesh: &Mesh) -> Result<(), String> {
+        let mut verts = mesh.vertices.clone();
+        verts.sort_by_key(|k| (k.x.to_bits(), k.y.to_bits(), k.z.to_bits()));
+        for i in 1..verts.len() {
+            if verts[i - 1] == verts[i] {
+                return Err(format!("duplicate vertices at {}", verts[i]));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_for_edge_matching(mesh: &Mesh) -> Result<(), String> {
+        let mut edges: BTreeMap<_, usize> = BTreeMap::new();
+        for t in &mesh.triangles {
+            for edge in [(t.x, t.y), (t.y, t.z), (t.z, t.x)] {
+                if t.x == t.y || t.y == t.z || t.x == t.z {
                     return Err("triangle with duplicate edges".to_string());
                 }
                 *edges.entry(edge).or_default() += 1;
@@ -1924,6 +2701,871 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_surface_points() {
+        let ctx = BoundContext::new();
+        let shape = sphere(&ctx, [0.0; 3], 0.6);
+        let shape: VmShape = shape.convert();
+
+        for threads in [0, 8] {
+            let settings = Settings {
+                min_depth: 5,
+                max_depth: 5,
+                threads,
+                ..Default::default()
+            };
+            let octree = Octree::build(&shape, settings);
+            let points = octree.surface_points();
+            assert!(points.len() > 100, "too few points: {}", points.len());
+            for (p, n) in &points {
+                let p = Vector3::from(*p);
+                let n = Vector3::from(*n);
+                assert!(
+                    (p.norm() - 0.6).abs() < 1e-3,
+                    "point {p:?} is not on the sphere"
+                );
+                assert!(
+                    n.dot(&p.normalize()) > 0.999,
+                    "normal {n:?} at {p:?} is not radial"
+                );
+            }
+
+            // Points should have been deduplicated
+            for (i, (a, _)) in points.iter().enumerate() {
+                for (b, _) in &points[i + 1..] {
+                    let d = (Vector3::from(*a) - Vector3::from(*b)).norm();
+                    assert!(d > 1e-5, "duplicate points {a:?}, {b:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_volume_and_area() {
+        let ctx = BoundContext::new();
+        let shape = sphere(&ctx, [0.0; 3], 1.0);
+        let shape: VmShape = shape.convert();
+
+        let settings = Settings {
+            min_depth: 6,
+            max_depth: 6,
+            threads: 0,
+            bounds: Bounds {
+                center: Vector3::zeros(),
+                size: 1.5,
+            },
+            ..Default::default()
+        };
+        let octree = Octree::build(&shape, settings);
+        let volume = octree.volume();
+        let area = octree.surface_area();
+        let pi = std::f32::consts::PI;
+        assert!(
+            (volume - 4.0 / 3.0 * pi).abs() < 0.01,
+            "bad volume: {volume}"
+        );
+        // The mesh is slightly faceted, so the area is overestimated
+        assert!((area / (4.0 * pi) - 1.0).abs() < 0.04, "bad area: {area}");
+
+        // A cube has exact flat faces, so measurements are exact
+        let shape = cube(&ctx, [-0.5, 0.25], [-0.5, 0.5], [-0.5, 0.5]);
+        let shape: VmShape = shape.convert();
+        let octree = Octree::build(&shape, settings);
+        assert!((octree.volume() - 0.75).abs() < 1e-4);
+        assert!((octree.surface_area() - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let ctx = BoundContext::new();
+        let a = sphere(&ctx, [-0.5, 0.0, 0.0], 0.3);
+        let b = sphere(&ctx, [0.5, 0.1, 0.0], 0.3);
+        let shape: VmShape = a.min(b).convert();
+
+        for threads in [0, 8] {
+            let settings = Settings {
+                min_depth: 5,
+                max_depth: 5,
+                threads,
+                ..Default::default()
+            };
+            let mesh = Octree::build(&shape, settings).walk_dual(settings);
+            let parts = mesh.connected_components();
+            assert_eq!(parts.len(), 2);
+            assert_eq!(
+                parts.iter().map(|m| m.triangles.len()).sum::<usize>(),
+                mesh.triangles.len()
+            );
+
+            let expected = 4.0 / 3.0 * std::f32::consts::PI * 0.3f32.powi(3);
+            let mut centers = vec![];
+            for m in &parts {
+                assert!(m.triangles.iter().all(|t| t.max() < m.vertices.len()));
+                let v = m.volume();
+                assert!((v / expected - 1.0).abs() < 0.05, "bad volume {v}");
+                let c = m.vertices.iter().sum::<Vector3<f32>>()
+                    / m.vertices.len() as f32;
+                centers.push(c.x);
+            }
+            centers.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert!((centers[0] + 0.5).abs() < 0.05, "{centers:?}");
+            assert!((centers[1] - 0.5).abs() < 0.05, "{centers:?}");
+
+            // A single solid has a single component
+            let shape: VmShape = sphere(&ctx, [0.0; 3], 0.5).convert();
+            let mesh = Octree::build(&shape, settings).walk_dual(settings);
+            let parts = mesh.connected_components();
+            assert_eq!(parts.len(), 1);
+            assert_eq!(parts[0].triangles.len(), mesh.triangles.len());
+        }
+        assert!(Mesh::new().connected_components().is_empty());
+    }
+
+    #[test]
+    fn test_self_intersections() {
+        let ctx = BoundContext::new();
+        let shape: VmShape = sphere(&ctx, [0.0; 3], 0.5).convert();
+        let settings = Settings {
+            min_depth: 5,
+            max_depth: 5,
+            threads: 0,
+            ..Default::default()
+        };
+        let mut mesh = Octree::build(&shape, settings).walk_dual(settings);
+        assert!(!mesh.has_self_intersections());
+
+        // Fold the mesh by pushing its rightmost vertex through the sphere,
+        // so that its triangles poke out of the far side
+        let (i, _) = mesh
+            .vertices
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.x.partial_cmp(&b.x).unwrap())
+            .unwrap();
+        mesh.vertices[i] = Vector3::new(-1.0, 0.0, 0.0);
+        assert!(mesh.has_self_intersections());
+
+        // Two triangles crossing each other
+        let mut mesh = Mesh {
+            vertices: vec![
+                Vector3::new(-1.0, -1.0, 0.0),
+                Vector3::new(1.0, -1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(0.0, 0.0, -1.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                Vector3::new(0.0, -2.0, 0.0),
+            ],
+            triangles: vec![Vector3::new(0, 1, 2), Vector3::new(3, 4, 5)],
+            uvs: None,
+        };
+        assert!(mesh.has_self_intersections());
+
+        // Moving the second triangle out of the way removes the intersection
+        for v in &mut mesh.vertices[3..] {
+            v.x += 5.0;
+        }
+        assert!(!mesh.has_self_intersections());
+        assert!(!Mesh::new().has_self_intersections());
+    }
+
+    #[test]
+    fn test_generate_uvs_triplanar() {
+        // A quad facing +X at x = 1, then a quad facing -Z at z = -2
+        let mut mesh = Mesh {
+            vertices: vec![
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(1.0, 1.0, 1.0),
+                Vector3::new(1.0, 0.0, 1.0),
+                Vector3::new(0.0, 0.0, -2.0),
+                Vector3::new(0.0, 3.0, -2.0),
+                Vector3::new(2.0, 3.0, -2.0),
+                Vector3::new(2.0, 0.0, -2.0),
+            ],
+            triangles: vec![
+                Vector3::new(0, 1, 2),
+                Vector3::new(0, 2, 3),
+                Vector3::new(4, 5, 6),
+                Vector3::new(4, 6, 7),
+            ],
+            uvs: None,
+        };
+        let uvs = mesh.generate_uvs_triplanar(0.5).to_vec();
+        assert_eq!(
+            uvs,
+            [
+                [0.0, 0.0],
+                [0.5, 0.0],
+                [0.5, 0.5],
+                [0.0, 0.5],
+                [0.0, 0.0],
+                [0.0, 1.5],
+                [1.0, 1.5],
+                [1.0, 0.0],
+            ]
+        );
+        assert_eq!(mesh.uvs, Some(uvs));
+    }
+
+    #[test]
+    fn test_contour_at() {
+        let ctx = BoundContext::new();
+        let settings = Settings {
+            min_depth: 4,
+            max_depth: 4,
+            threads: 0,
+            ..Default::default()
+        };
+        let shape: VmShape = sphere(&ctx, [0.0; 3], 0.6).convert();
+        let octree = Octree::build(&shape, settings);
+
+        let base = octree.contour_at(0.0).unwrap();
+        let mesh = octree.walk_dual(settings);
+        assert_eq!(base.vertices, mesh.vertices);
+        assert_eq!(base.triangles, mesh.triangles);
+
+        let offset = octree.contour_at(0.1).unwrap();
+        assert_eq!(offset.triangles, base.triangles);
+        for (a, b) in base.vertices.iter().zip(&offset.vertices) {
+            assert!(b.norm() > a.norm(), "vertex {b:?} moved inwards");
+            assert!(
+                (b.norm() - 0.7).abs() < 0.02,
+                "vertex {b:?} is not on the offset sphere"
+            );
+        }
+        assert!(offset.volume() > base.volume());
+        assert!(offset.validate().is_watertight());
+
+        // Cells are 0.125 units across, so this is too far to reuse them
+        assert!(matches!(
+            octree.contour_at(-0.2),
+            Err(crate::Error::ContourTooFar(-0.2))
+        ));
+    }
+
+    #[test]
+    fn test_contour_at_bounds() {
+        let ctx = BoundContext::new();
+        let center = Vector3::new(0.5, -0.25, 0.1);
+        let settings = Settings {
+            min_depth: 4,
+            max_depth: 4,
+            threads: 0,
+            bounds: Bounds { size: 2.0, center },
+            ..Default::default()
+        };
+        let shape: VmShape = sphere(&ctx, center.into(), 1.2).convert();
+        let octree = Octree::build(&shape, settings);
+
+        let offset = octree.contour_at(0.2).unwrap();
+        assert!(!offset.vertices.is_empty());
+        for v in &offset.vertices {
+            let n = (v - center).norm();
+            assert!(
+                (n - 1.4).abs() < 0.04,
+                "vertex {v:?} is not on the offset sphere: {n}"
+            );
+        }
+        assert!(offset.validate().is_watertight());
+
+        // Cells are 0.25 model units across
+        assert!(matches!(
+            octree.contour_at(-0.3),
+            Err(crate::Error::ContourTooFar(-0.3))
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let ctx = BoundContext::new();
+        let settings = Settings {
+            min_depth: 5,
+            max_depth: 5,
+            threads: 0,
+            ..Default::default()
+        };
+        let shape: VmShape = sphere(&ctx, [0.0; 3], 0.7).convert();
+        let report = Octree::build(&shape, settings).validate();
+        assert_eq!(
+            report,
+            MeshReport {
+                boundary_edges: 0,
+                non_manifold_edges: 0,
+                euler_characteristic: 2,
+            }
+        );
+        assert!(report.is_watertight());
+
+        // Two disjoint spheres
+        let shape = sphere(&ctx, [-0.5, 0.0, 0.0], 0.3).min(sphere(
+            &ctx,
+            [0.5, 0.0, 0.0],
+            0.3,
+        ));
+        let shape: VmShape = shape.convert();
+        let report = Octree::build(&shape, settings).validate();
+        assert!(report.is_watertight());
+        assert_eq!(report.euler_characteristic, 4);
+
+        // An open quad, plus an unused vertex
+        let mut quad = Mesh {
+            vertices: vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(5.0, 5.0, 5.0),
+            ],
+            triangles: vec![Vector3::new(0, 1, 2), Vector3::new(0, 2, 3)],
+            uvs: None,
+        };
+        let report = quad.validate();
+        assert_eq!(report.boundary_edges, 4);
+        assert_eq!(report.non_manifold_edges, 0);
+        assert_eq!(report.euler_characteristic, 1);
+        assert!(!report.is_watertight());
+
+        // A third triangle on the diagonal makes it non-manifold
+        quad.triangles.push(Vector3::new(0, 2, 4));
+        let report = quad.validate();
+        assert_eq!(report.boundary_edges, 6);
+        assert_eq!(report.non_manifold_edges, 1);
+    }
+
+    #[test]
+    fn test_feature_edges() {
+        // A regular tetrahedron, where every dihedral angle is about 70.5°
+        // (so the angle between face normals is about 109.5°)
+        let tet = Mesh {
+            vertices: vec![
+                Vector3::new(1.0, 1.0, 1.0),
+                Vector3::new(1.0, -1.0, -1.0),
+                Vector3::new(-1.0, 1.0, -1.0),
+                Vector3::new(-1.0, -1.0, 1.0),
+            ],
+            triangles: vec![
+                Vector3::new(0, 1, 2),
+                Vector3::new(0, 3, 1),
+                Vector3::new(0, 2, 3),
+                Vector3::new(1, 3, 2),
+            ],
+            uvs: None,
+        };
+        let all = [[0, 1], [0, 2], [0, 3], [1, 2], [1, 3], [2, 3]];
+        assert_eq!(tet.feature_edges(100f32.to_radians()), all);
+        assert!(tet.feature_edges(120f32.to_radians()).is_empty());
+
+        // A flat quad only has boundary edges
+        let quad = Mesh {
+            vertices: vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![Vector3::new(0, 1, 2), Vector3::new(0, 2, 3)],
+            uvs: None,
+        };
+        assert_eq!(quad.feature_edges(0.0), [[0, 1], [0, 3], [1, 2], [2, 3]]);
+
+        // Meshing a box finds its 12 edges
+        let ctx = BoundContext::new();
+        let bounds = [[-0.5, 0.25], [-0.5, 0.5], [-0.4, 0.6]];
+        let shape = cube(&ctx, bounds[0], bounds[1], bounds[2]);
+        let shape: VmShape = shape.convert();
+        let settings = Settings {
+            min_depth: 5,
+            max_depth: 5,
+            threads: 0,
+            ..Default::default()
+        };
+        let mesh = Octree::build(&shape, settings).walk_dual(settings);
+        let edges = mesh.feature_edges(30f32.to_radians());
+        assert!(!edges.is_empty());
+        let cell = 2.0 / (1 << settings.max_depth) as f32;
+
+        // Each box edge is identified by which face it's on along the two
+        // axes where it's fixed
+        let mut found: BTreeMap<[Option<usize>; 3], f32> = BTreeMap::new();
+        for [a, b] in edges {
+            let (a, b) = (mesh.vertices[a as usize], mesh.vertices[b as usize]);
+            let key = [0, 1, 2].map(|axis| {
+                bounds[axis].iter().position(|f| {
+                    (a[axis] - f).abs() < 1e-3 && (b[axis] - f).abs() < 1e-3
+                })
+            });
+            let fixed = key.iter().filter(|k| k.is_some()).count();
+            if fixed < 2 {
+                // Dual contouring can't place vertices exactly on a corner
+                // which isn't aligned to the cell grid, so it chamfers the
+                // corner; those chamfers are creases too, and must be within a
+                // couple of cells of a box corner.
+                let near_corner = |v: Vector3<f32>| {
+                    (0..8).any(|i| {
+                        let c = Vector3::from_fn(|axis, _| {
+                            bounds[axis][(i >> axis) & 1]
+                        });
+                        (v - c).norm() < 2.0 * cell
+                    })
+                };
+                assert!(
+                    near_corner(a) && near_corner(b),
+                    "edge {a:?} - {b:?} is not on a box edge"
+                );
+                continue;
+            }
+            // Skip zero-length edges at the box's corners
+            if fixed == 2 {
+                *found.entry(key).or_default() += (a - b).norm();
+            }
+        }
+        assert_eq!(found.len(), 12);
+
+        // Most of the length of each box edge is found
+        for (key, length) in found {
+            let axis = key.iter().position(|k| k.is_none()).unwrap();
+            let expected = bounds[axis][1] - bounds[axis][0];
+            assert!(length > expected * 0.75, "{key:?}: {length}");
+        }
+    }
+
+    #[test]
+    fn test_bake_ao() {
+        // A bowl: a spherical shell, cut off above the XY plane
+        let ctx = BoundContext::new();
+        let (x, y, z) = ctx.axes();
+        let r = (x.square() + y.square() + z.clone().square()).sqrt();
+        let shell = (r.clone() - 0.8).max(0.6 - r);
+        let shape: VmShape = shell.max(z).convert();
+
+        let settings = Settings {
+            min_depth: 5,
+            max_depth: 5,
+            threads: 0,
+            ..Default::default()
+        };
+        let mesh = Octree::build(&shape, settings).walk_dual(settings);
+        let ao = mesh.bake_ao(&shape, 64).unwrap();
+        assert_eq!(ao.len(), mesh.vertices.len());
+        assert!(ao.iter().all(|a| (0.0..=1.0).contains(a)));
+
+        // Compare the concave inside of the bowl with its convex outside
+        let mut inner = vec![];
+        let mut outer = vec![];
+        for (v, a) in mesh.vertices.iter().zip(&ao) {
+            if v.z > -0.1 {
+                continue;
+            } else if v.norm() < 0.7 {
+                inner.push(*a);
+            } else {
+                outer.push(*a);
+            }
+        }
+        let mean = |v: &[f32]| v.iter().sum::<f32>() / v.len() as f32;
+        let (inner, outer) = (mean(&inner), mean(&outer));
+        assert!(inner > 0.4, "inner occlusion is too low: {inner}");
+        assert!(outer < 0.1, "outer occlusion is too high: {outer}");
+    }
+
+    /// Minimal PLY parser, returning vertices (position and normal) and faces
+    fn parse_ply(data: &[u8]) -> (Vec<[f32; 6]>, Vec<Vec<usize>>) {
+        const END: &[u8] = b"end_header\n";
+        let n = data.windows(END.len()).position(|w| w == END).unwrap();
+        let header = std::str::from_utf8(&data[..n]).unwrap();
+        let mut body = &data[n + END.len()..];
+
+        let mut binary = None;
+        let mut counts = vec![];
+        for line in header.lines() {
+            let words: Vec<_> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["format", "ascii", "1.0"] => binary = Some(false),
+                ["format", "binary_little_endian", "1.0"] => {
+                    binary = Some(true)
+                }
+                ["element", _, n] => counts.push(n.parse::<usize>().unwrap()),
+                _ => (),
+            }
+        }
+        let [vert_count, face_count] = counts.as_slice().try_into().unwrap();
+
+        let mut verts = vec![];
+        let mut faces = vec![];
+        if binary.unwrap() {
+            let mut take = |n: usize| {
+                let (a, b) = body.split_at(n);
+                body = b;
+                a
+            };
+            for _ in 0..vert_count {
+                let mut v = [0.0; 6];
+                for f in &mut v {
+                    *f = f32::from_le_bytes(take(4).try_into().unwrap());
+                }
+                verts.push(v);
+            }
+            for _ in 0..face_count {
+                let n = take(1)[0] as usize;
+                faces.push(
+                    (0..n)
+                        .map(|_| {
+                            u32::from_le_bytes(take(4).try_into().unwrap())
+                                as usize
+                        })
+                        .collect(),
+                );
+            }
+            assert!(body.is_empty());
+        } else {
+            let body = std::str::from_utf8(body).unwrap();
+            let mut lines = body.lines();
+            for line in lines.by_ref().take(vert_count) {
+                let v: Vec<f32> = line
+                    .split_whitespace()
+                    .map(|w| w.parse().unwrap())
+                    .collect();
+                verts.push(v.try_into().unwrap());
+            }
+            for line in lines.by_ref().take(face_count) {
+                let f: Vec<usize> = line
+                    .split_whitespace()
+                    .map(|w| w.parse().unwrap())
+                    .collect();
+                assert_eq!(f[0], f.len() - 1);
+                faces.push(f[1..].to_vec());
+            }
+            assert!(lines.next().is_none());
+        }
+        (verts, faces)
+    }
+
+    #[test]
+    fn test_to_ply() {
+        let ctx = BoundContext::new();
+        let shape: VmShape = sphere(&ctx, [0.0; 3], 0.5).convert();
+        for (min_depth, max_depth) in [(4, 4), (2, 5)] {
+            let settings = Settings {
+                min_depth,
+                max_depth,
+                threads: 0,
+                ..Default::default()
+            };
+            let octree = Octree::build(&shape, settings);
+            let mesh = octree.walk_dual(settings);
+
+            // Each polygon replaces a triangle fan in the mesh, whose leaf
+            // vertices (but not edge intersections) are kept
+            let leaf_verts: std::collections::BTreeSet<usize> =
+                mesh.triangles.iter().flat_map(|t| [t.x, t.y]).collect();
+
+            let mut outputs = vec![];
+            for binary in [false, true] {
+                let mut out = vec![];
+                octree.to_ply(&mut out, binary).unwrap();
+                let (verts, faces) = parse_ply(&out);
+                assert_eq!(verts.len(), leaf_verts.len());
+                let sides: usize = faces.iter().map(|f| f.len()).sum();
+                assert_eq!(sides, mesh.triangles.len());
+                assert!(faces.iter().all(|f| f.len() == 3 || f.len() == 4));
+                if min_depth == max_depth {
+                    assert!(faces.iter().all(|f| f.len() == 4));
+                }
+                assert!(faces.iter().flatten().all(|i| *i < verts.len()));
+
+                // Normals point out of the sphere
+                for v in &verts {
+                    let p = Vector3::new(v[0], v[1], v[2]);
+                    let n = Vector3::new(v[3], v[4], v[5]);
+                    assert!((n.norm() - 1.0).abs() < 1e-5);
+                    assert!(p.normalize().dot(&n) > 0.9);
+                }
+                outputs.push((verts, faces));
+            }
+            assert_eq!(outputs[0].1, outputs[1].1);
+        }
+    }
+
+    #[test]
+    fn test_write_obj() {
+        let ctx = BoundContext::new();
+        let shape: VmShape = sphere(&ctx, [0.0; 3], 0.5).convert();
+        let settings = Settings {
+            min_depth: 4,
+            max_depth: 4,
+            threads: 0,
+            ..Default::default()
+        };
+        let mesh = Octree::build(&shape, settings).walk_dual(settings);
+
+        let mut out = vec![];
+        mesh.write_obj(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let mut verts = 0;
+        let mut normals = vec![];
+        let mut faces = vec![];
+        for line in out.lines() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("v") => verts += 1,
+                Some("vn") => normals.push(
+                    words
+                        .map(|w| w.parse::<f32>().unwrap())
+                        .collect::<Vec<_>>(),
+                ),
+                Some("f") => faces.extend(words.map(|w| {
+                    let (v, n) = w.split_once("//").unwrap();
+                    assert_eq!(v, n);
+                    v.parse::<usize>().unwrap()
+                })),
+                _ => assert!(line.starts_with('#')),
+            }
+        }
+        assert_eq!(verts, mesh.vertices.len());
+        assert_eq!(normals.len(), mesh.vertices.len());
+        assert_eq!(faces.len(), mesh.triangles.len() * 3);
+
+        // Every vertex is shared, and indices are 1-based
+        assert_eq!(*faces.iter().min().unwrap(), 1);
+        assert_eq!(*faces.iter().max().unwrap(), verts);
+
+        // Normals on a sphere point outwards
+        for (n, v) in normals.iter().zip(&mesh.vertices) {
+            let n = Vector3::new(n[0], n[1], n[2]);
+            assert!((n.norm() - 1.0).abs() < 1e-4);
+            assert!(n.dot(&v.normalize()) > 0.9, "bad normal {n} at {v}");
+        }
+    }
+
+    #[test]
+    fn test_merge_canonical() {
+        let ctx = BoundContext::new();
+        let shape = sphere(&ctx, [0.0; 3], 0.85);
+        let shape: VmShape = shape.convert();
+
+        let mut meshes = vec![];
+        for threads in [0, 8] {
+            let settings = Settings {
+                min_depth: 5,
+                max_depth: 5,
+                threads,
+                ..Default::default()
+            };
+            let octree = Octree::build(&shape, settings);
+            for c in &octree.cells {
+                if let Cell::Branch { index, thread } = (*c).into() {
+                    assert_eq!(thread, 0, "per-thread reference remains");
+                    assert!(index + 8 <= octree.cells.len());
+                }
+            }
+            meshes.push(octree.walk_dual(settings));
+        }
+
+        // Cell order depends on thread scheduling, so compare sorted vertices
+        let sorted = |m: &Mesh| {
+            let mut v: Vec<_> =
+                m.vertices.iter().map(|v| [v.x, v.y, v.z]).collect();
+            v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            v
+        };
+        assert_eq!(meshes[0].triangles.len(), meshes[1].triangles.len());
+        assert_eq!(sorted(&meshes[0]), sorted(&meshes[1]));
+    }
+
+    #[test]
+    fn test_qef_bias() {
+        // A noisy, nearly-flat patch: the intersection planes are all close to
+        // z = 0.5, but tilted slightly differently, so the unconstrained QEF
+        // solution lands well outside of the cell.
+        let bounds = CellBounds::new();
+        let build = |bias: f32| {
+            let mut qef = QuadraticErrorSolver::new();
+            for (pos, grad) in [
+                ([-1.0, -1.0, 0.5], [0.01, 0.0, 1.0]),
+                ([1.0, -1.0, 0.5], [0.02, 0.0, 1.0]),
+                ([-1.0, 0.0, 0.5], [0.01, 0.001, 1.0]),
+                ([1.0, 0.0, 0.5], [0.02, 0.001, 1.0]),
+            ] {
+                qef.add_intersection(
+                    Vector3::from(pos),
+                    nalgebra::Vector4::new(grad[0], grad[1], grad[2], 0.0),
+                );
+            }
+            if bias > 0.0 {
+                qef.add_center_bias(bounds.center(), bias);
+            }
+            qef.solve().0
+        };
+
+        let v = build(0.0);
+        assert!(!bounds.contains(v), "vertex {:?} is in the cell", v.pos);
+
+        let mut prev = f32::INFINITY;
+        for bias in [0.01, 0.1, 1.0, 10.0] {
+            let v = build(bias);
+            assert!(
+                bounds.contains(v),
+                "vertex {:?} escaped with bias {bias}",
+                v.pos
+            );
+            let d = (v.pos - bounds.center()).norm();
+            assert!(d <= prev + 1e-6, "bias {bias} moved vertex outwards");
+            prev = d;
+        }
+    }
+
+    #[test]
+    fn test_vertex_quantization() {
+        let ctx = BoundContext::new();
+        let shape = sphere(&ctx, [0.1, 0.2, 0.3], 0.6);
+        let shape: VmShape = shape.convert();
+
+        const STEP: f32 = 1.0 / 64.0;
+        let settings = Settings {
+            min_depth: 4,
+            max_depth: 4,
+            threads: 0,
+            ..Default::default()
+        };
+        let raw = Octree::build(&shape, settings);
+        let quantized = Octree::build(
+            &shape,
+            Settings {
+                vertex_quantization: Some(STEP),
+                ..settings
+            },
+        );
+
+        // Snapping moves each vertex by at most half a step along each axis
+        assert_eq!(raw.verts.len(), quantized.verts.len());
+        for (a, b) in raw.verts.iter().zip(&quantized.verts) {
+            for i in 0..3 {
+                let err = (a.pos[i] - b.pos[i]).abs();
+                assert!(err <= STEP / 2.0, "vertex moved too far ({err})");
+                let k = b.pos[i] / STEP;
+                assert_eq!(k, k.round(), "vertex {:?} is off-grid", b.pos);
+            }
+        }
+
+        // Repeated runs (including multithreaded runs) give identical vertices
+        let sorted = |m: &Mesh| {
+            let mut v: Vec<_> =
+                m.vertices.iter().map(|v| [v.x, v.y, v.z]).collect();
+            v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            v
+        };
+        let expected = sorted(&quantized.walk_dual(settings));
+        for threads in [0, 4, 8] {
+            let settings = Settings {
+                threads,
+                vertex_quantization: Some(STEP),
+                ..settings
+            };
+            let mesh = Octree::build(&shape, settings).walk_dual(settings);
+            assert_eq!(
+                sorted(&mesh),
+                expected,
+                "mismatch at {threads} threads"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rasterize() {
+        let ctx = BoundContext::new();
+        let shape = sphere(&ctx, [0.1, -0.2, 0.05], 0.6);
+        let shape: VmShape = shape.convert();
+
+        const N: usize = 48;
+        for threads in [0, 8] {
+            let settings = Settings {
+                min_depth: 5,
+                max_depth: 5,
+                threads,
+                ..Default::default()
+            };
+            let octree = Octree::build(&shape, settings);
+            let voxels = octree.rasterize(N);
+            assert_eq!(voxels.len(), N * N * N);
+
+            // The octree covers a 2×2×2 cube
+            let voxel_volume = (2.0 / N as f32).powi(3);
+            let volume =
+                voxels.iter().filter(|v| **v).count() as f32 * voxel_volume;
+            let expected = 4.0 / 3.0 * std::f32::consts::PI * 0.6f32.powi(3);
+            let err = (volume / expected - 1.0).abs();
+            assert!(err < 0.02, "bad volume {volume} (expected {expected})");
+
+            // Spot-check voxels at the center of the sphere and in a corner
+            let index = |p: [f32; 3]| {
+                let [i, j, k] =
+                    p.map(|v| ((v + 1.0) / 2.0 * N as f32) as usize);
+                i + j * N + k * N * N
+            };
+            assert!(voxels[index([0.1, -0.2, 0.05])]);
+            assert!(!voxels[index([0.9, 0.9, 0.9])]);
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let ctx = BoundContext::new();
+        let count = |octree: &Octree, s: &str| {
+            let mut out = vec![];
+            octree.to_dot(&mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert!(out.starts_with("digraph"));
+            out.matches(s).count()
+        };
+
+        let shape: VmShape = sphere(&ctx, [0.0; 3], 0.5).convert();
+        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD);
+        assert_eq!(count(&octree, "label"), 9);
+        assert_eq!(count(&octree, "->"), 8);
+        assert_eq!(count(&octree, r#""Branch\ndepth 0""#), 1);
+        assert_eq!(count(&octree, r#""Leaf\ndepth 1""#), 8);
+
+        let octree = Octree::build(&shape, DEPTH0_SINGLE_THREAD);
+        assert_eq!(count(&octree, "label"), 1);
+        assert_eq!(count(&octree, "->"), 0);
+
+        let shape: VmShape = sphere(&ctx, [2.0; 3], 0.1).convert();
+        let octree = Octree::build(&shape, DEPTH1_SINGLE_THREAD);
+        assert_eq!(count(&octree, "label"), 1);
+        assert_eq!(count(&octree, r#""Empty\ndepth 0""#), 1);
+    }
+
+    #[test]
+    fn test_refine() {
+        let ctx = BoundContext::new();
+        let shape: VmShape = sphere(&ctx, [0.1, 0.0, -0.2], 0.6).convert();
+        let coarse = Settings {
+            min_depth: 3,
+            max_depth: 3,
+            threads: 0,
+            ..Default::default()
+        };
+        let fine = Settings {
+            min_depth: 5,
+            max_depth: 5,
+            ..coarse
+        };
+
+        let octree = Octree::build(&shape, coarse);
+        let refined = octree.refine(2, &shape, coarse);
+        let direct = Octree::build(&shape, fine);
+        assert_eq!(refined.cells, direct.cells);
+
+        let a = refined.walk_dual(fine);
+        let b = direct.walk_dual(fine);
+        assert_eq!(a.triangles, b.triangles);
+        assert_eq!(a.vertices.len(), b.vertices.len());
+        for (va, vb) in a.vertices.iter().zip(&b.vertices) {
+            assert!((va - vb).norm() < 1e-6, "{va} != {vb}");
+        }
+        assert!(a.triangles.len() > octree.walk_dual(coarse).triangles.len());
+    }
+
     #[test]
     fn test_octree_bounds() {
         let ctx = BoundContext::new();
@@ -1936,6 +3578,9 @@ mod test {
             max_depth: 4,
             threads: 0,
             bounds: Bounds { size: 0.5, center },
+            max_cells: None,
+            vertex_quantization: None,
+            qef_bias: 0.0,
         };
 
         let octree = Octree::build(&shape, settings).walk_dual(settings);
@@ -32,4 +32,31 @@ impl Mesh {
         }
         Ok(())
     }
+
+    /// Writes a Wavefront OBJ file to the given output
+    ///
+    /// Unlike STL, the OBJ format is indexed: each vertex is written once (as
+    /// a `v` line) and shared by every triangle which uses it, so adjacency
+    /// is preserved.  Each vertex also gets a normal (as a `vn` line), which
+    /// is the normalized area-weighted sum of its triangles' normals, for
+    /// smooth shading.
+    pub fn write_obj<F: std::io::Write>(
+        &self,
+        out: &mut F,
+    ) -> Result<(), crate::Error> {
+        writeln!(out, "# OBJ file exported by Fidget")?;
+        for v in &self.vertices {
+            writeln!(out, "v {} {} {}", v.x, v.y, v.z)?;
+        }
+        for n in self.vertex_normals() {
+            let n = n.try_normalize(0.0).unwrap_or_default();
+            writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+        for t in &self.triangles {
+            // OBJ indices are 1-based
+            let [a, b, c] = [t.x + 1, t.y + 1, t.z + 1];
+            writeln!(out, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+        Ok(())
+    }
 }
@@ -58,6 +58,23 @@ impl QuadraticErrorSolver {
         self.btb += norm.dot(&pos).powi(2);
     }
 
+    /// Adds a regularization term pulling the solution toward a point
+    ///
+    /// This adds `weight · |v - center|²` to the error function, which keeps
+    /// the solution bounded when the intersection planes are nearly parallel
+    /// (at the cost of rounding off sharp features).  Because intersection
+    /// normals are normalized, a weight of 1 is comparable to the influence of
+    /// a single intersection.
+    pub fn add_center_bias(
+        &mut self,
+        center: nalgebra::Vector3<f32>,
+        weight: f32,
+    ) {
+        self.ata += nalgebra::Matrix3::identity() * weight;
+        self.atb += center * weight;
+        self.btb += center.norm_squared() * weight;
+    }
+
     /// Solve the given QEF, minimizing towards the mass point
     ///
     /// Returns a vertex localized within the given cell, and adjusts the solver
@@ -112,7 +129,13 @@ impl QuadraticErrorSolver {
                 return p;
             }
 
-            prev = Some((CellVertex { pos }, err));
+            prev = Some((
+                CellVertex {
+                    pos,
+                    ..Default::default()
+                },
+                err,
+            ));
         }
         prev.unwrap()
     }
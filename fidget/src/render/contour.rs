@@ -0,0 +1,415 @@
+//! Extraction of 2D isocontours with marching squares
+use crate::{
+    eval::{BulkEvaluator, EzShape, Shape},
+    render::RenderConfig,
+    shape::Bounds,
+    Error,
+};
+use nalgebra::{Point2, Transform2};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A single line of a contour, in shape coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    /// Points along the line
+    pub points: Vec<Point2<f32>>,
+
+    /// Whether the line is a closed loop
+    ///
+    /// If so, the last point connects back to the first; it is not repeated
+    /// at the end of `points`.
+    pub closed: bool,
+}
+
+/// Set of polylines tracing the zero-isocontour of a 2D shape
+///
+/// This is built by [`contour2d`]; see that function for details.
+#[derive(Clone, Debug)]
+pub struct Contours {
+    /// Extracted lines
+    ///
+    /// Each line is oriented so that the shape's interior is on its left
+    /// (i.e. outer boundaries run counterclockwise in shape coordinates).
+    pub lines: Vec<Polyline>,
+
+    /// Bounds of the sampled region
+    pub bounds: Bounds<2>,
+}
+
+impl Contours {
+    /// Writes the contours as an SVG file with a single `<path>`
+    ///
+    /// The SVG's `viewBox` is the sampled region, with the Y axis flipped so
+    /// that +Y points up (as in shape coordinates).  Closed lines end with a
+    /// `Z` command.  The path is stroked with a non-scaling hairline and no
+    /// fill, which is suitable for plotting or laser cutting.
+    pub fn write_svg<F: std::io::Write>(
+        &self,
+        out: &mut F,
+    ) -> Result<(), Error> {
+        let b = &self.bounds;
+        let (x0, y0) = (b.center.x - b.size, -b.center.y - b.size);
+        let w = b.size * 2.0;
+        write!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" "#)?;
+        writeln!(out, r#"viewBox="{x0} {y0} {w} {w}">"#)?;
+        write!(out, r#"<path d=""#)?;
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                write!(out, " ")?;
+            }
+            for (j, p) in line.points.iter().enumerate() {
+                let cmd = if j == 0 { "M" } else { " L" };
+                write!(out, "{cmd}{} {}", p.x, -p.y)?;
+            }
+            if line.closed {
+                write!(out, " Z")?;
+            }
+        }
+        write!(out, r#"" fill="none" stroke="black" stroke-width="1" "#)?;
+        writeln!(out, r#"vector-effect="non-scaling-stroke"/>"#)?;
+        writeln!(out, "</svg>")?;
+        Ok(())
+    }
+}
+
+/// Extracts the zero-isocontour of a 2D shape (at Z = 0) with marching squares
+///
+/// The region given by `config.bounds` is split into `config.image_size`
+/// cells along each axis, and the shape is sampled at cell corners; larger
+/// values give more detail and longer lines.  `config.threads` and
+/// `config.tile_sizes` are ignored.  Crossings are placed on cell edges by
+/// linear interpolation, and ambiguous (saddle) cells are resolved by
+/// evaluating the shape at the cell's center.
+///
+/// Lines are closed loops wherever the boundary is closed within the
+/// sampled region; boundaries which leave the region produce open lines.
+///
+/// ```
+/// use fidget::{render::{contour2d, RenderConfig}, vm::VmShape};
+/// # use fidget::eval::MathShape;
+/// let mut ctx = fidget::Context::new();
+/// let x = ctx.x();
+/// let y = ctx.y();
+/// let x2 = ctx.square(x)?;
+/// let y2 = ctx.square(y)?;
+/// let r = ctx.add(x2, y2)?;
+/// let r = ctx.sqrt(r)?;
+/// let circle = ctx.sub(r, 0.5)?;
+/// let shape = VmShape::new(&ctx, circle)?;
+///
+/// let cfg = RenderConfig::<2> {
+///     image_size: 32,
+///     ..Default::default()
+/// };
+/// let c = contour2d(&shape, &cfg)?;
+/// assert_eq!(c.lines.len(), 1);
+/// assert!(c.lines[0].closed);
+///
+/// let mut svg = vec![];
+/// c.write_svg(&mut svg)?;
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub fn contour2d<S: Shape>(
+    shape: &S,
+    config: &RenderConfig<2>,
+) -> Result<Contours, Error> {
+    let n = config.image_size;
+    let mut out = Contours {
+        lines: vec![],
+        bounds: config.bounds,
+    };
+    if n == 0 {
+        return Ok(out);
+    }
+    let mat: Transform2<f32> = config.bounds.transform();
+
+    // Converts from (fractional) corner indices to shape coordinates
+    let point = |i: f32, j: f32| {
+        let pos = |i: f32| 2.0 * i / n as f32 - 1.0;
+        mat.transform_point(&Point2::new(pos(i), pos(j)))
+    };
+
+    let tape = shape.ez_float_slice_tape();
+    let mut eval = S::new_float_slice_eval();
+    let m = n + 1;
+    let mut xs = Vec::with_capacity(m * m);
+    let mut ys = Vec::with_capacity(m * m);
+    for j in 0..m {
+        for i in 0..m {
+            let p = point(i as f32, j as f32);
+            xs.push(p.x);
+            ys.push(p.y);
+        }
+    }
+    let zs = vec![0.0; xs.len()];
+    let values = eval.eval(&tape, &xs, &ys, &zs, &[])?.to_vec();
+    let value = |i: usize, j: usize| values[i + j * m];
+    let inside = |i: usize, j: usize| value(i, j) < 0.0;
+
+    // Corners of cell (i, j) in counterclockwise order
+    let corners =
+        |i: usize, j: usize| [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+
+    // Find saddle cells, then evaluate their centers in a single batch
+    let mut saddles = vec![];
+    for j in 0..n {
+        for i in 0..n {
+            let c = corners(i, j).map(|(i, j)| inside(i, j));
+            if c[0] == c[2] && c[1] == c[3] && c[0] != c[1] {
+                saddles.push((i, j));
+            }
+        }
+    }
+    let mut joined = BTreeSet::new();
+    if !saddles.is_empty() {
+        xs.clear();
+        ys.clear();
+        for &(i, j) in &saddles {
+            let p = point(i as f32 + 0.5, j as f32 + 0.5);
+            xs.push(p.x);
+            ys.push(p.y);
+        }
+        let zs = vec![0.0; xs.len()];
+        let centers = eval.eval(&tape, &xs, &ys, &zs, &[])?;
+        for (&cell, &v) in saddles.iter().zip(centers) {
+            if v < 0.0 {
+                joined.insert(cell);
+            }
+        }
+    }
+
+    // Each crossing is identified by its grid edge: horizontal edges starting
+    // at corner (i, j) are `2 * (i + j * m)`, and vertical edges are one more.
+    // Crossings are computed from the edge's canonical corner order, so that
+    // both cells sharing an edge agree on its position.
+    let crossing = |e: usize| {
+        let (i, j) = ((e / 2) % m, (e / 2) / m);
+        let (a, b) = if e % 2 == 0 {
+            ((i, j), (i + 1, j))
+        } else {
+            ((i, j), (i, j + 1))
+        };
+        let (va, vb) = (value(a.0, a.1), value(b.0, b.1));
+        let t = va / (va - vb);
+        let t = if t.is_finite() {
+            t.clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+        point(
+            a.0 as f32 + t * (b.0 as f32 - a.0 as f32),
+            a.1 as f32 + t * (b.1 as f32 - a.1 as f32),
+        )
+    };
+
+    // Build directed segments, with the interior on the left.  Walking
+    // counterclockwise around a cell, an edge is an exit if it goes from
+    // inside to outside, and an entry otherwise; each exit connects to the
+    // following entry (or, for saddles with an outside center, the preceding
+    // one, which keeps the two inside corners separate).
+    let mut next = BTreeMap::new();
+    for j in 0..n {
+        for i in 0..n {
+            let c = corners(i, j).map(|(i, j)| inside(i, j));
+            let edges = [
+                2 * (i + j * m),
+                2 * ((i + 1) + j * m) + 1,
+                2 * (i + (j + 1) * m),
+                2 * (i + j * m) + 1,
+            ];
+            let saddle = c[0] == c[2] && c[1] == c[3];
+            for k in (0..4).filter(|&k| c[k] && !c[(k + 1) % 4]) {
+                let target = if saddle && !joined.contains(&(i, j)) {
+                    (k + 3) % 4
+                } else {
+                    // Find the next entry edge
+                    (1..4)
+                        .map(|d| (k + d) % 4)
+                        .find(|&e| !c[e] && c[(e + 1) % 4])
+                        .unwrap()
+                };
+                next.insert(edges[k], edges[target]);
+            }
+        }
+    }
+
+    // Chain segments into lines, starting with open lines (which begin at an
+    // edge without an incoming segment), then collecting closed loops.
+    let incoming: BTreeSet<usize> = next.values().cloned().collect();
+    let starts: Vec<usize> = next
+        .keys()
+        .filter(|e| !incoming.contains(e))
+        .cloned()
+        .collect();
+    let loops: Vec<usize> = next.keys().cloned().collect();
+
+    // Crossings at grid corners are shared by consecutive segments, so skip
+    // repeated points
+    let push = |points: &mut Vec<Point2<f32>>, p| {
+        if points.last() != Some(&p) {
+            points.push(p);
+        }
+    };
+    for start in starts.into_iter().chain(loops) {
+        let mut e = start;
+        let mut points = vec![];
+        let mut closed = false;
+        while let Some(t) = next.remove(&e) {
+            push(&mut points, crossing(e));
+            if t == start {
+                closed = true;
+                break;
+            }
+            e = t;
+        }
+        if !points.is_empty() {
+            if !closed {
+                push(&mut points, crossing(e));
+            } else if points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            out.lines.push(Polyline { points, closed });
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        context::{test_sphere, Context},
+        eval::MathShape,
+        vm::VmShape,
+    };
+
+    /// Returns twice the signed area of a closed polyline
+    fn area2(line: &Polyline) -> f32 {
+        let n = line.points.len();
+        (0..n)
+            .map(|i| {
+                let (a, b) = (line.points[i], line.points[(i + 1) % n]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_contour_circles() {
+        let mut ctx = Context::new();
+        let a = test_sphere(&mut ctx, [-0.5, 0.0], 0.3);
+        let b = test_sphere(&mut ctx, [0.5, 0.25], 0.2);
+        let root = ctx.min(a, b).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+
+        for image_size in [16, 64] {
+            let cfg = RenderConfig::<2> {
+                image_size,
+                ..Default::default()
+            };
+            let c = contour2d(&shape, &cfg).unwrap();
+            assert_eq!(c.lines.len(), 2);
+            for line in &c.lines {
+                assert!(line.closed);
+                // Outer boundaries are counterclockwise
+                assert!(area2(line) > 0.0);
+                for p in &line.points {
+                    let v = ctx
+                        .eval_xyz(root, p.x as f64, p.y as f64, 0.0)
+                        .unwrap();
+                    assert!(v.abs() < 4.0 / image_size as f64, "{v}");
+                }
+            }
+            // Higher resolutions produce more points
+            let total: usize = c.lines.iter().map(|l| l.points.len()).sum();
+            assert!(total >= image_size);
+        }
+
+        // A hole produces a clockwise loop
+        let hole = test_sphere(&mut ctx, [-0.5, 0.0], 0.1);
+        let hole = ctx.neg(hole).unwrap();
+        let ring = ctx.max(a, hole).unwrap();
+        let shape = VmShape::new(&ctx, ring).unwrap();
+        let cfg = RenderConfig::<2> {
+            image_size: 64,
+            ..Default::default()
+        };
+        let c = contour2d(&shape, &cfg).unwrap();
+        assert_eq!(c.lines.len(), 2);
+        let mut areas: Vec<f32> = c.lines.iter().map(area2).collect();
+        areas.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(areas[0] < 0.0 && areas[1] > 0.0);
+
+        let mut svg = vec![];
+        c.write_svg(&mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"viewBox="-1 -1 2 2""#));
+        assert_eq!(svg.matches('M').count(), 2);
+        assert_eq!(svg.matches('Z').count(), 2);
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_contour_open() {
+        // A half-plane crosses the bounds, so its boundary is an open line
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let root = ctx.sub(x, y).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        let cfg = RenderConfig::<2> {
+            image_size: 9,
+            ..Default::default()
+        };
+        let c = contour2d(&shape, &cfg).unwrap();
+        assert_eq!(c.lines.len(), 1);
+        let line = &c.lines[0];
+        assert!(!line.closed);
+        // Interior (x < y) is on the left, so the line runs from the bottom
+        // left to the top right
+        let (a, b) = (line.points[0], *line.points.last().unwrap());
+        assert_eq!(a, Point2::new(-1.0, -1.0));
+        assert_eq!(b, Point2::new(1.0, 1.0));
+        for p in &line.points {
+            assert!((p.x - p.y).abs() < 1e-6);
+        }
+
+        let mut svg = vec![];
+        c.write_svg(&mut svg).unwrap();
+        assert!(!String::from_utf8(svg).unwrap().contains('Z'));
+    }
+
+    #[test]
+    fn test_contour_saddle() {
+        // f = c - xy on a single cell with corners at (±1, ±1), which is
+        // inside at (1, 1) and (-1, -1), and ambiguous at the center
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let xy = ctx.mul(x, y).unwrap();
+        let cfg = RenderConfig::<2> {
+            image_size: 1,
+            ..Default::default()
+        };
+        for c in [0.5, -0.5] {
+            let root = ctx.sub(c, xy).unwrap();
+            let shape = VmShape::new(&ctx, root).unwrap();
+            let out = contour2d(&shape, &cfg).unwrap();
+            assert_eq!(out.lines.len(), 2);
+            for line in &out.lines {
+                assert_eq!(line.points.len(), 2);
+                let [a, b] = [line.points[0], line.points[1]];
+                if c > 0.0 {
+                    // The center is outside, so each line cuts off one of
+                    // the inside corners
+                    assert_eq!((a.x + a.y).signum(), (b.x + b.y).signum());
+                } else {
+                    // The center is inside, so each line cuts off one of
+                    // the outside corners
+                    assert_eq!((a.x - a.y).signum(), (b.x - b.y).signum());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! Dense sampling of a shape on a regular 3D grid
+use crate::{
+    context::{Context, Node},
+    eval::{BulkEvaluator, EzShape, MathShape, Shape},
+    render::RenderConfig,
+    shape::Bounds,
+    Error,
+};
+use nalgebra::{Point3, Transform3};
+use std::sync::Mutex;
+
+/// Samples a shape on a regular 3D grid, using a single thread
+///
+/// The grid has `config.image_size` samples on each axis, placed at voxel
+/// centers within `config.bounds`; `config.threads` and `config.tile_sizes`
+/// are ignored.  Values are returned in `x`-major order, i.e. the sample at
+/// `(i, j, k)` is at index `i + j * n + k * n * n`.
+///
+/// ```
+/// use fidget::{render::{sample_grid, RenderConfig}, vm::VmShape};
+/// # use fidget::eval::MathShape;
+/// let mut ctx = fidget::Context::new();
+/// let x = ctx.x();
+/// let shape = VmShape::new(&ctx, x)?;
+///
+/// let cfg = RenderConfig::<3> {
+///     image_size: 4,
+///     ..Default::default()
+/// };
+/// let out = sample_grid(&shape, &cfg)?;
+/// assert_eq!(out.len(), 64);
+/// assert_eq!(&out[..4], &[-0.75, -0.25, 0.25, 0.75]);
+/// # Ok::<(), fidget::Error>(())
+/// ```
+pub fn sample_grid<S: Shape>(
+    shape: &S,
+    config: &RenderConfig<3>,
+) -> Result<Vec<f32>, Error> {
+    let n = config.image_size;
+    let mut out = vec![0.0; n.pow(3)];
+    if out.is_empty() {
+        return Ok(out);
+    }
+    let tape = shape.ez_float_slice_tape();
+    let mut eval = S::new_float_slice_eval();
+    sample_slab::<S>(&mut eval, &tape, config, 0, &mut out)?;
+    Ok(out)
+}
+
+/// Samples a shape on a regular 3D grid, using `config.threads` threads
+///
+/// The grid is split into slabs along the Z axis, which are handed out to
+/// worker threads (each with its own evaluator) as they become free.  Each
+/// slab writes to a disjoint range of the output, so the result is identical
+/// to [`sample_grid`].
+pub fn sample_grid_parallel<S: Shape>(
+    shape: &S,
+    config: &RenderConfig<3>,
+) -> Result<Vec<f32>, Error> {
+    let n = config.image_size;
+    let threads = config.threads.max(1);
+    let mut out = vec![0.0; n.pow(3)];
+    if out.is_empty() {
+        return Ok(out);
+    }
+    let tape = shape.ez_float_slice_tape();
+
+    // Use a few slabs per thread, so that threads which finish early can pick
+    // up more work
+    let slab_depth = (n / (threads * 4)).max(1);
+    let slabs = Mutex::new(out.chunks_mut(n * n * slab_depth).enumerate());
+
+    std::thread::scope(|s| {
+        let mut handles = vec![];
+        for _ in 0..threads {
+            handles.push(s.spawn(|| -> Result<(), Error> {
+                let mut eval = S::new_float_slice_eval();
+                loop {
+                    let Some((i, slab)) = slabs.lock().unwrap().next() else {
+                        break Ok(());
+                    };
+                    sample_slab::<S>(
+                        &mut eval,
+                        &tape,
+                        config,
+                        i * slab_depth,
+                        slab,
+                    )?;
+                }
+            }));
+        }
+        handles.into_iter().try_for_each(|h| h.join().unwrap())
+    })?;
+    Ok(out)
+}
+
+/// Renders an orthographic "x-ray" image of a shape
+///
+/// Each pixel's ray is marched along the Z axis through `config.bounds`,
+/// taking `config.image_size` samples at voxel centers (as in
+/// [`sample_grid`]).  The result is the length of the ray which is inside the
+/// shape, in shape units, i.e. the number of samples with a negative value
+/// times the voxel size.  Pixels are in the same order as
+/// [`render3d`](crate::render::render3d), with the top row first.
+///
+/// Z planes are handed out to `config.threads` worker threads; each thread
+/// counts samples separately and the counts are summed at the end, so the
+/// result doesn't depend on the number of threads.
+pub fn render_xray<S: Shape>(
+    shape: &S,
+    config: &RenderConfig<3>,
+) -> Result<Vec<f32>, Error> {
+    let n = config.image_size;
+    let threads = config.threads.max(1);
+    if n == 0 {
+        return Ok(vec![]);
+    }
+    let tape = shape.ez_float_slice_tape();
+    let planes = Mutex::new(0..n);
+
+    let counts = std::thread::scope(|s| {
+        let mut handles = vec![];
+        for _ in 0..threads {
+            handles.push(s.spawn(|| -> Result<Vec<u32>, Error> {
+                let mut eval = S::new_float_slice_eval();
+                let mut plane = vec![0.0; n * n];
+                let mut counts = vec![0; n * n];
+                loop {
+                    let Some(k) = planes.lock().unwrap().next() else {
+                        break Ok(counts);
+                    };
+                    sample_slab::<S>(&mut eval, &tape, config, k, &mut plane)?;
+                    for (c, v) in counts.iter_mut().zip(&plane) {
+                        *c += (*v < 0.0) as u32;
+                    }
+                }
+            }));
+        }
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Result<Vec<_>, Error>>()
+    })?;
+
+    let step = config.bounds.size * 2.0 / n as f32;
+    let mut out = vec![0.0; n * n];
+    for (j, row) in out.chunks_mut(n).rev().enumerate() {
+        for (i, o) in row.iter_mut().enumerate() {
+            let c: u32 = counts.iter().map(|c| c[i + j * n]).sum();
+            *o = c as f32 * step;
+        }
+    }
+    Ok(out)
+}
+
+impl Context {
+    /// Bakes a node into a dense grid of distance samples
+    ///
+    /// The grid has `resolution` samples on each axis, placed at voxel centers
+    /// within `bounds` (the same layout as [`sample_grid`]), so that it can be
+    /// trilinearly interpolated like a 3D texture.  Sampling is multithreaded
+    /// and uses the JIT evaluator when the `jit` feature is enabled.
+    ///
+    /// ```
+    /// # let mut ctx = fidget::Context::new();
+    /// let x = ctx.x();
+    /// let out = ctx.bake_sdf(x, 4, Default::default())?;
+    /// assert_eq!(&out[..4], &[-0.75, -0.25, 0.25, 0.75]);
+    /// # Ok::<(), fidget::Error>(())
+    /// ```
+    pub fn bake_sdf(
+        &self,
+        node: Node,
+        resolution: usize,
+        bounds: Bounds<3>,
+    ) -> Result<Vec<f32>, Error> {
+        #[cfg(feature = "jit")]
+        type BakeShape = crate::jit::JitShape;
+        #[cfg(not(feature = "jit"))]
+        type BakeShape = crate::vm::VmShape;
+
+        let shape = BakeShape::new(self, node)?;
+        let cfg = RenderConfig {
+            image_size: resolution,
+            threads: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            bounds,
+            ..Default::default()
+        };
+        sample_grid_parallel(&shape, &cfg)
+    }
+}
+
+/// Samples a set of Z planes, starting at `z_start`, into `out`
+///
+/// The number of planes is given by the length of `out`.
+fn sample_slab<S: Shape>(
+    eval: &mut S::FloatSliceEval,
+    tape: &<S::FloatSliceEval as BulkEvaluator>::Tape,
+    config: &RenderConfig<3>,
+    z_start: usize,
+    out: &mut [f32],
+) -> Result<(), Error> {
+    let n = config.image_size;
+    let mat: Transform3<f32> = config.bounds.transform();
+
+    // Converts from a voxel index to the [-1, +1] region
+    let pos = |i: usize| (2.0 * i as f32 + 1.0) / n as f32 - 1.0;
+
+    let mut xs = Vec::with_capacity(n * n);
+    let mut ys = Vec::with_capacity(n * n);
+    let mut zs = Vec::with_capacity(n * n);
+    for (k, plane) in out.chunks_mut(n * n).enumerate() {
+        xs.clear();
+        ys.clear();
+        zs.clear();
+        let z = pos(z_start + k);
+        for j in 0..n {
+            let y = pos(j);
+            for i in 0..n {
+                let p = mat.transform_point(&Point3::new(pos(i), y, z));
+                xs.push(p.x);
+                ys.push(p.y);
+                zs.push(p.z);
+            }
+        }
+        let v = eval.eval(tape, &xs, &ys, &zs, &[])?;
+        plane.copy_from_slice(v);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::context::test_sphere;
+    use crate::vm::VmShape;
+
+    const COLONNADE: &str = include_str!("../../../models/colonnade.vm");
+
+    fn check_parallel<S: Shape + MathShape>() {
+        let (ctx, root) = Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let shape = S::new(&ctx, root).unwrap();
+        for image_size in [0, 1, 7, 32, 33] {
+            let serial = sample_grid(
+                &shape,
+                &RenderConfig {
+                    image_size,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            for threads in [1, 3, 8] {
+                let cfg = RenderConfig {
+                    image_size,
+                    threads,
+                    ..Default::default()
+                };
+                let parallel = sample_grid_parallel(&shape, &cfg).unwrap();
+                // Compare bits, so that NaN samples also match
+                assert!(
+                    serial
+                        .iter()
+                        .zip(parallel.iter())
+                        .all(|(a, b)| a.to_bits() == b.to_bits()),
+                    "mismatch at size {image_size} with {threads} threads"
+                );
+                assert_eq!(serial.len(), parallel.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_grid_parallel_vm() {
+        check_parallel::<VmShape>();
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn test_sample_grid_parallel_jit() {
+        check_parallel::<crate::jit::JitShape>();
+    }
+
+    #[test]
+    fn test_sample_grid_bounds() {
+        let mut ctx = Context::new();
+        let x = ctx.x();
+        let y = ctx.y();
+        let z = ctx.z();
+        let xy = ctx.add(x, y).unwrap();
+        let xyz = ctx.add(xy, z).unwrap();
+        let shape = VmShape::new(&ctx, xyz).unwrap();
+
+        let cfg = RenderConfig {
+            image_size: 2,
+            bounds: crate::shape::Bounds {
+                center: nalgebra::Vector3::new(1.0, 2.0, 3.0),
+                size: 2.0,
+            },
+            ..Default::default()
+        };
+        let out = sample_grid(&shape, &cfg).unwrap();
+        // Samples are at center ± 1 on each axis
+        let expected = [3.0, 5.0, 5.0, 7.0, 5.0, 7.0, 7.0, 9.0];
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_render_xray() {
+        let mut ctx = Context::new();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 0.5);
+        let shape = VmShape::new(&ctx, sphere).unwrap();
+
+        let n = 64;
+        let cfg = RenderConfig {
+            image_size: n,
+            threads: 1,
+            ..Default::default()
+        };
+        let out = render_xray(&shape, &cfg).unwrap();
+        assert_eq!(out.len(), n * n);
+        let step = 2.0 / n as f32;
+
+        // The center pixel accumulates the sphere's diameter
+        let center = out[n / 2 * n + n / 2];
+        assert!((center - 1.0).abs() <= 2.0 * step, "bad center {center}");
+
+        // Pixels further from the center accumulate less
+        let off_center = out[n / 2 * n + n * 5 / 8];
+        assert!(off_center > 0.0 && off_center < center - step);
+        assert_eq!(out[0], 0.0);
+        assert_eq!(out[n * n - 1], 0.0);
+
+        // The result doesn't depend on the number of threads
+        for threads in [2, 5] {
+            let cfg = RenderConfig {
+                image_size: n,
+                threads,
+                ..Default::default()
+            };
+            assert_eq!(render_xray(&shape, &cfg).unwrap(), out);
+        }
+    }
+
+    #[test]
+    fn test_render_xray_orientation() {
+        // The half-space y > 0 fills the top half of the image
+        let mut ctx = Context::new();
+        let y = ctx.y();
+        let below = ctx.neg(y).unwrap();
+        let shape = VmShape::new(&ctx, below).unwrap();
+        let n = 8;
+        let cfg = RenderConfig {
+            image_size: n,
+            ..Default::default()
+        };
+        let out = render_xray(&shape, &cfg).unwrap();
+        assert!(out[..n * n / 2].iter().all(|v| *v == 2.0));
+        assert!(out[n * n / 2..].iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn test_bake_sdf() {
+        let mut ctx = Context::new();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 0.5);
+
+        let n = 32;
+        let bounds = Bounds {
+            center: nalgebra::Vector3::new(0.1, -0.2, 0.0),
+            size: 1.5,
+        };
+        let grid = ctx.bake_sdf(sphere, n, bounds).unwrap();
+        assert_eq!(grid.len(), n.pow(3));
+
+        // Trilinear interpolation, with `p` in grid coordinates
+        let sample = |p: [f32; 3]| {
+            let mut i = [0; 3];
+            let mut t = [0.0; 3];
+            for a in 0..3 {
+                let f = p[a].clamp(0.0, (n - 1) as f32);
+                i[a] = (f as usize).min(n - 2);
+                t[a] = f - i[a] as f32;
+            }
+            let mut out = 0.0;
+            for corner in 0..8 {
+                let mut w = 1.0;
+                let mut index = 0;
+                for a in 0..3 {
+                    let hi = (corner >> a) & 1;
+                    w *= if hi == 1 { t[a] } else { 1.0 - t[a] };
+                    index += (i[a] + hi) * n.pow(a as u32);
+                }
+                out += w * grid[index];
+            }
+            out
+        };
+
+        let cell = bounds.size * 2.0 / n as f32;
+        for k in 0..50 {
+            let k = k as f32;
+            let p = nalgebra::Vector3::new(
+                (k * 0.37).sin(),
+                (k * 0.71).cos(),
+                (k * 0.13).sin() * 0.8,
+            ) + bounds.center;
+            // Convert to grid coordinates, where voxel centers are integers
+            let g = (p - bounds.center).add_scalar(bounds.size) / cell;
+            let v = sample([g.x - 0.5, g.y - 0.5, g.z - 0.5]);
+            let expected = p.norm() - 0.5;
+            assert!(
+                (v - expected).abs() < cell,
+                "bad value at {p:?}: {v} != {expected}"
+            );
+        }
+    }
+}
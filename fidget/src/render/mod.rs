@@ -8,14 +8,28 @@ use crate::eval::{BulkEvaluator, Shape, Tape, Trace, TracingEvaluator};
 use std::sync::Arc;
 
 mod config;
+mod contour;
+mod grid;
 mod render2d;
 mod render3d;
+mod rle;
 
 pub use config::RenderConfig;
+pub use contour::{contour2d, Contours, Polyline};
+pub use grid::{render_xray, sample_grid, sample_grid_parallel};
 pub use render2d::render as render2d;
+pub use render2d::render_aa as render2d_aa;
+pub use render2d::render_rle as render_2d_rle;
 pub use render3d::render as render3d;
+pub use render3d::render_depth as render3d_depth;
+pub use render3d::{render_clipped as render3d_clipped, ClipPlane};
+pub use render3d::{render_gbuffer as render3d_gbuffer, GBuffer};
 
-pub use render2d::{BitRenderMode, DebugRenderMode, RenderMode, SdfRenderMode};
+pub use render2d::{
+    BitRenderMode, DebugRenderMode, IsolineRenderMode, RenderMode,
+    SdfRenderMode,
+};
+pub use rle::{decode_rle, encode_rle};
 
 struct RenderHandle<S: Shape> {
     shape: S,
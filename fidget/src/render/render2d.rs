@@ -2,7 +2,10 @@
 use super::RenderHandle;
 use crate::{
     eval::{BulkEvaluator, Shape, TracingEvaluator},
-    render::config::{AlignedRenderConfig, Queue, RenderConfig, Tile},
+    render::{
+        config::{AlignedRenderConfig, Queue, RenderConfig, Tile},
+        encode_rle,
+    },
     types::Interval,
 };
 use nalgebra::Point2;
@@ -149,6 +152,54 @@ impl RenderMode for SdfRenderMode {
     }
 }
 
+/// Rendering mode which draws contour lines at a set of values
+///
+/// Each pixel is set to the (1-based) index of the first level in
+/// [`levels`](Self::levels) which is within [`band`](Self::band) of the
+/// pixel's value, or 0 if it isn't near any level, producing a
+/// topographic-map style image.  Only the first 255 levels can be drawn.
+pub struct IsolineRenderMode {
+    /// Values at which to draw contour lines
+    pub levels: Vec<f32>,
+    /// Half-width of each line, in shape units
+    pub band: f32,
+}
+
+impl IsolineRenderMode {
+    /// Builds a new isoline mode for the given render configuration
+    ///
+    /// The band is set to the size of one pixel, so lines of a distance field
+    /// are about two pixels wide.
+    pub fn new(levels: &[f32], config: &RenderConfig<2>) -> Self {
+        Self {
+            levels: levels.to_vec(),
+            band: config.bounds.size * 2.0 / config.image_size as f32,
+        }
+    }
+}
+
+impl RenderMode for IsolineRenderMode {
+    type Output = u8;
+    fn interval(&self, i: Interval, _depth: usize) -> Option<u8> {
+        let near = |v: &f32| {
+            i.lower() - self.band <= *v && *v <= i.upper() + self.band
+        };
+        if i.has_nan() || self.levels.iter().take(255).any(near) {
+            None
+        } else {
+            Some(0)
+        }
+    }
+    fn pixel(&self, f: f32) -> u8 {
+        self.levels
+            .iter()
+            .take(255)
+            .position(|v| (f - v).abs() <= self.band)
+            .map(|i| i as u8 + 1)
+            .unwrap_or(0)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 struct Scratch {
@@ -291,12 +342,13 @@ impl<S: Shape, M: RenderMode> Worker<'_, S, M> {
 
 ////////////////////////////////////////////////////////////////////////////////
 
-fn worker<S: Shape, M: RenderMode>(
+fn worker<S: Shape, M: RenderMode, T>(
     mut shape: RenderHandle<S>,
     queue: &Queue<2>,
     config: &AlignedRenderConfig<2>,
     mode: &M,
-) -> Vec<(Tile<2>, Vec<M::Output>)> {
+    pack: &impl Fn(Tile<2>, Vec<M::Output>) -> T,
+) -> Vec<(Tile<2>, T)> {
     let mut out = vec![];
     let scratch = Scratch::new(config.tile_sizes.last().unwrap_or(&0).pow(2));
 
@@ -314,7 +366,7 @@ fn worker<S: Shape, M: RenderMode>(
         w.image = vec![M::Output::default(); config.tile_sizes[0].pow(2)];
         w.render_tile_recurse(&mut shape, 0, tile, mode);
         let pixels = std::mem::take(&mut w.image);
-        out.push((tile, pixels))
+        out.push((tile, pack(tile, pixels)))
     }
     out
 }
@@ -335,6 +387,62 @@ pub fn render<S: Shape, M: RenderMode + Sync>(
     config: &RenderConfig<2>,
     mode: &M,
 ) -> Vec<M::Output> {
+    let (shape, config) = align(shape, config);
+    render_inner(shape, config, mode)
+}
+
+/// Renders a bitmap of the given shape as run-length spans
+///
+/// The result matches [`encode_rle`] of the image from [`render`] with
+/// [`BitRenderMode`], but each tile is encoded as soon as it's rendered, so the
+/// dense image is never stored; use [`decode_rle`](super::decode_rle) to
+/// recover it.
+pub fn render_rle<S: Shape>(
+    shape: S,
+    config: &RenderConfig<2>,
+) -> Vec<(u32, bool)> {
+    let (shape, config) = align(shape, config);
+    let size = config.orig_image_size;
+    let tile_size = config.tile_sizes[0];
+
+    // Encode each row of each tile, clipped to the image bounds
+    let out = render_tiles(shape, &config, &BitRenderMode, |tile, data| {
+        let width = size.saturating_sub(tile.corner[0]).min(tile_size);
+        data.chunks(tile_size)
+            .map(|row| encode_rle(&row[..width]))
+            .collect::<Vec<_>>()
+    });
+    let tiles_per_row = config.image_size / tile_size;
+    let mut rows = vec![vec![]; tiles_per_row.pow(2)];
+    for (tile, data) in out {
+        let i = tile.corner[0] / tile_size;
+        let j = tile.corner[1] / tile_size;
+        rows[i + j * tiles_per_row] = data;
+    }
+
+    // Stitch rows together in scanline order (with +Y up)
+    let mut spans: Vec<(u32, bool)> = vec![];
+    for y in (0..size).rev() {
+        let j = y / tile_size;
+        for i in 0..size.div_ceil(tile_size) {
+            for &(n, b) in &rows[i + j * tiles_per_row][y % tile_size] {
+                match spans.last_mut() {
+                    Some((m, v)) if *v == b && m.checked_add(n).is_some() => {
+                        *m += n
+                    }
+                    _ => spans.push((n, b)),
+                }
+            }
+        }
+    }
+    spans
+}
+
+/// Aligns the render configuration and applies its transform to the shape
+fn align<S: Shape>(
+    shape: S,
+    config: &RenderConfig<2>,
+) -> (S::TransformedShape, AlignedRenderConfig<2>) {
     let (config, mat) = config.align();
     assert!(config.image_size % config.tile_sizes[0] == 0);
     for i in 0..config.tile_sizes.len() - 1 {
@@ -344,9 +452,51 @@ pub fn render<S: Shape, M: RenderMode + Sync>(
     // Convert to a 4x4 matrix and apply to the shape
     let mat = mat.insert_row(2, 0.0);
     let mat = mat.insert_column(2, 0.0);
-    let shape = shape.apply_transform(mat);
+    (shape.apply_transform(mat), config)
+}
 
-    render_inner(shape, config, mode)
+/// Renders an anti-aliased coverage mask of the given shape
+///
+/// Each pixel is divided into a `samples × samples` grid of sub-pixels, which
+/// are rendered (as with [`BitRenderMode`]) in a single pass at `samples`
+/// times the configured resolution, so tiles of sub-pixels are still pruned
+/// with interval arithmetic and evaluated in bulk.  The output pixel is the
+/// fraction of its sub-pixels which are inside the shape, scaled to the range
+/// 0 (empty) to 255 (filled).
+///
+/// A `samples` value of 1 is equivalent to rendering with [`BitRenderMode`].
+///
+/// # Panics
+/// If `samples` is 0
+pub fn render_aa<S: Shape>(
+    shape: S,
+    config: &RenderConfig<2>,
+    samples: usize,
+) -> Vec<u8> {
+    assert!(samples > 0, "`samples` must be at least 1");
+    let size = config.image_size;
+    let hi_res = RenderConfig {
+        image_size: size * samples,
+        tile_sizes: config.tile_sizes.clone(),
+        threads: config.threads,
+        bounds: config.bounds,
+    };
+    let bits = render(shape, &hi_res, &BitRenderMode);
+
+    let n = samples.pow(2);
+    let mut out = vec![0; size.pow(2)];
+    for (y, row) in out.chunks_mut(size).enumerate() {
+        for (x, p) in row.iter_mut().enumerate() {
+            let mut count = 0;
+            for j in 0..samples {
+                let start = (y * samples + j) * size * samples + x * samples;
+                count +=
+                    bits[start..start + samples].iter().filter(|b| **b).count();
+            }
+            *p = ((count * 255 + n / 2) / n) as u8;
+        }
+    }
+    out
 }
 
 fn render_inner<S: Shape, M: RenderMode + Sync>(
@@ -354,6 +504,38 @@ fn render_inner<S: Shape, M: RenderMode + Sync>(
     config: AlignedRenderConfig<2>,
     mode: &M,
 ) -> Vec<M::Output> {
+    let out = render_tiles(shape, &config, mode, |_tile, data| data);
+
+    let mut image = vec![M::Output::default(); config.orig_image_size.pow(2)];
+    for (tile, data) in out.iter() {
+        let mut index = 0;
+        for j in 0..config.tile_sizes[0] {
+            let y = j + tile.corner[1];
+            for i in 0..config.tile_sizes[0] {
+                let x = i + tile.corner[0];
+                if y < config.orig_image_size && x < config.orig_image_size {
+                    let o = (config.orig_image_size - y - 1)
+                        * config.orig_image_size
+                        + x;
+                    image[o] = data[index];
+                }
+                index += 1;
+            }
+        }
+    }
+    image
+}
+
+/// Renders every tile of the image, converting each with `pack`
+///
+/// `pack` is called on worker threads, as soon as each tile is rendered;
+/// tiles are returned in arbitrary order.
+fn render_tiles<S: Shape, M: RenderMode + Sync, T: Send>(
+    shape: S,
+    config: &AlignedRenderConfig<2>,
+    mode: &M,
+    pack: impl Fn(Tile<2>, Vec<M::Output>) -> T + Sync,
+) -> Vec<(Tile<2>, T)> {
     let mut tiles = vec![];
     for i in 0..config.image_size / config.tile_sizes[0] {
         for j in 0..config.image_size / config.tile_sizes[0] {
@@ -365,45 +547,29 @@ fn render_inner<S: Shape, M: RenderMode + Sync>(
     }
 
     let i_tape = Arc::new(shape.interval_tape(Default::default()));
-    let queue = Queue::new(tiles);
-    let out = std::thread::scope(|s| {
+    let queue = &Queue::new(tiles);
+    let pack = &pack;
+    std::thread::scope(|s| {
         let mut handles = vec![];
         for _ in 0..config.threads {
             let shape = RenderHandle::new(shape.clone(), i_tape.clone());
-            handles
-                .push(s.spawn(|| worker::<S, M>(shape, &queue, &config, mode)));
+            handles.push(s.spawn(move || {
+                worker::<S, M, T>(shape, queue, config, mode, pack)
+            }));
         }
         let mut out = vec![];
         for h in handles {
             out.extend(h.join().unwrap().into_iter());
         }
         out
-    });
-
-    let mut image = vec![M::Output::default(); config.orig_image_size.pow(2)];
-    for (tile, data) in out.iter() {
-        let mut index = 0;
-        for j in 0..config.tile_sizes[0] {
-            let y = j + tile.corner[1];
-            for i in 0..config.tile_sizes[0] {
-                let x = i + tile.corner[0];
-                if y < config.orig_image_size && x < config.orig_image_size {
-                    let o = (config.orig_image_size - y - 1)
-                        * config.orig_image_size
-                        + x;
-                    image[o] = data[index];
-                }
-                index += 1;
-            }
-        }
-    }
-    image
+    })
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
+        context::test_sphere,
         eval::{MathShape, Shape},
         shape::Bounds,
         vm::{GenericVmShape, VmShape},
@@ -680,4 +846,178 @@ mod test {
     fn render_quarter_jit() {
         check_quarter::<crate::jit::JitShape>();
     }
+
+    fn check_threads<S: Shape + MathShape>() {
+        const COLONNADE: &str = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../models/colonnade.vm"
+        ));
+        let (ctx, root) = Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let shape = S::new(&ctx, root).unwrap();
+        let render_with = |threads| {
+            let cfg = RenderConfig::<2> {
+                image_size: 500, // not a multiple of the tile size
+                threads,
+                ..RenderConfig::default()
+            };
+            render(shape.clone(), &cfg, &SdfRenderMode)
+        };
+        let serial = render_with(1);
+        assert!(serial.iter().any(|p| *p != serial[0]));
+        for threads in [2, 3, 8] {
+            assert!(serial == render_with(threads), "mismatch at {threads}");
+        }
+    }
+
+    #[test]
+    fn render_threads_vm() {
+        check_threads::<VmShape>();
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn render_threads_jit() {
+        check_threads::<crate::jit::JitShape>();
+    }
+
+    /// Models which fold to a constant fill the whole image (or none of it)
+    fn check_constant<S: Shape + MathShape>() {
+        let cfg = RenderConfig::<2> {
+            image_size: 100, // not a multiple of the tile size
+            ..RenderConfig::default()
+        };
+        for (txt, filled) in [
+            "a const 1.0\nb const 2.0\nc sub a b",
+            "a const 2.0\nb const 1.0\nc sub a b",
+        ]
+        .into_iter()
+        .zip([true, false])
+        {
+            let (ctx, root) = Context::from_text(txt.as_bytes()).unwrap();
+            assert!(ctx.const_value(root).unwrap().is_some());
+            let shape = S::new(&ctx, root).unwrap();
+
+            let out = render(shape.clone(), &cfg, &BitRenderMode);
+            assert_eq!(out.len(), 100 * 100);
+            assert!(out.iter().all(|p| *p == filled));
+
+            let aa = render_aa(shape, &cfg, 2);
+            assert!(aa.iter().all(|p| *p == if filled { 255 } else { 0 }));
+        }
+    }
+
+    #[test]
+    fn render_constant_vm() {
+        check_constant::<VmShape>();
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn render_constant_jit() {
+        check_constant::<crate::jit::JitShape>();
+    }
+
+    /// The same generic renderer should produce matching images with the
+    /// interpreter and the JIT
+    #[cfg(feature = "jit")]
+    #[test]
+    fn render_vm_jit_match() {
+        let (ctx, root) = Context::from_text(QUARTER.as_bytes()).unwrap();
+        let vm = VmShape::new(&ctx, root).unwrap();
+        let jit = crate::jit::JitShape::new(&ctx, root).unwrap();
+        let cfg = RenderConfig::<2> {
+            image_size: 256,
+            ..RenderConfig::default()
+        };
+        let a = render(vm, &cfg, &BitRenderMode);
+        let b = render(jit, &cfg, &BitRenderMode);
+        assert!(a.iter().any(|p| *p));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_render_aa() {
+        let mut ctx = Context::new();
+        let circle = test_sphere(&mut ctx, [0.0; 2], 0.5);
+        let shape = VmShape::new(&ctx, circle).unwrap();
+
+        const SIZE: usize = 64;
+        let cfg = RenderConfig::<2> {
+            image_size: SIZE,
+            ..RenderConfig::default()
+        };
+
+        // Without supersampling, pixels are either filled or empty
+        let aliased = render_aa(shape.clone(), &cfg, 1);
+        let bits = render(shape.clone(), &cfg, &BitRenderMode);
+        assert!(aliased.iter().all(|p| *p == 0 || *p == 255));
+        assert!(aliased.iter().zip(&bits).all(|(a, b)| (*a == 255) == *b));
+
+        let smooth = render_aa(shape, &cfg, 4);
+        let mut gray = 0;
+        for (i, p) in smooth.iter().enumerate() {
+            let [px, py] = [i % SIZE, i / SIZE]
+                .map(|v| (v as f32 + 0.5) / SIZE as f32 * 2.0 - 1.0);
+            let r = px.hypot(py);
+            let pixel = 2.0 / SIZE as f32;
+            if r < 0.5 - pixel {
+                assert_eq!(*p, 255);
+            } else if r > 0.5 + pixel {
+                assert_eq!(*p, 0);
+            } else if *p != 0 && *p != 255 {
+                gray += 1;
+            }
+        }
+        assert!(gray > SIZE, "too few boundary pixels: {gray}");
+
+        // Coverage adds up to the circle's area (in a 2 × 2 image)
+        let total = smooth.iter().map(|p| *p as f32 / 255.0).sum::<f32>();
+        let area = total / SIZE.pow(2) as f32 * 4.0;
+        let expected = std::f32::consts::PI * 0.25;
+        assert!((area / expected - 1.0).abs() < 0.01, "bad area {area}");
+    }
+
+    #[test]
+    fn test_isolines() {
+        let mut ctx = Context::new();
+        let circle = test_sphere(&mut ctx, [0.0; 2], 0.25);
+        let shape = VmShape::new(&ctx, circle).unwrap();
+
+        let cfg = RenderConfig::<2> {
+            image_size: 256,
+            ..RenderConfig::default()
+        };
+        let levels = [0.0, 0.25, 0.5];
+        let mode = IsolineRenderMode::new(&levels, &cfg);
+        let out = render(shape, &cfg, &mode);
+
+        // Every marked pixel is on the ring for its level
+        let pixel = 2.0 / cfg.image_size as f32;
+        let mut counts = [0; 4];
+        for (i, v) in out.iter().enumerate() {
+            counts[*v as usize] += 1;
+            if *v == 0 {
+                continue;
+            }
+            let px = (i % cfg.image_size) as f32 * pixel - 1.0 + pixel / 2.0;
+            let py = (i / cfg.image_size) as f32 * pixel - 1.0 + pixel / 2.0;
+            let d = (px * px + py * py).sqrt() - 0.25;
+            let level = levels[*v as usize - 1];
+            assert!(
+                (d - level).abs() <= 2.0 * pixel,
+                "pixel at ({px}, {py}) is not on level {level}"
+            );
+        }
+
+        // Each ring is present, and larger rings have more pixels
+        assert!(counts[1] > 0);
+        assert!(counts[2] > counts[1]);
+        assert!(counts[3] > counts[2]);
+
+        // Walking outwards from the center, we cross each ring in order
+        let row = &out[cfg.image_size * cfg.image_size / 2..][..cfg.image_size];
+        let mut crossed = row[cfg.image_size / 2..].to_vec();
+        crossed.dedup();
+        assert_eq!(crossed, [0, 1, 0, 2, 0, 3, 0]);
+    }
 }
@@ -1,16 +1,64 @@
 //! 3D bitmap rendering / rasterization
 use super::RenderHandle;
 use crate::{
-    eval::{BulkEvaluator, Shape, TracingEvaluator},
+    eval::{BulkEvaluator, EzShape, Shape, TracingEvaluator},
     render::config::{AlignedRenderConfig, Queue, RenderConfig, Tile},
     types::Interval,
 };
 
-use nalgebra::Point3;
+use nalgebra::{Point3, Transform3, Vector3};
 use std::{collections::HashMap, sync::Arc};
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Plane used to clip a 3D render, revealing a cross-section of the model
+///
+/// Points where `normal · p > offset` are removed, so the model is intersected
+/// with the half-space `normal · p <= offset`.  The plane is given in the same
+/// coordinates as [`RenderConfig::bounds`].
+///
+/// Wherever the clipped model's surface lies on the plane, the cut face (or
+/// "cap") is drawn in `color`, rather than being shaded by its normal.
+#[derive(Copy, Clone, Debug)]
+pub struct ClipPlane {
+    /// Plane normal, pointing away from the region that is kept
+    pub normal: Vector3<f32>,
+    /// Offset of the plane along its normal
+    pub offset: f32,
+    /// Color used to draw the cap
+    pub color: [u8; 3],
+}
+
+/// Clip plane, transformed into voxel coordinates
+#[derive(Copy, Clone, Debug)]
+struct VoxelClip {
+    normal: Vector3<f32>,
+    offset: f32,
+    color: [u8; 3],
+}
+
+impl VoxelClip {
+    /// Returns the (signed) distance above the plane
+    fn eval(&self, p: Point3<f32>) -> f32 {
+        self.normal.dot(&p.coords) - self.offset
+    }
+
+    /// Returns the range of distances above the plane within a box
+    fn eval_interval(&self, x: Interval, y: Interval, z: Interval) -> Interval {
+        let mut lower = -self.offset;
+        let mut upper = -self.offset;
+        for (n, v) in self.normal.iter().zip([x, y, z]) {
+            let a = n * v.lower();
+            let b = n * v.upper();
+            lower += a.min(b);
+            upper += a.max(b);
+        }
+        Interval::new(lower, upper)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 struct Scratch {
     x: Vec<f32>,
     y: Vec<f32>,
@@ -39,6 +87,9 @@ impl Scratch {
 struct Worker<'a, S: Shape> {
     config: &'a AlignedRenderConfig<3>,
 
+    /// Optional clip plane, in voxel coordinates
+    clip: Option<VoxelClip>,
+
     /// Reusable workspace for evaluation, to minimize allocation
     scratch: Scratch,
 
@@ -77,11 +128,23 @@ impl<S: Shape> Worker<'_, S> {
         let y = Interval::new(base.y, base.y + tile_size as f32);
         let z = Interval::new(base.z, base.z + tile_size as f32);
 
-        let (i, trace) = self
+        let (mut i, trace) = self
             .eval_interval
             .eval(shape.i_tape(&mut self.tape_storage), x, y, z, &[])
             .unwrap();
 
+        // Intersect with the clip plane's half-space, i.e. max(shape, plane)
+        if let Some(clip) = &self.clip {
+            let c = clip.eval_interval(x, y, z);
+            if c.lower() > 0.0 {
+                return;
+            }
+            i = Interval::new(
+                i.lower().max(c.lower()),
+                i.upper().max(c.upper()),
+            );
+        }
+
         // Return early if this tile is completely empty or full, returning
         // `data_interval` to scratch memory for reuse.
         if i.upper() < 0.0 {
@@ -196,13 +259,6 @@ impl<S: Shape> Worker<'_, S> {
         let mut grad = 0;
         let mut depth = out.chunks(tile_size);
         for col in 0..self.scratch.columns.len() {
-            // Find the first set pixel in the column
-            let depth = depth.next().unwrap();
-            let k = match depth.iter().enumerate().find(|(_, d)| **d < 0.0) {
-                Some((i, _)) => i,
-                None => continue,
-            };
-
             // Get X and Y values from the `columns` array.  Note that we can't
             // iterate over the array directly because we're also modifying it
             // (below)
@@ -210,6 +266,31 @@ impl<S: Shape> Worker<'_, S> {
             let i = xy % tile_size;
             let j = xy / tile_size;
 
+            // Find the first set pixel in the column, checking whether it was
+            // produced by the clip plane (rather than the shape's surface)
+            let depth = depth.next().unwrap();
+            let (k, cap) = match self.clip {
+                None => match depth.iter().position(|d| *d < 0.0) {
+                    Some(k) => (k, false),
+                    None => continue,
+                },
+                Some(clip) => {
+                    let found = depth.iter().enumerate().find_map(|(k, d)| {
+                        let p = Point3::new(
+                            (tile.corner[0] + i) as f32,
+                            (tile.corner[1] + j) as f32,
+                            (tile.corner[2] + tile_size - 1 - k) as f32,
+                        );
+                        let c = clip.eval(p);
+                        (*d < 0.0 && c < 0.0).then_some((k, c >= *d))
+                    });
+                    match found {
+                        Some(v) => v,
+                        None => continue,
+                    }
+                }
+            };
+
             // Flip Z value, since voxels are packed front-to-back
             let k = tile_size - 1 - k;
 
@@ -219,6 +300,12 @@ impl<S: Shape> Worker<'_, S> {
             assert!(self.depth[o] < z);
             self.depth[o] = z;
 
+            // Caps are drawn in a flat color, without gradient evaluation
+            if cap {
+                self.color[o] = self.clip.unwrap().color;
+                continue;
+            }
+
             // Prepare to do gradient rendering of this point.
             // We step one voxel above the surface to reduce
             // glitchiness on edges and corners, where rendering
@@ -277,6 +364,7 @@ fn worker<S: Shape>(
     queues: &[Queue<3>],
     mut index: usize,
     config: &AlignedRenderConfig<3>,
+    clip: Option<VoxelClip>,
 ) -> HashMap<[usize; 2], Image> {
     let mut out = HashMap::new();
 
@@ -288,6 +376,7 @@ fn worker<S: Shape>(
         depth: vec![],
         color: vec![],
         config,
+        clip,
 
         eval_float_slice: S::FloatSliceEval::new(),
         eval_interval: S::IntervalEval::new(),
@@ -345,6 +434,19 @@ fn worker<S: Shape>(
 pub fn render<S: Shape>(
     shape: S,
     config: &RenderConfig<3>,
+) -> (Vec<u32>, Vec<[u8; 3]>) {
+    render_clipped(shape, config, None)
+}
+
+/// Renders the given tape into a 3D image, optionally cut by a clip plane
+///
+/// With `clip` set to `None`, this is equivalent to [`render`]; otherwise, the
+/// model is intersected with the plane's half-space, and the cut face is drawn
+/// in the plane's color (see [`ClipPlane`]).
+pub fn render_clipped<S: Shape>(
+    shape: S,
+    config: &RenderConfig<3>,
+    clip: Option<ClipPlane>,
 ) -> (Vec<u32>, Vec<[u8; 3]>) {
     let (config, mat) = config.align();
     assert!(config.image_size % config.tile_sizes[0] == 0);
@@ -352,13 +454,137 @@ pub fn render<S: Shape>(
         assert!(config.tile_sizes[i] % config.tile_sizes[i + 1] == 0);
     }
 
+    // The transform from voxels to model coordinates is affine, so the plane
+    // remains a plane (scaled to measure distance in model units)
+    let clip = clip.map(|c| {
+        let n = c.normal.normalize();
+        let scale = c.normal.norm();
+        let a = mat.fixed_slice::<3, 3>(0, 0);
+        let t = mat.fixed_slice::<3, 1>(0, 3);
+        VoxelClip {
+            normal: a.transpose() * n,
+            offset: c.offset / scale - n.dot(&t),
+            color: c.color,
+        }
+    });
+
     let shape = shape.apply_transform(mat);
-    render_inner(shape, config)
+    render_inner(shape, config, clip)
+}
+
+/// Per-pixel layers from rendering several models, for deferred shading
+///
+/// Each layer is an image of the same size, in the same pixel order as
+/// [`render`].
+#[derive(Debug)]
+pub struct GBuffer {
+    /// Depth of the nearest hit, or 0 where nothing was hit
+    pub depth: Vec<u32>,
+    /// Index (into the list of rendered shapes) of the nearest hit
+    pub id: Vec<Option<usize>>,
+    /// Normal of the nearest hit, encoded as a color (as in [`render`])
+    pub normal: Vec<[u8; 3]>,
+}
+
+/// Renders several shapes into a [`GBuffer`]
+///
+/// Each shape is rendered separately with [`render`], then the images are
+/// composited:  at each pixel, the nearest hit wins, recording its depth,
+/// normal, and the index of its shape in `shapes`.  If two shapes are hit at
+/// the same depth, the earlier shape wins.
+pub fn render_gbuffer<S: Shape>(
+    shapes: &[S],
+    config: &RenderConfig<3>,
+) -> GBuffer {
+    let size = config.image_size.pow(2);
+    let mut out = GBuffer {
+        depth: vec![0; size],
+        id: vec![None; size],
+        normal: vec![[0; 3]; size],
+    };
+    for (i, shape) in shapes.iter().enumerate() {
+        let (depth, normal) = render(shape.clone(), config);
+        for (o, (d, n)) in depth.into_iter().zip(normal).enumerate() {
+            if d > out.depth[o] {
+                out.depth[o] = d;
+                out.id[o] = Some(i);
+                out.normal[o] = n;
+            }
+        }
+    }
+    out
+}
+
+/// Renders a depth map of the given shape, with sub-voxel precision
+///
+/// Each pixel's ray travels along the -Z axis from the near plane (the top of
+/// `config.bounds`) to the far plane (the bottom of the bounds).  The first
+/// surface crossing is found in voxel steps with [`render`], then refined by
+/// bisection (with a point evaluator) until it's known to within `tolerance`.
+///
+/// The result is the distance from the near plane to the surface, in shape
+/// units, so it's in the range `[0, 2 * config.bounds.size]` and can be
+/// divided by that value to normalize it; pixels which are already inside the
+/// shape at the near plane have a depth of 0.  Pixels whose ray doesn't hit
+/// the shape before the far plane have a depth of `f32::INFINITY`.  Pixels
+/// are in the same order as [`render`].
+pub fn render_depth<S: Shape>(
+    shape: S,
+    config: &RenderConfig<3>,
+    tolerance: f32,
+) -> Vec<f32> {
+    let (depth, _) = render(shape.clone(), config);
+
+    let n = config.image_size;
+    let mat: Transform3<f32> = config.bounds.transform();
+    let size = config.bounds.size;
+    let tape = shape.ez_point_tape();
+    let mut eval = S::new_point_eval();
+
+    // Converts from a pixel coordinate to the [-1, +1] region
+    let pos = |i: f32| 2.0 * i / n as f32 - 1.0;
+
+    depth
+        .iter()
+        .enumerate()
+        .map(|(o, &d)| {
+            if d == 0 {
+                return f32::INFINITY;
+            }
+            let x = pos((o % n) as f32);
+            let y = pos((n - o / n - 1) as f32);
+            let mut eval_z = |z: f32| {
+                let p = mat.transform_point(&Point3::new(x, y, z));
+                eval.eval(&tape, p.x, p.y, p.z, &[]).unwrap().0
+            };
+
+            // The voxel at `d - 1` is inside the shape, and the voxel above it
+            // is outside (or above the near plane)
+            let mut lo = pos(d as f32 - 1.0);
+            let mut hi = pos(d as f32).min(1.0);
+            if lo >= 1.0 || eval_z(1.0) < 0.0 {
+                return 0.0;
+            }
+            while (hi - lo) * size > tolerance {
+                let mid = (lo + hi) / 2.0;
+                if mid <= lo || mid >= hi {
+                    break; // out of floating-point precision
+                }
+                if eval_z(mid) < 0.0 {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            (1.0 - (lo + hi) / 2.0) * size
+        })
+        .collect()
 }
 
-pub fn render_inner<S: Shape>(
+fn render_inner<S: Shape>(
     shape: S,
     config: AlignedRenderConfig<3>,
+    clip: Option<VoxelClip>,
 ) -> (Vec<u32>, Vec<[u8; 3]>) {
     let mut tiles = vec![];
     for i in 0..config.image_size / config.tile_sizes[0] {
@@ -384,7 +610,7 @@ pub fn render_inner<S: Shape>(
     // Special-case for single-threaded operation, to give simpler backtraces
     let out = if config.threads == 1 {
         let shape = RenderHandle::new(shape, i_tape);
-        worker::<S>(shape, tile_queues.as_slice(), 0, &config)
+        worker::<S>(shape, tile_queues.as_slice(), 0, &config, clip)
             .into_iter()
             .collect()
     } else {
@@ -394,9 +620,9 @@ pub fn render_inner<S: Shape>(
             let queues = tile_queues.as_slice();
             for i in 0..config.threads {
                 let handle = RenderHandle::new(shape.clone(), i_tape.clone());
-                handles.push(
-                    s.spawn(move || worker::<S>(handle, queues, i, config_ref)),
-                );
+                handles.push(s.spawn(move || {
+                    worker::<S>(handle, queues, i, config_ref, clip)
+                }));
             }
             let mut out = vec![];
             for h in handles {
@@ -433,7 +659,7 @@ pub fn render_inner<S: Shape>(
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{eval::MathShape, vm::VmShape, Context};
+    use crate::{context::test_sphere, eval::MathShape, vm::VmShape, Context};
 
     /// Make sure we don't crash if there's only a single tile
     #[test]
@@ -450,4 +676,211 @@ mod test {
         let out = cfg.run(shape);
         assert!(out.is_ok());
     }
+
+    #[test]
+    fn test_clip_plane() {
+        // Sphere with radius 0.5, clipped at z = 0
+        let mut ctx = Context::new();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 0.5);
+        let shape = VmShape::new(&ctx, sphere).unwrap();
+
+        const SIZE: usize = 128;
+        const CAP: [u8; 3] = [255, 0, 255];
+        for threads in [1, 8] {
+            let cfg = RenderConfig::<3> {
+                image_size: SIZE,
+                threads,
+                ..RenderConfig::default()
+            };
+            let clip = ClipPlane {
+                normal: Vector3::new(0.0, 0.0, 2.0), // not normalized
+                offset: 0.0,
+                color: CAP,
+            };
+            let (depth, color) =
+                render_clipped(shape.clone(), &cfg, Some(clip));
+
+            // Pixels away from the rim are on the cap, which is flat and
+            // colored (at the rim, the sphere's surface may be hit first)
+            let mut count = 0;
+            for (i, (d, c)) in depth.iter().zip(&color).enumerate() {
+                if *d != 0 {
+                    count += 1;
+                }
+                let [px, py] = [i % SIZE, i / SIZE]
+                    .map(|v| (v as f32 + 0.5) / SIZE as f32 * 2.0 - 1.0);
+                if px.hypot(py) < 0.45 {
+                    assert_eq!(*c, CAP);
+                    assert!(d.abs_diff(SIZE as u32 / 2) <= 1, "bad depth {d}");
+                }
+            }
+            // The cap is a disk with radius 0.5 (in a 2 × 2 image)
+            let area = count as f32 / SIZE.pow(2) as f32 * 4.0;
+            let expected = std::f32::consts::PI * 0.25;
+            assert!((area / expected - 1.0).abs() < 0.05, "bad area {area}");
+
+            // Clipping on the other side reveals the sphere's top half, which
+            // is shaded normally (and matches the unclipped render)
+            let clip = ClipPlane {
+                normal: Vector3::new(0.0, 0.0, -1.0),
+                offset: 0.0,
+                color: CAP,
+            };
+            let clipped = render_clipped(shape.clone(), &cfg, Some(clip));
+            let full = render(shape.clone(), &cfg);
+            assert!(clipped.0.iter().any(|d| *d != 0));
+            assert_eq!(clipped.0, full.0);
+            assert_eq!(clipped.1, full.1);
+            assert!(!clipped.1.contains(&CAP));
+
+            // A plane which removes everything produces an empty image
+            let clip = ClipPlane {
+                normal: Vector3::new(1.0, 0.0, 0.0),
+                offset: -0.75,
+                color: CAP,
+            };
+            let (depth, _) = render_clipped(shape.clone(), &cfg, Some(clip));
+            assert!(depth.iter().all(|d| *d == 0));
+        }
+    }
+
+    #[test]
+    fn test_gbuffer() {
+        let mut ctx = Context::new();
+        // The small sphere pokes out of the front of the large one
+        let big = test_sphere(&mut ctx, [0.0; 3], 0.5);
+        let small = test_sphere(&mut ctx, [0.3, 0.0, 0.4], 0.3);
+        let shapes = [
+            VmShape::new(&ctx, big).unwrap(),
+            VmShape::new(&ctx, small).unwrap(),
+        ];
+
+        const SIZE: usize = 64;
+        let cfg = RenderConfig::<3> {
+            image_size: SIZE,
+            ..RenderConfig::default()
+        };
+        let g = render_gbuffer(&shapes, &cfg);
+        let (big_depth, big_norm) = render(shapes[0].clone(), &cfg);
+        let (small_depth, small_norm) = render(shapes[1].clone(), &cfg);
+        for i in 0..SIZE.pow(2) {
+            let (b, s) = (big_depth[i], small_depth[i]);
+            assert_eq!(g.depth[i], b.max(s));
+            match g.id[i] {
+                None => assert_eq!(b.max(s), 0),
+                Some(0) => {
+                    assert!(b >= s && b > 0);
+                    assert_eq!(g.normal[i], big_norm[i]);
+                }
+                Some(1) => {
+                    assert!(s > b);
+                    assert_eq!(g.normal[i], small_norm[i]);
+                }
+                Some(id) => panic!("invalid id {id}"),
+            }
+        }
+
+        // Pixels are indexed from the top-left corner of the image
+        let pixel = |x: f32, y: f32| {
+            let [i, j] =
+                [x, -y].map(|v| ((v + 1.0) / 2.0 * SIZE as f32) as usize);
+            j * SIZE + i
+        };
+        assert_eq!(g.id[pixel(0.3, 0.0)], Some(1));
+        assert_eq!(g.id[pixel(-0.3, 0.0)], Some(0));
+        assert_eq!(g.id[pixel(0.0, 0.7)], None);
+    }
+
+    #[test]
+    fn test_render_depth() {
+        // Sphere with radius 0.5
+        let mut ctx = Context::new();
+        let sphere = test_sphere(&mut ctx, [0.0; 3], 0.5);
+        let shape = VmShape::new(&ctx, sphere).unwrap();
+
+        const SIZE: usize = 64;
+        for (center_z, size) in [(0.0, 1.0), (1.0, 2.0)] {
+            let cfg = RenderConfig::<3> {
+                image_size: SIZE,
+                bounds: crate::shape::Bounds {
+                    center: Vector3::new(0.0, 0.0, center_z),
+                    size,
+                },
+                ..RenderConfig::default()
+            };
+            let depth = render_depth(shape.clone(), &cfg, 1e-4);
+            assert_eq!(depth.len(), SIZE.pow(2));
+
+            let near = center_z + size;
+            for (i, d) in depth.iter().enumerate() {
+                // Pixels are sampled at integer coordinates (as in `render`)
+                let [px, py] = [i % SIZE, SIZE - i / SIZE - 1]
+                    .map(|v| (v as f32 / SIZE as f32 * 2.0 - 1.0) * size);
+                let r2 = px * px + py * py;
+                if r2 < 0.2 {
+                    let expected = near - (0.25 - r2).sqrt();
+                    assert!(
+                        (d - expected).abs() < 1e-3,
+                        "bad depth at {i}: {d} != {expected}"
+                    );
+                } else if r2 > 0.3 {
+                    assert_eq!(*d, f32::INFINITY);
+                }
+            }
+            let center = depth[(SIZE / 2 - 1) * SIZE + SIZE / 2];
+            assert!((center - (near - 0.5)).abs() < 2e-4, "bad {center}");
+
+            // A coarse tolerance is still within a voxel
+            let coarse = render_depth(shape.clone(), &cfg, 1.0);
+            let voxel = 2.0 * size / SIZE as f32;
+            for (a, b) in coarse.iter().zip(&depth) {
+                assert!(a == b || (a - b).abs() <= voxel);
+            }
+        }
+
+        // A shape which fills the near plane has zero depth
+        let x = ctx.x();
+        let shape = VmShape::new(&ctx, x).unwrap();
+        let cfg = RenderConfig::<3> {
+            image_size: 16,
+            ..RenderConfig::default()
+        };
+        let depth = render_depth(shape, &cfg, 1e-3);
+        assert_eq!(depth[0], 0.0);
+        assert_eq!(depth[15], f32::INFINITY);
+    }
+
+    /// The same generic renderer should produce matching images with the
+    /// interpreter and the JIT
+    #[cfg(feature = "jit")]
+    #[test]
+    fn test_vm_jit_match() {
+        const COLONNADE: &str = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../models/colonnade.vm"
+        ));
+        let (ctx, root) = Context::from_text(COLONNADE.as_bytes()).unwrap();
+        let vm = VmShape::new(&ctx, root).unwrap();
+        let jit = crate::jit::JitShape::new(&ctx, root).unwrap();
+
+        let cfg = RenderConfig::<3> {
+            image_size: 128,
+            ..RenderConfig::default()
+        };
+        let (vm_depth, vm_norm) = render(vm, &cfg);
+        let (jit_depth, jit_norm) = render(jit, &cfg);
+        assert!(vm_depth.iter().any(|d| *d != 0));
+        assert_eq!(vm_depth, jit_depth);
+        for (a, b) in vm_norm.iter().zip(&jit_norm) {
+            // Gradients may legitimately differ where a min / max is tied, in
+            // which case one backend can produce a degenerate (zero) gradient
+            // that's drawn in the fallback color.
+            if *a == [255, 0, 0] || *b == [255, 0, 0] {
+                continue;
+            }
+            for (a, b) in a.iter().zip(b) {
+                assert!(a.abs_diff(*b) <= 1, "normal mismatch: {a} != {b}");
+            }
+        }
+    }
 }
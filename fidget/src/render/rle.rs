@@ -0,0 +1,74 @@
+//! Run-length encoding for bitmap images
+
+/// Encodes a bitmap as run-length spans, in scanline order
+///
+/// Each span is a `(length, value)` tuple; adjacent spans always have
+/// different values, except when a run is too long to fit into a `u32`.
+///
+/// ```
+/// # use fidget::render::{decode_rle, encode_rle};
+/// let img = [false, false, true, true, true, false];
+/// let spans = encode_rle(&img);
+/// assert_eq!(spans, vec![(2, false), (3, true), (1, false)]);
+/// assert_eq!(decode_rle(&spans), img);
+/// ```
+pub fn encode_rle(image: &[bool]) -> Vec<(u32, bool)> {
+    let mut out: Vec<(u32, bool)> = vec![];
+    for &b in image {
+        match out.last_mut() {
+            Some((n, v)) if *v == b && *n < u32::MAX => *n += 1,
+            _ => out.push((1, b)),
+        }
+    }
+    out
+}
+
+/// Decodes run-length spans (from [`encode_rle`]) into a bitmap
+pub fn decode_rle(spans: &[(u32, bool)]) -> Vec<bool> {
+    let len = spans.iter().map(|(n, _)| *n as usize).sum();
+    let mut out = Vec::with_capacity(len);
+    for &(n, b) in spans {
+        out.resize(out.len() + n as usize, b);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        eval::MathShape,
+        render::{render_2d_rle, BitRenderMode, RenderConfig},
+        vm::VmShape,
+        Context,
+    };
+
+    #[test]
+    fn test_rle_round_trip() {
+        assert!(encode_rle(&[]).is_empty());
+        assert!(decode_rle(&[]).is_empty());
+
+        const HI: &str = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../models/hi.vm"
+        ));
+        let (ctx, root) = Context::from_text(HI.as_bytes()).unwrap();
+        let shape = VmShape::new(&ctx, root).unwrap();
+        for image_size in [32, 100, 256] {
+            let cfg = RenderConfig::<2> {
+                image_size,
+                ..RenderConfig::default()
+            };
+            let dense = cfg.run(shape.clone(), &BitRenderMode).unwrap();
+            let spans = render_2d_rle(shape.clone(), &cfg);
+            assert_eq!(spans, encode_rle(&dense));
+            assert_eq!(decode_rle(&spans), dense);
+
+            // The image is mostly empty, so spans are much smaller
+            assert!(spans.len() * 4 < dense.len());
+            for w in spans.windows(2) {
+                assert_ne!(w[0].1, w[1].1);
+            }
+        }
+    }
+}